@@ -0,0 +1,47 @@
+use clap::Parser;
+use crate::config::StrategyConfig;
+
+// 명령줄 인자 (설정 파일 값 위에 덮어씀)
+#[derive(Debug, Parser)]
+#[command(about = "Binance/Bitmart futures gap arbitrage")]
+pub struct Cli {
+    // 설정 파일 경로
+    #[arg(long, default_value = "config.toml")]
+    pub config: String,
+    // 거래 종목 (예: XRPUSDT)
+    #[arg(long)]
+    pub symbol: Option<String>,
+    // 기본 주문 수량
+    #[arg(long)]
+    pub size: Option<f64>,
+    // 주문을 보내지 않고 로그만 출력
+    #[arg(long)]
+    pub dry_run: bool,
+    // 거래소별 주문 API 지연만 측정하고 종료
+    #[arg(long)]
+    pub benchmark: bool,
+    // 거래소별 최소 수량 진입/청산으로 주문 경로를 실제 자금으로 점검하고 종료
+    #[arg(long)]
+    pub selftest: bool,
+    // 지연 측정 샘플 수
+    #[arg(long)]
+    pub samples: Option<usize>,
+}
+
+impl Cli {
+    // 명령줄 값으로 설정 덮어쓰기
+    pub fn apply(&self, config: &mut StrategyConfig) {
+        if let Some(symbol) = &self.symbol {
+            config.symbol = symbol.to_uppercase();
+        }
+        if let Some(size) = self.size {
+            config.position_size = size;
+        }
+        if let Some(samples) = self.samples {
+            config.benchmark_samples = samples;
+        }
+        if self.dry_run {
+            config.dry_run = true;
+        }
+    }
+}
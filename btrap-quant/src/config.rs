@@ -0,0 +1,812 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use crate::depth::{BinanceDepthMode, BITMART_DEPTH_LEVELS};
+use crate::event_log::QueueOverflow;
+use crate::handle_price::{BinanceStreamType, FeedAction, FieldPath, BITMART_TRADE_SAMPLE};
+use crate::market_hours::BlackoutWindow;
+use crate::price_calculator::{FillMode, GapDefinition};
+use crate::state::{DRY_RUN_STATE_PATH, STATE_PATH};
+use crate::volatility::adaptive_position_size;
+
+// 마진 타입 (isolated: 격리, cross: 교차)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MarginType {
+    Isolated,
+    Cross,
+}
+
+impl MarginType {
+    // Bitmart open_type 값
+    pub fn as_bitmart(&self) -> &'static str {
+        match self {
+            MarginType::Isolated => "isolated",
+            MarginType::Cross => "cross",
+        }
+    }
+
+    // Binance marginType 값
+    pub fn as_binance(&self) -> &'static str {
+        match self {
+            MarginType::Isolated => "ISOLATED",
+            MarginType::Cross => "CROSSED",
+        }
+    }
+
+    // 거래소 응답의 마진 타입 (Binance isolated/cross, Bitmart isolated/cross, 대소문자 무시)
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "isolated" => Some(MarginType::Isolated),
+            "cross" | "crossed" => Some(MarginType::Cross),
+            _ => None,
+        }
+    }
+}
+
+// Binance 주문 응답 형식 (newOrderRespType)
+// ack: 주문 번호만 반환 (가장 빠르지만 체결가/체결 수량 없음), result: 체결 결과 포함 (avgPrice, executedQty)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BinanceOrderRespType {
+    Ack,
+    Result,
+}
+
+impl BinanceOrderRespType {
+    pub fn as_binance(&self) -> &'static str {
+        match self {
+            BinanceOrderRespType::Ack => "ACK",
+            BinanceOrderRespType::Result => "RESULT",
+        }
+    }
+}
+
+// 진입 허용 방향 (both, only_gap1, only_gap2)
+// gap1: Binance 숏/Bitmart 롱, gap2: Binance 롱/Bitmart 숏 (invert_direction 적용 후 포지션 기준)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AllowedDirections {
+    Both,
+    OnlyGap1,
+    OnlyGap2,
+}
+
+impl AllowedDirections {
+    // Binance 포지션 기준으로 진입 허용 여부
+    pub fn allows(&self, binance_position: &str) -> bool {
+        match self {
+            AllowedDirections::Both => true,
+            AllowedDirections::OnlyGap1 => binance_position == "SHORT",
+            AllowedDirections::OnlyGap2 => binance_position == "LONG",
+        }
+    }
+}
+
+// 거래소 (binance, bitmart)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Exchange {
+    Binance,
+    Bitmart,
+}
+
+impl Exchange {
+    // 공유 맵과 로그에 쓰는 거래소 이름
+    pub fn name(&self) -> &'static str {
+        match self {
+            Exchange::Binance => "Binance",
+            Exchange::Bitmart => "Bitmart",
+        }
+    }
+}
+
+// 진입 방향별 maker 거래소 (gap1: Binance 숏/Bitmart 롱, gap2: Binance 롱/Bitmart 숏)
+// 지정한 거래소는 최우선 호가 지정가(maker), 반대쪽은 시장가(taker)로 진입, 없으면 양쪽 시장가
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct MakerLegs {
+    pub gap1: Option<Exchange>,
+    pub gap2: Option<Exchange>,
+}
+
+impl MakerLegs {
+    // Binance 포지션 기준 진입 방향의 maker 거래소
+    pub fn maker_for(&self, binance_position: &str) -> Option<Exchange> {
+        if binance_position == "SHORT" {
+            self.gap1
+        } else {
+            self.gap2
+        }
+    }
+}
+
+// 종목별 수수료 (%, 지정한 항목만 전역 수수료 대신 사용, 프로모션 등으로 종목마다 요율이 다를 때)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct FeeOverride {
+    pub binance_taker_fee: Option<f64>,
+    pub bitmart_taker_fee: Option<f64>,
+    pub binance_maker_fee: Option<f64>,
+    pub bitmart_maker_fee: Option<f64>,
+}
+
+// 갭 임계값 단위 (percent: 갭 %, ticks: 호가 단위 수, 현재 Bitmart 가격 기준 %로 환산)
+// 같은 %라도 고가 종목은 여러 틱, 저가 종목은 1틱 미만이라 틱 단위가 종목 간에 일관됨
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThresholdUnit {
+    Percent,
+    Ticks,
+}
+
+// Bitmart 계약 정보 (size는 정수 계약 수로 주문해야 함)
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct ContractSpec {
+    // 계약 1개당 기초자산 수량 (예: XRPUSDT 1계약 = 1 XRP)
+    pub contract_size: f64,
+    // 최소 주문 계약 수
+    pub min_contracts: u64,
+    // 주문 가격 소수 자릿수 (없으면 가격을 가장 짧은 표기 그대로 전송)
+    #[serde(default)]
+    pub price_decimals: Option<u32>,
+}
+
+impl ContractSpec {
+    // 기초자산 수량을 가장 가까운 정수 계약 수로 변환 (0계약 또는 최소 미만이면 오류)
+    pub fn contracts(&self, quantity: f64) -> Result<u64, String> {
+        let contracts = (quantity / self.contract_size).round();
+        if contracts < 1.0 {
+            return Err(format!(
+                "quantity {} rounds to 0 contracts (contract size {})",
+                quantity, self.contract_size
+            ));
+        }
+        let contracts = contracts as u64;
+        if contracts < self.min_contracts {
+            return Err(format!(
+                "quantity {} is {} contracts, below the minimum of {}",
+                quantity, contracts, self.min_contracts
+            ));
+        }
+        Ok(contracts)
+    }
+
+    // 목표 명목 금액(USDT)을 넘지 않는 정수 계약 수량 (기초자산 단위, 최소 계약 미만이면 오류)
+    pub fn notional_quantity(&self, notional_usd: f64, price: f64) -> Result<f64, String> {
+        if price <= 0.0 {
+            return Err(format!("cannot size {} USDT at price {}", notional_usd, price));
+        }
+        // 부동소수점 오차로 한 계약이 빠지지 않도록 약간의 여유
+        let contracts = (notional_usd / price / self.contract_size + 1e-9).floor() as u64;
+        if contracts == 0 || contracts < self.min_contracts {
+            return Err(format!(
+                "{} USDT at price {} is {} contracts, below the minimum of {}",
+                notional_usd,
+                price,
+                contracts,
+                self.min_contracts.max(1)
+            ));
+        }
+        Ok(contracts as f64 * self.contract_size)
+    }
+
+    // 주문 가격 문자열 (price_decimals 자릿수로 반올림, 유한한 양수만 허용)
+    pub fn format_price(&self, price: f64) -> Result<String, String> {
+        if !price.is_finite() || price <= 0.0 {
+            return Err(format!("invalid order price {}", price));
+        }
+        Ok(match self.price_decimals {
+            Some(decimals) => format!("{:.*}", decimals as usize, price),
+            None => price.to_string(),
+        })
+    }
+}
+
+// 전략 설정 (config.toml)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct StrategyConfig {
+    // 거래 종목
+    pub symbol: String,
+    // 주문을 보내지 않고 체결된 것으로 가정 (상태는 별도 파일에 저장)
+    pub dry_run: bool,
+    // REST API 주소
+    pub binance_rest_url: String,
+    pub bitmart_rest_url: String,
+    // WebSocket 주소 (wss:// 필수, Binance는 스트림 이름을 붙이는 기본 경로)
+    pub binance_ws_url: String,
+    pub bitmart_ws_url: String,
+    pub bitmart_depth_ws_url: String,
+    pub bitmart_private_ws_url: String,
+    // Binance 가격 스트림 종류 (agg_trade, book_ticker, mark_price)
+    pub binance_stream: BinanceStreamType,
+    // Binance 가격/호가창을 결합 스트림 한 연결로 수신 (/stream?streams=..., false면 스트림별 연결)
+    pub binance_combined_stream: bool,
+    // Binance 호가창 수신 방식 (partial: depth20 부분 호가, diff: 증분 호가 + 스냅샷 순서 검증)
+    pub binance_depth_mode: BinanceDepthMode,
+    // 가격 필드 경로 (Binance는 없으면 스트림 종류 기본값, Bitmart는 data 항목 기준)
+    pub binance_price_path: Option<FieldPath>,
+    pub bitmart_price_path: FieldPath,
+    // 갭 계산 기준 가격 (last_trade, mid, execution_price, bid_ask), 진입과 청산에 동일하게 적용
+    pub gap_definition: GapDefinition,
+    // 진입 시 최근 체결가 기준 갭과 호가 체결가 기준 갭이 모두 진입 임계값을 넘어야 하는지 여부
+    pub dual_gap_confirmation: bool,
+    // Binance 호가창이 없을 때 최근 가격에 적용할 합성 스프레드 (bp, 매수/매도 간격), 없으면 호가창 필수
+    // 이 값으로 계산한 가격은 추정치로 표시됨
+    pub binance_synthetic_spread_bps: Option<f64>,
+    // 갭 계산에 쓰는 두 거래소 가격의 최대 수신 경과 시간 (ms, 0이면 확인 안 함)
+    pub price_alignment_ms: u64,
+    // 전략 판단 최소 간격 (ms, 0이면 가격 업데이트마다 판단)
+    // 간격 안에 들어온 업데이트는 합쳐서 간격이 지난 뒤 최신 가격으로 한 번 판단
+    pub min_evaluation_interval_ms: u64,
+    // 피드별로 기억하는 최근 가격 메시지 키 수 (재접속 등으로 다시 온 업데이트를 건너뜀, 0이면 사용 안 함)
+    pub dedup_window: usize,
+    // 청산 후 거래소 잔량이 남았을 때 즉시 재청산 시도 횟수
+    pub close_retry_attempts: u32,
+    // 청산 주문 전 거래소 포지션 조회로 줄일 포지션이 있는 쪽만 청산 (상태와 거래소가 어긋나 청산 주문이 새 포지션을 여는 것 방지)
+    pub verify_position_before_close: bool,
+    // 진입 갭 임계값 (threshold_unit 단위)
+    pub entry_threshold: f64,
+    // 청산 갭 임계값 (threshold_unit 단위)
+    pub exit_threshold: f64,
+    // 진입/청산 임계값 단위 (수익 고정 회귀폭 exit_decay_start/end는 항상 %p)
+    pub threshold_unit: ThresholdUnit,
+    // 호가 단위 (ticks 단위에서 사용, 없으면 시작 시 Binance exchangeInfo PRICE_FILTER에서 조회)
+    pub tick_size: Option<f64>,
+    // 수익 고정: 보유 시간에 따라 요구하는 갭 회귀폭(%p)을 exit_decay_start에서 exit_decay_end로 선형 감소
+    // 청산 임계값은 max(exit_threshold, |진입 갭| - 요구 회귀폭) (exit_decay_secs가 0이면 사용 안 함)
+    pub exit_decay_secs: u64,
+    pub exit_decay_start: f64,
+    pub exit_decay_end: f64,
+    // 기본 주문 수량
+    pub position_size: f64,
+    // 목표 명목 금액 (USDT, 설정 시 position_size 대신 현재 가격과 Bitmart 계약 정보로 수량 계산)
+    pub notional_usd: Option<f64>,
+    // 변동성 기반 수량 조절 사용 여부
+    pub adaptive_sizing: bool,
+    // 변동성 계산에 사용할 최근 틱 수익률 개수
+    pub volatility_window: usize,
+    // 기본 수량을 그대로 쓰는 기준 변동성 (틱 수익률 표준편차, %)
+    pub target_volatility: f64,
+    // 변동성 조절 후 최소/최대 수량
+    pub min_position_size: f64,
+    pub max_position_size: f64,
+    // 테이커 수수료 (%)
+    pub binance_taker_fee: f64,
+    pub bitmart_taker_fee: f64,
+    // 메이커 수수료 (%)
+    pub binance_maker_fee: f64,
+    pub bitmart_maker_fee: f64,
+    // 종목별 수수료 덮어쓰기 (키: 종목)
+    pub fee_overrides: HashMap<String, FeeOverride>,
+    // Binance 수수료 배율 (BNB 수수료 할인, 예: 0.9면 10% 할인, 종목별 수수료에도 적용)
+    pub binance_fee_multiplier: f64,
+    // 기대 수익 계산에 쓰는 거래소별 체결 방식 (taker 또는 maker)
+    pub binance_fill_mode: FillMode,
+    pub bitmart_fill_mode: FillMode,
+    // Binance 주문 응답 형식 (result면 응답의 체결 수량/가격 사용, ack면 시장가 주문마다 주문 조회로 체결 확인)
+    pub binance_order_resp_type: BinanceOrderRespType,
+    // maker 지정가 주문을 post-only로 전송 (Binance GTX, Bitmart maker only: 즉시 체결될 가격이면 거부)
+    pub post_only: bool,
+    // post-only 거부 시 최우선 호가로 가격을 다시 정해 재시도하는 횟수
+    pub post_only_retries: u32,
+    // maker 진입 주문 미체결 시 재호가 대기 시간 (ms, maker_legs를 쓰면 0 불가: 체결 확인 없이 포지션을 기록하게 됨)
+    // 대기 후 미체결 잔량은 취소하고 최신 최우선 호가로 다시 주문, 기회가 사라지면 진입을 되돌림
+    pub maker_reprice_ms: u64,
+    // maker 진입 주문 최대 재호가 횟수 (초과 시 진입을 되돌림)
+    pub max_reprices: u32,
+    // 진입 방향별 maker/taker 거래소 배정 (진입 주문에만 적용, 청산은 양쪽 시장가)
+    pub maker_legs: MakerLegs,
+    // 손익분기 계산에 쓰는 예상 보유 시간 (시간)
+    pub expected_hold_hours: f64,
+    // 마진 타입 (isolated 또는 cross)
+    pub open_type: MarginType,
+    // 거래소에 설정되어 있어야 할 레버리지 (시작 시 양쪽 거래소 설정과 비교, 없으면 확인 안 함)
+    pub leverage: Option<u32>,
+    // 레버리지가 다르면 시작 시 거래소 설정을 leverage로 변경 (false면 경고만)
+    pub auto_set_leverage: bool,
+    // 알림 웹훅 URL (Discord/Slack/Telegram, 없으면 알림 안 함)
+    pub webhook_url: Option<String>,
+    // 알림 최소 간격 (초)
+    pub notify_min_interval_secs: u64,
+    // 롱/숏 배정 반전 (갭 > 0일 때 Binance 롱, Bitmart 숏)
+    pub invert_direction: bool,
+    // 진입 허용 방향 (한쪽 거래소 숏 제약 등으로 한 방향만 거래할 때)
+    pub allowed_directions: AllowedDirections,
+    // Bitmart 호가창 구독 단계 (depthAll5/20/50)
+    pub bitmart_depth_levels: u32,
+    // 호가 불균형 계산에 사용할 상위 호가 단계 수
+    pub imbalance_levels: usize,
+    // 공정가(잔량 가중 평균 가격) 계산에 쓰는 호가 단계 수
+    pub fair_value_levels: usize,
+    // 호가창 최대 허용 지연 (ms, 거래소 호가 시각 기준, 0이면 확인 안 함) - 초과하거나 교차된 호가창이면 거래 보류
+    pub max_book_age_ms: u64,
+    // 진입/청산 시 거래 기록에 남길 호가 단계 수 (0이면 스냅샷 안 함)
+    pub book_snapshot_levels: usize,
+    // 진입 시 호가 불균형이 거래 방향과 일치해야 하는지 여부
+    pub imbalance_gate: bool,
+    // 게이트 통과에 필요한 최소 불균형 차이 (-1.0 ~ 1.0)
+    pub min_imbalance: f64,
+    // 진입 시 갭이 빠르게 벌어지는 중이 아니어야 하는지 여부
+    pub gap_velocity_gate: bool,
+    // 갭 변화 속도 계산 구간 (ms)과 게이트 통과 최대 속도 (%p/초, 수렴 방향은 속도와 무관하게 통과)
+    pub gap_velocity_window_ms: u64,
+    pub max_gap_velocity: f64,
+    // 진입 갭이 왕복 비용(양쪽 진입/청산 수수료 + 호가 기준 예상 슬리피지)의 몇 배 이상이어야 하는지 (없으면 사용 안 함)
+    // 고정 임계값과 달리 종목별 수수료와 현재 호가 유동성에 따라 실제 진입 기준이 달라짐
+    pub min_gap_cost_ratio: Option<f64>,
+    // 진입에 필요한 최소 실현 변동성 (틱 수익률 표준편차, %, 없으면 사용 안 함)
+    // 변동이 거의 없는 시장에서 작은 갭으로 반복 진입하지 않도록 함
+    pub min_volatility: Option<f64>,
+    // 최소 변동성 계산에 사용할 최근 틱 수익률 개수
+    pub min_volatility_window: usize,
+    // 신규 진입을 하지 않는 점검 시간대 (UTC)
+    pub blackout_windows: Vec<BlackoutWindow>,
+    // 거래 중단으로 간주할 거래소 거부 코드
+    pub halt_error_codes: Vec<i64>,
+    // 거래 중단 감지 후 진입 재시도까지 대기 시간 (초)
+    pub halt_pause_secs: u64,
+    // 종목별 Bitmart 계약 정보
+    pub bitmart_contracts: HashMap<String, ContractSpec>,
+    // 주문 전 표준 입력으로 확인 (초기 실거래 테스트용)
+    pub confirm_trades: bool,
+    // 확인 대기 시간 (초, 초과 시 주문 안 함)
+    pub confirm_timeout_secs: u64,
+    // 양쪽 진입 주문 전체 제한 시간 (ms, 0이면 제한 없음) - 초과하면 중단하고 체결된 쪽을 되돌림 (추가 진입에는 적용 안 함)
+    pub entry_deadline_ms: u64,
+    // 손실 거래 청산 후 신규 진입 대기 시간 (초, 0이면 사용 안 함)
+    pub loss_cooldown_secs: u64,
+    // 청산 후 반대 방향 진입 대기 시간과 같은 방향 재진입 대기 시간 (초, 0이면 대기 없음)
+    // 임계값 근처의 작은 반전에 방향을 바꿔 잇달아 진입하는 것을 막음 (같은 방향은 더 짧게)
+    pub reversal_cooldown_secs: u64,
+    pub same_direction_cooldown_secs: u64,
+    // 세션 누적 실현 손실 한도 (USDT, 양수, 없으면 사용 안 함) - 넘으면 모든 포지션을 청산하고 재시작 전까지 거래 중단
+    pub max_session_loss: Option<f64>,
+    // 진입 후 거래소 보호 스탑 주문 (진입가 대비 불리한 방향 %, 없으면 사용 안 함)
+    // 봇이 멈춰도 거래소에서 포지션을 정리하며, 정상 청산 시 취소
+    pub protective_stop_pct: Option<f64>,
+    // 추가 진입 (피라미딩): 보유 중 진입 방향 갭이 직전 진입 갭보다 scale_in_gap_step(%p) 이상 벌어질 때마다
+    // scale_in_size만큼 추가 (최대 scale_in_max_steps회, 0이면 사용 안 함), 청산은 평균 진입 갭 기준으로 전체 수량
+    pub scale_in_max_steps: u32,
+    pub scale_in_gap_step: f64,
+    pub scale_in_size: f64,
+    // 진입 후 청산 신호를 무시하는 최소 보유 시간 (초, 0이면 사용 안 함, 손절 청산은 제외)
+    pub min_time_in_position_secs: u64,
+    // tokio 작업 스레드 수 (0이면 CPU 코어 수)
+    pub worker_threads: usize,
+    // 동시에 실행할 장기 태스크 최대 수 (피드, 상태 출력 등, 종목당 약 10개 필요)
+    pub max_active_tasks: usize,
+    // 메모리와 상태 파일에 보존할 최근 거래 기록 수 (초과분은 오래된 순으로 삭제)
+    pub trade_history_limit: usize,
+    // 전체 종목에서 동시에 열 수 있는 거래 수 (한도에 도달하면 신규 진입 건너뜀)
+    pub max_open_trades: usize,
+    // 최근 1시간 주문 수 상한 (로직 오류로 인한 주문 폭주 방지, 넘으면 재시작 전까지 모든 주문 중단, 0이면 제한 없음)
+    pub max_orders_per_hour: u32,
+    // Binance IP 분당 요청 가중치 한도와 비필수 요청을 미루기 시작하는 사용 비율
+    pub binance_weight_limit: u32,
+    pub binance_weight_pause_ratio: f64,
+    // 연결 실패/5xx가 연속 몇 번이면 해당 거래소 거래를 중단할지와 복구 확인 주기 (초)
+    pub venue_down_after_failures: u32,
+    pub venue_probe_interval_secs: u64,
+    // 시장 이벤트 녹화 파일 (JSON Lines, 없으면 녹화 안 함)
+    pub event_log_path: Option<String>,
+    // 녹화 대기열 크기와 가득 찼을 때 처리 (drop_oldest, block), 파일 flush 주기 (ms)
+    pub event_log_queue_capacity: usize,
+    pub event_log_overflow: QueueOverflow,
+    pub event_log_flush_ms: u64,
+    // 사후 분석용 최근 시장 이벤트 개수 (0이면 보관 안 함, 패닉/서킷 브레이커 발동 시 tick_dump_dir에 기록)
+    pub tick_ring_size: usize,
+    pub tick_dump_dir: String,
+    // 전략 판단 이벤트 스트림 TCP 주소 (예: 127.0.0.1:9870, 없으면 서버 없음)
+    pub event_stream_addr: Option<String>,
+    // 구독자별 이벤트 버퍼 크기 (넘치면 느린 구독자의 오래된 이벤트부터 버림)
+    pub event_stream_capacity: usize,
+    // 지연 측정 (--benchmark) 샘플 수와 경고 기준 (ms, 중앙값 기준)
+    pub benchmark_samples: usize,
+    pub latency_threshold_ms: f64,
+    // Bitmart WebSocket 오류 코드별 대응 (ignore, reconnect, fatal), 없는 코드는 ignore
+    pub bitmart_error_actions: HashMap<String, FeedAction>,
+}
+
+impl Default for StrategyConfig {
+    fn default() -> Self {
+        Self {
+            symbol: "XRPUSDT".to_string(),
+            dry_run: false,
+            binance_rest_url: "https://fapi.binance.com".to_string(),
+            bitmart_rest_url: "https://api-cloud.bitmart.com".to_string(),
+            binance_ws_url: "wss://fstream.binance.com/ws".to_string(),
+            bitmart_ws_url: "wss://openapi-ws-v2.bitmart.com/api?protocol=1.1".to_string(),
+            bitmart_depth_ws_url: "wss://openapi-ws-v2.bitmart.com/api?protocol=1.1".to_string(),
+            bitmart_private_ws_url: "wss://openapi-ws-v2.bitmart.com/user?protocol=1.1".to_string(),
+            binance_stream: BinanceStreamType::AggTrade,
+            binance_combined_stream: false,
+            binance_depth_mode: BinanceDepthMode::Partial,
+            binance_price_path: None,
+            bitmart_price_path: FieldPath::parse("deal_price").expect("valid default path"),
+            gap_definition: GapDefinition::BidAsk,
+            dual_gap_confirmation: false,
+            binance_synthetic_spread_bps: None,
+            price_alignment_ms: 0,
+            min_evaluation_interval_ms: 0,
+            dedup_window: 1000,
+            close_retry_attempts: 3,
+            verify_position_before_close: true,
+            entry_threshold: 0.3,
+            exit_threshold: 0.05,
+            threshold_unit: ThresholdUnit::Percent,
+            tick_size: None,
+            exit_decay_secs: 0,
+            exit_decay_start: 0.3,
+            exit_decay_end: 0.05,
+            position_size: 1.0,
+            notional_usd: None,
+            adaptive_sizing: false,
+            volatility_window: 100,
+            target_volatility: 0.01,
+            min_position_size: 1.0,
+            max_position_size: 1.0,
+            binance_taker_fee: 0.05,
+            bitmart_taker_fee: 0.06,
+            binance_maker_fee: 0.02,
+            bitmart_maker_fee: 0.02,
+            fee_overrides: HashMap::new(),
+            binance_fee_multiplier: 1.0,
+            binance_fill_mode: FillMode::Taker,
+            bitmart_fill_mode: FillMode::Taker,
+            binance_order_resp_type: BinanceOrderRespType::Result,
+            post_only: false,
+            post_only_retries: 3,
+            maker_reprice_ms: 0,
+            max_reprices: 3,
+            maker_legs: MakerLegs::default(),
+            expected_hold_hours: 8.0,
+            open_type: MarginType::Isolated,
+            leverage: None,
+            auto_set_leverage: false,
+            webhook_url: None,
+            notify_min_interval_secs: 10,
+            invert_direction: false,
+            allowed_directions: AllowedDirections::Both,
+            bitmart_depth_levels: 20,
+            imbalance_levels: 5,
+            fair_value_levels: 5,
+            max_book_age_ms: 5000,
+            book_snapshot_levels: 5,
+            imbalance_gate: false,
+            min_imbalance: 0.0,
+            gap_velocity_gate: false,
+            gap_velocity_window_ms: 2000,
+            max_gap_velocity: 0.05,
+            min_gap_cost_ratio: None,
+            min_volatility: None,
+            min_volatility_window: 100,
+            blackout_windows: Vec::new(),
+            halt_error_codes: vec![-4140], // Binance: Invalid symbol status for opening position
+            halt_pause_secs: 60,
+            bitmart_contracts: HashMap::from([(
+                "XRPUSDT".to_string(),
+                ContractSpec { contract_size: 1.0, min_contracts: 1, price_decimals: None },
+            )]),
+            confirm_trades: false,
+            confirm_timeout_secs: 10,
+            entry_deadline_ms: 5000,
+            loss_cooldown_secs: 0,
+            reversal_cooldown_secs: 0,
+            same_direction_cooldown_secs: 0,
+            protective_stop_pct: None,
+            scale_in_max_steps: 0,
+            scale_in_gap_step: 0.1,
+            scale_in_size: 1.0,
+            max_session_loss: None,
+            min_time_in_position_secs: 0,
+            worker_threads: 0,
+            max_active_tasks: 32,
+            trade_history_limit: 1000,
+            max_open_trades: 1,
+            max_orders_per_hour: 200,
+            binance_weight_limit: 2400,
+            binance_weight_pause_ratio: 0.8,
+            venue_down_after_failures: 3,
+            venue_probe_interval_secs: 10,
+            event_log_path: None,
+            event_log_queue_capacity: 4096,
+            event_log_overflow: QueueOverflow::DropOldest,
+            event_log_flush_ms: 1000,
+            tick_ring_size: 1000,
+            tick_dump_dir: "tick_dumps".to_string(),
+            event_stream_addr: None,
+            event_stream_capacity: 1024,
+            benchmark_samples: 20,
+            latency_threshold_ms: 200.0,
+            bitmart_error_actions: HashMap::new(),
+        }
+    }
+}
+
+impl StrategyConfig {
+    // 설정 파일 로드 (파일이 없으면 기본값 사용)
+    pub fn load(path: &str) -> Result<Self, String> {
+        let config = match fs::read_to_string(path) {
+            Ok(contents) => toml::from_str::<StrategyConfig>(&contents)
+                .map_err(|e| format!("Failed to parse {}: {}", path, e))?,
+            Err(_) => {
+                println!("[Config] {} not found, using defaults.", path);
+                StrategyConfig::default()
+            }
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    // 기본 주문 수량 (notional_usd가 있으면 price 기준 정수 계약 수량, 양쪽 거래소 같은 수량이라 명목 금액도 맞춰짐)
+    pub fn base_position_size(&self, price: f64) -> Result<f64, String> {
+        let Some(notional) = self.notional_usd else {
+            return Ok(self.position_size);
+        };
+        let spec = self
+            .bitmart_contracts
+            .get(&self.symbol)
+            .ok_or_else(|| format!("no Bitmart contract spec for {}", self.symbol))?;
+        spec.notional_quantity(notional, price)
+    }
+
+    // 실제 주문 수량 (adaptive_sizing이면 변동성에 반비례)
+    pub fn effective_position_size(&self, volatility: Option<f64>, price: f64) -> Result<f64, String> {
+        let base = self.base_position_size(price)?;
+        if !self.adaptive_sizing {
+            return Ok(base);
+        }
+        Ok(adaptive_position_size(
+            base,
+            volatility,
+            self.target_volatility,
+            self.min_position_size,
+            self.max_position_size,
+        ))
+    }
+
+    // 임계값을 price 기준 갭 %로 환산 (ticks: 호가 단위 threshold개가 price에서 차지하는 비율)
+    // 호가 단위나 가격을 모르면 NaN이라 갭 비교가 항상 거짓 (진입/청산 신호 없음)
+    pub fn threshold_pct(&self, threshold: f64, price: f64) -> f64 {
+        match self.threshold_unit {
+            ThresholdUnit::Percent => threshold,
+            ThresholdUnit::Ticks => match self.tick_size.filter(|_| price > 0.0) {
+                Some(tick_size) => threshold * tick_size / price * 100.0,
+                None => f64::NAN,
+            },
+        }
+    }
+
+    // 거래소/체결 방식별 적용 수수료 (%, 현재 종목의 fee_overrides 우선, Binance는 배율 적용)
+    pub fn fee_pct(&self, exchange: Exchange, fill_mode: FillMode) -> f64 {
+        let fees = self.fee_overrides.get(&self.symbol).copied().unwrap_or_default();
+        match (exchange, fill_mode) {
+            (Exchange::Binance, FillMode::Taker) => {
+                fees.binance_taker_fee.unwrap_or(self.binance_taker_fee) * self.binance_fee_multiplier
+            }
+            (Exchange::Binance, FillMode::Maker) => {
+                fees.binance_maker_fee.unwrap_or(self.binance_maker_fee) * self.binance_fee_multiplier
+            }
+            (Exchange::Bitmart, FillMode::Taker) => fees.bitmart_taker_fee.unwrap_or(self.bitmart_taker_fee),
+            (Exchange::Bitmart, FillMode::Maker) => fees.bitmart_maker_fee.unwrap_or(self.bitmart_maker_fee),
+        }
+    }
+
+    // 진입 갭 임계값 (%, price: 갭 계산 기준인 Bitmart 가격)
+    pub fn entry_threshold_pct(&self, price: f64) -> f64 {
+        self.threshold_pct(self.entry_threshold, price)
+    }
+
+    // 청산 갭 임계값 (%, 보유 시간 반영 전)
+    pub fn exit_threshold_pct(&self, price: f64) -> f64 {
+        self.threshold_pct(self.exit_threshold, price)
+    }
+
+    // 보유 시간을 반영한 청산 임계값 (%) - 보유 시간을 모르거나 수익 고정을 쓰지 않으면 exit_threshold
+    pub fn effective_exit_threshold(&self, entry_gap: f64, held_secs: Option<i64>, price: f64) -> f64 {
+        let exit_threshold = self.exit_threshold_pct(price);
+        let Some(held_secs) = held_secs.filter(|_| self.exit_decay_secs > 0 && !exit_threshold.is_nan()) else {
+            return exit_threshold;
+        };
+        let progress = (held_secs.max(0) as f64 / self.exit_decay_secs as f64).min(1.0);
+        let required = self.exit_decay_start + (self.exit_decay_end - self.exit_decay_start) * progress;
+        exit_threshold.max(entry_gap.abs() - required)
+    }
+
+    // 상태 파일 경로 (dry run이면 별도 파일)
+    pub fn state_path(&self) -> &'static str {
+        if self.dry_run {
+            DRY_RUN_STATE_PATH
+        } else {
+            STATE_PATH
+        }
+    }
+
+    // 설정 값 검증 (명령줄 덮어쓰기 후 다시 호출)
+    pub fn validate(&self) -> Result<(), String> {
+        if self.leverage == Some(0) {
+            return Err("leverage must be at least 1".to_string());
+        }
+        if self.auto_set_leverage && self.leverage.is_none() {
+            return Err("auto_set_leverage requires leverage".to_string());
+        }
+        // maker 진입은 재호가 루프에서 전량 체결을 확인한 뒤에만 포지션으로 기록
+        if self.maker_legs != MakerLegs::default() && self.maker_reprice_ms == 0 {
+            return Err("maker_legs requires maker_reprice_ms > 0 to confirm maker fills before recording the position".to_string());
+        }
+        // 구독 채널과 호가창 보존 단계가 같은 값을 쓰도록 지원 단계만 허용
+        if !BITMART_DEPTH_LEVELS.contains(&self.bitmart_depth_levels) {
+            return Err(format!("bitmart_depth_levels must be one of {:?}", BITMART_DEPTH_LEVELS));
+        }
+        if self.binance_combined_stream && self.binance_depth_mode == BinanceDepthMode::Diff {
+            return Err("binance_combined_stream supports only binance_depth_mode = \"partial\"".to_string());
+        }
+        if self.exit_threshold < 0.0 || self.exit_threshold >= self.entry_threshold {
+            return Err("exit_threshold must be non-negative and below entry_threshold".to_string());
+        }
+        if self.tick_size.is_some_and(|tick_size| tick_size <= 0.0) {
+            return Err("tick_size must be positive".to_string());
+        }
+        if self.exit_decay_secs > 0 && (self.exit_decay_end < 0.0 || self.exit_decay_end > self.exit_decay_start) {
+            return Err("exit_decay_end must be non-negative and not above exit_decay_start".to_string());
+        }
+        if self.position_size <= 0.0 {
+            return Err("position_size must be positive".to_string());
+        }
+        if self.max_session_loss.is_some_and(|loss| loss <= 0.0) {
+            return Err("max_session_loss must be positive".to_string());
+        }
+        if self.protective_stop_pct.is_some_and(|pct| pct <= 0.0 || pct >= 100.0) {
+            return Err("protective_stop_pct must be between 0 and 100".to_string());
+        }
+        if self.scale_in_max_steps > 0 {
+            if self.scale_in_gap_step <= 0.0 || self.scale_in_size <= 0.0 {
+                return Err("scale_in_gap_step and scale_in_size must be positive".to_string());
+            }
+            // 보호 스탑은 최초 진입 수량으로 걸리므로 추가 진입분을 보호하지 못함
+            if self.protective_stop_pct.is_some() {
+                return Err("scale_in_max_steps cannot be combined with protective_stop_pct".to_string());
+            }
+        }
+        if let Some(notional) = self.notional_usd {
+            if notional <= 0.0 {
+                return Err("notional_usd must be positive".to_string());
+            }
+            if !self.bitmart_contracts.contains_key(&self.symbol) {
+                return Err(format!("notional_usd requires a bitmart_contracts entry for {}", self.symbol));
+            }
+        }
+        if self.adaptive_sizing {
+            if self.volatility_window < 2 {
+                return Err("volatility_window must be at least 2".to_string());
+            }
+            if self.target_volatility <= 0.0 {
+                return Err("target_volatility must be positive".to_string());
+            }
+            if self.min_position_size <= 0.0 || self.min_position_size > self.max_position_size {
+                return Err("min_position_size must be positive and not above max_position_size".to_string());
+            }
+        }
+        if self.binance_taker_fee < 0.0 || self.bitmart_taker_fee < 0.0 {
+            return Err("taker fees must be non-negative".to_string());
+        }
+        if self.binance_maker_fee < 0.0 || self.bitmart_maker_fee < 0.0 {
+            return Err("maker fees must be non-negative".to_string());
+        }
+        for (symbol, fees) in &self.fee_overrides {
+            let rates = [fees.binance_taker_fee, fees.bitmart_taker_fee, fees.binance_maker_fee, fees.bitmart_maker_fee];
+            if rates.into_iter().flatten().any(|fee| fee < 0.0) {
+                return Err(format!("fee_overrides for {} must be non-negative", symbol));
+            }
+        }
+        if !(self.binance_fee_multiplier > 0.0 && self.binance_fee_multiplier <= 1.0) {
+            return Err("binance_fee_multiplier must be in (0, 1]".to_string());
+        }
+        if self.same_direction_cooldown_secs > self.reversal_cooldown_secs {
+            return Err("same_direction_cooldown_secs must not exceed reversal_cooldown_secs".to_string());
+        }
+        if self.expected_hold_hours < 0.0 {
+            return Err("expected_hold_hours must be non-negative".to_string());
+        }
+        if self.imbalance_levels == 0 {
+            return Err("imbalance_levels must be at least 1".to_string());
+        }
+        if self.fair_value_levels == 0 {
+            return Err("fair_value_levels must be at least 1".to_string());
+        }
+        if !(-1.0..=1.0).contains(&self.min_imbalance) {
+            return Err("min_imbalance must be between -1.0 and 1.0".to_string());
+        }
+        if self.gap_velocity_gate && (self.gap_velocity_window_ms == 0 || self.max_gap_velocity < 0.0) {
+            return Err("gap_velocity_window_ms must be positive and max_gap_velocity non-negative".to_string());
+        }
+        if self.min_volatility.is_some() && self.min_volatility_window < 2 {
+            return Err("min_volatility_window must be at least 2".to_string());
+        }
+        if self.min_gap_cost_ratio.is_some_and(|ratio| ratio <= 0.0) {
+            return Err("min_gap_cost_ratio must be positive".to_string());
+        }
+        if self.min_volatility.is_some_and(|min| min < 0.0) {
+            return Err("min_volatility must be non-negative".to_string());
+        }
+        for window in &self.blackout_windows {
+            window.parse()?;
+        }
+        if let Some(path) = &self.binance_price_path {
+            path.validate_against(self.binance_stream.sample_message())
+                .map_err(|e| format!("binance_price_path: {}", e))?;
+        }
+        self.bitmart_price_path
+            .validate_against(BITMART_TRADE_SAMPLE)
+            .map_err(|e| format!("bitmart_price_path: {}", e))?;
+        if self.binance_synthetic_spread_bps.is_some_and(|bps| !(0.0..10_000.0).contains(&bps)) {
+            return Err("binance_synthetic_spread_bps must be between 0 and 10000".to_string());
+        }
+        for (name, url) in [
+            ("binance_ws_url", &self.binance_ws_url),
+            ("bitmart_ws_url", &self.bitmart_ws_url),
+            ("bitmart_depth_ws_url", &self.bitmart_depth_ws_url),
+            ("bitmart_private_ws_url", &self.bitmart_private_ws_url),
+        ] {
+            if !url.starts_with("wss://") {
+                return Err(format!("{} must use wss:// (got {})", name, url));
+            }
+        }
+        if self.max_active_tasks == 0 {
+            return Err("max_active_tasks must be at least 1".to_string());
+        }
+        if self.trade_history_limit == 0 {
+            return Err("trade_history_limit must be at least 1".to_string());
+        }
+        if self.event_log_queue_capacity == 0 {
+            return Err("event_log_queue_capacity must be at least 1".to_string());
+        }
+        if self.event_log_flush_ms == 0 {
+            return Err("event_log_flush_ms must be at least 1".to_string());
+        }
+        if self.tick_ring_size > 0 && self.tick_dump_dir.trim().is_empty() {
+            return Err("tick_dump_dir must not be empty when tick_ring_size is set".to_string());
+        }
+        if self.event_stream_capacity == 0 {
+            return Err("event_stream_capacity must be at least 1".to_string());
+        }
+        if self.max_open_trades == 0 {
+            return Err("max_open_trades must be at least 1".to_string());
+        }
+        if self.binance_weight_limit == 0 {
+            return Err("binance_weight_limit must be at least 1".to_string());
+        }
+        if !(self.binance_weight_pause_ratio > 0.0 && self.binance_weight_pause_ratio <= 1.0) {
+            return Err(format!(
+                "binance_weight_pause_ratio must be in (0, 1] (got {})",
+                self.binance_weight_pause_ratio
+            ));
+        }
+        if self.venue_down_after_failures == 0 {
+            return Err("venue_down_after_failures must be at least 1".to_string());
+        }
+        if self.venue_probe_interval_secs == 0 {
+            return Err("venue_probe_interval_secs must be at least 1".to_string());
+        }
+        if self.benchmark_samples == 0 {
+            return Err("benchmark_samples must be at least 1".to_string());
+        }
+        if self.latency_threshold_ms <= 0.0 {
+            return Err("latency_threshold_ms must be positive".to_string());
+        }
+        if !self.bitmart_contracts.contains_key(&self.symbol) {
+            return Err(format!("bitmart_contracts has no entry for symbol {}", self.symbol));
+        }
+        for (symbol, spec) in &self.bitmart_contracts {
+            if spec.contract_size <= 0.0 {
+                return Err(format!("bitmart_contracts.{}.contract_size must be positive", symbol));
+            }
+            if spec.price_decimals.is_some_and(|decimals| decimals > 16) {
+                return Err(format!("bitmart_contracts.{}.price_decimals must be at most 16", symbol));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,65 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use crate::clock::SharedClock;
+use crate::config::StrategyConfig;
+use crate::control::{SymbolFlags, TradeConfirmer, TradingSwitch};
+use crate::dedup::MessageDedup;
+use crate::depth::{SharedDepth, SharedFairValue, SharedImbalance};
+use crate::event_log::MarketTap;
+use crate::feed::EvaluationThrottle;
+use crate::fees::SharedFunding;
+use crate::notify::Notifier;
+use crate::order::Order;
+use crate::portfolio::OpenTrades;
+use crate::price_calculator::PriceCalculator;
+use crate::state::SharedState;
+use crate::tasks::TaskTracker;
+use crate::trade_events::TradeEventBus;
+use crate::volatility::{SharedGapVelocity, SharedVolatility};
+
+// 거래소별 최근 가격과 수신 시각
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PriceTick {
+    pub price: f64,
+    pub received_at: DateTime<Utc>,
+}
+
+// 거래소별 최근 가격 (키: 거래소 이름, 최신 값만 덮어써서 크기 고정)
+pub type SharedPrices = Arc<Mutex<HashMap<String, PriceTick>>>;
+
+// 피드 태스크와 주문 집행이 공유하는 자원
+// 잠금 순서: shared_prices -> shared_state -> 그 외 공유 맵 (depth, imbalance, fair_value, volatility, gap_velocity, funding)
+// - 그 외 공유 맵은 잠근 채로 다른 잠금을 얻지 않음 (값을 복사하거나 짧게 읽고 바로 놓음)
+// - shared_state를 잡은 채 shared_prices를 잠그지 않음 (가격은 판단 전에 복사해서 전달)
+// - shared_prices는 주문 집행 동안 잡지 않음 (handle_price_update는 판단 전에 놓음)
+// 새 태스크에서 둘 이상을 잠글 때도 이 순서를 따라야 교착 상태가 생기지 않음
+// 전략 판단(execute_trade)은 EvaluationThrottle로 한 번에 하나만 실행 (shared_state를 잡고 주문하는 동안
+// 다른 피드의 틱은 잠금을 기다리지 않고 재판단만 요청, 판단이 끝나면 최신 가격으로 다시 판단)
+#[derive(Clone)]
+pub struct TradingContext {
+    pub order: Arc<Order>,
+    pub config: Arc<StrategyConfig>,
+    pub shared_prices: SharedPrices,
+    pub shared_depth: SharedDepth,
+    pub shared_imbalance: SharedImbalance,
+    pub shared_fair_value: SharedFairValue, // 거래소별 호가 기준 공정가 (가격 이상 감지용)
+    pub shared_volatility: SharedVolatility,
+    pub entry_volatility: SharedVolatility, // 최소 변동성 게이트용 실현 변동성 (min_volatility_window 기준)
+    pub shared_gap_velocity: SharedGapVelocity, // 최근 체결가 기준 갭 변화 속도
+    pub evaluation: Arc<EvaluationThrottle>, // 전략 판단 주기 제한 및 판단 횟수
+    pub shared_funding: SharedFunding,
+    pub shared_state: SharedState,
+    pub symbol_flags: Arc<SymbolFlags>,
+    pub trading: Arc<TradingSwitch>, // 전역 거래 일시 중지 (피드는 유지)
+    pub notifier: Arc<Notifier>,
+    pub price_calculator: Arc<PriceCalculator>,
+    pub confirmer: Arc<TradeConfirmer>,
+    pub tasks: Arc<TaskTracker>,
+    pub open_trades: Arc<OpenTrades>, // 전체 종목 공유 열린 거래 수
+    pub trade_events: Arc<TradeEventBus>, // 외부 구독자용 판단 이벤트
+    pub market_tap: MarketTap, // 시장 이벤트 녹화(event_log_path)와 사후 분석용 최근 이벤트 링(tick_ring_size)
+    pub dedup: Arc<MessageDedup>, // 피드별 중복 가격 메시지 제거
+    pub clock: SharedClock, // 보유 시간, 대기 시간, 호가 신선도 등 시간 의존 판단의 기준 시각
+}
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::oneshot;
+use crate::price_calculator::PriceCalculator;
+
+// 종목별 신규 진입 허용 플래그 (런타임 변경 가능)
+#[derive(Debug)]
+pub struct SymbolFlags {
+    flags: HashMap<String, AtomicBool>,
+}
+
+impl SymbolFlags {
+    pub fn new(symbols: &[&str]) -> Self {
+        Self {
+            flags: symbols
+                .iter()
+                .map(|symbol| (symbol.to_string(), AtomicBool::new(true)))
+                .collect(),
+        }
+    }
+
+    // 등록되지 않은 종목은 비활성으로 간주
+    pub fn is_enabled(&self, symbol: &str) -> bool {
+        self.flags
+            .get(symbol)
+            .map(|flag| flag.load(Ordering::SeqCst))
+            .unwrap_or(false)
+    }
+
+    // 활성화 상태 변경 (기존 포지션은 유지, 신규 진입만 차단)
+    pub fn set_enabled(&self, symbol: &str, enabled: bool, source: &str) -> Result<(), String> {
+        let flag = self
+            .flags
+            .get(symbol)
+            .ok_or_else(|| format!("Unknown symbol: {}", symbol))?;
+        let previous = flag.swap(enabled, Ordering::SeqCst);
+        if previous != enabled {
+            println!(
+                "[Control] {} {} by {}",
+                symbol,
+                if enabled { "enabled" } else { "disabled" },
+                source
+            );
+        }
+        Ok(())
+    }
+}
+
+// 전역 거래 일시 중지 스위치 (피드와 상태 출력은 유지하고 전략 판단만 멈춤, 런타임 변경 가능)
+// trading_enabled가 false면 신규 진입 중단, exits_enabled까지 false면 보유 포지션 청산도 중단
+#[derive(Debug)]
+pub struct TradingSwitch {
+    trading_enabled: AtomicBool,
+    exits_enabled: AtomicBool,
+}
+
+impl Default for TradingSwitch {
+    fn default() -> Self {
+        Self { trading_enabled: AtomicBool::new(true), exits_enabled: AtomicBool::new(true) }
+    }
+}
+
+impl TradingSwitch {
+    pub fn trading_enabled(&self) -> bool {
+        self.trading_enabled.load(Ordering::SeqCst)
+    }
+
+    pub fn exits_enabled(&self) -> bool {
+        self.exits_enabled.load(Ordering::SeqCst)
+    }
+
+    // 거래 중지 (include_exits면 보유 포지션 청산도 중지), 요청마다 요청 주체와 함께 기록
+    pub fn pause(&self, include_exits: bool, source: &str) {
+        self.trading_enabled.store(false, Ordering::SeqCst);
+        self.exits_enabled.store(!include_exits, Ordering::SeqCst);
+        println!(
+            "[Control] Trading paused by {} ({})",
+            source,
+            if include_exits { "entries and exits frozen" } else { "no new entries, open positions still managed" }
+        );
+    }
+
+    pub fn resume(&self, source: &str) {
+        self.trading_enabled.store(true, Ordering::SeqCst);
+        self.exits_enabled.store(true, Ordering::SeqCst);
+        println!("[Control] Trading resumed by {}", source);
+    }
+}
+
+// 주문 전 수동 확인 (confirm_trades 모드, 한 번에 하나의 요청만 대기)
+#[derive(Debug, Default)]
+pub struct TradeConfirmer {
+    pending: Mutex<Option<oneshot::Sender<bool>>>,
+}
+
+impl TradeConfirmer {
+    // 확인 요청 후 응답 대기 (시간 초과 시 거절로 처리)
+    pub async fn confirm(&self, description: &str, timeout: Duration) -> bool {
+        let (sender, receiver) = oneshot::channel();
+        *self.pending.lock().unwrap() = Some(sender);
+        println!("[Confirm] {}\n[Confirm] Send order? (y/n, {}s timeout)", description, timeout.as_secs());
+
+        let approved = matches!(tokio::time::timeout(timeout, receiver).await, Ok(Ok(true)));
+        self.pending.lock().unwrap().take();
+        if !approved {
+            println!("[Confirm] Order not approved. Skipping.");
+        }
+        approved
+    }
+
+    // 대기 중인 확인 요청에 응답
+    pub fn respond(&self, approved: bool) -> Result<(), String> {
+        let sender = self
+            .pending
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or_else(|| "No trade is waiting for confirmation".to_string())?;
+        sender
+            .send(approved)
+            .map_err(|_| "Confirmation request already expired".to_string())
+    }
+}
+
+// 가상 가격 시뮬레이션 출력 (실거래와 무관, 임계값 감 잡기용)
+fn print_what_if(calculator: &PriceCalculator, binance: &str, bitmart: &str, quantity: f64) -> Result<(), String> {
+    let parse = |value: &str| value.parse::<f64>().ok().filter(|price| *price > 0.0);
+    let (Some(binance), Some(bitmart)) = (parse(binance), parse(bitmart)) else {
+        return Err("Usage: whatif <BINANCE_PRICE> <BITMART_PRICE>".to_string());
+    };
+    let result = calculator.what_if(binance, bitmart, quantity);
+    println!(
+        "[WhatIf] Binance {}, Bitmart {}: gap {:.4}%, net of fees {:.4}%, direction Binance {}/Bitmart {}, {} (expected PnL {:.4} USDT for {})",
+        binance,
+        bitmart,
+        result.gap,
+        result.net_gap,
+        result.binance_position,
+        result.bitmart_position,
+        if result.would_enter { "would enter" } else { "would not enter" },
+        result.expected_pnl,
+        quantity
+    );
+    Ok(())
+}
+
+// 현재 호가창을 JSON 파일로 저장 (경로가 없으면 book_dump_<SYMBOL>_<시각>.json)
+async fn write_book_dump(calculator: &PriceCalculator, path: Option<&str>, quantity: f64) -> Result<(), String> {
+    let dump = calculator.current_book_dump(quantity).await;
+    if dump.venues.is_empty() {
+        return Err("No order book received yet".to_string());
+    }
+    let path = path.map(str::to_string).unwrap_or_else(|| {
+        format!("book_dump_{}_{}.json", dump.symbol, dump.dumped_at.format("%Y%m%d_%H%M%S%.3f"))
+    });
+    let json = serde_json::to_string_pretty(&dump).map_err(|e| format!("Failed to serialize order book dump: {}", e))?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    let mut exchanges: Vec<_> = dump.venues.iter().collect();
+    exchanges.sort_by_key(|(exchange_name, _)| exchange_name.as_str());
+    for (exchange_name, venue) in exchanges {
+        println!(
+            "[Control] {} book ({} bids, {} asks, book time {}): {:?} buy {} @ {:?}, sell @ {:?}",
+            exchange_name,
+            venue.book.bids.len(),
+            venue.book.asks.len(),
+            venue.book.ms_t,
+            venue.fill_mode,
+            quantity,
+            venue.buy_price,
+            venue.sell_price
+        );
+    }
+    println!("[Control] Order book dump written to {}", path);
+    Ok(())
+}
+
+// 표준 입력 명령 처리
+// enable <SYMBOL> / disable <SYMBOL> / y / n (주문 확인 응답) / whatif <BINANCE> <BITMART> (가상 가격 시뮬레이션)
+// dumpbook [PATH] (현재 호가창과 주문 수량 기준 예상 체결가를 JSON으로 저장)
+// pause (신규 진입 중지) / pause all (청산까지 중지) / resume (거래 재개)
+pub async fn read_commands(
+    symbol_flags: Arc<SymbolFlags>,
+    trading: Arc<TradingSwitch>,
+    confirmer: Arc<TradeConfirmer>,
+    calculator: Arc<PriceCalculator>,
+    quantity: f64,
+) {
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let result = match parts.as_slice() {
+            ["enable", symbol] => symbol_flags.set_enabled(&symbol.to_uppercase(), true, "stdin"),
+            ["disable", symbol] => symbol_flags.set_enabled(&symbol.to_uppercase(), false, "stdin"),
+            ["y"] | ["yes"] => confirmer.respond(true),
+            ["n"] | ["no"] => confirmer.respond(false),
+            ["whatif", binance, bitmart] => print_what_if(&calculator, binance, bitmart, quantity),
+            ["pause"] => {
+                trading.pause(false, "stdin");
+                Ok(())
+            }
+            ["pause", "all"] => {
+                trading.pause(true, "stdin");
+                Ok(())
+            }
+            ["resume"] => {
+                trading.resume("stdin");
+                Ok(())
+            }
+            ["dumpbook"] => write_book_dump(&calculator, None, quantity).await,
+            ["dumpbook", path] => write_book_dump(&calculator, Some(path), quantity).await,
+            [] => Ok(()),
+            _ => Err(format!("Unknown command: {}", line.trim())),
+        };
+        if let Err(e) = result {
+            eprintln!("[Control] {}", e);
+        }
+    }
+}
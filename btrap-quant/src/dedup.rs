@@ -0,0 +1,81 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+// 메시지 식별 키 (거래소가 준 업데이트 ID, 없으면 가격과 거래소 시각)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MessageKey {
+    Id(u64),
+    PriceTime { price_bits: u64, ts: String },
+}
+
+impl MessageKey {
+    pub fn price_time(price: f64, ts: impl ToString) -> Self {
+        MessageKey::PriceTime { price_bits: price.to_bits(), ts: ts.to_string() }
+    }
+}
+
+// 최근에 본 키 (capacity개를 넘으면 오래된 키부터 잊음)
+#[derive(Debug)]
+pub struct RecentKeys {
+    capacity: usize,
+    seen: HashSet<MessageKey>,
+    order: VecDeque<MessageKey>,
+}
+
+impl RecentKeys {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, seen: HashSet::new(), order: VecDeque::new() }
+    }
+
+    // 처음 본 키면 기록하고 true
+    pub fn insert(&mut self, key: MessageKey) -> bool {
+        if self.seen.contains(&key) {
+            return false;
+        }
+        if self.order.len() == self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.seen.insert(key.clone());
+        self.order.push_back(key);
+        true
+    }
+}
+
+// 피드별 중복 메시지 제거 (재접속이나 결합 스트림으로 같은 업데이트가 다시 오는 경우)
+// 변동성/갭 속도 등 누적 지표가 같은 체결을 두 번 반영하지 않도록 가격 처리 전에 확인
+pub struct MessageDedup {
+    window: usize,
+    feeds: Mutex<HashMap<String, RecentKeys>>,
+    checked: AtomicU64,
+    duplicates: AtomicU64,
+}
+
+impl MessageDedup {
+    // window: 피드별로 기억할 최근 키 수 (0이면 중복 확인 안 함)
+    pub fn new(window: usize) -> Self {
+        Self { window, feeds: Mutex::new(HashMap::new()), checked: AtomicU64::new(0), duplicates: AtomicU64::new(0) }
+    }
+
+    // 이미 본 메시지면 true (키가 없거나 사용하지 않으면 항상 false)
+    pub fn is_duplicate(&self, feed: &str, key: Option<MessageKey>) -> bool {
+        let Some(key) = key.filter(|_| self.window > 0) else {
+            return false;
+        };
+        self.checked.fetch_add(1, Ordering::Relaxed);
+        let mut feeds = self.feeds.lock().unwrap();
+        let recent = feeds.entry(feed.to_string()).or_insert_with(|| RecentKeys::new(self.window));
+        if recent.insert(key) {
+            return false;
+        }
+        self.duplicates.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    // 누적 (확인한 메시지 수, 건너뛴 중복 수)
+    pub fn counts(&self) -> (u64, u64) {
+        (self.checked.load(Ordering::Relaxed), self.duplicates.load(Ordering::Relaxed))
+    }
+}
@@ -0,0 +1,811 @@
+use futures_util::{stream::StreamExt, SinkExt};
+use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use crate::config::StrategyConfig;
+use crate::event_log::{MarketEvent, MarketTap};
+use crate::feed::RECONNECT_DELAY;
+use crate::handle_price::{bitmart_data_items, bitmart_error_action, FeedAction};
+use crate::subscriptions::{bitmart_subscribe, Subscriptions};
+
+// 거래소별 최신 호가창 (키: 거래소 이름, 스냅샷을 덮어써서 이력 누적 없음)
+pub type SharedDepth = Arc<Mutex<HashMap<String, OrderBook>>>;
+// 거래소별 최신 호가 불균형 (키: 거래소 이름)
+pub type SharedImbalance = Arc<Mutex<HashMap<String, f64>>>;
+// 거래소별 호가 기준 공정가 (키: 거래소 이름, 호가 한쪽이 비면 제거)
+pub type SharedFairValue = Arc<Mutex<HashMap<String, f64>>>;
+
+// Bitmart depthAll 호가 항목
+#[derive(Debug, Clone, Deserialize)]
+pub struct DepthAllItem {
+    pub price: String,
+    pub vol: String,
+}
+
+// Bitmart depthAll 데이터
+#[derive(Debug, Clone, Deserialize)]
+pub struct DepthAllData {
+    pub symbol: String,
+    pub asks: Vec<DepthAllItem>,
+    pub bids: Vec<DepthAllItem>,
+    pub ms_t: i64,
+}
+
+// 숫자로 변환된 호가 단계
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Level {
+    pub price: f64,
+    pub vol: f64,
+}
+
+// 수신 시 한 번만 파싱해 저장하는 호가창 (체결가/불균형 계산은 숫자로만 처리)
+// 원본 문자열은 이벤트 로그에만 필요하므로 저장 전에 기록
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderBook {
+    pub symbol: String,
+    pub asks: Vec<Level>,
+    pub bids: Vec<Level>,
+    pub ms_t: i64,
+}
+
+impl OrderBook {
+    // 최우선 매수호가가 최우선 매도호가 이상인지 (재접속 직후 등 비정상 호가)
+    pub fn is_crossed(&self) -> bool {
+        match (self.bids.first(), self.asks.first()) {
+            (Some(bid), Some(ask)) => bid.price >= ask.price,
+            _ => false,
+        }
+    }
+
+    // 체결가 계산에 쓸 수 있는 호가창인지 (교차하지 않았고, max_age_ms가 0이 아니면 호가 시각이 그 이내)
+    pub fn is_valid_at(&self, max_age_ms: u64, now_ms: i64) -> bool {
+        !self.is_crossed() && (max_age_ms == 0 || now_ms - self.ms_t <= max_age_ms as i64)
+    }
+
+    pub fn is_valid(&self, max_age_ms: u64) -> bool {
+        self.is_valid_at(max_age_ms, chrono::Utc::now().timestamp_millis())
+    }
+}
+
+impl From<&DepthAllData> for OrderBook {
+    // 숫자가 아닌 호가 단계는 제외
+    fn from(depth: &DepthAllData) -> Self {
+        let to_levels = |items: &[DepthAllItem]| {
+            items
+                .iter()
+                .filter_map(|item| {
+                    Some(Level {
+                        price: item.price.parse().ok()?,
+                        vol: item.vol.parse().ok()?,
+                    })
+                })
+                .collect()
+        };
+        OrderBook {
+            symbol: depth.symbol.clone(),
+            asks: to_levels(&depth.asks),
+            bids: to_levels(&depth.bids),
+            ms_t: depth.ms_t,
+        }
+    }
+}
+
+// Bitmart 호가 업데이트 (depthAll은 항상 전체 호가, depthIncrease는 type이 update면 바뀐 단계만)
+#[derive(Debug, Clone)]
+pub enum BitmartDepthUpdate {
+    Snapshot(DepthAllData),
+    Delta(DepthAllData),
+}
+
+// Bitmart 호가 메시지 해석 (구조체로 바로 읽지 않고 group/table로 호가 채널인지 먼저 확인)
+// 호가 채널이 아니면 None, data는 객체 하나나 배열 모두 허용하고 형식이 맞지 않는 항목만 건너뜀
+// symbol이 없으면 채널 이름(futures/depthAll20:XRPUSDT)의 종목 사용, 호가 단계는 객체({price, vol})나 [가격, 수량] 배열
+pub fn parse_bitmart_depth(json: &Value) -> Option<Vec<BitmartDepthUpdate>> {
+    let group = json.get("group").or_else(|| json.get("table"))?.as_str()?;
+    let (channel, channel_symbol) = group.split_once(':').unwrap_or((group, ""));
+    let incremental = if channel.starts_with("futures/depthIncrease") {
+        true
+    } else if channel.starts_with("futures/depthAll") {
+        false
+    } else {
+        return None;
+    };
+    let updates = bitmart_data_items(json)
+        .into_iter()
+        .filter_map(|item| {
+            let Some(depth) = bitmart_depth_data(item, channel_symbol) else {
+                eprintln!("[Depth] Skipping malformed Bitmart {} item: {}", channel, item);
+                return None;
+            };
+            let delta = incremental && item.get("type").and_then(Value::as_str) == Some("update");
+            Some(if delta { BitmartDepthUpdate::Delta(depth) } else { BitmartDepthUpdate::Snapshot(depth) })
+        })
+        .collect();
+    Some(updates)
+}
+
+fn bitmart_depth_data(item: &Value, channel_symbol: &str) -> Option<DepthAllData> {
+    let symbol = item.get("symbol").and_then(Value::as_str).unwrap_or(channel_symbol);
+    if symbol.is_empty() {
+        return None;
+    }
+    let levels = |key: &str| -> Option<Vec<DepthAllItem>> {
+        let Some(levels) = item.get(key) else {
+            return Some(Vec::new());
+        };
+        levels
+            .as_array()?
+            .iter()
+            .map(|level| {
+                let field = |value: Option<&Value>| value.and_then(Value::as_str).map(str::to_string);
+                let (price, vol) = match level {
+                    Value::Array(pair) => (field(pair.first()), field(pair.get(1))),
+                    _ => (field(level.get("price")), field(level.get("vol"))),
+                };
+                Some(DepthAllItem { price: price?, vol: vol? })
+            })
+            .collect()
+    };
+    Some(DepthAllData {
+        symbol: symbol.to_string(),
+        asks: levels("asks")?,
+        bids: levels("bids")?,
+        ms_t: item.get("ms_t").or_else(|| item.get("ts")).and_then(Value::as_i64)?,
+    })
+}
+
+// 증분 호가를 전체 호가에 반영 (수량 0인 단계는 삭제, 상위 levels 단계만 유지)
+pub fn apply_depth_delta(book: &mut DepthAllData, delta: DepthAllData, levels: usize) {
+    for item in delta.bids {
+        apply_depth_level(&mut book.bids, item.price, item.vol, true);
+    }
+    for item in delta.asks {
+        apply_depth_level(&mut book.asks, item.price, item.vol, false);
+    }
+    book.bids.truncate(levels);
+    book.asks.truncate(levels);
+    book.ms_t = delta.ms_t;
+}
+
+// 가격 단계 갱신 (수량 0이면 삭제, 숫자가 아니면 무시, descending: 매수 호가처럼 가격 내림차순)
+fn apply_depth_level(levels: &mut Vec<DepthAllItem>, price: String, vol: String, descending: bool) {
+    let (Ok(key), Ok(quantity)) = (price.parse::<f64>(), vol.parse::<f64>()) else {
+        return;
+    };
+    let position = levels.binary_search_by(|item| {
+        let level = item.price.parse::<f64>().unwrap_or(f64::NAN);
+        if descending { key.total_cmp(&level) } else { level.total_cmp(&key) }
+    });
+    match (position, quantity == 0.0) {
+        (Ok(index), true) => {
+            levels.remove(index);
+        }
+        (Ok(index), false) => levels[index].vol = vol,
+        (Err(_), true) => {}
+        (Err(index), false) => levels.insert(index, DepthAllItem { price, vol }),
+    }
+}
+
+// Binance 부분 호가 (depth20) 메시지
+#[derive(Debug, Deserialize)]
+struct BinanceDepthUpdate {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "T")]
+    transaction_time: i64,
+    #[serde(rename = "b")]
+    bids: Vec<[String; 2]>,
+    #[serde(rename = "a")]
+    asks: Vec<[String; 2]>,
+}
+
+impl From<BinanceDepthUpdate> for DepthAllData {
+    fn from(update: BinanceDepthUpdate) -> Self {
+        let to_items = |levels: Vec<[String; 2]>| {
+            levels
+                .into_iter()
+                .map(|[price, vol]| DepthAllItem { price, vol })
+                .collect()
+        };
+        DepthAllData {
+            symbol: update.symbol,
+            asks: to_items(update.asks),
+            bids: to_items(update.bids),
+            ms_t: update.transaction_time,
+        }
+    }
+}
+
+// Bitmart depthAll 채널이 지원하는 호가 단계
+pub const BITMART_DEPTH_LEVELS: [u32; 3] = [5, 20, 50];
+const DEFAULT_BITMART_DEPTH_LEVELS: u32 = 20;
+
+// Bitmart depthAll 구독 채널 (설정은 validate에서 확인하지만, 지원하지 않는 단계면 경고 후 20단계 사용)
+pub fn bitmart_depth_channel(symbol: &str, levels: u32) -> String {
+    let levels = if BITMART_DEPTH_LEVELS.contains(&levels) {
+        levels
+    } else {
+        eprintln!(
+            "[Depth] Bitmart does not support depthAll{} (supported: {:?}). Falling back to depthAll{}.",
+            levels, BITMART_DEPTH_LEVELS, DEFAULT_BITMART_DEPTH_LEVELS
+        );
+        DEFAULT_BITMART_DEPTH_LEVELS
+    };
+    format!("futures/depthAll{}:{}", levels, symbol)
+}
+
+// 상위 N단계 잔량 합계
+fn sum_volume(items: &[Level], levels: usize) -> f64 {
+    items.iter().take(levels).map(|level| level.vol).sum()
+}
+
+// 호가 불균형 계산: (매수 잔량 - 매도 잔량) / (매수 잔량 + 매도 잔량)
+// 호가가 비어 있으면 None
+pub fn calculate_imbalance(depth: &OrderBook, levels: usize) -> Option<f64> {
+    let bid_vol = sum_volume(&depth.bids, levels);
+    let ask_vol = sum_volume(&depth.asks, levels);
+    let total = bid_vol + ask_vol;
+    if total <= 0.0 {
+        return None;
+    }
+    Some((bid_vol - ask_vol) / total)
+}
+
+// 호가 기준 공정가: 양쪽 상위 N단계의 잔량 가중 평균 가격
+// 단계가 N개보다 적으면 있는 만큼 사용, 한쪽이라도 비어 있거나 잔량이 0이면 None
+pub fn fair_value(depth: &OrderBook, levels: usize) -> Option<f64> {
+    if depth.bids.is_empty() || depth.asks.is_empty() {
+        return None;
+    }
+    let top = depth.bids.iter().take(levels).chain(depth.asks.iter().take(levels));
+    let (notional, volume) = top.fold((0.0, 0.0), |(notional, volume), level| {
+        (notional + level.price * level.vol, volume + level.vol)
+    });
+    (volume > 0.0).then(|| notional / volume)
+}
+
+// 최우선 호가 중간값 (한쪽이 비어 있으면 None)
+pub fn mid_price(depth: &OrderBook) -> Option<f64> {
+    Some((depth.bids.first()?.price + depth.asks.first()?.price) / 2.0)
+}
+
+// maker 주문 가격: 매수는 최우선 매수호가, 매도는 최우선 매도호가 (해당 쪽이 비어 있으면 None)
+pub fn maker_price(depth: &OrderBook, is_buy: bool) -> Option<f64> {
+    let levels = if is_buy { &depth.bids } else { &depth.asks };
+    Some(levels.first()?.price)
+}
+
+// 거래 기록용 상위 호가 (사후 분석용, 크기 제한을 위해 상위 N단계만 보관)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TopOfBook {
+    pub bids: Vec<Level>,
+    pub asks: Vec<Level>,
+    pub ms_t: i64,
+}
+
+// 거래소별 호가창에서 상위 levels단계만 복사 (키: 거래소 이름)
+pub fn snapshot_books(books: &HashMap<String, OrderBook>, levels: usize) -> HashMap<String, TopOfBook> {
+    books
+        .iter()
+        .map(|(exchange, book)| {
+            let top = TopOfBook {
+                bids: book.bids.iter().take(levels).copied().collect(),
+                asks: book.asks.iter().take(levels).copied().collect(),
+                ms_t: book.ms_t,
+            };
+            (exchange.clone(), top)
+        })
+        .collect()
+}
+
+// 호가창 저장 및 불균형/공정가 갱신
+async fn store_depth(
+    exchange_name: &str,
+    depth: DepthAllData,
+    shared_depth: &SharedDepth,
+    shared_imbalance: &SharedImbalance,
+    shared_fair_value: &SharedFairValue,
+    config: &StrategyConfig,
+    tap: &MarketTap,
+) {
+    tap.record(MarketEvent::depth(exchange_name, &depth)).await;
+    let book = OrderBook::from(&depth);
+    let imbalance = calculate_imbalance(&book, config.imbalance_levels);
+    let fair = fair_value(&book, config.fair_value_levels);
+    shared_depth.lock().await.insert(exchange_name.to_string(), book);
+
+    match fair {
+        Some(value) => {
+            shared_fair_value.lock().await.insert(exchange_name.to_string(), value);
+        }
+        None => {
+            shared_fair_value.lock().await.remove(exchange_name);
+        }
+    }
+
+    let mut imbalances = shared_imbalance.lock().await;
+    match imbalance {
+        Some(value) => {
+            imbalances.insert(exchange_name.to_string(), value);
+        }
+        None => {
+            imbalances.remove(exchange_name);
+        }
+    }
+}
+
+// Binance 결합 스트림의 호가 메시지 저장 (data 필드)
+pub async fn store_binance_depth_value(
+    data: Value,
+    sink: &DepthSink,
+    config: &StrategyConfig,
+    tap: &MarketTap,
+) -> Result<(), String> {
+    let update = serde_json::from_value::<BinanceDepthUpdate>(data).map_err(|e| e.to_string())?;
+    store_depth("Binance", update.into(), &sink.depth, &sink.imbalance, &sink.fair_value, config, tap).await;
+    Ok(())
+}
+
+// 종목별 호가 저장 위치 (한 연결에서 받은 호가를 심볼로 구분해 해당 종목의 공유 상태에 저장)
+#[derive(Clone)]
+pub struct DepthSink {
+    pub depth: SharedDepth,
+    pub imbalance: SharedImbalance,
+    pub fair_value: SharedFairValue,
+}
+
+// Bitmart 구독 응답 (구독 채널마다 하나씩 옴)
+#[derive(Debug, Deserialize)]
+struct SubscribeAck {
+    action: String,
+    group: String,
+    success: bool,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+// 구독 응답이면 (채널, 성공 여부 또는 오류 메시지), 아니면 None
+pub fn parse_subscribe_ack(text: &str) -> Option<(String, Result<(), String>)> {
+    let ack = serde_json::from_str::<SubscribeAck>(text).ok().filter(|ack| ack.action == "subscribe")?;
+    let result = if ack.success { Ok(()) } else { Err(ack.error.unwrap_or_else(|| "unknown error".to_string())) };
+    Some((ack.group, result))
+}
+
+// Bitmart 호가창 WebSocket (여러 종목을 한 연결에서 구독, 수신한 호가는 심볼별 저장 위치로 전달)
+// 오류 코드에 reconnect가 설정되어 있으면 재접속, 그 외 연결 종료 시 반환
+pub async fn fetch_bitmart_depth(
+    websocket_url: &str,
+    sinks: HashMap<String, DepthSink>,
+    config: Arc<StrategyConfig>,
+    tap: MarketTap,
+) {
+    let mut channels: Vec<String> = sinks
+        .keys()
+        .map(|symbol| bitmart_depth_channel(symbol, config.bitmart_depth_levels))
+        .collect();
+    channels.sort();
+    let mut subscriptions = Subscriptions::new();
+    subscriptions.register(bitmart_subscribe(&channels));
+
+    'connect: loop {
+        println!("Connecting to Bitmart depth WebSocket ({})...", channels.join(", "));
+
+        match connect_async(websocket_url).await {
+            Ok((ws_stream, _)) => {
+                println!("Connected to Bitmart depth WebSocket.");
+
+                let (mut write, mut read) = ws_stream.split();
+                // 증분 호가용 종목별 전체 호가 (재접속하면 새 스냅샷부터 다시 시작)
+                let mut books: HashMap<String, DepthAllData> = HashMap::new();
+
+                if let Err(e) = subscriptions.send_all(&mut write).await {
+                    eprintln!("Failed to send depth subscription message to Bitmart: {}", e);
+                    return;
+                }
+
+                while let Some(msg) = read.next().await {
+                    match msg {
+                        Ok(Message::Text(text)) => {
+                            // 채널별 구독 응답 (실패한 종목만 호가 없이 남고 나머지는 계속 수신)
+                            if let Some((channel, result)) = parse_subscribe_ack(&text) {
+                                match result {
+                                    Ok(()) => println!("Subscribed to Bitmart depth {}.", channel),
+                                    Err(e) => eprintln!("Bitmart depth subscription to {} failed: {}", channel, e),
+                                }
+                                continue;
+                            }
+                            let json = match serde_json::from_str::<Value>(&text) {
+                                Ok(json) => json,
+                                Err(e) => {
+                                    eprintln!("Error parsing depth from Bitmart: {}", e);
+                                    continue;
+                                }
+                            };
+                            let Some(updates) = parse_bitmart_depth(&json) else {
+                                // 호가 메시지가 아니면 오류 코드인지 확인 (그 외 제어 메시지는 무시)
+                                match bitmart_error_action(&json, &config.bitmart_error_actions, "Bitmart depth") {
+                                    Some(FeedAction::Reconnect) => {
+                                        tokio::time::sleep(RECONNECT_DELAY).await;
+                                        continue 'connect;
+                                    }
+                                    Some(FeedAction::Fatal) => return,
+                                    Some(FeedAction::Ignore) | None => {}
+                                }
+                                continue;
+                            };
+                            // 묶음 업데이트는 순서대로 모두 반영 (증분은 스냅샷을 받은 종목에만 적용)
+                            for update in updates {
+                                let depth = match update {
+                                    BitmartDepthUpdate::Snapshot(depth) => {
+                                        books.insert(depth.symbol.clone(), depth.clone());
+                                        depth
+                                    }
+                                    BitmartDepthUpdate::Delta(delta) => {
+                                        let Some(book) = books.get_mut(&delta.symbol) else {
+                                            continue;
+                                        };
+                                        apply_depth_delta(book, delta, config.bitmart_depth_levels as usize);
+                                        book.clone()
+                                    }
+                                };
+                                let Some(sink) = sinks.get(&depth.symbol) else {
+                                    continue;
+                                };
+                                store_depth("Bitmart", depth, &sink.depth, &sink.imbalance, &sink.fair_value, &config, &tap).await;
+                            }
+                        }
+                        Ok(Message::Ping(payload)) => {
+                            if let Err(e) = write.send(Message::Pong(payload)).await {
+                                eprintln!("Failed to send pong to Bitmart depth: {}", e);
+                                break;
+                            }
+                        }
+                        Ok(Message::Close(_)) => break,
+                        Err(e) => {
+                            eprintln!("WebSocket error from Bitmart depth: {}", e);
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("Failed to connect to Bitmart depth WebSocket: {}", e);
+            }
+        }
+        return;
+    }
+}
+
+// Binance 호가창 WebSocket (depth20 부분 호가)
+pub async fn fetch_binance_depth(
+    websocket_url: &str,
+    shared_depth: SharedDepth,
+    shared_imbalance: SharedImbalance,
+    shared_fair_value: SharedFairValue,
+    config: Arc<StrategyConfig>,
+    tap: MarketTap,
+) {
+    println!("Connecting to Binance depth WebSocket...");
+
+    match connect_async(websocket_url).await {
+        Ok((ws_stream, _)) => {
+            println!("Connected to Binance depth WebSocket.");
+
+            let (mut write, mut read) = ws_stream.split();
+
+            while let Some(msg) = read.next().await {
+                match msg {
+                    Ok(Message::Text(text)) => {
+                        match serde_json::from_str::<BinanceDepthUpdate>(&text) {
+                            Ok(update) => {
+                                store_depth("Binance", update.into(), &shared_depth, &shared_imbalance, &shared_fair_value, &config, &tap).await;
+                            }
+                            Err(e) => eprintln!("Error parsing depth from Binance: {}", e),
+                        }
+                    }
+                    Ok(Message::Ping(payload)) => {
+                        if let Err(e) = write.send(Message::Pong(payload)).await {
+                            eprintln!("Failed to send pong to Binance depth: {}", e);
+                            break;
+                        }
+                    }
+                    Ok(Message::Close(_)) => break,
+                    Err(e) => {
+                        eprintln!("WebSocket error from Binance depth: {}", e);
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("Failed to connect to Binance depth WebSocket: {}", e);
+        }
+    }
+}
+
+// Binance 호가창 수신 방식
+// Partial: depth20 부분 호가 (매 메시지가 상위 20단계 전체라 순서 검증 불필요)
+// Diff: 증분 호가 + REST 스냅샷 (업데이트 ID로 누락을 검증하고 누락 시 재동기화)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BinanceDepthMode {
+    Partial,
+    Diff,
+}
+
+// 증분 호가에서 공유 호가창으로 내보내는 단계 수 (부분 호가와 동일)
+const BINANCE_DIFF_PUBLISHED_LEVELS: usize = 20;
+// REST 스냅샷 호가 단계 수
+const BINANCE_SNAPSHOT_LIMIT: u32 = 1000;
+
+// Binance 증분 호가 메시지 (U: 첫 업데이트 ID, u: 마지막 업데이트 ID, pu: 직전 메시지의 u)
+#[derive(Debug, Clone, Deserialize)]
+pub struct BinanceDiffUpdate {
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "T")]
+    pub transaction_time: i64,
+    #[serde(rename = "U")]
+    pub first_update_id: u64,
+    #[serde(rename = "u")]
+    pub final_update_id: u64,
+    #[serde(rename = "pu")]
+    pub prev_final_update_id: u64,
+    #[serde(rename = "b")]
+    pub bids: Vec<[String; 2]>,
+    #[serde(rename = "a")]
+    pub asks: Vec<[String; 2]>,
+}
+
+// Binance REST 호가 스냅샷 (GET /fapi/v1/depth)
+#[derive(Debug, Clone, Deserialize)]
+pub struct BinanceDepthSnapshot {
+    #[serde(rename = "lastUpdateId")]
+    pub last_update_id: u64,
+    #[serde(rename = "T", default)]
+    pub transaction_time: i64,
+    pub bids: Vec<[String; 2]>,
+    pub asks: Vec<[String; 2]>,
+}
+
+// 증분 호가 처리 결과
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffOutcome {
+    // 스냅샷 대기 중이라 보관
+    Buffered,
+    // 스냅샷보다 오래된 업데이트라 버림
+    Stale,
+    Applied,
+    // 업데이트 ID가 이어지지 않음: 호가창을 비우고 새 스냅샷으로 재동기화 필요
+    Gap,
+}
+
+// 증분 호가로 유지하는 Binance 호가창 (스냅샷 + 순서 검증된 업데이트)
+// 절차: 스트림 업데이트를 보관 -> 스냅샷 수신 -> u < lastUpdateId 버림
+// -> 첫 업데이트는 U <= lastUpdateId <= u 이어야 함 -> 이후 pu가 직전 u와 같아야 함 (아니면 재동기화)
+#[derive(Debug, Clone)]
+pub struct DiffBook {
+    symbol: String,
+    snapshot_id: Option<u64>,
+    last_final_id: Option<u64>,
+    bids: Vec<DepthAllItem>, // 가격 내림차순
+    asks: Vec<DepthAllItem>, // 가격 오름차순
+    ms_t: i64,
+    pending: Vec<BinanceDiffUpdate>,
+}
+
+impl DiffBook {
+    pub fn new(symbol: &str) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            snapshot_id: None,
+            last_final_id: None,
+            bids: Vec::new(),
+            asks: Vec::new(),
+            ms_t: 0,
+            pending: Vec::new(),
+        }
+    }
+
+    // 스냅샷 기준으로 동기화되어 호가창을 쓸 수 있는지
+    pub fn is_synced(&self) -> bool {
+        self.snapshot_id.is_some()
+    }
+
+    // 호가창을 비우고 다음 스냅샷까지 업데이트를 보관
+    pub fn reset(&mut self) {
+        self.snapshot_id = None;
+        self.last_final_id = None;
+        self.bids.clear();
+        self.asks.clear();
+        self.pending.clear();
+    }
+
+    // 스냅샷 적용 후 보관한 업데이트를 순서대로 반영 (누락이 있으면 Gap)
+    pub fn apply_snapshot(&mut self, snapshot: BinanceDepthSnapshot) -> DiffOutcome {
+        let to_items = |levels: Vec<[String; 2]>| {
+            levels.into_iter().map(|[price, vol]| DepthAllItem { price, vol }).collect::<Vec<_>>()
+        };
+        self.bids = to_items(snapshot.bids);
+        self.asks = to_items(snapshot.asks);
+        self.ms_t = snapshot.transaction_time;
+        self.snapshot_id = Some(snapshot.last_update_id);
+        self.last_final_id = None;
+        let mut outcome = DiffOutcome::Applied;
+        for update in std::mem::take(&mut self.pending) {
+            match self.on_update(update) {
+                DiffOutcome::Gap => return DiffOutcome::Gap,
+                DiffOutcome::Applied => outcome = DiffOutcome::Applied,
+                _ => {}
+            }
+        }
+        outcome
+    }
+
+    // 증분 업데이트 처리
+    pub fn on_update(&mut self, update: BinanceDiffUpdate) -> DiffOutcome {
+        let Some(snapshot_id) = self.snapshot_id else {
+            self.pending.push(update);
+            return DiffOutcome::Buffered;
+        };
+        match self.last_final_id {
+            None => {
+                if update.final_update_id < snapshot_id {
+                    return DiffOutcome::Stale;
+                }
+                if update.first_update_id > snapshot_id {
+                    return DiffOutcome::Gap;
+                }
+            }
+            Some(last) if update.prev_final_update_id != last => return DiffOutcome::Gap,
+            Some(_) => {}
+        }
+        for [price, vol] in update.bids {
+            apply_depth_level(&mut self.bids, price, vol, true);
+        }
+        for [price, vol] in update.asks {
+            apply_depth_level(&mut self.asks, price, vol, false);
+        }
+        self.last_final_id = Some(update.final_update_id);
+        self.ms_t = update.transaction_time;
+        DiffOutcome::Applied
+    }
+
+    // 상위 levels 단계 호가 (공유 호가창 저장용)
+    pub fn top(&self, levels: usize) -> DepthAllData {
+        DepthAllData {
+            symbol: self.symbol.clone(),
+            bids: self.bids.iter().take(levels).cloned().collect(),
+            asks: self.asks.iter().take(levels).cloned().collect(),
+            ms_t: self.ms_t,
+        }
+    }
+}
+
+// Binance REST 호가 스냅샷 요청
+pub async fn fetch_binance_depth_snapshot(
+    client: &reqwest::Client,
+    rest_url: &str,
+    symbol: &str,
+) -> Result<BinanceDepthSnapshot, String> {
+    let url = format!("{}/fapi/v1/depth?symbol={}&limit={}", rest_url, symbol, BINANCE_SNAPSHOT_LIMIT);
+    let response = client.get(&url).send().await.map_err(|e| e.to_string())?;
+    let status = response.status();
+    let text = response.text().await.map_err(|e| e.to_string())?;
+    if !status.is_success() {
+        return Err(format!("HTTP {}: {}", status, text));
+    }
+    serde_json::from_str(&text).map_err(|e| format!("{}: {}", e, text))
+}
+
+// Binance 증분 호가 WebSocket (스냅샷으로 동기화하고 누락이 감지되면 새 스냅샷으로 재동기화)
+// 스냅샷을 받는 동안에도 스트림 업데이트를 계속 보관
+pub async fn fetch_binance_diff_depth(
+    websocket_url: &str,
+    rest_url: &str,
+    sink: DepthSink,
+    config: Arc<StrategyConfig>,
+    tap: MarketTap,
+) {
+    println!("Connecting to Binance diff depth WebSocket...");
+    let (ws_stream, _) = match connect_async(websocket_url).await {
+        Ok(connection) => connection,
+        Err(e) => {
+            eprintln!("Failed to connect to Binance diff depth WebSocket: {}", e);
+            return;
+        }
+    };
+    println!("Connected to Binance diff depth WebSocket.");
+    let (mut write, mut read) = ws_stream.split();
+
+    let client = reqwest::Client::new();
+    let mut book = DiffBook::new(&config.symbol);
+    let mut snapshot_task: Option<tokio::task::JoinHandle<Result<BinanceDepthSnapshot, String>>> = None;
+    loop {
+        if !book.is_synced() && snapshot_task.is_none() {
+            let (client, rest_url, symbol) = (client.clone(), rest_url.to_string(), config.symbol.clone());
+            snapshot_task = Some(tokio::spawn(async move { fetch_binance_depth_snapshot(&client, &rest_url, &symbol).await }));
+        }
+        let snapshot = async {
+            match snapshot_task.as_mut() {
+                Some(task) => task.await,
+                None => std::future::pending().await,
+            }
+        };
+        let outcome = tokio::select! {
+            result = snapshot => {
+                snapshot_task = None;
+                match result {
+                    Ok(Ok(snapshot)) => {
+                        let snapshot_id = snapshot.last_update_id;
+                        let outcome = book.apply_snapshot(snapshot);
+                        if outcome != DiffOutcome::Gap {
+                            println!("[Depth] Binance book synced at update {}.", snapshot_id);
+                        }
+                        outcome
+                    }
+                    Ok(Err(e)) => {
+                        eprintln!("[Depth] Binance depth snapshot failed: {}. Retrying.", e);
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                        DiffOutcome::Buffered
+                    }
+                    Err(e) => {
+                        eprintln!("[Depth] Binance depth snapshot task failed: {}", e);
+                        DiffOutcome::Buffered
+                    }
+                }
+            }
+            msg = read.next() => {
+                match msg {
+                    Some(Ok(Message::Text(text))) => match serde_json::from_str::<BinanceDiffUpdate>(&text) {
+                        Ok(update) => {
+                            let ids = (update.first_update_id, update.final_update_id, update.prev_final_update_id);
+                            let outcome = book.on_update(update);
+                            if outcome == DiffOutcome::Gap {
+                                eprintln!("[Depth] Binance diff out of sequence (U {}, u {}, pu {}).", ids.0, ids.1, ids.2);
+                            }
+                            outcome
+                        }
+                        Err(e) => {
+                            eprintln!("Error parsing diff depth from Binance: {}", e);
+                            continue;
+                        }
+                    },
+                    Some(Ok(Message::Ping(payload))) => {
+                        if let Err(e) = write.send(Message::Pong(payload)).await {
+                            eprintln!("Failed to send pong to Binance diff depth: {}", e);
+                            return;
+                        }
+                        continue;
+                    }
+                    Some(Ok(Message::Close(_))) | None => return,
+                    Some(Err(e)) => {
+                        eprintln!("WebSocket error from Binance diff depth: {}", e);
+                        return;
+                    }
+                    Some(Ok(_)) => continue,
+                }
+            }
+        };
+        match outcome {
+            DiffOutcome::Applied => {
+                let depth = book.top(BINANCE_DIFF_PUBLISHED_LEVELS);
+                store_depth("Binance", depth, &sink.depth, &sink.imbalance, &sink.fair_value, &config, &tap).await;
+            }
+            DiffOutcome::Gap => {
+                // 누락된 업데이트가 있는 호가창은 거래에 쓰지 않음 (재동기화까지 공유 호가창에서 제거)
+                eprintln!("[Depth] Binance book gap detected. Resyncing from a new snapshot.");
+                book.reset();
+                sink.depth.lock().await.remove("Binance");
+                sink.imbalance.lock().await.remove("Binance");
+                sink.fair_value.lock().await.remove("Binance");
+            }
+            DiffOutcome::Buffered | DiffOutcome::Stale => {}
+        }
+    }
+}
@@ -0,0 +1,359 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+use tokio::sync::{broadcast, mpsc, Notify};
+use tokio::task::JoinHandle;
+use crate::depth::{DepthAllData, DepthAllItem};
+
+// 이벤트 로그 형식 버전
+// 필드 구조를 바꾸면 버전을 올리고 read_events에서 이전 버전 처리를 추가할 것
+pub const EVENT_LOG_VERSION: u32 = 1;
+const EVENT_LOG_FORMAT: &str = "btrap-market-events";
+
+// 녹화/재생용 시장 이벤트 (ts: epoch ms)
+// 호가는 [가격, 잔량] 문자열 그대로 저장해 재생 시 원본과 동일하게 복원
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MarketEvent {
+    Trade { venue: String, symbol: String, price: f64, ts: i64 },
+    Depth { venue: String, symbol: String, bids: Vec<[String; 2]>, asks: Vec<[String; 2]>, ts: i64 },
+}
+
+impl MarketEvent {
+    // 호가창 스냅샷 이벤트
+    pub fn depth(venue: &str, depth: &DepthAllData) -> Self {
+        let levels = |items: &[DepthAllItem]| {
+            items
+                .iter()
+                .map(|item| [item.price.clone(), item.vol.clone()])
+                .collect()
+        };
+        MarketEvent::Depth {
+            venue: venue.to_string(),
+            symbol: depth.symbol.clone(),
+            bids: levels(&depth.bids),
+            asks: levels(&depth.asks),
+            ts: depth.ms_t,
+        }
+    }
+}
+
+// 로그 첫 줄 (형식 이름과 버전)
+#[derive(Debug, Serialize, Deserialize)]
+struct EventLogHeader {
+    format: String,
+    version: u32,
+}
+
+// JSON Lines 이벤트 로그 작성기 (첫 줄은 헤더, 이후 한 줄에 이벤트 하나)
+pub struct EventLogWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> EventLogWriter<W> {
+    // 헤더를 쓰고 작성기 생성
+    pub fn new(mut writer: W) -> Result<Self, String> {
+        let header = EventLogHeader { format: EVENT_LOG_FORMAT.to_string(), version: EVENT_LOG_VERSION };
+        write_line(&mut writer, &header)?;
+        Ok(Self { writer })
+    }
+
+    // 이미 헤더가 있는 로그에 이어 쓰기
+    fn append(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn write(&mut self, event: &MarketEvent) -> Result<(), String> {
+        write_line(&mut self.writer, event)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+fn write_line<W: Write, T: Serialize>(writer: &mut W, value: &T) -> Result<(), String> {
+    let line = serde_json::to_string(value).map_err(|e| format!("Failed to serialize event: {}", e))?;
+    writeln!(writer, "{}", line).map_err(|e| format!("Failed to write event: {}", e))
+}
+
+// 이벤트 로그 읽기 (헤더 형식/버전 확인)
+pub fn read_events<R: BufRead>(reader: R) -> Result<Vec<MarketEvent>, String> {
+    let mut lines = reader.lines();
+    let header = lines
+        .next()
+        .ok_or_else(|| "Event log is empty".to_string())?
+        .map_err(|e| format!("Failed to read event log: {}", e))?;
+    check_header(&header)?;
+
+    let mut events = Vec::new();
+    for (index, line) in lines.enumerate() {
+        let line = line.map_err(|e| format!("Failed to read event log: {}", e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event = serde_json::from_str::<MarketEvent>(&line)
+            .map_err(|e| format!("Invalid event on line {}: {}", index + 2, e))?;
+        events.push(event);
+    }
+    Ok(events)
+}
+
+fn check_header(line: &str) -> Result<(), String> {
+    let header = serde_json::from_str::<EventLogHeader>(line)
+        .map_err(|e| format!("Invalid event log header: {}", e))?;
+    if header.format != EVENT_LOG_FORMAT {
+        return Err(format!("Unknown event log format: {}", header.format));
+    }
+    if header.version != EVENT_LOG_VERSION {
+        return Err(format!(
+            "Event log version {} is not supported (expected {})",
+            header.version, EVENT_LOG_VERSION
+        ));
+    }
+    Ok(())
+}
+
+// 녹화 대기열이 가득 찼을 때 처리 방식
+// drop_oldest: 가장 오래된 이벤트를 버리고 바로 반환 (피드가 디스크 지연에 막히지 않음)
+// block: 자리가 날 때까지 대기 (이벤트를 잃지 않지만 디스크가 느리면 피드도 느려짐)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueOverflow {
+    DropOldest,
+    Block,
+}
+
+enum EventSender {
+    DropOldest(broadcast::Sender<MarketEvent>),
+    Block(mpsc::Sender<MarketEvent>),
+}
+
+enum EventReceiver {
+    DropOldest(broadcast::Receiver<MarketEvent>),
+    Block(mpsc::Receiver<MarketEvent>),
+}
+
+impl EventReceiver {
+    // 다음 이벤트 (송신 측이 모두 닫히면 None, 대기열에서 버려진 이벤트는 개수만 로그)
+    async fn recv(&mut self) -> Option<MarketEvent> {
+        match self {
+            EventReceiver::DropOldest(receiver) => loop {
+                match receiver.recv().await {
+                    Ok(event) => return Some(event),
+                    Err(RecvError::Lagged(skipped)) => log_dropped(skipped),
+                    Err(RecvError::Closed) => return None,
+                }
+            },
+            EventReceiver::Block(receiver) => receiver.recv().await,
+        }
+    }
+
+    // 대기 없이 이미 쌓인 이벤트만 가져옴
+    fn try_recv(&mut self) -> Option<MarketEvent> {
+        match self {
+            EventReceiver::DropOldest(receiver) => loop {
+                match receiver.try_recv() {
+                    Ok(event) => return Some(event),
+                    Err(TryRecvError::Lagged(skipped)) => log_dropped(skipped),
+                    Err(_) => return None,
+                }
+            },
+            EventReceiver::Block(receiver) => receiver.try_recv().ok(),
+        }
+    }
+}
+
+fn log_dropped(skipped: u64) {
+    eprintln!("[Recorder] Event queue full, dropped {} oldest events", skipped);
+}
+
+// 실시간 피드 녹화기 (여러 태스크에서 공유)
+// 기록은 대기열에 넣고 바로 반환하며, 파일 쓰기는 백그라운드 태스크가 모아서 처리하고 주기적으로 flush
+pub struct EventRecorder {
+    sender: EventSender,
+    shutdown: Arc<Notify>,
+    writer_task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl EventRecorder {
+    // 로그 파일 열기 (새 파일이면 헤더 작성, 기존 파일이면 버전 확인 후 이어 쓰기) 후 쓰기 태스크 시작
+    // capacity: 대기열 크기, flush_interval: 파일에 반영하는 주기
+    pub fn open(path: &str, capacity: usize, overflow: QueueOverflow, flush_interval: Duration) -> Result<Self, String> {
+        let existing = File::open(path).ok().and_then(|file| BufReader::new(file).lines().next());
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("Failed to open {}: {}", path, e))?;
+        let writer = match existing {
+            Some(header) => {
+                check_header(&header.map_err(|e| format!("Failed to read {}: {}", path, e))?)?;
+                EventLogWriter::append(BufWriter::new(file))
+            }
+            None => EventLogWriter::new(BufWriter::new(file))?,
+        };
+        let (sender, receiver) = match overflow {
+            QueueOverflow::DropOldest => {
+                let (sender, receiver) = broadcast::channel(capacity);
+                (EventSender::DropOldest(sender), EventReceiver::DropOldest(receiver))
+            }
+            QueueOverflow::Block => {
+                let (sender, receiver) = mpsc::channel(capacity);
+                (EventSender::Block(sender), EventReceiver::Block(receiver))
+            }
+        };
+        let shutdown = Arc::new(Notify::new());
+        let writer_task = tokio::spawn(write_events(writer, receiver, flush_interval, Arc::clone(&shutdown)));
+        Ok(Self { sender, shutdown, writer_task: Mutex::new(Some(writer_task)) })
+    }
+
+    // 이벤트를 대기열에 넣음 (쓰기 태스크가 종료됐으면 버림)
+    pub async fn record(&self, event: MarketEvent) {
+        match &self.sender {
+            EventSender::DropOldest(sender) => {
+                let _ = sender.send(event);
+            }
+            EventSender::Block(sender) => {
+                let _ = sender.send(event).await;
+            }
+        }
+    }
+
+    // 종료: 대기열에 남은 이벤트를 모두 쓰고 flush한 뒤 쓰기 태스크 종료 (이후 기록은 버림)
+    pub async fn close(&self) {
+        let Some(writer_task) = self.writer_task.lock().unwrap().take() else {
+            return;
+        };
+        self.shutdown.notify_one();
+        if let Err(e) = writer_task.await {
+            eprintln!("[Recorder] Writer task failed: {}", e);
+        }
+    }
+}
+
+// 쓰기 태스크: 도착한 이벤트를 한꺼번에 버퍼에 쓰고 flush_interval마다, 그리고 종료 시 flush
+async fn write_events(
+    mut writer: EventLogWriter<BufWriter<File>>,
+    mut receiver: EventReceiver,
+    flush_interval: Duration,
+    shutdown: Arc<Notify>,
+) {
+    let mut flush = tokio::time::interval(flush_interval);
+    flush.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            event = receiver.recv() => match event {
+                Some(event) => {
+                    write_event(&mut writer, &event);
+                    while let Some(event) = receiver.try_recv() {
+                        write_event(&mut writer, &event);
+                    }
+                }
+                None => break,
+            },
+            _ = flush.tick() => flush_events(&mut writer),
+            _ = shutdown.notified() => {
+                while let Some(event) = receiver.try_recv() {
+                    write_event(&mut writer, &event);
+                }
+                break;
+            }
+        }
+    }
+    flush_events(&mut writer);
+}
+
+// 실패는 로그만 남김
+fn write_event(writer: &mut EventLogWriter<BufWriter<File>>, event: &MarketEvent) {
+    if let Err(e) = writer.write(event) {
+        eprintln!("[Recorder] {}", e);
+    }
+}
+
+fn flush_events(writer: &mut EventLogWriter<BufWriter<File>>) {
+    if let Err(e) = writer.writer.flush() {
+        eprintln!("[Recorder] Failed to flush event log: {}", e);
+    }
+}
+
+// 최근 시장 이벤트 링 버퍼 (사후 분석용, 가득 차면 가장 오래된 이벤트부터 버림)
+// 계속 녹화하지 않고 패닉이나 서킷 브레이커 발동 직전 상황만 파일로 남김
+pub struct TickRing {
+    capacity: usize,
+    dump_dir: String,
+    events: Mutex<VecDeque<MarketEvent>>,
+}
+
+impl TickRing {
+    pub fn new(capacity: usize, dump_dir: &str) -> Self {
+        Self { capacity, dump_dir: dump_dir.to_string(), events: Mutex::new(VecDeque::with_capacity(capacity)) }
+    }
+
+    pub fn push(&self, event: MarketEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut events = self.events.lock().unwrap_or_else(|e| e.into_inner());
+        if events.len() == self.capacity {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+
+    // 보관 중인 이벤트 (오래된 순)
+    pub fn snapshot(&self) -> Vec<MarketEvent> {
+        self.events.lock().unwrap_or_else(|e| e.into_inner()).iter().cloned().collect()
+    }
+
+    // 보관 중인 이벤트를 dump_dir의 시각별 파일로 기록 (이벤트 로그 형식이라 replay로 재생 가능)
+    // 패닉 중에도 호출되므로 오염된 잠금도 그대로 사용, 기록한 파일 경로 반환
+    pub fn dump(&self, reason: &str) -> Result<String, String> {
+        std::fs::create_dir_all(&self.dump_dir).map_err(|e| format!("Failed to create {}: {}", self.dump_dir, e))?;
+        let slug: String = reason.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' }).collect();
+        let file_name = format!("ticks-{}-{}.jsonl", Utc::now().format("%Y%m%d-%H%M%S%.3f"), slug);
+        let path = Path::new(&self.dump_dir).join(file_name).to_string_lossy().to_string();
+        let file = File::create(&path).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+        let mut writer = EventLogWriter::new(BufWriter::new(file))?;
+        for event in self.snapshot() {
+            writer.write(&event)?;
+        }
+        writer.into_inner().flush().map_err(|e| format!("Failed to write {}: {}", path, e))?;
+        Ok(path)
+    }
+}
+
+// 피드의 시장 이벤트 기록 대상 (녹화기와 최근 이벤트 링, 둘 다 선택)
+#[derive(Clone, Default)]
+pub struct MarketTap {
+    pub recorder: Option<Arc<EventRecorder>>,
+    pub ring: Option<Arc<TickRing>>,
+}
+
+impl MarketTap {
+    pub async fn record(&self, event: MarketEvent) {
+        if let Some(ring) = &self.ring {
+            ring.push(event.clone());
+        }
+        if let Some(recorder) = &self.recorder {
+            recorder.record(event).await;
+        }
+    }
+
+    // 최근 이벤트를 파일로 기록 (링이 없으면 무시, 실패는 로그만 남김)
+    pub fn dump_ring(&self, reason: &str) {
+        let Some(ring) = &self.ring else {
+            return;
+        };
+        match ring.dump(reason) {
+            Ok(path) => eprintln!("[Recorder] Dumped recent market events to {} ({})", path, reason),
+            Err(e) => eprintln!("[Recorder] Failed to dump recent market events: {}", e),
+        }
+    }
+}
@@ -0,0 +1,387 @@
+use futures_util::{stream::StreamExt, SinkExt};
+use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use chrono::{DateTime, Utc};
+use crate::context::{PriceTick, TradingContext};
+use crate::event_log::MarketEvent;
+use crate::depth::{store_binance_depth_value, DepthSink};
+use crate::handle_price::{
+    binance_message_key, bitmart_error_action, extract_bitmart_trades, route_combined, BinancePayload, BinanceStreamType,
+    CombinedEnvelope, CombinedRoute, FeedAction,
+};
+use crate::safety::FlattenGuard;
+use crate::strategy::execute_trade;
+use crate::subscriptions::{bitmart_subscribe, Subscriptions};
+use crate::volatility::{GapVelocity, RollingVolatility};
+
+// 두 거래소 가격이 모두 정렬 구간 안에 수신되었는지 확인 (window_ms가 0이면 항상 true)
+pub fn prices_aligned(binance: &PriceTick, bitmart: &PriceTick, now: DateTime<Utc>, window_ms: u64) -> bool {
+    if window_ms == 0 {
+        return true;
+    }
+    let window = chrono::Duration::milliseconds(window_ms as i64);
+    now - binance.received_at <= window && now - bitmart.received_at <= window
+}
+
+// 전략 판단 주기 제한 (호가/가격은 매 메시지 갱신하고 판단만 최소 간격으로 합침)
+// 판단은 한 번에 하나만 실행: 주문 집행 중 들어온 요청은 기다리지 않고 재판단 표시만 남기며
+// 진행 중인 판단이 끝나면 그 시점 최신 가격으로 한 번 더 판단 (주문 전에 복사한 오래된 가격으로 판단하지 않음)
+pub struct EvaluationThrottle {
+    min_interval: Duration,
+    pending: Notify,
+    updates: AtomicU64,
+    evaluations: AtomicU64,
+    running: AtomicBool,
+    rerun: AtomicBool,
+}
+
+impl EvaluationThrottle {
+    // min_interval_ms: 0이면 제한 없음 (업데이트마다 바로 판단)
+    pub fn new(min_interval_ms: u64) -> Self {
+        Self {
+            min_interval: Duration::from_millis(min_interval_ms),
+            pending: Notify::new(),
+            updates: AtomicU64::new(0),
+            evaluations: AtomicU64::new(0),
+            running: AtomicBool::new(false),
+            rerun: AtomicBool::new(false),
+        }
+    }
+
+    pub fn is_throttled(&self) -> bool {
+        !self.min_interval.is_zero()
+    }
+
+    pub fn min_interval(&self) -> Duration {
+        self.min_interval
+    }
+
+    // 판단 요청 (대기 중인 요청이 있으면 하나로 합쳐짐, 제한이 없으면 횟수만 기록)
+    pub fn request(&self) {
+        self.updates.fetch_add(1, Ordering::Relaxed);
+        if self.is_throttled() {
+            self.pending.notify_one();
+        }
+    }
+
+    // 판단 요청이 들어올 때까지 대기
+    pub async fn next(&self) {
+        self.pending.notified().await;
+    }
+
+    // 판단 시작 (다른 판단이 진행 중이면 재판단 표시만 남기고 false)
+    // 재판단 표시를 먼저 남겨야 진행 중인 판단이 끝나는 순간과 겹쳐도 요청이 사라지지 않음
+    pub fn try_begin(&self) -> bool {
+        self.rerun.store(true, Ordering::SeqCst);
+        if self.running.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+        self.rerun.store(false, Ordering::SeqCst);
+        true
+    }
+
+    // 판단 종료 (진행 중 재판단 요청이 있었고 다시 판단을 맡았으면 true, 호출 측이 최신 가격으로 다시 판단)
+    pub fn finish(&self) -> bool {
+        self.running.store(false, Ordering::SeqCst);
+        self.rerun.swap(false, Ordering::SeqCst) && !self.running.swap(true, Ordering::SeqCst)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    pub fn record_evaluation(&self) {
+        self.evaluations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // 누적 (판단 요청 수, 실제 판단 수)
+    pub fn counts(&self) -> (u64, u64) {
+        (self.updates.load(Ordering::Relaxed), self.evaluations.load(Ordering::Relaxed))
+    }
+}
+
+// 최신 가격으로 전략 판단 (다른 판단이 진행 중이면 재판단만 요청하고 바로 반환해 피드를 막지 않음)
+// 판단마다 shared_prices에서 가격을 새로 읽으므로 주문 집행이 늦어져도 끝난 뒤에는 그 시점 가격으로 판단
+async fn evaluate_latest(ctx: &TradingContext) {
+    if !ctx.evaluation.try_begin() {
+        return;
+    }
+    loop {
+        let latest = {
+            let prices = ctx.shared_prices.lock().await;
+            (prices.get("Binance").copied(), prices.get("Bitmart").copied())
+        };
+        if let (Some(binance), Some(bitmart)) = latest {
+            if prices_aligned(&binance, &bitmart, ctx.clock.now(), ctx.config.price_alignment_ms) {
+                let imbalances = ctx.shared_imbalance.lock().await.clone();
+                ctx.evaluation.record_evaluation();
+                // 주문 조건 확인 및 실행
+                execute_trade(ctx, binance, bitmart, &imbalances).await;
+            }
+        }
+        if !ctx.evaluation.finish() {
+            return;
+        }
+    }
+}
+
+// 판단 주기 제한 시 판단 태스크: 요청이 오면 최신 가격으로 판단 후 최소 간격만큼 대기
+// 대기 중 들어온 요청은 하나로 합쳐져 다음 판단에서 그 시점 최신 가격을 사용
+pub async fn run_throttled_evaluations(ctx: TradingContext) {
+    loop {
+        ctx.evaluation.next().await;
+        evaluate_latest(&ctx).await;
+        tokio::time::sleep(ctx.evaluation.min_interval()).await;
+    }
+}
+
+// 가격 업데이트 핸들러
+// 잠금 순서 (TradingContext 참고): shared_prices는 가격 기록과 파생 지표 갱신 동안만 잡고, 판단 전에 놓음
+pub async fn handle_price_update(exchange_name: &str, new_price: f64, ctx: &TradingContext) {
+    let mut prices = ctx.shared_prices.lock().await; // 비동기 Mutex 잠금
+
+    // 현재 거래소 가격 업데이트
+    let now = ctx.clock.now();
+    prices.insert(exchange_name.to_string(), PriceTick { price: new_price, received_at: now });
+
+    ctx.market_tap
+        .record(MarketEvent::Trade {
+            venue: exchange_name.to_string(),
+            symbol: ctx.config.symbol.clone(),
+            price: new_price,
+            ts: now.timestamp_millis(),
+        })
+        .await;
+
+    // Binance 체결가로 실현 변동성 갱신
+    if exchange_name == "Binance" {
+        ctx.shared_volatility
+            .lock()
+            .await
+            .entry(ctx.config.symbol.clone())
+            .or_insert_with(|| RollingVolatility::new(ctx.config.volatility_window))
+            .update(new_price);
+        if ctx.config.min_volatility.is_some() {
+            ctx.entry_volatility
+                .lock()
+                .await
+                .entry(ctx.config.symbol.clone())
+                .or_insert_with(|| RollingVolatility::new(ctx.config.min_volatility_window))
+                .update(new_price);
+        }
+    }
+
+    // 두 거래소의 가격 비교 (한쪽 피드가 지연되면 갭을 무시)
+    if let (Some(&binance), Some(&bitmart)) = (prices.get("Binance"), prices.get("Bitmart")) {
+        if !prices_aligned(&binance, &bitmart, now, ctx.config.price_alignment_ms) {
+            return;
+        }
+        // 최근 체결가 기준 갭으로 변화 속도 갱신
+        if bitmart.price > 0.0 {
+            ctx.shared_gap_velocity
+                .lock()
+                .await
+                .entry(ctx.config.symbol.clone())
+                .or_insert_with(|| GapVelocity::new(ctx.config.gap_velocity_window_ms))
+                .update(now.timestamp_millis(), (binance.price - bitmart.price) / bitmart.price * 100.0);
+        }
+        // 주문 집행 중 다른 피드의 가격 갱신을 막지 않도록 판단 전에 가격 잠금 해제
+        drop(prices);
+        // 판단 주기 제한 시 판단 태스크가 최신 가격으로 판단
+        ctx.evaluation.request();
+        if !ctx.evaluation.is_throttled() {
+            evaluate_latest(ctx).await;
+        }
+    }
+}
+
+// 재접속 대기 시간
+pub const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+// WebSocket에서 가격 가져오기
+// Bitmart 오류 코드에 reconnect가 설정되어 있으면 재접속, 그 외 연결 종료 시 반환
+pub async fn fetch_price(websocket_url: &str, exchange_name: &str, ctx: TradingContext) {
+    // 이 태스크가 패닉하면 열린 포지션 정리 시도 (dry run은 실제 포지션이 없으므로 제외)
+    let _flatten_guard = (!ctx.config.dry_run)
+        .then(|| FlattenGuard::new(Arc::clone(&ctx.order), Arc::clone(&ctx.shared_state), &ctx.config.symbol));
+
+    // Binance는 접속 주소에 스트림이 포함되어 구독 메시지가 없음
+    let mut subscriptions = Subscriptions::new();
+    if exchange_name == "Bitmart" {
+        subscriptions.register(bitmart_subscribe(&[format!("futures/trade:{}", ctx.config.symbol)]));
+    }
+
+    'connect: loop {
+        println!("Connecting to {} WebSocket...", exchange_name);
+
+        match connect_async(websocket_url).await {
+            Ok((ws_stream, _)) => {
+                println!("Connected to {} WebSocket.", exchange_name);
+
+                let (mut write, mut read) = ws_stream.split();
+
+                if let Err(e) = subscriptions.send_all(&mut write).await {
+                    eprintln!("Failed to send subscription message to {}: {}", exchange_name, e);
+                    return;
+                }
+
+                while let Some(msg) = read.next().await {
+                    match msg {
+                        Ok(Message::Text(text)) => {
+                            if exchange_name == "Binance" {
+                                let stream_type = ctx.config.binance_stream;
+                                // 가격 필드 경로가 설정되어 있으면 스트림 구조체 대신 경로로 추출
+                                if let Some(path) = &ctx.config.binance_price_path {
+                                    match serde_json::from_str::<Value>(&text) {
+                                        Ok(json) => match path.extract(&json) {
+                                            Some(new_price) => {
+                                                if !ctx.dedup.is_duplicate(exchange_name, binance_message_key(&json, new_price)) {
+                                                    handle_price_update(exchange_name, new_price, &ctx).await;
+                                                }
+                                            }
+                                            None => eprintln!("No price at '{}' in Binance message: {}", path, text),
+                                        },
+                                        Err(e) => eprintln!("Error parsing JSON from {}: {}", exchange_name, e),
+                                    }
+                                    continue;
+                                }
+                                match BinancePayload::parse(stream_type, &text) {
+                                    Ok(payload) => {
+                                        // 재접속 등으로 다시 온 업데이트는 건너뜀
+                                        if let Some(new_price) = payload.price() {
+                                            if !ctx.dedup.is_duplicate(exchange_name, payload.key()) {
+                                                handle_price_update(exchange_name, new_price, &ctx).await;
+                                            }
+                                        }
+                                    }
+                                    Err(e) => eprintln!("Error parsing {:?} message from Binance: {}", stream_type, e),
+                                }
+                            } else if exchange_name == "Bitmart" {
+                                match serde_json::from_str::<Value>(&text) {
+                                    Ok(json) => {
+                                        match bitmart_error_action(&json, &ctx.config.bitmart_error_actions, exchange_name) {
+                                            Some(FeedAction::Ignore) => continue,
+                                            Some(FeedAction::Reconnect) => {
+                                                tokio::time::sleep(RECONNECT_DELAY).await;
+                                                continue 'connect;
+                                            }
+                                            Some(FeedAction::Fatal) => return,
+                                            None => {}
+                                        }
+                                        for (new_price, key) in extract_bitmart_trades(&json, &ctx.config.bitmart_price_path) {
+                                            if !ctx.dedup.is_duplicate(exchange_name, key) {
+                                                handle_price_update(exchange_name, new_price, &ctx).await;
+                                            }
+                                        }
+                                    }
+                                    Err(e) => eprintln!("Error parsing JSON from {}: {}", exchange_name, e),
+                                }
+                            }
+                        }
+                        Ok(Message::Ping(payload)) => {
+                            if let Err(e) = write.send(Message::Pong(payload)).await {
+                                eprintln!("Failed to send pong to {}: {}", exchange_name, e);
+                                break;
+                            }
+                        }
+                        Ok(Message::Close(_)) => break,
+                        Err(e) => {
+                            eprintln!("WebSocket error from {}: {}", exchange_name, e);
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("Failed to connect to {} WebSocket: {}", exchange_name, e);
+            }
+        }
+        return;
+    }
+}
+
+// Binance 결합 스트림 (가격과 호가창을 한 연결로 수신, 봉투의 stream 이름으로 가격/호가 처리 구분)
+// 연결 종료 시 반환 (스트림별 연결과 동일)
+pub async fn fetch_binance_combined(websocket_url: &str, ctx: TradingContext) {
+    let _flatten_guard = (!ctx.config.dry_run)
+        .then(|| FlattenGuard::new(Arc::clone(&ctx.order), Arc::clone(&ctx.shared_state), &ctx.config.symbol));
+    let price_stream = ctx.config.binance_stream.stream_name(&ctx.config.symbol);
+    let depth_stream = BinanceStreamType::depth_stream_name(&ctx.config.symbol);
+    let sink = DepthSink {
+        depth: Arc::clone(&ctx.shared_depth),
+        imbalance: Arc::clone(&ctx.shared_imbalance),
+        fair_value: Arc::clone(&ctx.shared_fair_value),
+    };
+
+    println!("Connecting to Binance combined WebSocket ({}, {})...", price_stream, depth_stream);
+    let (ws_stream, _) = match connect_async(websocket_url).await {
+        Ok(connection) => connection,
+        Err(e) => {
+            eprintln!("Failed to connect to Binance combined WebSocket: {}", e);
+            return;
+        }
+    };
+    println!("Connected to Binance combined WebSocket.");
+    let (mut write, mut read) = ws_stream.split();
+
+    while let Some(msg) = read.next().await {
+        match msg {
+            Ok(Message::Text(text)) => {
+                let envelope = match CombinedEnvelope::parse(&text) {
+                    Ok(envelope) => envelope,
+                    Err(e) => {
+                        eprintln!("Error parsing Binance combined stream envelope: {}", e);
+                        continue;
+                    }
+                };
+                match route_combined(&envelope.stream, &price_stream, &depth_stream) {
+                    CombinedRoute::Price => {
+                        // 가격 필드 경로가 설정되어 있으면 스트림 구조체 대신 경로로 추출
+                        let price = match &ctx.config.binance_price_path {
+                            Some(path) => path
+                                .extract(&envelope.data)
+                                .map(|price| (price, binance_message_key(&envelope.data, price)))
+                                .ok_or_else(|| format!("No price at '{}'", path)),
+                            None => BinancePayload::from_value(ctx.config.binance_stream, envelope.data)
+                                .map_err(|e| e.to_string())
+                                .and_then(|payload| {
+                                    payload.price().map(|price| (price, payload.key())).ok_or_else(|| "unparsable price".to_string())
+                                }),
+                        };
+                        match price {
+                            Ok((new_price, key)) => {
+                                if !ctx.dedup.is_duplicate("Binance", key) {
+                                    handle_price_update("Binance", new_price, &ctx).await;
+                                }
+                            }
+                            Err(e) => eprintln!("Error parsing {} message from Binance: {}", price_stream, e),
+                        }
+                    }
+                    CombinedRoute::Depth => {
+                        if let Err(e) = store_binance_depth_value(envelope.data, &sink, &ctx.config, &ctx.market_tap).await {
+                            eprintln!("Error parsing depth from Binance: {}", e);
+                        }
+                    }
+                    CombinedRoute::Unknown => eprintln!("Unexpected Binance combined stream: {}", envelope.stream),
+                }
+            }
+            Ok(Message::Ping(payload)) => {
+                if let Err(e) = write.send(Message::Pong(payload)).await {
+                    eprintln!("Failed to send pong to Binance combined stream: {}", e);
+                    break;
+                }
+            }
+            Ok(Message::Close(_)) => break,
+            Err(e) => {
+                eprintln!("WebSocket error from Binance combined stream: {}", e);
+                break;
+            }
+            _ => {}
+        }
+    }
+}
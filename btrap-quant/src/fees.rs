@@ -0,0 +1,37 @@
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use crate::config::{Exchange, StrategyConfig};
+use crate::price_calculator::FillMode;
+
+// 최근 펀딩비 (None이면 아직 조회 전)
+pub type SharedFunding = Arc<Mutex<Option<FundingRates>>>;
+
+// 거래소별 펀딩비 (%, 8시간 기준)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FundingRates {
+    pub binance: f64,
+    pub bitmart: f64,
+}
+
+// 보유 기간 동안 예상 펀딩 비용 (%)
+// 펀딩비가 양수면 롱이 지불하고 숏이 수취
+pub fn funding_cost(binance_position: &str, funding_rates: &FundingRates, hold_hours: f64) -> f64 {
+    let intervals = hold_hours / 8.0;
+    let per_interval = if binance_position == "LONG" {
+        funding_rates.binance - funding_rates.bitmart
+    } else {
+        funding_rates.bitmart - funding_rates.binance
+    };
+    per_interval * intervals
+}
+
+// 손익분기 갭 (%): 양쪽 진입/청산 테이커 수수료 (종목별 수수료, Binance 배율 반영) + 예상 펀딩 비용
+// 두 방향 중 더 불리한 쪽 기준
+pub fn break_even_gap(config: &StrategyConfig, funding_rates: &FundingRates) -> f64 {
+    let fees = 2.0 * (config.fee_pct(Exchange::Binance, FillMode::Taker) + config.fee_pct(Exchange::Bitmart, FillMode::Taker));
+    let worst_funding = ["LONG", "SHORT"]
+        .iter()
+        .map(|position| funding_cost(position, funding_rates, config.expected_hold_hours))
+        .fold(f64::MIN, f64::max);
+    fees + worst_funding.max(0.0)
+}
@@ -0,0 +1,288 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use crate::dedup::MessageKey;
+
+// 가격 피드로 사용할 Binance 스트림 종류
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BinanceStreamType {
+    AggTrade,
+    BookTicker,
+    MarkPrice,
+}
+
+impl BinanceStreamType {
+    // 스트림 이름 (예: xrpusdt@aggTrade)
+    pub fn stream_name(&self, symbol: &str) -> String {
+        let suffix = match self {
+            BinanceStreamType::AggTrade => "aggTrade",
+            BinanceStreamType::BookTicker => "bookTicker",
+            BinanceStreamType::MarkPrice => "markPrice",
+        };
+        format!("{}@{}", symbol.to_lowercase(), suffix)
+    }
+
+    // 호가창 스트림 이름 (depth20 부분 호가, 100ms)
+    pub fn depth_stream_name(symbol: &str) -> String {
+        format!("{}@depth20@100ms", symbol.to_lowercase())
+    }
+
+    // 증분 호가 스트림 이름 (100ms)
+    pub fn diff_depth_stream_name(symbol: &str) -> String {
+        format!("{}@depth@100ms", symbol.to_lowercase())
+    }
+
+    // 가격 필드 경로 검증용 예시 메시지
+    pub fn sample_message(&self) -> &'static str {
+        match self {
+            BinanceStreamType::AggTrade => {
+                r#"{"e":"aggTrade","E":1700000000000,"s":"XRPUSDT","a":1,"p":"0.5012","q":"100","f":1,"l":1,"T":1700000000000,"m":false}"#
+            }
+            BinanceStreamType::BookTicker => {
+                r#"{"e":"bookTicker","u":1,"s":"XRPUSDT","b":"0.5011","B":"1000","a":"0.5013","A":"800","T":1700000000000,"E":1700000000000}"#
+            }
+            BinanceStreamType::MarkPrice => {
+                r#"{"e":"markPriceUpdate","E":1700000000000,"s":"XRPUSDT","p":"0.5012","i":"0.5010","r":"0.0001","T":1700000000000}"#
+            }
+        }
+    }
+}
+
+// Bitmart futures/trade data 항목 예시 (가격 필드 경로 검증용)
+pub const BITMART_TRADE_SAMPLE: &str =
+    r#"{"symbol":"XRPUSDT","deal_price":"0.5012","deal_vol":"10","way":1,"created_at":"2024-01-01T00:00:00.000Z"}"#;
+
+// 메시지 안의 가격 필드 경로 (점으로 구분, 숫자 세그먼트는 배열 인덱스, 예: "p", "data.0.deal_price")
+// 값은 숫자 문자열 또는 숫자
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct FieldPath(Vec<String>);
+
+impl FieldPath {
+    pub fn parse(path: &str) -> Result<Self, String> {
+        let segments: Vec<String> = path.split('.').map(|s| s.trim().to_string()).collect();
+        if segments.iter().any(|s| s.is_empty()) {
+            return Err(format!("Invalid field path '{}'", path));
+        }
+        Ok(Self(segments))
+    }
+
+    // 경로의 값을 가격으로 변환 (없거나 숫자가 아니면 None)
+    pub fn extract(&self, json: &Value) -> Option<f64> {
+        let mut value = json;
+        for segment in &self.0 {
+            value = match value {
+                Value::Array(items) => items.get(segment.parse::<usize>().ok()?)?,
+                _ => value.get(segment)?,
+            };
+        }
+        match value {
+            Value::String(s) => s.parse().ok(),
+            Value::Number(n) => n.as_f64(),
+            _ => None,
+        }
+    }
+
+    // 예시 메시지에서 가격을 추출할 수 있는지 확인
+    pub fn validate_against(&self, sample: &str) -> Result<(), String> {
+        let json = serde_json::from_str::<Value>(sample).map_err(|e| format!("Invalid sample message: {}", e))?;
+        match self.extract(&json) {
+            Some(_) => Ok(()),
+            None => Err(format!("Field path '{}' finds no price in sample message {}", self, sample)),
+        }
+    }
+}
+
+impl TryFrom<String> for FieldPath {
+    type Error = String;
+
+    fn try_from(path: String) -> Result<Self, Self::Error> {
+        Self::parse(&path)
+    }
+}
+
+impl fmt::Display for FieldPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.join("."))
+    }
+}
+
+// aggTrade 메시지 (체결가, 집계 체결 ID)
+#[derive(Debug, Deserialize)]
+pub struct AggTrade {
+    #[serde(rename = "p")]
+    pub price: String,
+    #[serde(rename = "a", default)]
+    pub trade_id: Option<u64>,
+}
+
+// bookTicker 메시지 (최우선 호가, 호가 업데이트 ID)
+#[derive(Debug, Deserialize)]
+pub struct BookTicker {
+    #[serde(rename = "b")]
+    pub best_bid: String,
+    #[serde(rename = "a")]
+    pub best_ask: String,
+    #[serde(rename = "u", default)]
+    pub update_id: Option<u64>,
+}
+
+// markPriceUpdate 메시지 (마크 가격, 이벤트 시각)
+#[derive(Debug, Deserialize)]
+pub struct MarkPrice {
+    #[serde(rename = "p")]
+    pub mark_price: String,
+    #[serde(rename = "E", default)]
+    pub event_time: Option<i64>,
+}
+
+// 설정된 스트림 종류에 따른 Binance 메시지
+#[derive(Debug)]
+pub enum BinancePayload {
+    AggTrade(AggTrade),
+    BookTicker(BookTicker),
+    MarkPrice(MarkPrice),
+}
+
+impl BinancePayload {
+    // 스트림 종류에 맞는 구조체로 파싱
+    pub fn parse(stream_type: BinanceStreamType, text: &str) -> Result<Self, serde_json::Error> {
+        Ok(match stream_type {
+            BinanceStreamType::AggTrade => BinancePayload::AggTrade(serde_json::from_str(text)?),
+            BinanceStreamType::BookTicker => BinancePayload::BookTicker(serde_json::from_str(text)?),
+            BinanceStreamType::MarkPrice => BinancePayload::MarkPrice(serde_json::from_str(text)?),
+        })
+    }
+
+    // 이미 파싱된 메시지 (결합 스트림의 data)에서 파싱
+    pub fn from_value(stream_type: BinanceStreamType, value: Value) -> Result<Self, serde_json::Error> {
+        Ok(match stream_type {
+            BinanceStreamType::AggTrade => BinancePayload::AggTrade(serde_json::from_value(value)?),
+            BinanceStreamType::BookTicker => BinancePayload::BookTicker(serde_json::from_value(value)?),
+            BinanceStreamType::MarkPrice => BinancePayload::MarkPrice(serde_json::from_value(value)?),
+        })
+    }
+
+    // 가격 추출 (bookTicker는 중간가)
+    pub fn price(&self) -> Option<f64> {
+        match self {
+            BinancePayload::AggTrade(trade) => trade.price.parse().ok(),
+            BinancePayload::BookTicker(ticker) => {
+                let bid = ticker.best_bid.parse::<f64>().ok()?;
+                let ask = ticker.best_ask.parse::<f64>().ok()?;
+                Some((bid + ask) / 2.0)
+            }
+            BinancePayload::MarkPrice(mark) => mark.mark_price.parse().ok(),
+        }
+    }
+
+    // 중복 확인 키 (체결/호가는 업데이트 ID, 마크 가격은 가격과 이벤트 시각)
+    pub fn key(&self) -> Option<MessageKey> {
+        match self {
+            BinancePayload::AggTrade(trade) => trade.trade_id.map(MessageKey::Id),
+            BinancePayload::BookTicker(ticker) => ticker.update_id.map(MessageKey::Id),
+            BinancePayload::MarkPrice(mark) => Some(MessageKey::price_time(self.price()?, mark.event_time?)),
+        }
+    }
+}
+
+// 필드 경로로 가격을 읽은 Binance 메시지의 중복 확인 키 (a: 집계 체결 ID, u: 업데이트 ID, 없으면 가격과 E)
+pub fn binance_message_key(json: &Value, price: f64) -> Option<MessageKey> {
+    if let Some(id) = json.get("a").or_else(|| json.get("u")).and_then(Value::as_u64) {
+        return Some(MessageKey::Id(id));
+    }
+    json.get("E").and_then(Value::as_i64).map(|ts| MessageKey::price_time(price, ts))
+}
+
+// Binance 결합 스트림 메시지 봉투 (단일 스트림은 원본 메시지만 오고 결합 스트림은 스트림 이름과 함께 감쌈)
+#[derive(Debug, Deserialize)]
+pub struct CombinedEnvelope {
+    pub stream: String,
+    pub data: Value,
+}
+
+impl CombinedEnvelope {
+    pub fn parse(text: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(text)
+    }
+}
+
+// 결합 스트림 메시지를 처리할 대상 (구독한 스트림 이름으로 구분)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CombinedRoute {
+    Price,
+    Depth,
+    Unknown,
+}
+
+pub fn route_combined(stream: &str, price_stream: &str, depth_stream: &str) -> CombinedRoute {
+    if stream == price_stream {
+        CombinedRoute::Price
+    } else if stream == depth_stream {
+        CombinedRoute::Depth
+    } else {
+        CombinedRoute::Unknown
+    }
+}
+
+// 결합 스트림 접속 주소 (단일 스트림 기본 경로 .../ws를 .../stream?streams=a/b로 변환)
+pub fn binance_combined_url(ws_url: &str, streams: &[String]) -> String {
+    let base = ws_url.trim_end_matches('/');
+    let base = base.strip_suffix("/ws").unwrap_or(base);
+    format!("{}/stream?streams={}", base, streams.join("/"))
+}
+
+// Bitmart data 필드의 항목들 (배열/객체 모두 처리)
+pub fn bitmart_data_items(json: &Value) -> Vec<&Value> {
+    match json.get("data") {
+        Some(Value::Array(items)) => items.iter().collect(),
+        Some(item @ Value::Object(_)) => vec![item],
+        _ => Vec::new(),
+    }
+}
+
+// Bitmart 체결 메시지에서 체결가 추출 (data 항목 순서대로, 경로는 각 항목 기준)
+pub fn extract_bitmart_prices(json: &Value, price_path: &FieldPath) -> Vec<f64> {
+    extract_bitmart_trades(json, price_path).into_iter().map(|(price, _)| price).collect()
+}
+
+// 체결가와 중복 확인 키 (체결 ID가 없으므로 가격과 created_at)
+pub fn extract_bitmart_trades(json: &Value, price_path: &FieldPath) -> Vec<(f64, Option<MessageKey>)> {
+    bitmart_data_items(json)
+        .into_iter()
+        .filter_map(|entry| {
+            let price = price_path.extract(entry)?;
+            let key = entry.get("created_at").and_then(Value::as_str).map(|ts| MessageKey::price_time(price, ts));
+            Some((price, key))
+        })
+        .collect()
+}
+
+// Bitmart WebSocket 오류 코드에 대한 피드 대응
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedAction {
+    Ignore,    // 로그만 남기고 계속 읽음
+    Reconnect, // 연결을 끊고 다시 접속
+    Fatal,     // 피드 종료
+}
+
+// Bitmart 오류 메시지의 코드 (errorCode 또는 error_code, 문자열/숫자 모두 허용)
+pub fn bitmart_error_code(json: &Value) -> Option<String> {
+    let code = json.get("errorCode").or_else(|| json.get("error_code"))?;
+    match code {
+        Value::String(code) => Some(code.clone()),
+        Value::Number(code) => Some(code.to_string()),
+        _ => None,
+    }
+}
+
+// 오류 메시지에 대한 대응 (오류가 아니면 None, 설정에 없는 코드는 Ignore)
+pub fn bitmart_error_action(json: &Value, actions: &HashMap<String, FeedAction>, feed_name: &str) -> Option<FeedAction> {
+    let code = bitmart_error_code(json)?;
+    let action = actions.get(&code).copied().unwrap_or(FeedAction::Ignore);
+    eprintln!("[Feed] {} error code {} ({}): {:?}", feed_name, code, json, action);
+    Some(action)
+}
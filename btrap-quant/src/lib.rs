@@ -0,0 +1,34 @@
+// btrap-quant 라이브러리: 거래소 피드, 주문, 전략 로직
+pub mod cli;
+pub mod clock;
+pub mod config;
+pub mod context;
+pub mod control;
+pub mod dedup;
+pub mod depth;
+pub mod event_log;
+pub mod feed;
+pub mod fees;
+pub mod handle_price;
+pub mod market_hours;
+pub mod notify;
+pub mod order;
+pub mod portfolio;
+pub mod price_calculator;
+pub mod private_feed;
+pub mod safety;
+pub mod state;
+pub mod status;
+pub mod strategy;
+pub mod subscriptions;
+pub mod tasks;
+pub mod trade_events;
+pub mod venue;
+pub mod volatility;
+
+pub use config::StrategyConfig;
+pub use context::TradingContext;
+pub use order::Order;
+pub use price_calculator::PriceCalculator;
+pub use state::{TradeRecord, TradingState};
+pub use strategy::execute_trade;
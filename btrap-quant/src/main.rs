@@ -1,154 +1,120 @@
-use futures_util::{stream::StreamExt, SinkExt}; // StreamExt 및 SinkExt 가져오기
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
-use serde_json::Value;
-use std::sync::{Arc};
+use std::sync::Arc;
 use std::collections::HashMap;
+use std::time::Duration;
+use tokio::net::TcpListener;
 use tokio::sync::Mutex;
 use reqwest::Client;
-mod order;
-use crate::order::Order; // Import the Order module
+use clap::Parser;
+use btrap_quant::cli::Cli;
+use btrap_quant::clock::RealClock;
+use btrap_quant::config::{AllowedDirections, StrategyConfig, ThresholdUnit};
+use btrap_quant::context::{SharedPrices, TradingContext};
+use btrap_quant::control::{read_commands, SymbolFlags, TradeConfirmer, TradingSwitch};
+use btrap_quant::dedup::MessageDedup;
+use btrap_quant::depth::{
+    fetch_binance_depth, fetch_binance_diff_depth, fetch_bitmart_depth, BinanceDepthMode, DepthSink, SharedDepth, SharedFairValue,
+    SharedImbalance,
+};
+use btrap_quant::event_log::{EventRecorder, MarketTap, TickRing};
+use btrap_quant::feed::{fetch_binance_combined, fetch_price, run_throttled_evaluations, EvaluationThrottle};
+use btrap_quant::handle_price::{binance_combined_url, BinanceStreamType};
+use btrap_quant::notify::Notifier;
+use btrap_quant::order::{BinancePrecision, BinanceWeight, ClientOrderIds, Order, OrderBudget, SelfTestResult};
+use btrap_quant::portfolio::OpenTrades;
+use btrap_quant::price_calculator::PriceCalculator;
+use btrap_quant::private_feed::{fetch_binance_private, fetch_bitmart_private};
+use btrap_quant::state::{SharedState, TradingState};
+use btrap_quant::status::{refresh_funding_rates, report_status};
+use btrap_quant::strategy::reconcile_inflight_orders;
+use btrap_quant::tasks::TaskTracker;
+use btrap_quant::trade_events::{serve_trade_events, TradeEventBus};
+use btrap_quant::venue::{probe_down_venues, VenueHealth};
 
-// 공유 데이터 타입 정의
-type SharedPrices = Arc<Mutex<HashMap<String, f64>>>;
-
-// 주문 집행 함수 (실제 주문 실행)
-async fn execute_trade(
-    order: Arc<Order>,
-    binance_price: f64,
-    bitmart_price: f64,
-) {
-    let percent_diff = ((binance_price - bitmart_price) / bitmart_price) * 100.0;
-
-    if percent_diff > 0.3 {
-        println!(
-            "Gap exceeds 0.3%. Executing trade: Binance Short, Bitmart Long.\nBinance: {:.4}, Bitmart: {:.4}, Gap: {:.4}%",
-            binance_price, bitmart_price, percent_diff
-        );
-        // Binance 숏 주문
-        match order.place_market_order_binance("XRPUSDT", "SELL", 1.0).await {
-            Ok(response) => println!("[Order] Binance Short Order Response: {:?}", response),
-            Err(e) => eprintln!("[Order] Binance Short Order Failed: {}", e),
-        }
-        // Bitmart 롱 주문
-        match order.place_market_order_bitmart("XRPUSDT", "buy", 1.0).await {
-            Ok(response) => println!("[Order] Bitmart Long Order Response: {:?}", response),
-            Err(e) => eprintln!("[Order] Bitmart Long Order Failed: {}", e),
-        }
-    } else if percent_diff < -0.3 {
-        println!(
-            "Gap exceeds -0.3%. Executing trade: Binance Long, Bitmart Short.\nBinance: {:.4}, Bitmart: {:.4}, Gap: {:.4}%",
-            binance_price, bitmart_price, percent_diff
-        );
-        // Binance 롱 주문
-        match order.place_market_order_binance("XRPUSDT", "BUY", 1.0).await {
-            Ok(response) => println!("[Order] Binance Long Order Response: {:?}", response),
-            Err(e) => eprintln!("[Order] Binance Long Order Failed: {}", e),
-        }
-        // Bitmart 숏 주문
-        match order.place_market_order_bitmart("XRPUSDT", "sell", 1.0).await {
-            Ok(response) => println!("[Order] Bitmart Short Order Response: {:?}", response),
-            Err(e) => eprintln!("[Order] Bitmart Short Order Failed: {}", e),
+fn main() {
+    // 설정 로드 (명령줄 인자로 덮어쓴 뒤 다시 검증)
+    let cli = Cli::parse();
+    let mut config = match StrategyConfig::load(&cli.config) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("[Config] {}", e);
+            return;
         }
+    };
+    cli.apply(&mut config);
+    if let Err(e) = config.validate() {
+        eprintln!("[Config] {}", e);
+        return;
     }
-}
+    println!("[Config] Effective configuration ({}): {:#?}", cli.config, config);
 
-// 가격 업데이트 핸들러
-async fn handle_price_update(
-    exchange_name: &str,
-    new_price: f64,
-    shared_prices: &SharedPrices,
-    order: Arc<Order>,
-) {
-    let mut prices = shared_prices.lock().await; // 비동기 Mutex 잠금
+    // 모든 태스크가 공유하는 런타임 (작업 스레드 수는 설정값)
+    let mut builder = tokio::runtime::Builder::new_multi_thread();
+    if config.worker_threads > 0 {
+        builder.worker_threads(config.worker_threads);
+    }
+    let runtime = match builder.enable_all().build() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("[Config] Failed to start runtime: {}", e);
+            return;
+        }
+    };
+    runtime.block_on(run(cli, config));
+}
 
-    // 현재 거래소 가격 업데이트
-    prices.insert(exchange_name.to_string(), new_price);
+async fn run(cli: Cli, mut config: StrategyConfig) {
+    let symbol = config.symbol.clone();
 
-    // 두 거래소의 가격 비교
-    if let (Some(&binance_price), Some(&bitmart_price)) = (prices.get("Binance"), prices.get("Bitmart")) {
-        // 주문 조건 확인 및 실행
-        execute_trade(order.clone(), binance_price, bitmart_price).await;
+    let binance_ws_url = config.binance_ws_url.trim_end_matches('/').to_string();
+    let binance_url = format!("{}/{}", binance_ws_url, config.binance_stream.stream_name(&symbol));
+    let binance_depth_url = format!("{}/{}", binance_ws_url, BinanceStreamType::depth_stream_name(&symbol));
+    println!(
+        "[Config] Binance price stream: {:?}{}",
+        config.binance_stream,
+        if config.binance_combined_stream { " (combined with depth on one connection)" } else { "" }
+    );
+    if config.dry_run {
+        println!("[Config] Dry run: orders are logged but not sent, state is kept in {}", config.state_path());
     }
-}
 
-// WebSocket에서 가격 가져오기
-async fn fetch_price(
-    websocket_url: &str,
-    exchange_name: &str,
-    shared_prices: SharedPrices, // 공유 데이터 구조 추가
-    order: Arc<Order>,
-) {
-    println!("Connecting to {} WebSocket...", exchange_name);
-
-    match connect_async(websocket_url).await {
-        Ok((ws_stream, _)) => {
-            println!("Connected to {} WebSocket.", exchange_name);
-
-            let (mut write, mut read) = ws_stream.split();
-
-            if exchange_name == "Bitmart" {
-                let sub_msg = serde_json::json!({
-                    "action": "subscribe",
-                    "args": ["futures/trade:XRPUSDT"]
-                });
-                if let Err(e) = write.send(Message::Text(sub_msg.to_string())).await {
-                    eprintln!("Failed to send subscription message to {}: {}", exchange_name, e);
-                    return;
-                }
-            }
+    if config.confirm_trades {
+        println!("[Config] Confirm mode: every order waits for y/n on stdin ({}s timeout)", config.confirm_timeout_secs);
+    }
 
-            while let Some(msg) = read.next().await {
-                match msg {
-                    Ok(Message::Text(text)) => {
-                        match serde_json::from_str::<Value>(&text) {
-                            Ok(json) => {
-                                if exchange_name == "Binance" {
-                                    if let Some(price_str) = json.get("p").and_then(|v| v.as_str()) {
-                                        if let Ok(new_price) = price_str.parse::<f64>() {
-                                            handle_price_update(exchange_name, new_price, &shared_prices, order.clone()).await;
-                                        }
-                                    }
-                                } else if exchange_name == "Bitmart" {
-                                    if let Some(data) = json.get("data").and_then(|v| v.as_array()) {
-                                        for entry in data {
-                                            if let Some(price_str) = entry.get("deal_price").and_then(|v| v.as_str()) {
-                                                if let Ok(new_price) = price_str.parse::<f64>() {
-                                                    handle_price_update(exchange_name, new_price, &shared_prices, order.clone()).await;
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            Err(e) => eprintln!("Error parsing JSON from {}: {}", exchange_name, e),
-                        }
-                    }
-                    Ok(Message::Ping(payload)) => {
-                        write.send(Message::Pong(payload)).await.unwrap();
-                    }
-                    Ok(Message::Close(_)) => break,
-                    Err(e) => {
-                        eprintln!("WebSocket error from {}: {}", exchange_name, e);
-                        break;
-                    }
-                    _ => {}
-                }
-            }
-        }
-        Err(e) => {
-            eprintln!("Failed to connect to {} WebSocket: {}", exchange_name, e);
-        }
+    if config.invert_direction {
+        println!("[Config] Direction: inverted (positive gap -> Binance Long, Bitmart Short)");
+    } else {
+        println!("[Config] Direction: normal (positive gap -> Binance Short, Bitmart Long)");
     }
-}
 
-#[tokio::main]
-async fn main() {
-    let binance_url = "wss://fstream.binance.com/ws/xrpusdt@aggTrade";
-    let bitmart_url = "wss://openapi-ws-v2.bitmart.com/api?protocol=1.1";
+    if config.allowed_directions != AllowedDirections::Both {
+        println!("[Config] Allowed directions: {:?}", config.allowed_directions);
+    }
 
     // 공유 데이터 구조 생성
     let shared_prices: SharedPrices = Arc::new(Mutex::new(HashMap::new()));
+    let shared_depth: SharedDepth = Arc::new(Mutex::new(HashMap::new()));
+    let shared_imbalance: SharedImbalance = Arc::new(Mutex::new(HashMap::new()));
+    let shared_fair_value: SharedFairValue = Arc::new(Mutex::new(HashMap::new()));
 
+    // 거래 상태 복원
+    let mut trading_state = match TradingState::load(config.state_path()) {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("[State] {}", e);
+            return;
+        }
+    };
+    let trimmed = trading_state.trim_history(config.trade_history_limit);
+    if trimmed > 0 {
+        println!("[State] Dropped {} oldest trade records (limit {})", trimmed, config.trade_history_limit);
+    }
+    if trading_state.is_trading {
+        println!(
+            "[State] Restored open position: Binance {:?}, Bitmart {:?}, entry gap {:.4}%",
+            trading_state.binance_position, trading_state.bitmart_position, trading_state.entry_gap
+        );
+    }
     // HTTP 클라이언트 생성
     let client = Client::new();
 
@@ -160,19 +126,328 @@ async fn main() {
         bitmart_api_key: "YOUR_BITMART_API_KEY".to_string(),
         bitmart_secret_key: "YOUR_BITMART_SECRET_KEY".to_string(),
         bitmart_memo: "YOUR_BITMART_MEMO".to_string(),
+        open_type: config.open_type,
+        binance_base_url: config.binance_rest_url.clone(),
+        bitmart_base_url: config.bitmart_rest_url.clone(),
+        bitmart_contracts: config.bitmart_contracts.clone(),
+        binance_weight: BinanceWeight::new(config.binance_weight_limit, config.binance_weight_pause_ratio),
+        venue_health: VenueHealth::new(config.venue_down_after_failures),
+        binance_precision: BinancePrecision::default(),
+        order_budget: OrderBudget::new(config.max_orders_per_hour),
+        binance_resp_type: config.binance_order_resp_type,
+        client_order_ids: ClientOrderIds::default(),
     });
 
-    // Binance WebSocket
-    let binance_shared = Arc::clone(&shared_prices);
-    let binance_order = Arc::clone(&order);
-    tokio::spawn(fetch_price(binance_url, "Binance", binance_shared, binance_order));
+    // 주문 전송 후 기록 전에 중단된 진입이 있으면 거래소 주문 내역으로 복원
+    if !config.dry_run {
+        reconcile_inflight_orders(&order, &config, &mut trading_state).await;
+    }
+    let open_trades = Arc::new(OpenTrades::new(config.max_open_trades, usize::from(trading_state.is_trading)));
+    let shared_state: SharedState = Arc::new(Mutex::new(trading_state));
+
+    // Binance 주문 자릿수 (실패하면 수량/가격을 그대로 전송)
+    match order.load_precision_binance(&symbol).await {
+        Ok(precision) => println!(
+            "[Config] Binance {} precision: quantity {} decimals, price {} decimals",
+            symbol, precision.quantity, precision.price
+        ),
+        Err(e) => eprintln!("[Config] Failed to load Binance {} precision, sending unrounded values: {}", symbol, e),
+    }
+
+    // 틱 단위 임계값: 호가 단위가 설정에 없으면 Binance 거래소 필터에서 조회
+    if config.threshold_unit == ThresholdUnit::Ticks {
+        if config.tick_size.is_none() {
+            match order.fetch_tick_size_binance(&symbol).await {
+                Ok(Some(tick_size)) => config.tick_size = Some(tick_size),
+                Ok(None) => {
+                    eprintln!("[Config] threshold_unit = \"ticks\" but {} has no PRICE_FILTER tick size; set tick_size", symbol);
+                    return;
+                }
+                Err(e) => {
+                    eprintln!("[Config] Failed to fetch {} tick size for tick thresholds: {}", symbol, e);
+                    return;
+                }
+            }
+        }
+        println!(
+            "[Config] Thresholds in ticks: entry {}, exit {} (tick size {})",
+            config.entry_threshold,
+            config.exit_threshold,
+            config.tick_size.unwrap_or_default()
+        );
+    }
+    let config = Arc::new(config);
+
+    // 지연 측정 모드: 거래소별 통계만 출력하고 종료
+    if cli.benchmark {
+        run_benchmark(&order, &config).await;
+        return;
+    }
+
+    // Binance 마진 타입을 설정과 일치시킴 (Bitmart는 주문마다 open_type 지정)
+    println!("[Config] Margin type: {}", config.open_type.as_bitmart());
+    if !config.dry_run {
+        if let Err(e) = order.set_margin_type_binance(&symbol, config.open_type).await {
+            eprintln!("[Order] Failed to set Binance margin type: {}", e);
+        }
+    }
+    check_margin_settings(&order, &config).await;
+
+    // 자체 점검 모드: 실제 주문으로 거래소별 진입/청산 확인 후 종료 (실패 시 종료 코드 1)
+    if cli.selftest {
+        if !run_self_test(&order, &config).await {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // 시장 이벤트 녹화 (백테스트 재생용)
+    let recorder = match &config.event_log_path {
+        Some(path) => match EventRecorder::open(
+            path,
+            config.event_log_queue_capacity,
+            config.event_log_overflow,
+            Duration::from_millis(config.event_log_flush_ms),
+        ) {
+            Ok(recorder) => {
+                println!("[Recorder] Recording market events to {}", path);
+                Some(Arc::new(recorder))
+            }
+            Err(e) => {
+                eprintln!("[Recorder] {}", e);
+                return;
+            }
+        },
+        None => None,
+    };
+    // 사후 분석용 최근 시장 이벤트 (패닉 시 기본 패닉 출력 전에 파일로 기록)
+    let ring = (config.tick_ring_size > 0).then(|| Arc::new(TickRing::new(config.tick_ring_size, &config.tick_dump_dir)));
+    let market_tap = MarketTap { recorder, ring };
+    if market_tap.ring.is_some() {
+        let panic_tap = market_tap.clone();
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            panic_tap.dump_ring("panic");
+            default_hook(info);
+        }));
+    }
+
+    // 전략 판단 이벤트 스트림 (외부 대시보드/로거용)
+    let event_listener = match &config.event_stream_addr {
+        Some(addr) => match TcpListener::bind(addr).await {
+            Ok(listener) => {
+                println!("[Events] Streaming trade events on {}", addr);
+                Some(listener)
+            }
+            Err(e) => {
+                eprintln!("[Events] Failed to bind {}: {}", addr, e);
+                return;
+            }
+        },
+        None => None,
+    };
+
+    let ctx = TradingContext {
+        order,
+        config: Arc::clone(&config),
+        shared_prices,
+        shared_depth: Arc::clone(&shared_depth),
+        shared_imbalance: Arc::clone(&shared_imbalance),
+        shared_fair_value: Arc::clone(&shared_fair_value),
+        shared_volatility: Arc::new(Mutex::new(HashMap::new())),
+        entry_volatility: Arc::new(Mutex::new(HashMap::new())),
+        shared_gap_velocity: Arc::new(Mutex::new(HashMap::new())),
+        evaluation: Arc::new(EvaluationThrottle::new(config.min_evaluation_interval_ms)),
+        shared_funding: Arc::new(Mutex::new(None)),
+        shared_state,
+        symbol_flags: Arc::new(SymbolFlags::new(&[&symbol])),
+        trading: Arc::new(TradingSwitch::default()),
+        price_calculator: Arc::new(PriceCalculator::new(Arc::clone(&config), Arc::clone(&shared_depth))),
+        notifier: Arc::new(Notifier::new(client.clone(), config.webhook_url.clone(), config.notify_min_interval_secs)),
+        confirmer: Arc::new(TradeConfirmer::default()),
+        tasks: Arc::new(TaskTracker::new(config.max_active_tasks)),
+        open_trades,
+        trade_events: Arc::new(TradeEventBus::new(config.event_stream_capacity)),
+        market_tap: market_tap.clone(),
+        dedup: Arc::new(MessageDedup::new(config.dedup_window)),
+        clock: Arc::new(RealClock),
+    };
+    let tasks = Arc::clone(&ctx.tasks);
+
+    // Binance WebSocket (결합 스트림이면 가격과 호가창을 한 연결로)
+    let binance_ctx = ctx.clone();
+    if config.binance_combined_stream {
+        let streams = [config.binance_stream.stream_name(&symbol), BinanceStreamType::depth_stream_name(&symbol)];
+        let combined_url = binance_combined_url(&binance_ws_url, &streams);
+        tasks.spawn("Binance combined feed", async move { fetch_binance_combined(&combined_url, binance_ctx).await });
+    } else {
+        tasks.spawn("Binance price feed", async move { fetch_price(&binance_url, "Binance", binance_ctx).await });
+    }
 
     // Bitmart WebSocket
-    let bitmart_shared = Arc::clone(&shared_prices);
-    let bitmart_order = Arc::clone(&order);
-    tokio::spawn(fetch_price(bitmart_url, "Bitmart", bitmart_shared, bitmart_order));
+    let (bitmart_url, bitmart_ctx) = (config.bitmart_ws_url.clone(), ctx.clone());
+    tasks.spawn("Bitmart price feed", async move { fetch_price(&bitmart_url, "Bitmart", bitmart_ctx).await });
+
+    // 호가창 WebSocket
+    let (depth, imbalance, depth_config) = (Arc::clone(&shared_depth), Arc::clone(&shared_imbalance), Arc::clone(&config));
+    let (fair_value, depth_tap) = (Arc::clone(&shared_fair_value), market_tap.clone());
+    if config.binance_depth_mode == BinanceDepthMode::Diff {
+        let diff_url = format!("{}/{}", binance_ws_url, BinanceStreamType::diff_depth_stream_name(&symbol));
+        let sink = DepthSink { depth, imbalance, fair_value };
+        let rest_url = config.binance_rest_url.clone();
+        tasks.spawn("Binance depth feed", async move { fetch_binance_diff_depth(&diff_url, &rest_url, sink, depth_config, depth_tap).await });
+    } else if !config.binance_combined_stream {
+        tasks.spawn("Binance depth feed", async move { fetch_binance_depth(&binance_depth_url, depth, imbalance, fair_value, depth_config, depth_tap).await });
+    }
+    let depth_sink = DepthSink {
+        depth: Arc::clone(&shared_depth),
+        imbalance: Arc::clone(&shared_imbalance),
+        fair_value: Arc::clone(&shared_fair_value),
+    };
+    let (bitmart_depth_url, depth_sinks) = (config.bitmart_depth_ws_url.clone(), HashMap::from([(symbol.clone(), depth_sink)]));
+    let depth_config = Arc::clone(&config);
+    tasks.spawn("Bitmart depth feed", async move { fetch_bitmart_depth(&bitmart_depth_url, depth_sinks, depth_config, market_tap).await });
+
+    // 주문 체결 private 채널 (dry run은 실제 주문이 없으므로 제외)
+    if !config.dry_run {
+        let private_ctx = ctx.clone();
+        tasks.spawn("Binance private feed", async move { fetch_binance_private(&binance_ws_url, private_ctx).await });
+        let (bitmart_private_url, private_ctx) = (config.bitmart_private_ws_url.clone(), ctx.clone());
+        tasks.spawn("Bitmart private feed", async move { fetch_bitmart_private(&bitmart_private_url, private_ctx).await });
+    }
+
+    if let Some(listener) = event_listener {
+        tasks.spawn("trade event server", serve_trade_events(listener, Arc::clone(&ctx.trade_events)));
+    }
+
+    // 판단 주기 제한 시 가격 업데이트를 합쳐서 판단
+    if ctx.evaluation.is_throttled() {
+        tasks.spawn("strategy evaluation", run_throttled_evaluations(ctx.clone()));
+    }
+
+    // 상태 출력
+    tasks.spawn("status report", report_status(ctx.clone()));
+
+    // 펀딩비 갱신
+    tasks.spawn("funding refresh", refresh_funding_rates(ctx.clone()));
+
+    // 장애로 중단된 거래소 복구 확인
+    tasks.spawn("venue probe", probe_down_venues(ctx.clone()));
+
+    // 런타임 명령 (enable/disable <SYMBOL>, pause [all]/resume, 주문 확인 y/n, whatif <BINANCE> <BITMART>)
+    tasks.spawn("command reader", read_commands(
+        Arc::clone(&ctx.symbol_flags),
+        Arc::clone(&ctx.trading),
+        Arc::clone(&ctx.confirmer),
+        Arc::clone(&ctx.price_calculator),
+        config.position_size,
+    ));
+
+    let session_start = chrono::Utc::now();
 
     // Keep the main task alive
     tokio::signal::ctrl_c().await.unwrap();
     println!("Shutting down...");
+
+    // 세션 요약 (이번 실행 중 청산된 거래)
+    let (trade_count, captured) = ctx.shared_state.lock().await.captured_since(session_start);
+    println!("[Summary] {} trades closed this session, captured {:.4} USDT net of fees", trade_count, captured);
+
+    // 녹화 대기열에 남은 이벤트를 파일에 반영
+    if let Some(recorder) = &ctx.market_tap.recorder {
+        recorder.close().await;
+    }
+}
+
+// 거래소별 자체 점검 (모두 성공하면 true)
+async fn run_self_test(order: &Order, config: &StrategyConfig) -> bool {
+    if config.dry_run {
+        eprintln!("[SelfTest] Self-test places real orders and cannot run with dry_run enabled.");
+        return false;
+    }
+    println!("[SelfTest] Opening and closing a minimum-size {} position on each venue...", config.symbol);
+    let binance = order.self_test_binance(&config.symbol).await;
+    report_self_test(&binance, &config.symbol);
+    // 포지션이 남았을 수 있으면 다음 거래소는 점검하지 않음
+    if binance.leaked {
+        return false;
+    }
+    let bitmart = order.self_test_bitmart(&config.symbol).await;
+    report_self_test(&bitmart, &config.symbol);
+    binance.outcome.is_ok() && bitmart.outcome.is_ok()
+}
+
+fn report_self_test(result: &SelfTestResult, symbol: &str) {
+    let quantity = result.quantity.map_or("-".to_string(), |q| q.to_string());
+    match &result.outcome {
+        Ok(()) => println!("[SelfTest] {}: OK (quantity {})", result.venue, quantity),
+        Err(e) => eprintln!("[SelfTest] {}: FAILED (quantity {}): {}", result.venue, quantity, e),
+    }
+    if result.leaked {
+        eprintln!(
+            "[SelfTest] !!! {} {} POSITION MAY STILL BE OPEN. CLOSE IT MANUALLY ON THE EXCHANGE NOW. !!!",
+            result.venue, symbol
+        );
+    }
+}
+
+// 거래소 쪽 레버리지/마진 타입 출력 후 설정과 다르면 경고 (auto_set_leverage면 레버리지 변경)
+// 레버리지가 다르면 같은 수량이라도 필요 증거금과 청산 가격이 달라짐
+async fn check_margin_settings(order: &Order, config: &StrategyConfig) {
+    let symbol = &config.symbol;
+    let (binance, bitmart) = tokio::join!(
+        order.fetch_margin_settings_binance(symbol),
+        order.fetch_margin_settings_bitmart(symbol)
+    );
+    for (venue, settings) in [("Binance", binance), ("Bitmart", bitmart)] {
+        let settings = match settings {
+            Ok(settings) => settings,
+            Err(e) => {
+                eprintln!("[Config] Failed to fetch {} {} leverage/margin: {}", venue, symbol, e);
+                continue;
+            }
+        };
+        println!("[Config] {} {}: {}", venue, symbol, settings);
+        let mismatches = settings.mismatches(config.leverage, config.open_type);
+        if mismatches.is_empty() {
+            continue;
+        }
+        eprintln!("[Config] WARNING: {} {} differs from config: {}", venue, symbol, mismatches.join(", "));
+        let Some(leverage) = config.leverage.filter(|&leverage| config.auto_set_leverage && settings.leverage != Some(leverage)) else {
+            continue;
+        };
+        if config.dry_run {
+            println!("[DryRun] Would set {} {} leverage to {}x", venue, symbol, leverage);
+            continue;
+        }
+        let result = match venue {
+            "Binance" => order.set_leverage_binance(symbol, leverage).await,
+            _ => order.set_leverage_bitmart(symbol, leverage, config.open_type).await,
+        };
+        match result {
+            Ok(()) => println!("[Config] {} {} leverage set to {}x", venue, symbol, leverage),
+            Err(e) => eprintln!("[Order] Failed to set {} leverage: {}", venue, e),
+        }
+    }
+}
+
+// 거래소별 왕복 지연 출력 (기준 초과 거래소는 경고)
+async fn run_benchmark(order: &Order, config: &StrategyConfig) {
+    println!("[Benchmark] Measuring order API latency ({} samples per venue)...", config.benchmark_samples);
+    for (venue, stats) in order.benchmark_latency(config.benchmark_samples).await {
+        let Some(stats) = stats else {
+            eprintln!("[Benchmark] {}: all {} probes failed", venue, config.benchmark_samples);
+            continue;
+        };
+        println!(
+            "[Benchmark] {}: min {:.1}ms, median {:.1}ms, p99 {:.1}ms ({} ok, {} failed)",
+            venue, stats.min_ms, stats.median_ms, stats.p99_ms, stats.samples, stats.failures
+        );
+        if stats.median_ms > config.latency_threshold_ms {
+            eprintln!(
+                "[Benchmark] WARNING: {} median latency {:.1}ms exceeds {:.1}ms threshold, avoid trading this venue",
+                venue, stats.median_ms, config.latency_threshold_ms
+            );
+        }
+    }
 }
@@ -0,0 +1,92 @@
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use serde::Deserialize;
+
+// 점검 시간대 (UTC "HH:MM", 자정을 넘는 구간 허용)
+#[derive(Debug, Clone, Deserialize)]
+pub struct BlackoutWindow {
+    pub start: String,
+    pub end: String,
+}
+
+impl BlackoutWindow {
+    // 시작/종료 시각 파싱
+    pub fn parse(&self) -> Result<(NaiveTime, NaiveTime), String> {
+        let parse_time = |value: &str| {
+            NaiveTime::parse_from_str(value, "%H:%M")
+                .map_err(|e| format!("Invalid blackout time '{}': {}", value, e))
+        };
+        Ok((parse_time(&self.start)?, parse_time(&self.end)?))
+    }
+
+    // 주어진 시각이 점검 시간대 안에 있는지 확인
+    pub fn contains(&self, now: DateTime<Utc>) -> bool {
+        let Ok((start, end)) = self.parse() else {
+            return false;
+        };
+        let time = now.time();
+        if start <= end {
+            time >= start && time < end
+        } else {
+            // 자정을 넘는 구간 (예: 23:50 ~ 00:10)
+            time >= start || time < end
+        }
+    }
+}
+
+// 점검 시간대 및 거래 중단 감지 상태 (종목별)
+#[derive(Debug, Clone, Default)]
+pub struct MarketGuard {
+    in_blackout: bool,
+    halted_until: Option<DateTime<Utc>>,
+    // 재시작 전까지 유지되는 전역 정지 사유 (세션 손실 한도 초과 등)
+    killed: Option<String>,
+}
+
+impl MarketGuard {
+    // 신규 진입 가능 여부 (점검 시간대이거나 거래 중단 대기 중이면 false)
+    pub fn allows_entry(&mut self, windows: &[BlackoutWindow], now: DateTime<Utc>) -> bool {
+        if self.killed.is_some() {
+            return false;
+        }
+        let in_blackout = windows.iter().any(|window| window.contains(now));
+        if in_blackout != self.in_blackout {
+            if in_blackout {
+                println!("[Guard] Entered blackout window. New entries paused.");
+            } else {
+                println!("[Guard] Left blackout window. New entries resumed.");
+            }
+            self.in_blackout = in_blackout;
+        }
+
+        if let Some(until) = self.halted_until {
+            if now < until {
+                return false;
+            }
+            // 대기 시간이 지나면 다음 틱에서 다시 시도 (다시 거부되면 재중단)
+            println!("[Guard] Halt pause elapsed. Retrying entries.");
+            self.halted_until = None;
+        }
+
+        !in_blackout
+    }
+
+    // 재시작 전까지 신규 진입 영구 중단
+    pub fn kill(&mut self, reason: String) {
+        eprintln!("[Guard] KILL SWITCH: {}. Trading halted until restart.", reason);
+        self.killed = Some(reason);
+    }
+
+    pub fn killed(&self) -> Option<&str> {
+        self.killed.as_deref()
+    }
+
+    // 거래 중단 거부 코드 수신 시 일정 시간 진입 중단
+    pub fn halt(&mut self, symbol: &str, code: i64, now: DateTime<Utc>, pause_secs: u64) {
+        let until = now + Duration::seconds(pause_secs as i64);
+        println!(
+            "[Guard] {} halted (rejection code {}). Pausing entries until {}.",
+            symbol, code, until
+        );
+        self.halted_until = Some(until);
+    }
+}
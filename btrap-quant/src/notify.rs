@@ -0,0 +1,89 @@
+use chrono::{DateTime, Duration, Utc};
+use reqwest::Client;
+use std::sync::Mutex;
+
+// 알림 이벤트
+#[derive(Debug, Clone)]
+pub enum AlertEvent {
+    Entry { binance_position: String, bitmart_position: String, gap: f64, quantity: f64 },
+    Exit { entry_gap: f64, exit_gap: f64, quantity: f64, captured_pnl: f64 },
+    Error(String),
+    CircuitBreaker(String),
+}
+
+impl AlertEvent {
+    fn message(&self) -> String {
+        match self {
+            AlertEvent::Entry { binance_position, bitmart_position, gap, quantity } => format!(
+                "[Entry] Binance {}, Bitmart {}, gap {:.4}%, quantity {}",
+                binance_position, bitmart_position, gap, quantity
+            ),
+            AlertEvent::Exit { entry_gap, exit_gap, quantity, captured_pnl } => format!(
+                "[Exit] entry gap {:.4}%, exit gap {:.4}%, quantity {}, captured {:.4} USDT",
+                entry_gap, exit_gap, quantity, captured_pnl
+            ),
+            AlertEvent::Error(message) => format!("[Error] {}", message),
+            AlertEvent::CircuitBreaker(message) => format!("[Circuit Breaker] {}", message),
+        }
+    }
+}
+
+// 전송 제한 상태
+#[derive(Debug, Default)]
+struct RateLimit {
+    last_sent: Option<DateTime<Utc>>,
+    suppressed: u32,
+}
+
+// 웹훅 알림 (Discord/Slack/Telegram 호환 JSON 전송)
+// URL이 없으면 아무것도 하지 않음
+pub struct Notifier {
+    client: Client,
+    webhook_url: Option<String>,
+    min_interval: Duration,
+    rate_limit: Mutex<RateLimit>,
+}
+
+impl Notifier {
+    pub fn new(client: Client, webhook_url: Option<String>, min_interval_secs: u64) -> Self {
+        Self {
+            client,
+            webhook_url,
+            min_interval: Duration::seconds(min_interval_secs as i64),
+            rate_limit: Mutex::new(RateLimit::default()),
+        }
+    }
+
+    // 알림 전송 (백그라운드 전송, 최소 간격 안의 알림은 버리고 개수만 기록)
+    pub fn notify(&self, event: AlertEvent) {
+        let Some(url) = self.webhook_url.clone() else {
+            return;
+        };
+
+        let mut message = event.message();
+        {
+            let mut rate_limit = self.rate_limit.lock().unwrap();
+            let now = Utc::now();
+            if let Some(last_sent) = rate_limit.last_sent {
+                if now - last_sent < self.min_interval {
+                    rate_limit.suppressed += 1;
+                    return;
+                }
+            }
+            if rate_limit.suppressed > 0 {
+                message = format!("{} ({} alerts suppressed)", message, rate_limit.suppressed);
+            }
+            rate_limit.last_sent = Some(now);
+            rate_limit.suppressed = 0;
+        }
+
+        // Discord는 content, Slack/Telegram은 text 필드 사용
+        let body = serde_json::json!({ "content": message, "text": message });
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            if let Err(e) = client.post(&url).json(&body).send().await {
+                eprintln!("[Notify] Failed to send alert: {}", e);
+            }
+        });
+    }
+}
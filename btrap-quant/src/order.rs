@@ -1,25 +1,814 @@
 use reqwest::Client;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use hmac::{Hmac, Mac};
 use sha2::Sha256;
 use hex::encode;
 use chrono::Utc;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use crate::config::{BinanceOrderRespType, ContractSpec, MarginType};
+use crate::venue::VenueHealth;
 
 type HmacSha256 = Hmac<Sha256>;
 
-// Binance 시장가 주문 응답 구조체
+// Binance 서명 파라미터 위치 (엔드포인트별로 쿼리 문자열 또는 form 본문)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SignedParams {
+    Query,
+    Body,
+}
+
+// Bitmart 요청 User-Agent (기본값이 없는 클라이언트는 일부 게이트웨이에서 거부될 수 있음)
+pub const BITMART_USER_AGENT: &str = concat!("btrap-quant/", env!("CARGO_PKG_VERSION"));
+
+// Bitmart 서명: HMAC(timestamp + payload), 16진 문자열 (payload: 요청 본문 또는 쿼리 문자열)
+pub fn bitmart_signature(secret: &str, timestamp: i64, payload: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(format!("{}{}", timestamp, payload).as_bytes());
+    encode(mac.finalize().into_bytes())
+}
+
+// 서명한 Bitmart 요청 본문
+// Bitmart는 전송된 본문 바이트 그대로 서명을 검증하므로 공백이나 키 순서가 하나라도 달라지면 인증 실패
+// 서명한 문자열을 그대로 보내야 하며 서명 이후 재직렬화하지 않음 (본문은 생성 후 변경 불가)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedBitmartBody {
+    body: String,
+    timestamp: i64,
+    signature: String,
+}
+
+impl SignedBitmartBody {
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    pub fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    // 전송된 본문이 서명한 본문과 같은 서명을 만드는지 (다르면 거래소가 인증 실패로 거부)
+    pub fn verifies(&self, secret: &str, sent: &str) -> bool {
+        bitmart_signature(secret, self.timestamp, sent) == self.signature
+    }
+}
+
+// Binance: Post Only 주문이 maker로 체결될 수 없어 거부됨
+const BINANCE_POST_ONLY_REJECTED: i64 = -5022;
+
+// 거래소별 주문 방향 표기 (Binance: BUY/SELL, Bitmart: buy/sell)
+pub fn order_side(exchange: &str, is_buy: bool) -> &'static str {
+    match (exchange, is_buy) {
+        ("Binance", true) => "BUY",
+        ("Binance", false) => "SELL",
+        (_, true) => "buy",
+        (_, false) => "sell",
+    }
+}
+
+// 포지션(LONG/SHORT)을 여는 주문 방향
+pub fn opening_side(position_side: &str, exchange: &str) -> &'static str {
+    order_side(exchange, position_side == "LONG")
+}
+
+// 포지션(LONG/SHORT)을 청산하는 주문 방향 (롱은 매도, 숏은 매수로 청산)
+pub fn closing_side(position_side: &str, exchange: &str) -> &'static str {
+    order_side(exchange, position_side != "LONG")
+}
+
+// 진입 주문 방향으로 열린 포지션 (BUY/buy면 LONG)
+pub fn position_of_side(side: &str) -> &'static str {
+    if side.eq_ignore_ascii_case("buy") {
+        "LONG"
+    } else {
+        "SHORT"
+    }
+}
+
+// 주문 오류
+#[derive(Debug)]
+pub enum OrderError {
+    // 네트워크/HTTP 오류
+    Http(reqwest::Error),
+    // 거래소가 주문을 거부함 (거래소 오류 코드 포함)
+    Rejected { code: i64, message: String },
+    // 응답 파싱 실패
+    Parse(String),
+    // 거래소 규격에 맞지 않는 주문 수량 (주문 전송 안 함)
+    InvalidSize(String),
+    // 거래소 서버 오류 (5xx 응답)
+    Unavailable(String),
+    // 진입 제한 시간 초과로 주문 결과를 기다리지 않고 중단 (제한 시간 ms)
+    DeadlineExceeded(u64),
+    // 세션 주문 수 상한 초과로 주문 전송 안 함 (시간당 상한)
+    OrderBudgetExceeded(u32),
+    // 주문은 접수됐지만 체결 여부를 확인하지 못함 (주문 ID, 체결됐을 수 있으므로 포지션으로 확인 필요)
+    FillUnconfirmed(String),
+    // 주문이 체결 없이 종료됨 (주문 ID)
+    Unfilled(String),
+    // 되돌린 뒤에도 거래소 포지션이 남아 있음 (거래소별 잔량)
+    ResidualPosition(String),
+}
+
+impl OrderError {
+    // 거래소 거부 코드 (거부가 아니면 None)
+    pub fn rejection_code(&self) -> Option<i64> {
+        match self {
+            OrderError::Rejected { code, .. } => Some(*code),
+            _ => None,
+        }
+    }
+
+    // post-only 주문이 즉시 체결될 가격이라 거부됨 (Binance -5022)
+    // Bitmart maker-only 주문은 거부 대신 접수 후 취소되므로 해당 없음
+    pub fn is_post_only_rejection(&self) -> bool {
+        self.rejection_code() == Some(BINANCE_POST_ONLY_REJECTED)
+    }
+
+    // 거래소 장애로 볼 오류 (연결 실패, 시간 초과, 5xx 응답)
+    pub fn is_outage(&self) -> bool {
+        match self {
+            OrderError::Http(e) => {
+                e.is_connect() || e.is_timeout() || e.status().is_some_and(|status| status.is_server_error())
+            }
+            OrderError::Unavailable(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for OrderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderError::Http(e) => write!(f, "HTTP error: {}", e),
+            OrderError::Rejected { code, message } => write!(f, "Rejected ({}): {}", code, message),
+            OrderError::Parse(e) => write!(f, "Failed to parse response: {}", e),
+            OrderError::InvalidSize(e) => write!(f, "Invalid order size: {}", e),
+            OrderError::Unavailable(e) => write!(f, "Exchange unavailable: {}", e),
+            OrderError::DeadlineExceeded(ms) => write!(f, "Entry deadline of {}ms exceeded", ms),
+            OrderError::OrderBudgetExceeded(limit) => {
+                write!(f, "Order budget of {} orders per hour exceeded; order placement halted", limit)
+            }
+            OrderError::FillUnconfirmed(id) => write!(f, "Order {} accepted but fill not confirmed", id),
+            OrderError::Unfilled(id) => write!(f, "Order {} ended without a fill", id),
+            OrderError::ResidualPosition(e) => write!(f, "Position still open after unwind: {}", e),
+        }
+    }
+}
+
+impl From<reqwest::Error> for OrderError {
+    fn from(e: reqwest::Error) -> Self {
+        OrderError::Http(e)
+    }
+}
+
+// Binance 오류 응답
 #[derive(Debug, Deserialize)]
-pub struct BinanceOrderResponse {
-    pub symbol: String,
-    pub order_id: u64, // snake_case로 변경
-    pub status: String,
+struct BinanceErrorResponse {
+    code: i64,
+    msg: String,
 }
 
-// Bitmart 시장가 주문 응답 구조체
+// Binance 주문 응답 (ACK면 체결 필드가 없거나 0, RESULT면 체결 결과 포함)
 #[derive(Debug, Deserialize)]
-pub struct BitmartOrderResponse {
-    pub message: String,
-    pub code: i32,
+#[serde(rename_all = "camelCase")]
+struct BinanceOrderResponse {
+    symbol: String,
+    order_id: u64, // 응답 필드는 orderId
+    executed_qty: Option<String>,
+    avg_price: Option<String>,
+    cum_quote: Option<String>, // 체결 금액 (USDT)
+    update_time: Option<i64>,
+}
+
+// Bitmart 주문 본문 (serde로 직렬화해 문자열 값이 JSON을 깨지 않게 함, None 필드는 생략)
+// 가격은 ContractSpec::format_price로 자릿수를 맞춘 문자열, size는 정수 계약 수
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BitmartOrderBody<'a> {
+    pub symbol: &'a str,
+    pub side: u8, // bitmart_side_code 참고
+    #[serde(rename = "type")]
+    pub order_type: &'a str, // "market" or "limit"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<String>,
+    pub size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u8>, // 1 GTC, 4 maker only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trigger_price: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price_way: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price_type: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reduce_only: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_order_id: Option<String>,
+    pub open_type: &'a str,
+    pub timestamp: i64,
+}
+
+// Bitmart 주문 방향 코드 (방향과 진입/청산을 함께 표현)
+// 1: 매수 롱 진입, 2: 매수 숏 청산, 3: 매도 롱 청산, 4: 매도 숏 진입
+pub fn bitmart_side_code(is_buy: bool, is_close: bool) -> u8 {
+    match (is_buy, is_close) {
+        (true, false) => 1,
+        (true, true) => 2,
+        (false, true) => 3,
+        (false, false) => 4,
+    }
+}
+
+impl<'a> BitmartOrderBody<'a> {
+    // 시장가 주문 본문 (side는 bitmart_side_code 값)
+    pub fn market(symbol: &'a str, side: u8, size: u64, open_type: MarginType, timestamp: i64) -> Self {
+        Self {
+            symbol,
+            side,
+            order_type: "market",
+            price: None,
+            size,
+            mode: None,
+            trigger_price: None,
+            price_way: None,
+            price_type: None,
+            reduce_only: None,
+            client_order_id: None,
+            open_type: open_type.as_bitmart(),
+            timestamp,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, OrderError> {
+        serde_json::to_string(self).map_err(|e| OrderError::Parse(e.to_string()))
+    }
+}
+
+// Bitmart 응답 본문 (성공 코드 1000이 아니면 Rejected)
+fn bitmart_response(text: &str) -> Result<Value, OrderError> {
+    let json = serde_json::from_str::<Value>(text).map_err(|e| OrderError::Parse(format!("{}: {}", e, text)))?;
+    let code = json.get("code").and_then(|c| c.as_i64()).unwrap_or_default();
+    if code != 1000 {
+        let message = json.get("message").and_then(|m| m.as_str()).unwrap_or_default().to_string();
+        return Err(OrderError::Rejected { code, message });
+    }
+    Ok(json)
+}
+
+// Bitmart 주문 취소 본문
+#[derive(Debug, Serialize)]
+struct BitmartCancelBody<'a> {
+    symbol: &'a str,
+    order_id: &'a str,
+}
+
+// Bitmart 시장가 주문 응답 (data는 성공 시에만 포함)
+#[derive(Debug, Deserialize)]
+struct BitmartOrderResponse {
+    message: String,
+    code: i32,
+    data: Option<BitmartOrderData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BitmartOrderData {
+    #[serde(default)]
+    order_id: Value, // 숫자 또는 문자열
+    price: Option<String>,
+}
+
+// 거래소 공통 체결 정보 (주문 응답과 private 채널 체결을 같은 형태로 정규화)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fill {
+    pub exchange: &'static str, // "Binance" or "Bitmart"
+    pub symbol: String,
+    pub is_buy: bool,
+    pub order_id: String,
+    pub filled_qty: f64, // Bitmart private 채널 체결은 계약 수
+    pub avg_price: Option<f64>, // 응답에 체결가가 없으면 None
+    pub fee: Option<f64>, // USDT 수수료 (응답에 없으면 None)
+    pub ts: i64, // ms
+    pub raw: Value, // 거래소 원본 응답
+}
+
+// 체결 내역 수수료 합계 ((수수료, USDT 여부) 목록, 비어 있거나 합산할 수 없는 항목이 있으면 None)
+fn sum_usdt_fees(fees: impl Iterator<Item = (Option<f64>, bool)>) -> Option<f64> {
+    let mut total = None;
+    for (fee, is_usdt) in fees {
+        match fee {
+            Some(fee) if is_usdt => total = Some(total.unwrap_or(0.0) + fee),
+            _ => return None,
+        }
+    }
+    total
+}
+
+// 숫자 문자열 파싱 (0은 값 없음으로 처리)
+fn parse_positive(value: Option<&str>) -> Option<f64> {
+    value?.parse::<f64>().ok().filter(|v| *v > 0.0)
+}
+
+// ACK 응답 후 주문 상태 조회 횟수와 간격 (시장가는 곧바로 체결되므로 짧게)
+const BINANCE_ACK_FILL_POLLS: u32 = 5;
+const BINANCE_ACK_FILL_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+// Binance 주문 응답 -> Fill (체결 수량이 없으면 quantity 사용, 수수료는 응답에 없음)
+fn binance_fill(text: &str, is_buy: bool, quantity: f64) -> Result<Fill, OrderError> {
+    let raw = serde_json::from_str::<Value>(text).map_err(|e| OrderError::Parse(e.to_string()))?;
+    let response = BinanceOrderResponse::deserialize(&raw).map_err(|e| OrderError::Parse(e.to_string()))?;
+    let executed = parse_positive(response.executed_qty.as_deref());
+    // avgPrice가 없거나 0이면 체결 금액 / 체결 수량
+    let avg_price = parse_positive(response.avg_price.as_deref()).or_else(|| {
+        Some(parse_positive(response.cum_quote.as_deref())? / executed?)
+    });
+    Ok(Fill {
+        exchange: "Binance",
+        symbol: response.symbol,
+        is_buy,
+        order_id: response.order_id.to_string(),
+        filled_qty: executed.unwrap_or(quantity),
+        avg_price,
+        fee: None,
+        ts: response.update_time.unwrap_or_else(|| Utc::now().timestamp_millis()),
+        raw,
+    })
+}
+
+// Binance IP 요청 가중치 사용량 (X-MBX-USED-WEIGHT-1M 응답 헤더, 매 분 초기화)
+// 한도에 가까우면 펀딩비 갱신 같은 비필수 요청을 미뤄 IP 차단을 피함
+// 복제한 Order도 같은 IP 한도를 쓰므로 사용량은 공유
+#[derive(Debug, Clone)]
+pub struct BinanceWeight {
+    limit: u32,
+    pause_ratio: f64,
+    usage: Arc<Mutex<Option<(i64, u32)>>>, // (기록한 분, 사용 가중치)
+}
+
+impl BinanceWeight {
+    pub const HEADER: &'static str = "X-MBX-USED-WEIGHT-1M";
+
+    pub fn new(limit: u32, pause_ratio: f64) -> Self {
+        Self { limit, pause_ratio, usage: Arc::new(Mutex::new(None)) }
+    }
+
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    // 응답 헤더의 사용량 기록 (헤더가 없으면 무시)
+    pub fn record(&self, headers: &reqwest::header::HeaderMap) {
+        let Some(used) = headers
+            .get(Self::HEADER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u32>().ok())
+        else {
+            return;
+        };
+        self.record_at(used, Utc::now().timestamp_millis());
+    }
+
+    // 같은 분 안에서는 늦게 도착한 응답이 값을 낮추지 않도록 최댓값 유지
+    pub fn record_at(&self, used: u32, now_ms: i64) {
+        let minute = now_ms.div_euclid(60_000);
+        let mut usage = self.usage.lock().unwrap();
+        *usage = match *usage {
+            Some((recorded, previous)) if recorded == minute => Some((minute, previous.max(used))),
+            _ => Some((minute, used)),
+        };
+    }
+
+    // 현재 분의 사용 가중치 (분이 바뀌었으면 0)
+    pub fn used_at(&self, now_ms: i64) -> u32 {
+        match *self.usage.lock().unwrap() {
+            Some((minute, used)) if minute == now_ms.div_euclid(60_000) => used,
+            _ => 0,
+        }
+    }
+
+    pub fn used(&self) -> u32 {
+        self.used_at(Utc::now().timestamp_millis())
+    }
+
+    // 비필수 요청을 미뤄야 하는지 여부 (사용량이 limit * pause_ratio 이상)
+    pub fn should_defer_at(&self, now_ms: i64) -> bool {
+        self.used_at(now_ms) as f64 >= self.limit as f64 * self.pause_ratio
+    }
+
+    pub fn should_defer(&self) -> bool {
+        self.should_defer_at(Utc::now().timestamp_millis())
+    }
+}
+
+// 세션 주문 수 상한 (최근 1시간 주문 수, 로직 오류로 주문이 폭주하는 것을 막는 안전장치)
+// 거래소 요청 한도와 별개로 모든 주문 전송 전에 확인하고, 한 번 넘으면 재시작 전까지 모든 주문 차단
+// 복제한 Order도 같은 기록을 공유
+#[derive(Debug, Clone)]
+pub struct OrderBudget {
+    max_per_hour: u32, // 0이면 제한 없음
+    sent: Arc<Mutex<VecDeque<i64>>>, // 최근 1시간 주문 시각 (ms)
+    tripped: Arc<AtomicBool>,
+}
+
+impl OrderBudget {
+    const WINDOW_MS: i64 = 3_600_000;
+
+    pub fn new(max_per_hour: u32) -> Self {
+        Self { max_per_hour, sent: Arc::new(Mutex::new(VecDeque::new())), tripped: Arc::new(AtomicBool::new(false)) }
+    }
+
+    pub fn max_per_hour(&self) -> u32 {
+        self.max_per_hour
+    }
+
+    pub fn tripped(&self) -> bool {
+        self.tripped.load(Ordering::SeqCst)
+    }
+
+    // 최근 1시간 주문 수
+    pub fn count_at(&self, now_ms: i64) -> usize {
+        let mut sent = self.sent.lock().unwrap();
+        Self::prune(&mut sent, now_ms);
+        sent.len()
+    }
+
+    // 주문 한 건 전송 허가 (허가하면 기록, 상한에 도달했으면 차단 상태로 전환 후 거부)
+    pub fn acquire_at(&self, now_ms: i64) -> Result<(), OrderError> {
+        if self.max_per_hour == 0 {
+            return Ok(());
+        }
+        if self.tripped() {
+            return Err(OrderError::OrderBudgetExceeded(self.max_per_hour));
+        }
+        let mut sent = self.sent.lock().unwrap();
+        Self::prune(&mut sent, now_ms);
+        if sent.len() >= self.max_per_hour as usize {
+            self.tripped.store(true, Ordering::SeqCst);
+            eprintln!(
+                "[Guard] !!! ORDER BUDGET TRIPPED: {} orders in the last hour reached max_orders_per_hour {}. All order placement halted until restart. !!!",
+                sent.len(),
+                self.max_per_hour
+            );
+            return Err(OrderError::OrderBudgetExceeded(self.max_per_hour));
+        }
+        sent.push_back(now_ms);
+        Ok(())
+    }
+
+    pub fn acquire(&self) -> Result<(), OrderError> {
+        self.acquire_at(Utc::now().timestamp_millis())
+    }
+
+    fn prune(sent: &mut VecDeque<i64>, now_ms: i64) {
+        while sent.front().is_some_and(|&ts| now_ms - ts >= Self::WINDOW_MS) {
+            sent.pop_front();
+        }
+    }
+}
+
+// 진입 주문 clientOrderId 발급 (거래 번호 + 거래소 태그 b/m + 순번, 예: btq1700000000000b1)
+// 진입 전에 거래 번호를 상태 파일에 저장해 두면 기록 전에 중단돼도 재시작 시 이 접두어로 주문 내역을 찾을 수 있음
+// Bitmart client_order_id는 영문/숫자만 허용해 구분자 없이 붙임, 복제한 Order도 같은 거래 번호를 공유
+#[derive(Debug, Clone, Default)]
+pub struct ClientOrderIds {
+    current: Arc<Mutex<Option<(String, u32)>>>,
+}
+
+pub const CLIENT_ORDER_PREFIX: &str = "btq";
+
+impl ClientOrderIds {
+    // 거래 번호 (진입 시작 시각 기준)
+    pub fn trade_id_at(now_ms: i64) -> String {
+        format!("{}{}", CLIENT_ORDER_PREFIX, now_ms)
+    }
+
+    // 거래 시작 (반환값이 사라지면 이후 주문에는 clientOrderId를 붙이지 않음)
+    pub fn begin(&self, trade_id: &str) -> ClientOrderScope {
+        *self.current.lock().unwrap() = Some((trade_id.to_string(), 0));
+        ClientOrderScope { ids: self.clone() }
+    }
+
+    // 다음 주문 clientOrderId (진행 중인 거래가 없으면 None)
+    pub fn next(&self, exchange_name: &str) -> Option<String> {
+        let mut current = self.current.lock().unwrap();
+        let (trade_id, seq) = current.as_mut()?;
+        *seq += 1;
+        Some(format!("{}{}{}", trade_id, if exchange_name == "Binance" { "b" } else { "m" }, seq))
+    }
+}
+
+// 진행 중인 거래 범위 (drop 시 거래 종료)
+pub struct ClientOrderScope {
+    ids: ClientOrderIds,
+}
+
+impl Drop for ClientOrderScope {
+    fn drop(&mut self) {
+        *self.ids.current.lock().unwrap() = None;
+    }
+}
+
+// 거래소 쪽 종목 레버리지와 마진 타입 (포지션 조회 응답 기준, 응답에 없으면 None)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MarginSettings {
+    pub leverage: Option<u32>,
+    pub margin_type: Option<MarginType>,
+}
+
+impl MarginSettings {
+    // 설정과 다른 항목 설명 (거래소에서 확인하지 못한 항목과 leverage가 없는 설정은 건너뜀)
+    pub fn mismatches(&self, leverage: Option<u32>, margin_type: MarginType) -> Vec<String> {
+        let mut mismatches = Vec::new();
+        if let (Some(actual), Some(expected)) = (self.leverage, leverage) {
+            if actual != expected {
+                mismatches.push(format!("leverage {}x (config {}x)", actual, expected));
+            }
+        }
+        if let Some(actual) = self.margin_type {
+            if actual != margin_type {
+                mismatches.push(format!("margin {} (config {})", actual.as_bitmart(), margin_type.as_bitmart()));
+            }
+        }
+        mismatches
+    }
+}
+
+impl fmt::Display for MarginSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.leverage {
+            Some(leverage) => write!(f, "leverage {}x", leverage)?,
+            None => write!(f, "leverage unknown")?,
+        }
+        write!(f, ", margin {}", self.margin_type.map_or("unknown", |margin_type| margin_type.as_bitmart()))
+    }
+}
+
+// 레버리지 문자열 ("20", "10.00")
+fn parse_leverage(value: Option<&Value>) -> Option<u32> {
+    let leverage = match value? {
+        Value::String(text) => text.parse::<f64>().ok()?,
+        value => value.as_f64()?,
+    };
+    (leverage >= 1.0).then(|| leverage.round() as u32)
+}
+
+// Binance positionRisk 응답의 종목 설정 (양방향 모드는 LONG/SHORT 설정이 같으므로 첫 항목 사용)
+pub fn binance_margin_settings(json: &Value, symbol: &str) -> MarginSettings {
+    let Some(position) = json
+        .as_array()
+        .and_then(|positions| positions.iter().find(|p| p.get("symbol").and_then(Value::as_str) == Some(symbol)))
+    else {
+        return MarginSettings::default();
+    };
+    MarginSettings {
+        leverage: parse_leverage(position.get("leverage")),
+        margin_type: position.get("marginType").and_then(Value::as_str).and_then(MarginType::parse),
+    }
+}
+
+// Bitmart 포지션 응답의 종목 설정 (data 배열 중 첫 항목, 포지션이 없으면 비어 있을 수 있음)
+pub fn bitmart_margin_settings(json: &Value, symbol: &str) -> MarginSettings {
+    let Some(position) = json
+        .get("data")
+        .and_then(Value::as_array)
+        .and_then(|positions| positions.iter().find(|p| p.get("symbol").and_then(Value::as_str) == Some(symbol)))
+    else {
+        return MarginSettings::default();
+    };
+    MarginSettings {
+        leverage: parse_leverage(position.get("leverage")),
+        margin_type: position.get("open_type").and_then(Value::as_str).and_then(MarginType::parse),
+    }
+}
+
+// Binance 종목 주문 자릿수 (exchangeInfo quantityPrecision, pricePrecision)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SymbolPrecision {
+    pub quantity: u32,
+    pub price: u32,
+}
+
+// 자릿수에 맞춘 주문 수량 (초과 자릿수는 내림: 올리면 의도보다 많이 주문)
+pub fn format_quantity(quantity: f64, precision: u32) -> String {
+    let factor = 10f64.powi(precision as i32);
+    // 부동소수점 오차로 한 단위가 빠지지 않도록 약간의 여유
+    let truncated = (quantity * factor + 1e-9).floor() / factor;
+    format!("{:.*}", precision as usize, truncated)
+}
+
+// 자릿수에 맞춘 주문 가격 (가장 가까운 값으로 반올림)
+pub fn format_price(price: f64, precision: u32) -> String {
+    format!("{:.*}", precision as usize, price)
+}
+
+// 시작 시 조회한 Binance 종목별 자릿수 (복제한 Order도 공유)
+// 조회 전이거나 실패한 종목은 값을 그대로 전송 (자릿수 초과 시 -1111 거부 가능)
+#[derive(Debug, Clone, Default)]
+pub struct BinancePrecision {
+    symbols: Arc<Mutex<HashMap<String, SymbolPrecision>>>,
+}
+
+impl BinancePrecision {
+    pub fn set(&self, symbol: &str, precision: SymbolPrecision) {
+        self.symbols.lock().unwrap().insert(symbol.to_string(), precision);
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<SymbolPrecision> {
+        self.symbols.lock().unwrap().get(symbol).copied()
+    }
+
+    pub fn quantity(&self, symbol: &str, quantity: f64) -> String {
+        match self.get(symbol) {
+            Some(precision) => format_quantity(quantity, precision.quantity),
+            None => quantity.to_string(),
+        }
+    }
+
+    pub fn price(&self, symbol: &str, price: f64) -> String {
+        match self.get(symbol) {
+            Some(precision) => format_price(price, precision.price),
+            None => price.to_string(),
+        }
+    }
+}
+
+// 거래소별 왕복 지연 통계 (ms)
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencyStats {
+    pub venue: &'static str,
+    pub samples: usize,
+    pub failures: usize,
+    pub min_ms: f64,
+    pub median_ms: f64,
+    pub p99_ms: f64,
+}
+
+impl LatencyStats {
+    // 측정값으로 통계 계산 (nearest-rank 백분위, 성공한 측정이 없으면 None)
+    pub fn from_samples(venue: &'static str, mut samples: Vec<f64>, failures: usize) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort_by(|a, b| a.total_cmp(b));
+        let percentile = |p: f64| {
+            let rank = (p * samples.len() as f64).ceil() as usize;
+            samples[rank.clamp(1, samples.len()) - 1]
+        };
+        Some(Self {
+            venue,
+            samples: samples.len(),
+            failures,
+            min_ms: samples[0],
+            median_ms: percentile(0.5),
+            p99_ms: percentile(0.99),
+        })
+    }
+}
+
+// 자체 점검 결과 (거래소별 최소 수량 진입 후 즉시 청산)
+#[derive(Debug)]
+pub struct SelfTestResult {
+    pub venue: &'static str,
+    pub quantity: Option<f64>, // 주문한 최소 수량 (주문 전 실패 시 None)
+    pub outcome: Result<(), String>,
+    pub leaked: bool, // 진입 후 청산을 확인하지 못해 포지션이 남아 있을 수 있음
+}
+
+impl SelfTestResult {
+    fn failed(venue: &'static str, quantity: Option<f64>, error: String, leaked: bool) -> Self {
+        Self { venue, quantity, outcome: Err(error), leaked }
+    }
+}
+
+// Binance 최소 주문 수량 (최소 수량과 최소 명목 금액을 모두 만족하도록 step 단위로 올림)
+pub fn binance_min_quantity(step_size: f64, min_quantity: f64, min_notional: Option<f64>, price: f64) -> Option<f64> {
+    if step_size <= 0.0 || price <= 0.0 {
+        return None;
+    }
+    let required = min_quantity.max(min_notional.unwrap_or(0.0) / price);
+    // 부동소수점 오차로 한 단계 더 올라가지 않도록 여유를 둠
+    let steps = (required / step_size - 1e-9).ceil().max(1.0);
+    Some((steps * step_size * 1e8).round() / 1e8)
+}
+
+// 거부된 주문 정보 (진단용)
+#[derive(Debug, Clone)]
+pub struct RejectionContext {
+    pub exchange: &'static str, // "Binance" or "Bitmart"
+    pub symbol: String,
+    pub quantity: f64,
+    pub price: Option<f64>, // 명목 금액 추정용 (없으면 잔고 비교 생략)
+}
+
+// 거부 시점의 거래소 상태 (조회 실패한 항목은 None)
+#[derive(Debug, Clone, Default)]
+pub struct ExchangeSnapshot {
+    pub clock_skew_ms: Option<i64>, // 로컬 시각 - 거래소 시각
+    pub available_balance: Option<f64>, // USDT
+    pub position: Option<f64>, // 롱 +, 숏 -
+    pub symbol_status: Option<String>,
+    pub step_size: Option<f64>,
+    pub min_quantity: Option<f64>,
+    pub min_notional: Option<f64>,
+}
+
+// Binance 종목 규격 (진단용)
+#[derive(Debug, Clone)]
+struct SymbolFilters {
+    status: String,
+    step_size: Option<f64>,
+    min_quantity: Option<f64>,
+    min_notional: Option<f64>,
+    tick_size: Option<f64>,
+    precision: Option<SymbolPrecision>,
+}
+
+// 거부 진단 결과
+#[derive(Debug, Clone)]
+pub struct RejectionReport {
+    pub code: i64,
+    pub causes: Vec<String>,   // 추정 원인
+    pub findings: Vec<String>, // 조회한 거래소 상태 (조회 실패 포함)
+}
+
+impl RejectionReport {
+    pub fn print(&self) {
+        println!("[Diagnose] Rejection code {}:", self.code);
+        for cause in &self.causes {
+            println!("[Diagnose]   likely cause: {}", cause);
+        }
+        for finding in &self.findings {
+            println!("[Diagnose]   {}", finding);
+        }
+    }
+}
+
+// 허용 시각 오차 (초과하면 서명 타임스탬프 거부 가능)
+const MAX_CLOCK_SKEW_MS: i64 = 1000;
+
+// 거부 코드와 거래소 상태로 추정 원인 설명
+pub fn explain_rejection(code: i64, context: &RejectionContext, snapshot: &ExchangeSnapshot) -> Vec<String> {
+    let mut causes = Vec::new();
+    let notional = context.price.map(|price| price * context.quantity);
+
+    // 코드로 알 수 있는 원인
+    match (context.exchange, code) {
+        ("Binance", -1021) | ("Bitmart", 30008) => {
+            causes.push("request timestamp outside the exchange window (clock skew)".to_string())
+        }
+        ("Binance", -1022) | ("Bitmart", 30007) => {
+            causes.push("invalid signature: check API secret (and Bitmart memo)".to_string())
+        }
+        ("Binance", -2019) => causes.push("insufficient margin for the order".to_string()),
+        ("Binance", -1111) | ("Binance", -1013) | ("Binance", -4003) | ("Binance", -4164) => {
+            causes.push("quantity or notional violates the symbol filters".to_string())
+        }
+        ("Binance", -2022) => causes.push("reduce-only order would not reduce the position".to_string()),
+        ("Binance", -4140) => causes.push("symbol is not open for new positions".to_string()),
+        _ => {}
+    }
+
+    // 거래소 상태로 확인되는 원인
+    if let Some(skew) = snapshot.clock_skew_ms.filter(|skew| skew.abs() > MAX_CLOCK_SKEW_MS) {
+        causes.push(format!("local clock is {}ms off the exchange clock; sync system time (NTP)", skew));
+    }
+    if let Some(status) = snapshot.symbol_status.as_deref().filter(|status| *status != "TRADING") {
+        causes.push(format!("{} status is {}", context.symbol, status));
+    }
+    if let Some(step) = snapshot.step_size.filter(|step| *step > 0.0) {
+        let steps = context.quantity / step;
+        if (steps - steps.round()).abs() > 1e-9 {
+            causes.push(format!("quantity {} is not a multiple of step size {}", context.quantity, step));
+        }
+    }
+    if let Some(min) = snapshot.min_quantity.filter(|min| context.quantity < *min) {
+        causes.push(format!("quantity {} is below minimum {}", context.quantity, min));
+    }
+    if let (Some(min), Some(notional)) = (snapshot.min_notional, notional) {
+        if notional < min {
+            causes.push(format!("notional {:.4} USDT is below minimum {}", notional, min));
+        }
+    }
+    if let (Some(balance), Some(notional)) = (snapshot.available_balance, notional) {
+        if balance < notional {
+            causes.push(format!(
+                "available balance {:.4} USDT is below order notional {:.4} USDT (fine only with enough leverage)",
+                balance, notional
+            ));
+        }
+    }
+
+    if causes.is_empty() {
+        causes.push("no known cause matched; check the exchange error documentation".to_string());
+    }
+    causes
 }
 
 // Order 구조체 정의
@@ -31,6 +820,16 @@ pub struct Order {
     pub bitmart_api_key: String,
     pub bitmart_secret_key: String,
     pub bitmart_memo: String,
+    pub open_type: MarginType, // 양쪽 거래소 마진 타입
+    pub binance_base_url: String, // 예: https://fapi.binance.com
+    pub bitmart_base_url: String, // 예: https://api-cloud.bitmart.com
+    pub bitmart_contracts: HashMap<String, ContractSpec>, // 종목별 Bitmart 계약 정보
+    pub binance_weight: BinanceWeight, // Binance 응답 헤더 기준 요청 가중치 사용량
+    pub venue_health: VenueHealth, // 주문 경로 요청 기준 거래소별 장애 상태
+    pub binance_precision: BinancePrecision, // exchangeInfo 기준 종목별 수량/가격 자릿수
+    pub order_budget: OrderBudget, // 세션 주문 수 상한 (모든 주문 전송 전 확인)
+    pub binance_resp_type: BinanceOrderRespType, // Binance 주문 newOrderRespType
+    pub client_order_ids: ClientOrderIds, // 진행 중인 진입 거래의 주문 clientOrderId
 }
 
 impl Order {
@@ -40,55 +839,1092 @@ impl Order {
         symbol: &str,
         side: &str, // "BUY" or "SELL"
         quantity: f64,
-    ) -> Result<BinanceOrderResponse, reqwest::Error> {
-        let base_url = "https://fapi.binance.com/fapi/v1/order";
+    ) -> Result<Fill, OrderError> {
         let timestamp = Utc::now().timestamp_millis();
+        let query = self.binance_market_order_query(symbol, side, quantity, timestamp);
+
+        let text = self.submit_order_binance(&query).await?;
+        self.binance_market_fill(symbol, &text, side == "BUY", quantity).await
+    }
+
+    // Binance 지정가 주문 (post_only면 GTX: 즉시 체결될 가격이면 거부되어 taker 수수료를 내지 않음)
+    // 대기 주문이면 체결 수량 0 (이후 체결은 private 채널로 확인)
+    pub async fn place_limit_order_binance(
+        &self,
+        symbol: &str,
+        side: &str, // "BUY" or "SELL"
+        quantity: f64,
+        price: f64,
+        post_only: bool,
+    ) -> Result<Fill, OrderError> {
         let query = format!(
-            "symbol={}&side={}&type=MARKET&quantity={}&timestamp={}",
-            symbol, side, quantity, timestamp
+            "symbol={}&side={}&type=LIMIT&timeInForce={}&quantity={}&price={}&newOrderRespType={}&timestamp={}",
+            symbol,
+            side,
+            if post_only { "GTX" } else { "GTC" },
+            self.binance_precision.quantity(symbol, quantity),
+            self.binance_precision.price(symbol, price),
+            self.binance_resp_type.as_binance(),
+            Utc::now().timestamp_millis()
         );
+        let text = self.submit_order_binance(&query).await?;
+        binance_fill(&text, side == "BUY", 0.0)
+    }
 
-        let signature = self.sign_binance(&query);
+    // Binance 스탑 시장가 주문 (마크 가격이 stop_price에 닿으면 시장가 실행, 보호 주문용)
+    // 대기 주문이므로 체결 수량 0
+    pub async fn place_stop_market_binance(
+        &self,
+        symbol: &str,
+        side: &str, // "BUY" or "SELL"
+        quantity: f64,
+        stop_price: f64,
+        reduce_only: bool,
+    ) -> Result<Fill, OrderError> {
+        let query = format!(
+            "symbol={}&side={}&type=STOP_MARKET&quantity={}&stopPrice={}&workingType=MARK_PRICE{}&newOrderRespType={}&timestamp={}",
+            symbol,
+            side,
+            self.binance_precision.quantity(symbol, quantity),
+            self.binance_precision.price(symbol, stop_price),
+            if reduce_only { "&reduceOnly=true" } else { "" },
+            self.binance_resp_type.as_binance(),
+            Utc::now().timestamp_millis()
+        );
+        let text = self.submit_order_binance(&query).await?;
+        binance_fill(&text, side == "BUY", 0.0)
+    }
+
+    // Binance 주문 취소 (이미 체결/취소되어 없는 주문이면 성공으로 처리)
+    pub async fn cancel_order_binance(&self, symbol: &str, order_id: &str) -> Result<(), OrderError> {
+        let query = format!("symbol={}&orderId={}&timestamp={}", symbol, order_id, Utc::now().timestamp_millis());
+        match self.send_signed_binance(reqwest::Method::DELETE, "/fapi/v1/order", &query, SignedParams::Query).await {
+            Ok(_) => Ok(()),
+            // -2011: Unknown order sent
+            Err(OrderError::Rejected { code: -2011, .. }) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
 
-        let url = format!("{}?{}&signature={}", base_url, query, signature);
-        let response = self
-            .client
-            .post(&url)
-            .header("X-MBX-APIKEY", &self.binance_api_key)
-            .send()
-            .await?;
+    // Binance 마진 타입 설정 (이미 같은 타입이면 성공으로 처리)
+    pub async fn set_margin_type_binance(&self, symbol: &str, margin_type: MarginType) -> Result<(), OrderError> {
+        let timestamp = Utc::now().timestamp_millis();
+        let query = format!(
+            "symbol={}&marginType={}&timestamp={}",
+            symbol,
+            margin_type.as_binance(),
+            timestamp
+        );
+        match self.send_signed_binance(reqwest::Method::POST, "/fapi/v1/marginType", &query, SignedParams::Body).await {
+            Ok(_) => Ok(()),
+            // -4046: No need to change margin type
+            Err(OrderError::Rejected { code: -4046, .. }) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
 
-        Ok(response.json::<BinanceOrderResponse>().await?)
+    // Binance 레버리지 설정
+    pub async fn set_leverage_binance(&self, symbol: &str, leverage: u32) -> Result<(), OrderError> {
+        let query = format!("symbol={}&leverage={}&timestamp={}", symbol, leverage, Utc::now().timestamp_millis());
+        self.send_signed_binance(reqwest::Method::POST, "/fapi/v1/leverage", &query, SignedParams::Body).await?;
+        Ok(())
     }
 
-    // Bitmart 시장가 주문
+    // Binance 종목 레버리지와 마진 타입
+    pub async fn fetch_margin_settings_binance(&self, symbol: &str) -> Result<MarginSettings, OrderError> {
+        let query = format!("symbol={}&timestamp={}", symbol, Utc::now().timestamp_millis());
+        let text = self.send_signed_binance(reqwest::Method::GET, "/fapi/v2/positionRisk", &query, SignedParams::Query).await?;
+        let json = serde_json::from_str::<Value>(&text).map_err(|e| OrderError::Parse(e.to_string()))?;
+        Ok(binance_margin_settings(&json, symbol))
+    }
+
+    // Binance 현재 포지션 수량 (롱 +, 숏 -)
+    pub async fn fetch_position_binance(&self, symbol: &str) -> Result<f64, OrderError> {
+        let query = format!("symbol={}&timestamp={}", symbol, Utc::now().timestamp_millis());
+        let text = self.send_signed_binance(reqwest::Method::GET, "/fapi/v2/positionRisk", &query, SignedParams::Query).await?;
+        let json = serde_json::from_str::<Value>(&text).map_err(|e| OrderError::Parse(e.to_string()))?;
+        let positions = json
+            .as_array()
+            .ok_or_else(|| OrderError::Parse(format!("Unexpected positionRisk response: {}", json)))?;
+        // 단방향 모드는 종목당 하나, 양방향 모드는 LONG/SHORT 합산
+        positions
+            .iter()
+            .filter(|p| p.get("symbol").and_then(|s| s.as_str()) == Some(symbol))
+            .map(|p| {
+                p.get("positionAmt")
+                    .and_then(|v| v.as_str())
+                    .and_then(|v| v.parse::<f64>().ok())
+                    .ok_or_else(|| OrderError::Parse(format!("Missing positionAmt: {}", p)))
+            })
+            .sum()
+    }
+
+    // Binance 포지션 전량 청산 (거래소 포지션 수량 기준, reduceOnly로 반대 포지션 진입 방지)
+    // closePosition=true는 STOP_MARKET/TAKE_PROFIT_MARKET 전용이라 시장가는 조회 수량으로 주문
+    // 청산할 포지션이 없으면 Ok(None)
+    pub async fn close_position_binance(
+        &self,
+        symbol: &str,
+        side: &str, // "BUY" (숏 청산) or "SELL" (롱 청산)
+    ) -> Result<Option<Fill>, OrderError> {
+        let amount = self.fetch_position_binance(symbol).await?;
+        let quantity = if side == "BUY" { -amount } else { amount };
+        if quantity <= 0.0 {
+            return Ok(None);
+        }
+        self.close_market_order_binance(symbol, side, quantity).await.map(Some)
+    }
+
+    // Binance 시장가 청산 주문 (reduceOnly로 지정 수량만 줄이고 반대 포지션을 새로 열지 않음)
+    pub async fn close_market_order_binance(
+        &self,
+        symbol: &str,
+        side: &str, // "BUY" (숏 청산) or "SELL" (롱 청산)
+        quantity: f64,
+    ) -> Result<Fill, OrderError> {
+        let query = format!(
+            "{}&reduceOnly=true",
+            self.binance_market_order_query(symbol, side, quantity, Utc::now().timestamp_millis())
+        );
+        let text = self.submit_order_binance(&query).await?;
+        self.binance_market_fill(symbol, &text, side == "BUY", quantity).await
+    }
+
+    // Binance 시장가 주문 응답 -> Fill
+    // ACK 응답은 체결 정보가 없어 주문을 조회해 체결 수량/가격을 확인 (전량 체결로 가정하지 않음)
+    // 종료 상태가 될 때까지 짧게 재조회하고, 끝내 종료되지 않으면 마지막으로 확인한 부분 체결을 반환
+    // 체결 없이 종료되면 Unfilled, 조회 실패 등으로 체결을 하나도 확인하지 못하면 FillUnconfirmed
+    async fn binance_market_fill(&self, symbol: &str, text: &str, is_buy: bool, quantity: f64) -> Result<Fill, OrderError> {
+        if self.binance_resp_type == BinanceOrderRespType::Result {
+            return binance_fill(text, is_buy, quantity);
+        }
+        let order_id = binance_fill(text, is_buy, 0.0)?.order_id;
+        let mut observed = None;
+        for attempt in 0..BINANCE_ACK_FILL_POLLS {
+            if attempt > 0 {
+                tokio::time::sleep(BINANCE_ACK_FILL_POLL_INTERVAL).await;
+            }
+            let query = format!("symbol={}&orderId={}&timestamp={}", symbol, order_id, Utc::now().timestamp_millis());
+            let text = match self.send_signed_binance(reqwest::Method::GET, "/fapi/v1/order", &query, SignedParams::Query).await {
+                Ok(text) => text,
+                Err(e) => {
+                    eprintln!("[Order] Failed to query Binance order {} fill: {}", order_id, e);
+                    continue;
+                }
+            };
+            let fill = binance_fill(&text, is_buy, 0.0)?;
+            match fill.raw.get("status").and_then(|s| s.as_str()) {
+                Some("FILLED" | "CANCELED" | "EXPIRED" | "REJECTED") if fill.filled_qty > 0.0 => return Ok(fill),
+                Some("CANCELED" | "EXPIRED" | "REJECTED") => return Err(OrderError::Unfilled(order_id)),
+                _ if fill.filled_qty > 0.0 => observed = Some(fill),
+                _ => {}
+            }
+        }
+        match observed {
+            Some(fill) => {
+                println!(
+                    "[Order] Binance order {} still open after {} status checks. Using observed fill of {}.",
+                    order_id, BINANCE_ACK_FILL_POLLS, fill.filled_qty
+                );
+                Ok(fill)
+            }
+            None => Err(OrderError::FillUnconfirmed(order_id)),
+        }
+    }
+
+    // Binance 주문 전송 (세션 주문 수 상한 확인 후, 진행 중인 거래가 있으면 clientOrderId 추가)
+    async fn submit_order_binance(&self, query: &str) -> Result<String, OrderError> {
+        self.order_budget.acquire()?;
+        let query = match self.client_order_ids.next("Binance") {
+            Some(id) => format!("{}&newClientOrderId={}", query, id),
+            None => query.to_string(),
+        };
+        self.send_signed_binance(reqwest::Method::POST, "/fapi/v1/order", &query, SignedParams::Query).await
+    }
+
+    // 거래 번호로 시작하는 clientOrderId 주문의 체결 (since_ms 이후 주문, 체결 수량이 없는 주문은 제외)
+    pub async fn fetch_client_orders_binance(&self, symbol: &str, trade_id: &str, since_ms: i64) -> Result<Vec<Fill>, OrderError> {
+        let query = format!("symbol={}&startTime={}&timestamp={}", symbol, since_ms, Utc::now().timestamp_millis());
+        let text = self.send_signed_binance(reqwest::Method::GET, "/fapi/v1/allOrders", &query, SignedParams::Query).await?;
+        let json = serde_json::from_str::<Value>(&text).map_err(|e| OrderError::Parse(e.to_string()))?;
+        let orders = json
+            .as_array()
+            .ok_or_else(|| OrderError::Parse(format!("Unexpected allOrders response: {}", json)))?;
+        Ok(orders
+            .iter()
+            .filter(|o| o.get("clientOrderId").and_then(|id| id.as_str()).is_some_and(|id| id.starts_with(trade_id)))
+            .filter_map(|o| {
+                let filled_qty = parse_positive(o.get("executedQty").and_then(|v| v.as_str()))?;
+                Some(Fill {
+                    exchange: "Binance",
+                    symbol: symbol.to_string(),
+                    is_buy: o.get("side").and_then(|s| s.as_str()) == Some("BUY"),
+                    order_id: o.get("orderId").map(|id| id.to_string()).unwrap_or_default(),
+                    filled_qty,
+                    avg_price: parse_positive(o.get("avgPrice").and_then(|v| v.as_str())),
+                    fee: None,
+                    ts: o.get("updateTime").and_then(|t| t.as_i64()).unwrap_or(since_ms),
+                    raw: o.clone(),
+                })
+            })
+            .collect())
+    }
+
+    // Binance 서명 요청 (오류 응답은 OrderError::Rejected로 변환)
+    // 서명은 실제로 보내는 파라미터 문자열 그대로에 대해 계산하고 같은 위치(쿼리 또는 본문)에 붙임
+    async fn send_signed_binance(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        params: &str,
+        placement: SignedParams,
+    ) -> Result<String, OrderError> {
+        let signed = format!("{}&signature={}", params, self.sign_binance(params));
+        let request = match placement {
+            SignedParams::Query => self.client.request(method, format!("{}{}?{}", self.binance_base_url, path, signed)),
+            SignedParams::Body => self
+                .client
+                .request(method, format!("{}{}", self.binance_base_url, path))
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .body(signed),
+        };
+        let result = async {
+            let response = request.header("X-MBX-APIKEY", &self.binance_api_key).send().await?;
+            self.binance_weight.record(response.headers());
+
+            let status = response.status();
+            let text = response.text().await?;
+            if status.is_server_error() {
+                return Err(OrderError::Unavailable(format!("HTTP {}: {}", status, text)));
+            }
+            if !status.is_success() {
+                return Err(match serde_json::from_str::<BinanceErrorResponse>(&text) {
+                    Ok(error) => OrderError::Rejected { code: error.code, message: error.msg },
+                    Err(_) => OrderError::Parse(format!("HTTP {}: {}", status, text)),
+                });
+            }
+            Ok(text)
+        }
+        .await;
+        self.venue_health.observe("Binance", &result);
+        result
+    }
+
+    // Binance 사용자 데이터 스트림 listenKey 발급 (서명 없이 API 키만 필요)
+    pub async fn create_listen_key_binance(&self) -> Result<String, OrderError> {
+        let text = self.send_listen_key_binance(self.client.post(self.listen_key_url())).await?;
+        let json = serde_json::from_str::<Value>(&text).map_err(|e| OrderError::Parse(e.to_string()))?;
+        json.get("listenKey")
+            .and_then(|v| v.as_str())
+            .map(|key| key.to_string())
+            .ok_or_else(|| OrderError::Parse(format!("Missing listenKey: {}", json)))
+    }
+
+    // listenKey 연장 (60분 뒤 만료되므로 주기적으로 호출)
+    pub async fn keepalive_listen_key_binance(&self) -> Result<(), OrderError> {
+        self.send_listen_key_binance(self.client.put(self.listen_key_url())).await?;
+        Ok(())
+    }
+
+    fn listen_key_url(&self) -> String {
+        format!("{}/fapi/v1/listenKey", self.binance_base_url)
+    }
+
+    // listenKey 요청 전송 (오류 응답은 OrderError::Rejected로 변환)
+    async fn send_listen_key_binance(&self, request: reqwest::RequestBuilder) -> Result<String, OrderError> {
+        let response = request.header("X-MBX-APIKEY", &self.binance_api_key).send().await?;
+        self.binance_weight.record(response.headers());
+        let status = response.status();
+        let text = response.text().await?;
+        if !status.is_success() {
+            return Err(match serde_json::from_str::<BinanceErrorResponse>(&text) {
+                Ok(error) => OrderError::Rejected { code: error.code, message: error.msg },
+                Err(_) => OrderError::Parse(format!("HTTP {}: {}", status, text)),
+            });
+        }
+        Ok(text)
+    }
+
+    // Bitmart private WebSocket 로그인 메시지
+    // 서명: HMAC(timestamp + "#" + memo + "#bitmart.WebSocket")
+    pub fn bitmart_ws_login(&self, timestamp: i64) -> Value {
+        let signature = self.sign_bitmart(&format!("#{}#bitmart.WebSocket", self.bitmart_memo), timestamp);
+        serde_json::json!({
+            "action": "access",
+            "args": [self.bitmart_api_key, timestamp.to_string(), signature, "web"]
+        })
+    }
+
+    // Bitmart 시장가 진입 주문
     pub async fn place_market_order_bitmart(
         &self,
         symbol: &str,
         side: &str, // "buy" or "sell"
-        size: f64,
-    ) -> Result<BitmartOrderResponse, reqwest::Error> {
-        let base_url = "https://api-cloud.bitmart.com/futures/v1/submit-order";
+        size: f64, // 기초자산 수량 (계약 수로 변환)
+    ) -> Result<Fill, OrderError> {
+        self.market_order_bitmart(symbol, side, size, false).await
+    }
+
+    // Bitmart 시장가 청산 주문 (청산 방향 코드로 보내 반대 포지션을 새로 열지 않음)
+    pub async fn close_market_order_bitmart(
+        &self,
+        symbol: &str,
+        side: &str, // "buy" (숏 청산) or "sell" (롱 청산)
+        size: f64, // 기초자산 수량 (계약 수로 변환)
+    ) -> Result<Fill, OrderError> {
+        self.market_order_bitmart(symbol, side, size, true).await
+    }
+
+    // 주문 응답에는 체결 수량이 없어 주문한 계약 수를 기초자산 수량으로 환산해 기록
+    async fn market_order_bitmart(&self, symbol: &str, side: &str, size: f64, is_close: bool) -> Result<Fill, OrderError> {
+        let contracts = self.bitmart_contract_count(symbol, size)?;
         let timestamp = Utc::now().timestamp_millis();
-        let body = format!(
-            "{{\"symbol\": \"{}\", \"side\": \"{}\", \"type\": \"market\", \"size\": {}, \"timestamp\": {}}}",
-            symbol, side, size, timestamp
-        );
+        let code = bitmart_side_code(side == "buy", is_close);
+        let body = BitmartOrderBody {
+            client_order_id: self.client_order_ids.next("Bitmart"),
+            ..BitmartOrderBody::market(symbol, code, contracts, self.open_type, timestamp)
+        }
+        .to_json()?;
+        let mut fill = self.submit_order_bitmart("/futures/v1/submit-order", symbol, side, body, timestamp).await?;
+        let contract_size = self.bitmart_contracts.get(symbol).map_or(1.0, |spec| spec.contract_size);
+        fill.filled_qty = contracts as f64 * contract_size;
+        Ok(fill)
+    }
 
-        let signature = self.sign_bitmart(&body, timestamp);
+    // Bitmart 지정가 주문 (post_only면 mode 4: maker only, 즉시 체결될 가격이면 거래소가 취소)
+    // 대기 주문이므로 체결 수량 0 (이후 체결은 private 채널로 확인)
+    pub async fn place_limit_order_bitmart(
+        &self,
+        symbol: &str,
+        side: &str, // "buy" or "sell"
+        size: f64, // 기초자산 수량 (계약 수로 변환)
+        price: f64,
+        post_only: bool,
+    ) -> Result<Fill, OrderError> {
+        let contracts = self.bitmart_contract_count(symbol, size)?;
+        let timestamp = Utc::now().timestamp_millis();
+        let body = BitmartOrderBody {
+            order_type: "limit",
+            price: Some(self.bitmart_price(symbol, price)?),
+            mode: Some(if post_only { 4 } else { 1 }),
+            client_order_id: self.client_order_ids.next("Bitmart"),
+            ..BitmartOrderBody::market(symbol, bitmart_side_code(side == "buy", false), contracts, self.open_type, timestamp)
+        }
+        .to_json()?;
+        let mut fill = self.submit_order_bitmart("/futures/v1/submit-order", symbol, side, body, timestamp).await?;
+        fill.filled_qty = 0.0;
+        Ok(fill)
+    }
 
-        let response = self
-            .client
-            .post(base_url)
+    // Bitmart 스탑 시장가 주문 (계획 주문: 공정가가 stop_price에 닿으면 시장가 실행, 보호 주문용)
+    // price_way: 1 가격 상승 시 실행 (매수 스탑), 2 가격 하락 시 실행 (매도 스탑) / price_type 2: 공정가 기준
+    pub async fn place_stop_market_bitmart(
+        &self,
+        symbol: &str,
+        side: &str, // "buy" or "sell"
+        size: f64, // 기초자산 수량 (계약 수로 변환)
+        stop_price: f64,
+        reduce_only: bool,
+    ) -> Result<Fill, OrderError> {
+        let contracts = self.bitmart_contract_count(symbol, size)?;
+        let timestamp = Utc::now().timestamp_millis();
+        let body = BitmartOrderBody {
+            trigger_price: Some(self.bitmart_price(symbol, stop_price)?),
+            price_way: Some(if side == "buy" { 1 } else { 2 }),
+            price_type: Some(2),
+            reduce_only: Some(reduce_only),
+            ..BitmartOrderBody::market(symbol, bitmart_side_code(side == "buy", reduce_only), contracts, self.open_type, timestamp)
+        }
+        .to_json()?;
+        self.submit_order_bitmart("/contract/private/submit-plan-order", symbol, side, body, timestamp).await
+    }
+
+    // Bitmart 계획 주문 취소
+    pub async fn cancel_plan_order_bitmart(&self, symbol: &str, order_id: &str) -> Result<(), OrderError> {
+        self.cancel_bitmart("/contract/private/cancel-plan-order", symbol, order_id).await
+    }
+
+    // Bitmart 일반 주문 취소 (미체결 지정가 주문)
+    pub async fn cancel_order_bitmart(&self, symbol: &str, order_id: &str) -> Result<(), OrderError> {
+        self.cancel_bitmart("/contract/private/cancel-order", symbol, order_id).await
+    }
+
+    async fn cancel_bitmart(&self, path: &str, symbol: &str, order_id: &str) -> Result<(), OrderError> {
+        let timestamp = Utc::now().timestamp_millis();
+        let body = serde_json::to_string(&BitmartCancelBody { symbol, order_id })
+            .map_err(|e| OrderError::Parse(e.to_string()))?;
+        let request = self.bitmart_post(path, self.sign_bitmart_body(body, timestamp));
+        let text = self.send_bitmart(request).await?;
+        let json = serde_json::from_str::<Value>(&text).map_err(|e| OrderError::Parse(format!("{}: {}", e, text)))?;
+        let code = json.get("code").and_then(|c| c.as_i64()).unwrap_or_default();
+        if code != 1000 {
+            let message = json.get("message").and_then(|m| m.as_str()).unwrap_or_default().to_string();
+            return Err(OrderError::Rejected { code, message });
+        }
+        Ok(())
+    }
+
+    // Bitmart 주문 전송 (세션 주문 수 상한 확인 후, 체결 수량은 호출 측에서 설정)
+    async fn submit_order_bitmart(
+        &self,
+        path: &str,
+        symbol: &str,
+        side: &str,
+        body: String,
+        timestamp: i64,
+    ) -> Result<Fill, OrderError> {
+        self.order_budget.acquire()?;
+        let request = self.bitmart_post(path, self.sign_bitmart_body(body, timestamp));
+        let text = self.send_bitmart(request).await?;
+        let raw = serde_json::from_str::<Value>(&text).map_err(|e| OrderError::Parse(format!("{}: {}", e, text)))?;
+        let parsed = BitmartOrderResponse::deserialize(&raw)
+            .map_err(|e| OrderError::Parse(format!("{}: {}", e, text)))?;
+        // Bitmart 성공 코드는 1000
+        if parsed.code != 1000 {
+            return Err(OrderError::Rejected { code: parsed.code as i64, message: parsed.message });
+        }
+        let order_id = match parsed.data.as_ref().map(|data| &data.order_id) {
+            Some(Value::String(id)) => id.clone(),
+            Some(Value::Number(id)) => id.to_string(),
+            _ => String::new(),
+        };
+        Ok(Fill {
+            exchange: "Bitmart",
+            symbol: symbol.to_string(),
+            is_buy: side == "buy",
+            order_id,
+            filled_qty: 0.0,
+            avg_price: parse_positive(parsed.data.as_ref().and_then(|data| data.price.as_deref())),
+            fee: None,
+            ts: timestamp,
+            raw,
+        })
+    }
+
+    // Bitmart 요청 전송 후 응답 본문 반환 (5xx는 장애로 기록, 그 외 오류 코드는 호출 측에서 확인)
+    // 서명한 본문을 그대로 담은 Bitmart POST 요청
+    fn bitmart_post(&self, path: &str, signed: SignedBitmartBody) -> reqwest::RequestBuilder {
+        self.client
+            .post(format!("{}{}", self.bitmart_base_url, path))
             .header("X-BM-KEY", &self.bitmart_api_key)
-            .header("X-BM-SIGN", signature)
-            .header("X-BM-TIMESTAMP", timestamp.to_string())
+            .header("X-BM-SIGN", &signed.signature)
+            .header("X-BM-TIMESTAMP", signed.timestamp.to_string())
             .header("Content-Type", "application/json")
-            .body(body)
-            .send()
-            .await?;
+            .header("User-Agent", BITMART_USER_AGENT)
+            .body(signed.body)
+    }
+
+    // 서명한 Bitmart GET 요청 (query: 서명 대상 쿼리 문자열, 없으면 빈 문자열)
+    fn bitmart_get(&self, path: &str, query: &str) -> reqwest::RequestBuilder {
+        let timestamp = Utc::now().timestamp_millis();
+        let url = if query.is_empty() {
+            format!("{}{}", self.bitmart_base_url, path)
+        } else {
+            format!("{}{}?{}", self.bitmart_base_url, path, query)
+        };
+        self.client
+            .get(url)
+            .header("X-BM-KEY", &self.bitmart_api_key)
+            .header("X-BM-SIGN", self.sign_bitmart(query, timestamp))
+            .header("X-BM-TIMESTAMP", timestamp.to_string())
+            .header("User-Agent", BITMART_USER_AGENT)
+    }
+
+    async fn send_bitmart(&self, request: reqwest::RequestBuilder) -> Result<String, OrderError> {
+        let result = async {
+            let response = request.send().await?;
+            let status = response.status();
+            let text = response.text().await?;
+            if status.is_server_error() {
+                return Err(OrderError::Unavailable(format!("HTTP {}: {}", status, text)));
+            }
+            Ok(text)
+        }
+        .await;
+        self.venue_health.observe("Bitmart", &result);
+        result
+    }
+
+    // Bitmart 포지션 전량 청산 (시장가 전량 청산 플래그가 없어 거래소 포지션 수량으로 주문)
+    // 청산할 포지션이 없으면 Ok(None)
+    pub async fn close_position_bitmart(
+        &self,
+        symbol: &str,
+        side: &str, // "buy" (숏 청산) or "sell" (롱 청산)
+    ) -> Result<Option<Fill>, OrderError> {
+        let amount = self.fetch_position_bitmart(symbol).await?;
+        let quantity = if side == "buy" { -amount } else { amount };
+        if quantity <= 0.0 {
+            return Ok(None);
+        }
+        self.close_market_order_bitmart(symbol, side, quantity).await.map(Some)
+    }
+
+    // Bitmart 레버리지 설정 (마진 타입과 함께 지정)
+    pub async fn set_leverage_bitmart(&self, symbol: &str, leverage: u32, open_type: MarginType) -> Result<(), OrderError> {
+        let timestamp = Utc::now().timestamp_millis();
+        let body = serde_json::json!({
+            "symbol": symbol,
+            "leverage": leverage.to_string(),
+            "open_type": open_type.as_bitmart(),
+        })
+        .to_string();
+        let request = self.bitmart_post("/contract/private/submit-leverage", self.sign_bitmart_body(body, timestamp));
+        let text = self.send_bitmart(request).await?;
+        bitmart_response(&text).map(|_| ())
+    }
+
+    // Bitmart 종목 레버리지와 마진 타입
+    pub async fn fetch_margin_settings_bitmart(&self, symbol: &str) -> Result<MarginSettings, OrderError> {
+        let query = format!("symbol={}", symbol);
+        let request = self.bitmart_get("/contract/private/position", &query);
+        let text = self.send_bitmart(request).await?;
+        Ok(bitmart_margin_settings(&bitmart_response(&text)?, symbol))
+    }
+
+    // Bitmart 현재 포지션 수량 (기초자산 단위, 롱 +, 숏 -)
+    pub async fn fetch_position_bitmart(&self, symbol: &str) -> Result<f64, OrderError> {
+        let query = format!("symbol={}", symbol);
+        let request = self.bitmart_get("/contract/private/position", &query);
+        let text = self.send_bitmart(request).await?;
+        let json = serde_json::from_str::<Value>(&text).map_err(|e| OrderError::Parse(format!("{}: {}", e, text)))?;
+        let code = json.get("code").and_then(|c| c.as_i64()).unwrap_or_default();
+        if code != 1000 {
+            let message = json.get("message").and_then(|m| m.as_str()).unwrap_or_default().to_string();
+            return Err(OrderError::Rejected { code, message });
+        }
+        let contract_size = self
+            .bitmart_contracts
+            .get(symbol)
+            .map(|spec| spec.contract_size)
+            .ok_or_else(|| OrderError::InvalidSize(format!("no Bitmart contract spec for {}", symbol)))?;
+        let positions = json
+            .get("data")
+            .and_then(|d| d.as_array())
+            .ok_or_else(|| OrderError::Parse(format!("Missing position data: {}", json)))?;
+        // position_type: 1 롱, 2 숏 / current_amount: 계약 수
+        positions
+            .iter()
+            .filter(|p| p.get("symbol").and_then(|s| s.as_str()) == Some(symbol))
+            .map(|p| {
+                let amount = p
+                    .get("current_amount")
+                    .and_then(|v| v.as_str())
+                    .and_then(|v| v.parse::<f64>().ok())
+                    .ok_or_else(|| OrderError::Parse(format!("Missing current_amount: {}", p)))?;
+                let sign = if p.get("position_type").and_then(|t| t.as_i64()) == Some(2) { -1.0 } else { 1.0 };
+                Ok(sign * amount * contract_size)
+            })
+            .sum()
+    }
+
+    // 거래 번호로 시작하는 client_order_id 주문의 체결 (since_ms 이후 주문, 기초자산 수량으로 환산)
+    // side: 1, 2 매수 / 3, 4 매도, deal_size: 체결 계약 수
+    pub async fn fetch_client_orders_bitmart(&self, symbol: &str, trade_id: &str, since_ms: i64) -> Result<Vec<Fill>, OrderError> {
+        let query = format!(
+            "symbol={}&start_time={}&end_time={}",
+            symbol,
+            since_ms / 1000,
+            Utc::now().timestamp_millis() / 1000 + 1
+        );
+        let request = self.bitmart_get("/contract/private/order-history", &query);
+        let json = bitmart_response(&self.send_bitmart(request).await?)?;
+        let contract_size = self.bitmart_contracts.get(symbol).map_or(1.0, |spec| spec.contract_size);
+        let orders = json
+            .get("data")
+            .and_then(|d| d.as_array())
+            .ok_or_else(|| OrderError::Parse(format!("Missing order history data: {}", json)))?;
+        Ok(orders
+            .iter()
+            .filter(|o| o.get("client_order_id").and_then(|id| id.as_str()).is_some_and(|id| id.starts_with(trade_id)))
+            .filter_map(|o| {
+                let contracts = parse_positive(o.get("deal_size").and_then(|v| v.as_str()))?;
+                let order_id = match o.get("order_id") {
+                    Some(Value::String(id)) => id.clone(),
+                    Some(id) => id.to_string(),
+                    None => String::new(),
+                };
+                Some(Fill {
+                    exchange: "Bitmart",
+                    symbol: symbol.to_string(),
+                    is_buy: matches!(o.get("side").and_then(|s| s.as_i64()), Some(1 | 2)),
+                    order_id,
+                    filled_qty: contracts * contract_size,
+                    avg_price: parse_positive(o.get("deal_avg_price").and_then(|v| v.as_str())),
+                    fee: None,
+                    ts: o.get("update_time").and_then(|t| t.as_i64()).unwrap_or(since_ms),
+                    raw: o.clone(),
+                })
+            })
+            .collect())
+    }
+
+    // 주문의 실제 체결 수수료 (USDT, 응답에 수수료가 있으면 그대로 사용)
+    // 체결 내역이 아직 없거나 USDT 외 자산으로 부과되어 합산할 수 없으면 None
+    pub async fn fetch_fill_fee(&self, fill: &Fill) -> Result<Option<f64>, OrderError> {
+        if fill.fee.is_some() {
+            return Ok(fill.fee);
+        }
+        match fill.exchange {
+            "Binance" => self.fetch_fill_fee_binance(&fill.symbol, &fill.order_id).await,
+            _ => self.fetch_fill_fee_bitmart(&fill.symbol, &fill.order_id).await,
+        }
+    }
+
+    // Binance 주문별 체결 내역의 수수료 합계 (commission, commissionAsset)
+    async fn fetch_fill_fee_binance(&self, symbol: &str, order_id: &str) -> Result<Option<f64>, OrderError> {
+        let query = format!("symbol={}&orderId={}&timestamp={}", symbol, order_id, Utc::now().timestamp_millis());
+        let text = self.send_signed_binance(reqwest::Method::GET, "/fapi/v1/userTrades", &query, SignedParams::Query).await?;
+        let json = serde_json::from_str::<Value>(&text).map_err(|e| OrderError::Parse(e.to_string()))?;
+        let trades = json
+            .as_array()
+            .ok_or_else(|| OrderError::Parse(format!("Unexpected userTrades response: {}", json)))?;
+        Ok(sum_usdt_fees(trades.iter().map(|t| {
+            let fee = t.get("commission").and_then(|v| v.as_str()).and_then(|v| v.parse::<f64>().ok());
+            (fee, t.get("commissionAsset").and_then(|v| v.as_str()) == Some("USDT"))
+        })))
+    }
+
+    // Bitmart 주문별 체결 내역의 수수료 합계 (paid_fees, USDT 정산)
+    async fn fetch_fill_fee_bitmart(&self, symbol: &str, order_id: &str) -> Result<Option<f64>, OrderError> {
+        let query = format!("symbol={}", symbol);
+        let request = self.bitmart_get("/contract/private/trades", &query);
+        let text = self.send_bitmart(request).await?;
+        let json = serde_json::from_str::<Value>(&text).map_err(|e| OrderError::Parse(format!("{}: {}", e, text)))?;
+        let code = json.get("code").and_then(|c| c.as_i64()).unwrap_or_default();
+        if code != 1000 {
+            let message = json.get("message").and_then(|m| m.as_str()).unwrap_or_default().to_string();
+            return Err(OrderError::Rejected { code, message });
+        }
+        let trades = json
+            .get("data")
+            .and_then(|d| d.as_array())
+            .ok_or_else(|| OrderError::Parse(format!("Missing trade data: {}", json)))?;
+        Ok(sum_usdt_fees(
+            trades
+                .iter()
+                .filter(|t| t.get("order_id").and_then(|v| v.as_str()) == Some(order_id))
+                .map(|t| (t.get("paid_fees").and_then(|v| v.as_str()).and_then(|v| v.parse::<f64>().ok()), true)),
+        ))
+    }
+
+    // Binance 마크 가격 (자체 점검 최소 명목 금액 계산용)
+    async fn fetch_mark_price_binance(&self, symbol: &str) -> Result<f64, OrderError> {
+        let url = format!("{}/fapi/v1/premiumIndex?symbol={}", self.binance_base_url, symbol);
+        let json = self.get_json(&url).await?;
+        json.get("markPrice")
+            .and_then(|v| v.as_str())
+            .and_then(|v| v.parse::<f64>().ok())
+            .ok_or_else(|| OrderError::Parse(format!("Missing markPrice: {}", json)))
+    }
+
+    // Binance 자체 점검: 최소 수량 매수 후 reduceOnly 전량 청산, 거래소 포지션 0 확인
+    // 기존 포지션이 있으면 전량 청산 대상이 되므로 주문하지 않음
+    pub async fn self_test_binance(&self, symbol: &str) -> SelfTestResult {
+        let venue = "Binance";
+        match self.fetch_position_binance(symbol).await {
+            Ok(amount) if amount != 0.0 => {
+                return SelfTestResult::failed(venue, None, format!("existing {} position of {}", symbol, amount), false)
+            }
+            Ok(_) => {}
+            Err(e) => return SelfTestResult::failed(venue, None, format!("position check failed: {}", e), false),
+        }
+        let quantity = match self.fetch_symbol_filters_binance(symbol).await {
+            Ok(filters) => match self.fetch_mark_price_binance(symbol).await {
+                Ok(price) => binance_min_quantity(
+                    filters.step_size.unwrap_or_default(),
+                    filters.min_quantity.unwrap_or_default(),
+                    filters.min_notional,
+                    price,
+                ),
+                Err(e) => return SelfTestResult::failed(venue, None, format!("mark price unavailable: {}", e), false),
+            },
+            Err(e) => return SelfTestResult::failed(venue, None, format!("symbol filters unavailable: {}", e), false),
+        };
+        let Some(quantity) = quantity else {
+            return SelfTestResult::failed(venue, None, "cannot derive minimum quantity from filters".to_string(), false);
+        };
+        if let Err(e) = self.place_market_order_binance(symbol, "BUY", quantity).await {
+            return SelfTestResult::failed(venue, Some(quantity), format!("open failed: {}", e), false);
+        }
+        if let Err(e) = self.close_position_binance(symbol, "SELL").await {
+            return SelfTestResult::failed(venue, Some(quantity), format!("close failed: {}", e), true);
+        }
+        Self::self_test_flat(venue, quantity, self.fetch_position_binance(symbol).await)
+    }
+
+    // Bitmart 자체 점검: 최소 계약 수 매수 후 거래소 포지션 수량만큼 청산, 포지션 0 확인
+    pub async fn self_test_bitmart(&self, symbol: &str) -> SelfTestResult {
+        let venue = "Bitmart";
+        let Some(spec) = self.bitmart_contracts.get(symbol) else {
+            return SelfTestResult::failed(venue, None, format!("no Bitmart contract spec for {}", symbol), false);
+        };
+        match self.fetch_position_bitmart(symbol).await {
+            Ok(amount) if amount != 0.0 => {
+                return SelfTestResult::failed(venue, None, format!("existing {} position of {}", symbol, amount), false)
+            }
+            Ok(_) => {}
+            Err(e) => return SelfTestResult::failed(venue, None, format!("position check failed: {}", e), false),
+        }
+        let quantity = spec.contract_size * spec.min_contracts as f64;
+        if let Err(e) = self.place_market_order_bitmart(symbol, "buy", quantity).await {
+            return SelfTestResult::failed(venue, Some(quantity), format!("open failed: {}", e), false);
+        }
+        if let Err(e) = self.close_position_bitmart(symbol, "sell").await {
+            return SelfTestResult::failed(venue, Some(quantity), format!("close failed: {}", e), true);
+        }
+        Self::self_test_flat(venue, quantity, self.fetch_position_bitmart(symbol).await)
+    }
+
+    // 청산 후 포지션 확인 (확인하지 못하면 남아 있는 것으로 간주)
+    fn self_test_flat(venue: &'static str, quantity: f64, position: Result<f64, OrderError>) -> SelfTestResult {
+        match position {
+            Ok(amount) if amount.abs() <= f64::EPSILON => {
+                SelfTestResult { venue, quantity: Some(quantity), outcome: Ok(()), leaked: false }
+            }
+            Ok(amount) => SelfTestResult::failed(venue, Some(quantity), format!("position {} left after close", amount), true),
+            Err(e) => SelfTestResult::failed(venue, Some(quantity), format!("could not confirm close: {}", e), true),
+        }
+    }
 
-        Ok(response.json::<BitmartOrderResponse>().await?)
+    // Binance 현재 펀딩비 (%, 8시간 기준)
+    pub async fn fetch_funding_rate_binance(&self, symbol: &str) -> Result<f64, OrderError> {
+        let url = format!("{}/fapi/v1/premiumIndex?symbol={}", self.binance_base_url, symbol);
+        let json = self.get_json(&url).await?;
+        json.get("lastFundingRate")
+            .and_then(|v| v.as_str())
+            .and_then(|v| v.parse::<f64>().ok())
+            .map(|rate| rate * 100.0)
+            .ok_or_else(|| OrderError::Parse(format!("Missing lastFundingRate: {}", json)))
+    }
+
+    // Bitmart 현재 펀딩비 (%, 8시간 기준)
+    pub async fn fetch_funding_rate_bitmart(&self, symbol: &str) -> Result<f64, OrderError> {
+        let url = format!("{}/contract/public/funding-rate?symbol={}", self.bitmart_base_url, symbol);
+        let json = self.get_json(&url).await?;
+        json.get("data")
+            .and_then(|data| data.get("rate_value"))
+            .and_then(|v| v.as_str())
+            .and_then(|v| v.parse::<f64>().ok())
+            .map(|rate| rate * 100.0)
+            .ok_or_else(|| OrderError::Parse(format!("Missing rate_value: {}", json)))
+    }
+
+    // 주문 API 서버까지 왕복 지연 측정 (서버 시간 조회, 주문/계정에 영향 없음)
+    // 반환: 거래소별 통계 (모든 요청이 실패한 거래소는 None)
+    pub async fn benchmark_latency(&self, samples: usize) -> Vec<(&'static str, Option<LatencyStats>)> {
+        let venues = [
+            ("Binance", format!("{}/fapi/v1/time", self.binance_base_url)),
+            ("Bitmart", format!("{}/system/time", self.bitmart_base_url)),
+        ];
+        let mut results = Vec::new();
+        for (venue, url) in venues {
+            let mut elapsed = Vec::with_capacity(samples);
+            let mut failures = 0;
+            for _ in 0..samples {
+                let started = Instant::now();
+                let result = self.client.get(&url).send().await.and_then(|r| r.error_for_status());
+                match result {
+                    Ok(response) => {
+                        self.binance_weight.record(response.headers());
+                        // 본문까지 받은 시점을 왕복 완료로 봄
+                        let _ = response.bytes().await;
+                        elapsed.push(started.elapsed().as_secs_f64() * 1000.0);
+                    }
+                    Err(e) => {
+                        eprintln!("[Order] {} latency probe failed: {}", venue, e);
+                        failures += 1;
+                    }
+                }
+            }
+            results.push((venue, LatencyStats::from_samples(venue, elapsed, failures)));
+        }
+        results
+    }
+
+    // 주문 거부 진단 (서버 시각, 잔고, 포지션, 종목 규격을 다시 조회해 원인 추정, 주문 없음)
+    pub async fn diagnose_rejection(&self, code: i64, context: &RejectionContext) -> RejectionReport {
+        let mut snapshot = ExchangeSnapshot::default();
+        let mut findings = Vec::new();
+        // 진단은 비필수 요청이므로 가중치 한도에 가까우면 조회 없이 코드로만 추정
+        if context.exchange == "Binance" && self.binance_weight.should_defer() {
+            findings.push(format!(
+                "exchange state: not fetched (Binance weight {}/{})",
+                self.binance_weight.used(),
+                self.binance_weight.limit()
+            ));
+            return RejectionReport { code, causes: explain_rejection(code, context, &snapshot), findings };
+        }
+        let mut note = |label: &str, result: Result<String, OrderError>| match result {
+            Ok(value) => findings.push(format!("{}: {}", label, value)),
+            Err(e) => findings.push(format!("{}: unavailable ({})", label, e)),
+        };
+
+        let skew = self.server_time_millis(context.exchange).await.map(|server| Utc::now().timestamp_millis() - server);
+        snapshot.clock_skew_ms = skew.as_ref().ok().copied();
+        note("clock skew", skew.map(|ms| format!("{}ms", ms)));
+
+        let balance = match context.exchange {
+            "Binance" => self.fetch_available_balance_binance().await,
+            _ => self.fetch_available_balance_bitmart().await,
+        };
+        snapshot.available_balance = balance.as_ref().ok().copied();
+        note("available balance", balance.map(|b| format!("{} USDT", b)));
+
+        let position = match context.exchange {
+            "Binance" => self.fetch_position_binance(&context.symbol).await,
+            _ => self.fetch_position_bitmart(&context.symbol).await,
+        };
+        snapshot.position = position.as_ref().ok().copied();
+        note("position", position.map(|p| p.to_string()));
+
+        match context.exchange {
+            "Binance" => match self.fetch_symbol_filters_binance(&context.symbol).await {
+                Ok(filters) => {
+                    findings.push(format!("symbol filters: {:?}", filters));
+                    snapshot.symbol_status = Some(filters.status);
+                    snapshot.step_size = filters.step_size;
+                    snapshot.min_quantity = filters.min_quantity;
+                    snapshot.min_notional = filters.min_notional;
+                }
+                Err(e) => findings.push(format!("symbol filters: unavailable ({})", e)),
+            },
+            _ => match self.bitmart_contracts.get(&context.symbol) {
+                // Bitmart 수량은 계약 단위로 주문
+                Some(spec) => {
+                    findings.push(format!("contract spec: {:?}", spec));
+                    snapshot.step_size = Some(spec.contract_size);
+                    snapshot.min_quantity = Some(spec.contract_size * spec.min_contracts as f64);
+                }
+                None => findings.push(format!("contract spec: none configured for {}", context.symbol)),
+            },
+        }
+
+        RejectionReport { code, causes: explain_rejection(code, context, &snapshot), findings }
+    }
+
+    // 공개 GET 요청 (Binance 응답이면 가중치 사용량 기록)
+    async fn get_json(&self, url: &str) -> Result<Value, OrderError> {
+        let response = self.client.get(url).send().await?;
+        self.binance_weight.record(response.headers());
+        let status = response.status();
+        if status.is_server_error() {
+            return Err(OrderError::Unavailable(format!("HTTP {}", status)));
+        }
+        Ok(response.json::<Value>().await?)
+    }
+
+    // 거래소 응답 확인용 가벼운 요청 (장애 복구 확인)
+    pub async fn probe_venue(&self, exchange: &str) -> Result<(), OrderError> {
+        self.server_time_millis(exchange).await.map(|_| ())
+    }
+
+    // 거래소 서버 시각 (epoch ms)
+    async fn server_time_millis(&self, exchange: &str) -> Result<i64, OrderError> {
+        let (url, pointer) = match exchange {
+            "Binance" => (format!("{}/fapi/v1/time", self.binance_base_url), "/serverTime"),
+            _ => (format!("{}/system/time", self.bitmart_base_url), "/data/server_time"),
+        };
+        let json = self.get_json(&url).await?;
+        json.pointer(pointer)
+            .and_then(|v| v.as_i64())
+            .ok_or_else(|| OrderError::Parse(format!("Missing server time: {}", json)))
+    }
+
+    // Binance USDT 사용 가능 잔고
+    async fn fetch_available_balance_binance(&self) -> Result<f64, OrderError> {
+        let query = format!("timestamp={}", Utc::now().timestamp_millis());
+        let text = self.send_signed_binance(reqwest::Method::GET, "/fapi/v2/balance", &query, SignedParams::Query).await?;
+        let json = serde_json::from_str::<Value>(&text).map_err(|e| OrderError::Parse(e.to_string()))?;
+        json.as_array()
+            .and_then(|assets| assets.iter().find(|a| a.get("asset").and_then(|v| v.as_str()) == Some("USDT")))
+            .and_then(|asset| asset.get("availableBalance")?.as_str()?.parse::<f64>().ok())
+            .ok_or_else(|| OrderError::Parse(format!("Missing USDT balance: {}", json)))
+    }
+
+    // Bitmart USDT 사용 가능 잔고
+    async fn fetch_available_balance_bitmart(&self) -> Result<f64, OrderError> {
+        let response = self.bitmart_get("/contract/private/assets-detail", "").send().await?;
+        let json = response.json::<Value>().await?;
+        json.get("data")
+            .and_then(|d| d.as_array())
+            .and_then(|assets| assets.iter().find(|a| a.get("currency").and_then(|v| v.as_str()) == Some("USDT")))
+            .and_then(|asset| asset.get("available_balance")?.as_str()?.parse::<f64>().ok())
+            .ok_or_else(|| OrderError::Parse(format!("Missing USDT balance: {}", json)))
+    }
+
+    // Binance 종목 상태 및 수량/명목 금액 필터
+    async fn fetch_symbol_filters_binance(&self, symbol: &str) -> Result<SymbolFilters, OrderError> {
+        let url = format!("{}/fapi/v1/exchangeInfo", self.binance_base_url);
+        let json = self.get_json(&url).await?;
+        let info = json
+            .get("symbols")
+            .and_then(|s| s.as_array())
+            .and_then(|symbols| symbols.iter().find(|s| s.get("symbol").and_then(|v| v.as_str()) == Some(symbol)))
+            .ok_or_else(|| OrderError::Parse(format!("{} not in exchangeInfo", symbol)))?;
+        let filter = |filter_type: &str, key: &str| {
+            info.get("filters")?
+                .as_array()?
+                .iter()
+                .find(|f| f.get("filterType").and_then(|v| v.as_str()) == Some(filter_type))?
+                .get(key)?
+                .as_str()?
+                .parse::<f64>()
+                .ok()
+        };
+        Ok(SymbolFilters {
+            status: info.get("status").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+            // 시장가 주문은 MARKET_LOT_SIZE 적용 (없으면 LOT_SIZE)
+            step_size: filter("MARKET_LOT_SIZE", "stepSize").or_else(|| filter("LOT_SIZE", "stepSize")),
+            min_quantity: filter("MARKET_LOT_SIZE", "minQty").or_else(|| filter("LOT_SIZE", "minQty")),
+            min_notional: filter("MIN_NOTIONAL", "notional"),
+            tick_size: filter("PRICE_FILTER", "tickSize"),
+            precision: match (info.get("quantityPrecision"), info.get("pricePrecision")) {
+                (Some(quantity), Some(price)) => Some(SymbolPrecision {
+                    quantity: quantity.as_u64().unwrap_or_default() as u32,
+                    price: price.as_u64().unwrap_or_default() as u32,
+                }),
+                _ => None,
+            },
+        })
+    }
+
+    // Binance 종목 자릿수 조회 후 저장 (이후 주문 수량/가격을 이 자릿수로 전송)
+    pub async fn load_precision_binance(&self, symbol: &str) -> Result<SymbolPrecision, OrderError> {
+        let precision = self
+            .fetch_symbol_filters_binance(symbol)
+            .await?
+            .precision
+            .ok_or_else(|| OrderError::Parse(format!("{} has no quantityPrecision/pricePrecision", symbol)))?;
+        self.binance_precision.set(symbol, precision);
+        Ok(precision)
+    }
+
+    // Binance 호가 단위 (exchangeInfo PRICE_FILTER, 없으면 None)
+    pub async fn fetch_tick_size_binance(&self, symbol: &str) -> Result<Option<f64>, OrderError> {
+        Ok(self.fetch_symbol_filters_binance(symbol).await?.tick_size)
+    }
+
+    // 동기 방식 포지션 정리 (런타임 밖 별도 스레드에서 호출, best-effort)
+    // 다른 청산 경로와 같이 reduceOnly/청산 방향 코드로 보내 상태가 어긋나도 반대 포지션을 새로 열지 않음
+    // 주문 수 상한 확인과 거래소 장애 기록도 일반 주문과 동일
+    // 반환: 거래소별 결과 로그
+    pub fn flatten_blocking(
+        &self,
+        symbol: &str,
+        binance_position: Option<&str>,
+        bitmart_position: Option<&str>,
+        quantities: (f64, f64), // (Binance, Bitmart) 청산 수량
+    ) -> Vec<String> {
+        let (binance_quantity, bitmart_quantity) = quantities;
+        let client = reqwest::blocking::Client::new();
+        let mut results = Vec::new();
+
+        if let Some(position) = binance_position {
+            let side = closing_side(position, "Binance");
+            let query = format!(
+                "{}&reduceOnly=true",
+                self.binance_market_order_query(symbol, side, binance_quantity, Utc::now().timestamp_millis())
+            );
+            let url = format!(
+                "{}/fapi/v1/order?{}&signature={}",
+                self.binance_base_url,
+                query,
+                self.sign_binance(&query)
+            );
+            let result = self.send_blocking("Binance", client.post(&url).header("X-MBX-APIKEY", &self.binance_api_key));
+            results.push(match result {
+                Ok(text) => format!("Binance {} {} {}: {}", side, binance_quantity, symbol, text),
+                Err(e) => format!("Binance {} {} {} failed: {}", side, binance_quantity, symbol, e),
+            });
+        }
+
+        if let Some(position) = bitmart_position {
+            let side = closing_side(position, "Bitmart");
+            let timestamp = Utc::now().timestamp_millis();
+            let contracts = match self.bitmart_contract_count(symbol, bitmart_quantity) {
+                Ok(contracts) => contracts,
+                Err(e) => {
+                    results.push(format!("Bitmart {} {} {} failed: {}", side, bitmart_quantity, symbol, e));
+                    return results;
+                }
+            };
+            let code = bitmart_side_code(side == "buy", true);
+            let body = match BitmartOrderBody::market(symbol, code, contracts, self.open_type, timestamp).to_json() {
+                Ok(body) => body,
+                Err(e) => {
+                    results.push(format!("Bitmart {} {} {} failed: {}", side, bitmart_quantity, symbol, e));
+                    return results;
+                }
+            };
+            let signed = self.sign_bitmart_body(body, timestamp);
+            let request = client
+                .post(format!("{}/futures/v1/submit-order", self.bitmart_base_url))
+                .header("X-BM-KEY", &self.bitmart_api_key)
+                .header("X-BM-SIGN", signed.signature())
+                .header("X-BM-TIMESTAMP", timestamp.to_string())
+                .header("Content-Type", "application/json")
+                .header("User-Agent", BITMART_USER_AGENT)
+                .body(signed.body);
+            let result = self.send_blocking("Bitmart", request);
+            results.push(match result {
+                Ok(text) => format!("Bitmart {} {} {}: {}", side, bitmart_quantity, symbol, text),
+                Err(e) => format!("Bitmart {} {} {} failed: {}", side, bitmart_quantity, symbol, e),
+            });
+        }
+
+        results
+    }
+
+    // 동기 주문 전송 (세션 주문 수 상한 확인 후, 5xx는 장애로 기록)
+    fn send_blocking(&self, exchange: &'static str, request: reqwest::blocking::RequestBuilder) -> Result<String, OrderError> {
+        self.order_budget.acquire()?;
+        let result = request.send().and_then(|response| Ok((response.status(), response.text()?)));
+        let result = match result {
+            Ok((status, text)) if status.is_server_error() => Err(OrderError::Unavailable(format!("HTTP {}: {}", status, text))),
+            Ok((_, text)) => Ok(text),
+            Err(e) => Err(OrderError::Http(e)),
+        };
+        self.venue_health.observe(exchange, &result);
+        result
+    }
+
+    // Binance 시장가 주문 쿼리 (수량은 종목 자릿수에 맞춤)
+    fn binance_market_order_query(&self, symbol: &str, side: &str, quantity: f64, timestamp: i64) -> String {
+        format!(
+            "symbol={}&side={}&type=MARKET&quantity={}&newOrderRespType={}&timestamp={}",
+            symbol,
+            side,
+            self.binance_precision.quantity(symbol, quantity),
+            self.binance_resp_type.as_binance(),
+            timestamp
+        )
+    }
+
+    // 기초자산 수량 -> Bitmart 정수 계약 수 (반올림 시 수량이 달라지면 경고)
+    pub fn bitmart_contract_count(&self, symbol: &str, quantity: f64) -> Result<u64, OrderError> {
+        let spec = self
+            .bitmart_contracts
+            .get(symbol)
+            .ok_or_else(|| OrderError::InvalidSize(format!("no Bitmart contract spec for {}", symbol)))?;
+        let contracts = spec.contracts(quantity).map_err(OrderError::InvalidSize)?;
+        let rounded = contracts as f64 * spec.contract_size;
+        if (rounded - quantity).abs() > f64::EPSILON {
+            eprintln!(
+                "[Order] Bitmart {} quantity {} rounded to {} contracts ({})",
+                symbol, quantity, contracts, rounded
+            );
+        }
+        Ok(contracts)
+    }
+
+    // 종목 계약 정보의 소수 자릿수에 맞춘 Bitmart 주문 가격
+    pub fn bitmart_price(&self, symbol: &str, price: f64) -> Result<String, OrderError> {
+        let spec = self
+            .bitmart_contracts
+            .get(symbol)
+            .ok_or_else(|| OrderError::InvalidSize(format!("no Bitmart contract spec for {}", symbol)))?;
+        spec.format_price(price).map_err(OrderError::InvalidSize)
     }
 
     // Binance 서명 생성
@@ -100,9 +1936,12 @@ impl Order {
 
     // Bitmart 서명 생성
     fn sign_bitmart(&self, body: &str, timestamp: i64) -> String {
-        let payload = format!("{}{}", timestamp, body);
-        let mut mac = HmacSha256::new_from_slice(self.bitmart_secret_key.as_bytes()).unwrap();
-        mac.update(payload.as_bytes());
-        encode(mac.finalize().into_bytes())
+        bitmart_signature(&self.bitmart_secret_key, timestamp, body)
+    }
+
+    // Bitmart 요청 본문 서명 (반환된 본문을 그대로 전송해야 서명이 맞음)
+    pub fn sign_bitmart_body(&self, body: String, timestamp: i64) -> SignedBitmartBody {
+        let signature = self.sign_bitmart(&body, timestamp);
+        SignedBitmartBody { body, timestamp, signature }
     }
 }
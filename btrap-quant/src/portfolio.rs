@@ -0,0 +1,35 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// 전체 종목에서 동시에 열린 거래 수 제한 (상관된 위험 제한용)
+// 진입 주문 전에 자리를 예약하고, 진입이 무산되거나 청산이 끝나면 반납
+pub struct OpenTrades {
+    limit: usize,
+    count: AtomicUsize,
+}
+
+impl OpenTrades {
+    // open: 재시작 시 상태 파일에서 복원된 열린 거래 수
+    pub fn new(limit: usize, open: usize) -> Self {
+        Self { limit, count: AtomicUsize::new(open) }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::SeqCst)
+    }
+
+    // 자리가 있으면 예약 후 true, 한도에 도달했으면 false
+    pub fn try_reserve(&self) -> bool {
+        self.count
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| (count < self.limit).then_some(count + 1))
+            .is_ok()
+    }
+
+    // 예약 반납 (이미 0이면 무시)
+    pub fn release(&self) {
+        let _ = self.count.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| count.checked_sub(1));
+    }
+}
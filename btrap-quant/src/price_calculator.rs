@@ -0,0 +1,416 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::config::{Exchange, StrategyConfig};
+use crate::depth::{Level, OrderBook, SharedDepth};
+use crate::strategy::entry_positions;
+
+// 체결 방식 (taker: 호가를 따라 올라가며 체결, maker: 최우선 호가에 대기 후 체결)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FillMode {
+    Taker,
+    Maker,
+}
+
+// 갭 계산에 쓰는 가격 정의
+// LastTrade: 최근 체결가. 호가창 없이 동작하지만 스프레드를 무시해 실제 체결 시 갭이 더 작음
+// Mid: 최우선 호가 중간가. 체결가보다 노이즈가 적지만 역시 스프레드를 무시함
+// ExecutionPrice: 방향별로 수량만큼 호가를 소진한 체결가. 가장 정확하지만 호가창이 필요함
+// BidAsk: 방향별 최우선 호가 (Binance 매수면 Binance 매도호가 vs Bitmart 매수호가, 반대 방향은 그 반대)
+//         호가창이 없는 거래소만 최근 체결가로 대체
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GapDefinition {
+    LastTrade,
+    Mid,
+    ExecutionPrice,
+    BidAsk,
+}
+
+// 갭 계산 입력 (최근 체결가와 호가창, 없는 값은 None)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Quotes<'a> {
+    pub binance_last: Option<f64>,
+    pub bitmart_last: Option<f64>,
+    pub binance_book: Option<&'a OrderBook>,
+    pub bitmart_book: Option<&'a OrderBook>,
+}
+
+// 진입 기회 (방향, 갭, 갭 계산에 쓴 가격, 수량)
+// gap: (Binance 가격 - Bitmart 가격) / Bitmart 가격 * 100
+#[derive(Debug, Clone, PartialEq)]
+pub struct Opportunity {
+    pub binance_position: &'static str,
+    pub bitmart_position: &'static str,
+    pub gap: f64,
+    pub binance_price: f64,
+    pub bitmart_price: f64,
+    pub quantity: f64,
+    // Binance 호가창 없이 합성 스프레드로 추정한 가격을 썼는지 여부
+    pub estimated: bool,
+}
+
+// 진입 방향의 갭 이중 확인 결과 (최근 체결가 기준, 호가 체결가 기준, 계산할 수 없으면 None)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GapConfirmation {
+    pub last_trade: Option<f64>,
+    pub execution: Option<f64>,
+    pub last_trade_agrees: bool,
+    pub execution_agrees: bool,
+}
+
+impl GapConfirmation {
+    pub fn confirmed(&self) -> bool {
+        self.last_trade_agrees && self.execution_agrees
+    }
+}
+
+// 왕복 비용 (%, 진입/청산 가격 대비): 수수료와 호가 소진에 따른 예상 슬리피지
+// 진입은 거래소별 체결 방식, 청산은 시장가 기준
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoundTripCost {
+    pub fees: f64,
+    pub slippage: f64,
+}
+
+impl RoundTripCost {
+    pub fn total(&self) -> f64 {
+        self.fees + self.slippage
+    }
+
+    // 갭 / 왕복 비용 (비용이 0이면 무한대)
+    pub fn gap_ratio(&self, gap: f64) -> f64 {
+        let total = self.total();
+        if total > 0.0 { gap.abs() / total } else { f64::INFINITY }
+    }
+}
+
+// 가상 가격 시뮬레이션 결과 (주문/상태 변경 없음)
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhatIf {
+    pub gap: f64,     // (Binance - Bitmart) / Bitmart * 100
+    pub net_gap: f64, // |gap|에서 양쪽 진입/청산 수수료(%) 차감
+    pub binance_position: &'static str,
+    pub bitmart_position: &'static str,
+    pub would_enter: bool,
+    pub expected_pnl: f64, // 갭이 0으로 수렴한다고 가정한 수익 (USDT, 수수료 차감)
+}
+
+// 호가창 덤프의 거래소별 항목 (전체 호가와 덤프 수량 기준 예상 체결가, 잔량 부족 시 None)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VenueBookDump {
+    pub book: OrderBook,
+    pub fill_mode: FillMode,
+    pub buy_price: Option<f64>,
+    pub sell_price: Option<f64>,
+}
+
+// 특정 시점 양쪽 거래소 호가창 덤프 (유동성 디버깅용, 키: 거래소 이름)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookDump {
+    pub symbol: String,
+    pub dumped_at: DateTime<Utc>,
+    pub quantity: f64,
+    pub venues: HashMap<String, VenueBookDump>,
+}
+
+// 즉시 체결 가능한 최우선 호가 (매수면 매도호가, 매도면 매수호가, 호가창이나 해당 호가가 없으면 None)
+fn best_quote(book: Option<&OrderBook>, is_buy: bool) -> Option<f64> {
+    let book = book?;
+    let levels = if is_buy { &book.asks } else { &book.bids };
+    levels.first().map(|level| level.price)
+}
+
+// 수량만큼 taker로 체결할 때 최우선 호가 대비 슬리피지 (%, 잔량 부족이면 None)
+fn slippage_pct(book: Option<&OrderBook>, is_buy: bool, quantity: f64, fill_mode: FillMode) -> Option<f64> {
+    let Some(book) = book.filter(|_| fill_mode == FillMode::Taker) else {
+        return Some(0.0);
+    };
+    let Some(best) = best_quote(Some(book), is_buy) else {
+        return Some(0.0);
+    };
+    let price = PriceCalculator::calculate_execution_price(book, is_buy, quantity, FillMode::Taker)?;
+    Some((price - best).abs() / best * 100.0)
+}
+
+// 호가창 기반 예상 체결가 및 기대 수익 계산
+pub struct PriceCalculator {
+    config: Arc<StrategyConfig>,
+    shared_depth: SharedDepth,
+}
+
+impl PriceCalculator {
+    pub fn new(config: Arc<StrategyConfig>, shared_depth: SharedDepth) -> Self {
+        Self { config, shared_depth }
+    }
+
+    // 예상 체결가
+    // Taker: 반대편 호가를 수량만큼 소진한 평균가 (잔량 부족 시 None)
+    // Maker: 같은 편 최우선 호가에 대기 (교차 없이 호가 그대로 체결된다고 가정)
+    pub fn calculate_execution_price(
+        book: &OrderBook,
+        is_buy: bool,
+        quantity: f64,
+        fill_mode: FillMode,
+    ) -> Option<f64> {
+        match fill_mode {
+            FillMode::Maker => {
+                let levels = if is_buy { &book.bids } else { &book.asks };
+                levels.first().map(|level| level.price)
+            }
+            FillMode::Taker => {
+                let levels = if is_buy { &book.asks } else { &book.bids };
+                let mut remaining = quantity;
+                let mut cost = 0.0;
+                for level in levels {
+                    let filled = remaining.min(level.vol);
+                    cost += filled * level.price;
+                    remaining -= filled;
+                    if remaining <= 0.0 {
+                        return Some(cost / quantity);
+                    }
+                }
+                None
+            }
+        }
+    }
+
+    // 진입 후 갭이 완전히 수렴한다고 가정한 기대 수익 (USDT, 양쪽 진입/청산 수수료 차감)
+    // 각 거래소의 체결 방식(fill mode)에 따라 체결가와 수수료를 적용
+    pub async fn expected_pnl(&self, binance_position: &str, quantity: f64) -> Option<f64> {
+        let depth = self.shared_depth.lock().await;
+        let binance_book = depth.get("Binance")?;
+        let bitmart_book = depth.get("Bitmart")?;
+
+        let binance_buy = binance_position == "LONG";
+        let binance_price = Self::calculate_execution_price(binance_book, binance_buy, quantity, self.config.binance_fill_mode)?;
+        let bitmart_price = Self::calculate_execution_price(bitmart_book, !binance_buy, quantity, self.config.bitmart_fill_mode)?;
+
+        let gross = if binance_buy {
+            (bitmart_price - binance_price) * quantity
+        } else {
+            (binance_price - bitmart_price) * quantity
+        };
+        let (binance_fee, bitmart_fee) = self.fee_rates();
+        // 진입/청산 두 번 수수료 지불
+        let fees = 2.0 * quantity * (binance_price * binance_fee + bitmart_price * bitmart_fee);
+        Some(gross - fees)
+    }
+
+    // 거래소별 수수료율 (체결 방식, 종목별 수수료, Binance 배율 반영, 비율)
+    pub fn fee_rates(&self) -> (f64, f64) {
+        (
+            self.config.fee_pct(Exchange::Binance, self.config.binance_fill_mode) / 100.0,
+            self.config.fee_pct(Exchange::Bitmart, self.config.bitmart_fill_mode) / 100.0,
+        )
+    }
+
+    // 청산된 거래의 실현 스프레드 수익 (USDT, 양쪽 진입/청산 수수료 차감)
+    // Binance 숏이면 갭 축소분(entry - exit), 롱이면 갭 확대분(exit - entry)이 수익
+    // price: 명목 금액 환산 기준 가격 (갭의 기준인 Bitmart 가격)
+    pub fn spread_capture(&self, binance_position: &str, entry_gap: f64, exit_gap: f64, quantity: f64, price: f64) -> f64 {
+        let (binance_fee, bitmart_fee) = self.estimated_fees(quantity, price, price);
+        Self::gross_capture(binance_position, entry_gap, exit_gap, quantity, price) - 2.0 * (binance_fee + bitmart_fee)
+    }
+
+    // 수수료 차감 전 실현 스프레드 수익 (USDT)
+    pub fn gross_capture(binance_position: &str, entry_gap: f64, exit_gap: f64, quantity: f64, price: f64) -> f64 {
+        let captured_gap = if binance_position == "SHORT" {
+            entry_gap - exit_gap
+        } else {
+            exit_gap - entry_gap
+        };
+        captured_gap / 100.0 * quantity * price
+    }
+
+    // 한 번 체결(진입 또는 청산)할 때 거래소별 추정 수수료 (USDT, 설정 수수료율 기준)
+    pub fn estimated_fees(&self, quantity: f64, binance_price: f64, bitmart_price: f64) -> (f64, f64) {
+        let (binance_fee, bitmart_fee) = self.fee_rates();
+        (quantity * binance_price * binance_fee, quantity * bitmart_price * bitmart_fee)
+    }
+
+    // 가상 가격에서의 진입 판단 (호가창 없이 두 가격만 사용, 진입 임계값과 허용 방향 적용)
+    pub fn what_if(&self, binance_price: f64, bitmart_price: f64, quantity: f64) -> WhatIf {
+        let config = &self.config;
+        let gap = ((binance_price - bitmart_price) / bitmart_price) * 100.0;
+        let (binance_position, bitmart_position) = entry_positions(gap, config.invert_direction);
+        let (binance_fee, bitmart_fee) = self.fee_rates();
+        WhatIf {
+            gap,
+            net_gap: gap.abs() - 2.0 * (binance_fee + bitmart_fee) * 100.0,
+            binance_position,
+            bitmart_position,
+            would_enter: gap.abs() > config.entry_threshold_pct(bitmart_price) && config.allowed_directions.allows(binance_position),
+            expected_pnl: self.spread_capture(binance_position, gap, 0.0, quantity, bitmart_price),
+        }
+    }
+
+    // 이 방향 진입 후 청산까지의 왕복 비용 (호가 잔량이 수량보다 부족하면 None)
+    // 슬리피지: taker 주문의 수량 소진 평균가와 최우선 호가 차이, maker 진입과 호가창이 없는 거래소는 0
+    pub fn round_trip_cost(&self, quotes: &Quotes, binance_buy: bool, quantity: f64) -> Option<RoundTripCost> {
+        let config = &self.config;
+        let synthetic = self.synthetic_binance_book(quotes);
+        let binance_book = quotes.binance_book.or(synthetic.as_ref());
+        let fees = config.fee_pct(Exchange::Binance, config.binance_fill_mode)
+            + config.fee_pct(Exchange::Bitmart, config.bitmart_fill_mode)
+            + config.fee_pct(Exchange::Binance, FillMode::Taker)
+            + config.fee_pct(Exchange::Bitmart, FillMode::Taker);
+        let slippage = slippage_pct(binance_book, binance_buy, quantity, config.binance_fill_mode)?
+            + slippage_pct(quotes.bitmart_book, !binance_buy, quantity, config.bitmart_fill_mode)?
+            + slippage_pct(binance_book, !binance_buy, quantity, FillMode::Taker)?
+            + slippage_pct(quotes.bitmart_book, binance_buy, quantity, FillMode::Taker)?;
+        Some(RoundTripCost { fees, slippage })
+    }
+
+    // 설정된 갭 정의에 따른 방향별 기준 가격 (Binance, Bitmart)
+    // binance_buy: 이 방향에서 Binance를 매수하는지 여부 (ExecutionPrice에서만 의미 있음)
+    pub fn gap_prices(&self, quotes: &Quotes, binance_buy: bool, quantity: f64) -> Option<(f64, f64)> {
+        self.gap_prices_for(self.config.gap_definition, quotes, binance_buy, quantity)
+    }
+
+    // 지정한 갭 정의에 따른 방향별 기준 가격 (Binance, Bitmart)
+    fn gap_prices_for(&self, definition: GapDefinition, quotes: &Quotes, binance_buy: bool, quantity: f64) -> Option<(f64, f64)> {
+        let synthetic = self.synthetic_binance_book(quotes);
+        let binance_book = quotes.binance_book.or(synthetic.as_ref());
+        match definition {
+            GapDefinition::LastTrade => Some((quotes.binance_last?, quotes.bitmart_last?)),
+            GapDefinition::Mid => Some((mid_price(binance_book?)?, mid_price(quotes.bitmart_book?)?)),
+            GapDefinition::ExecutionPrice => Some((
+                Self::calculate_execution_price(binance_book?, binance_buy, quantity, self.config.binance_fill_mode)?,
+                Self::calculate_execution_price(quotes.bitmart_book?, !binance_buy, quantity, self.config.bitmart_fill_mode)?,
+            )),
+            GapDefinition::BidAsk => Some((
+                best_quote(binance_book, binance_buy).or(quotes.binance_last)?,
+                best_quote(quotes.bitmart_book, !binance_buy).or(quotes.bitmart_last)?,
+            )),
+        }
+    }
+
+    // Binance 호가창이 없을 때 최근 가격 기준 합성 호가창 (binance_synthetic_spread_bps 설정 시에만)
+    // 최우선 매수/매도 = 최근 가격 ∓ 스프레드/2, 잔량 무제한 (실제 호가가 아닌 추정치)
+    fn synthetic_binance_book(&self, quotes: &Quotes) -> Option<OrderBook> {
+        if quotes.binance_book.is_some() {
+            return None;
+        }
+        let spread_bps = self.config.binance_synthetic_spread_bps?;
+        let last = quotes.binance_last?;
+        let half_spread = last * spread_bps / 10_000.0 / 2.0;
+        let level = |price: f64| vec![Level { price, vol: f64::INFINITY }];
+        Some(OrderBook {
+            symbol: self.config.symbol.clone(),
+            asks: level(last + half_spread),
+            bids: level(last - half_spread),
+            ms_t: 0,
+        })
+    }
+
+    // 갭 계산에 합성 Binance 호가창을 쓰는지 여부
+    fn binance_price_estimated(&self, quotes: &Quotes) -> bool {
+        self.config.gap_definition != GapDefinition::LastTrade && self.synthetic_binance_book(quotes).is_some()
+    }
+
+    // 갭 (%): (Binance 가격 - Bitmart 가격) / Bitmart 가격 * 100
+    pub fn gap(&self, quotes: &Quotes, binance_buy: bool, quantity: f64) -> Option<f64> {
+        let (binance_price, bitmart_price) = self.gap_prices(quotes, binance_buy, quantity)?;
+        Some(((binance_price - bitmart_price) / bitmart_price) * 100.0)
+    }
+
+    // 두 방향(Binance 숏/Bitmart 롱, Binance 롱/Bitmart 숏) 중 진입 조건을 만족하는 더 큰 갭
+    // 갭 정의가 ExecutionPrice면 방향별 체결 호가가 달라 스프레드가 반영됨 (주문 실행 없음)
+    pub fn best_opportunity(&self, quotes: &Quotes, quantity: f64) -> Option<Opportunity> {
+        let config = &self.config;
+        let estimated = self.binance_price_estimated(quotes);
+        [("SHORT", "LONG"), ("LONG", "SHORT")]
+            .into_iter()
+            .filter_map(|(binance_position, bitmart_position)| {
+                let binance_buy = binance_position == "LONG";
+                let (binance_price, bitmart_price) = self.gap_prices(quotes, binance_buy, quantity)?;
+                let gap = ((binance_price - bitmart_price) / bitmart_price) * 100.0;
+                // 갭 부호가 이 방향의 진입을 가리킬 때만 유효
+                let valid = gap.abs() > config.entry_threshold_pct(bitmart_price)
+                    && entry_positions(gap, config.invert_direction) == (binance_position, bitmart_position);
+                valid.then_some(Opportunity {
+                    binance_position,
+                    bitmart_position,
+                    gap,
+                    binance_price,
+                    bitmart_price,
+                    quantity,
+                    estimated,
+                })
+            })
+            .max_by(|a, b| a.gap.abs().total_cmp(&b.gap.abs()))
+    }
+
+    // 진입 방향의 갭을 최근 체결가와 호가 체결가로 각각 계산해 둘 다 진입 조건을 만족하는지 확인
+    // 최근 체결가가 오래되어 실제 호가와 어긋나는 진입을 거르기 위한 이중 확인
+    pub fn gap_confirmation(&self, quotes: &Quotes, binance_position: &str, quantity: f64) -> GapConfirmation {
+        let binance_buy = binance_position == "LONG";
+        // (갭 %, 기준 Bitmart 가격)
+        let gap = |definition: GapDefinition| {
+            let (binance_price, bitmart_price) = self.gap_prices_for(definition, quotes, binance_buy, quantity)?;
+            Some((((binance_price - bitmart_price) / bitmart_price) * 100.0, bitmart_price))
+        };
+        // 갭이 임계값을 넘고 부호가 이 방향의 진입을 가리켜야 함
+        let agrees = |gap: Option<(f64, f64)>| {
+            gap.is_some_and(|(gap, price)| {
+                gap.abs() > self.config.entry_threshold_pct(price)
+                    && entry_positions(gap, self.config.invert_direction).0 == binance_position
+            })
+        };
+        let (last_trade, execution) = (gap(GapDefinition::LastTrade), gap(GapDefinition::ExecutionPrice));
+        GapConfirmation {
+            last_trade: last_trade.map(|(gap, _)| gap),
+            execution: execution.map(|(gap, _)| gap),
+            last_trade_agrees: agrees(last_trade),
+            execution_agrees: agrees(execution),
+        }
+    }
+
+    // 호가창 덤프 (거래소별 체결 방식으로 quantity만큼 매수/매도할 때의 예상 체결가 포함)
+    pub fn book_dump(&self, books: &HashMap<String, OrderBook>, quantity: f64, now: DateTime<Utc>) -> BookDump {
+        let venues = books
+            .iter()
+            .map(|(exchange_name, book)| {
+                let fill_mode = match exchange_name.as_str() {
+                    "Binance" => self.config.binance_fill_mode,
+                    _ => self.config.bitmart_fill_mode,
+                };
+                let dump = VenueBookDump {
+                    book: book.clone(),
+                    fill_mode,
+                    buy_price: Self::calculate_execution_price(book, true, quantity, fill_mode),
+                    sell_price: Self::calculate_execution_price(book, false, quantity, fill_mode),
+                };
+                (exchange_name.clone(), dump)
+            })
+            .collect();
+        BookDump { symbol: self.config.symbol.clone(), dumped_at: now, quantity, venues }
+    }
+
+    // 현재 공유 호가창 덤프
+    pub async fn current_book_dump(&self, quantity: f64) -> BookDump {
+        let depth = self.shared_depth.lock().await;
+        self.book_dump(&depth, quantity, Utc::now())
+    }
+
+    // 현재 가격/호가창 기준 최적 진입 기회
+    pub async fn current_opportunity(&self, binance_last: Option<f64>, bitmart_last: Option<f64>, quantity: f64) -> Option<Opportunity> {
+        let depth = self.shared_depth.lock().await;
+        let quotes = Quotes {
+            binance_last,
+            bitmart_last,
+            binance_book: depth.get("Binance"),
+            bitmart_book: depth.get("Bitmart"),
+        };
+        self.best_opportunity(&quotes, quantity)
+    }
+
+}
+
+// 최우선 호가 중간가
+fn mid_price(book: &OrderBook) -> Option<f64> {
+    Some((book.bids.first()?.price + book.asks.first()?.price) / 2.0)
+}
@@ -0,0 +1,220 @@
+use futures_util::{stream::StreamExt, SinkExt};
+use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+use chrono::Utc;
+use serde_json::Value;
+use std::time::Duration;
+use crate::context::TradingContext;
+use crate::feed::RECONNECT_DELAY;
+use crate::handle_price::{bitmart_data_items, bitmart_error_action, FeedAction};
+use crate::order::Fill;
+use crate::subscriptions::{bitmart_subscribe, Subscriptions};
+
+// listenKey 연장 주기 (만료 60분)
+const LISTEN_KEY_KEEPALIVE: Duration = Duration::from_secs(30 * 60);
+
+// Binance ORDER_TRADE_UPDATE 이벤트에서 체결 추출 (x == TRADE인 경우만)
+// 수수료는 USDT로 부과된 경우만 기록
+pub fn parse_binance_fill(json: &Value) -> Option<Fill> {
+    if json.get("e")?.as_str()? != "ORDER_TRADE_UPDATE" {
+        return None;
+    }
+    let order = json.get("o")?;
+    if order.get("x")?.as_str()? != "TRADE" {
+        return None;
+    }
+    let number = |key: &str| order.get(key)?.as_str()?.parse::<f64>().ok();
+    let fee = match order.get("N").and_then(|v| v.as_str()) {
+        Some("USDT") => number("n"),
+        _ => None,
+    };
+    Some(Fill {
+        exchange: "Binance",
+        symbol: order.get("s")?.as_str()?.to_string(),
+        is_buy: order.get("S")?.as_str()? == "BUY",
+        order_id: order.get("i").map(|id| id.to_string()).unwrap_or_default(),
+        filled_qty: number("l")?,
+        avg_price: Some(number("L")?),
+        fee,
+        ts: order.get("T").and_then(|t| t.as_i64()).unwrap_or_else(|| Utc::now().timestamp_millis()),
+        raw: order.clone(),
+    })
+}
+
+// Bitmart futures/order 메시지에서 체결 추출 (state 4: 완료된 주문의 누적 체결 계약 수)
+// side: 1 buy_open_long, 2 buy_close_short, 3 sell_close_long, 4 sell_open_short
+pub fn parse_bitmart_fills(json: &Value) -> Vec<Fill> {
+    if json.get("group").and_then(|g| g.as_str()) != Some("futures/order") {
+        return Vec::new();
+    }
+    bitmart_data_items(json)
+        .into_iter()
+        .filter_map(|item| {
+            let order = item.get("order")?;
+            if order.get("state")?.as_i64()? != 4 {
+                return None;
+            }
+            let number = |key: &str| order.get(key)?.as_str()?.parse::<f64>().ok();
+            let filled_qty = number("deal_size")?;
+            if filled_qty <= 0.0 {
+                return None;
+            }
+            Some(Fill {
+                exchange: "Bitmart",
+                symbol: order.get("symbol")?.as_str()?.to_string(),
+                is_buy: matches!(order.get("side")?.as_i64()?, 1 | 2),
+                order_id: order.get("order_id").and_then(|id| id.as_str()).unwrap_or_default().to_string(),
+                filled_qty,
+                avg_price: Some(number("deal_avg_price")?),
+                fee: None,
+                ts: order.get("update_time").and_then(|t| t.as_i64()).unwrap_or_else(|| Utc::now().timestamp_millis()),
+                raw: order.clone(),
+            })
+        })
+        .collect()
+}
+
+// 체결을 거래 상태에 반영 (Bitmart 체결량은 계약 수이므로 기초자산 수량으로 환산)
+async fn record_fill(ctx: &TradingContext, mut fill: Fill) {
+    if fill.exchange == "Bitmart" {
+        if let Some(spec) = ctx.config.bitmart_contracts.get(&fill.symbol) {
+            fill.filled_qty *= spec.contract_size;
+        }
+    }
+    println!(
+        "[Private] {} fill: {} {} {} @ {}",
+        fill.exchange,
+        fill.symbol,
+        if fill.is_buy { "BUY" } else { "SELL" },
+        fill.filled_qty,
+        fill.avg_price.unwrap_or_default()
+    );
+    let mut state = ctx.shared_state.lock().await;
+    state.apply_fill(&fill);
+    if let Err(e) = state.save(ctx.config.state_path()) {
+        eprintln!("[State] {}", e);
+    }
+}
+
+// Binance 사용자 데이터 스트림 (listenKey 발급 -> 접속 -> 주기적 연장, 실패 시 재시도)
+pub async fn fetch_binance_private(websocket_base_url: &str, ctx: TradingContext) {
+    loop {
+        let listen_key = match ctx.order.create_listen_key_binance().await {
+            Ok(key) => key,
+            Err(e) => {
+                eprintln!("[Private] Binance listenKey request failed: {}. Retrying...", e);
+                tokio::time::sleep(RECONNECT_DELAY).await;
+                continue;
+            }
+        };
+
+        let url = format!("{}/{}", websocket_base_url, listen_key);
+        match connect_async(url.as_str()).await {
+            Ok((ws_stream, _)) => {
+                println!("[Private] Connected to Binance user data stream.");
+                let (mut write, mut read) = ws_stream.split();
+                let mut keepalive = tokio::time::interval(LISTEN_KEY_KEEPALIVE);
+                keepalive.tick().await;
+
+                loop {
+                    tokio::select! {
+                        _ = keepalive.tick() => {
+                            if let Err(e) = ctx.order.keepalive_listen_key_binance().await {
+                                eprintln!("[Private] Binance listenKey keepalive failed: {}", e);
+                            }
+                        }
+                        msg = read.next() => match msg {
+                            Some(Ok(Message::Text(text))) => {
+                                let Ok(json) = serde_json::from_str::<Value>(&text) else {
+                                    eprintln!("[Private] Error parsing Binance message: {}", text);
+                                    continue;
+                                };
+                                if json.get("e").and_then(|e| e.as_str()) == Some("listenKeyExpired") {
+                                    eprintln!("[Private] Binance listenKey expired. Reconnecting...");
+                                    break;
+                                }
+                                if let Some(fill) = parse_binance_fill(&json) {
+                                    record_fill(&ctx, fill).await;
+                                }
+                            }
+                            Some(Ok(Message::Ping(payload))) => {
+                                // 전송 실패는 다음 read에서 오류로 감지됨
+                                let _ = write.send(Message::Pong(payload)).await;
+                            }
+                            Some(Ok(Message::Close(_))) | None => break,
+                            Some(Err(e)) => {
+                                eprintln!("[Private] Binance WebSocket error: {}", e);
+                                break;
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            Err(e) => eprintln!("[Private] Failed to connect to Binance user data stream: {}", e),
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+// Bitmart private 채널 (로그인 -> 등록된 구독(futures/order) 전송, 인증 실패 시 재시도)
+pub async fn fetch_bitmart_private(websocket_url: &str, ctx: TradingContext) {
+    let mut subscriptions = Subscriptions::new();
+    subscriptions.register(bitmart_subscribe(&["futures/order".to_string()]));
+    loop {
+        match connect_async(websocket_url).await {
+            Ok((ws_stream, _)) => {
+                let (mut write, mut read) = ws_stream.split();
+                let login = ctx.order.bitmart_ws_login(Utc::now().timestamp_millis());
+                if let Err(e) = write.send(Message::Text(login.to_string())).await {
+                    eprintln!("[Private] Failed to send Bitmart login: {}", e);
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                    continue;
+                }
+
+                while let Some(msg) = read.next().await {
+                    match msg {
+                        Ok(Message::Text(text)) => {
+                            let Ok(json) = serde_json::from_str::<Value>(&text) else {
+                                eprintln!("[Private] Error parsing Bitmart message: {}", text);
+                                continue;
+                            };
+                            match bitmart_error_action(&json, &ctx.config.bitmart_error_actions, "Bitmart private") {
+                                Some(FeedAction::Ignore) => continue,
+                                Some(FeedAction::Reconnect) => break,
+                                Some(FeedAction::Fatal) => return,
+                                None => {}
+                            }
+                            // 로그인 응답: 성공하면 주문 채널 구독, 실패하면 재접속
+                            if json.get("action").and_then(|a| a.as_str()) == Some("access") {
+                                if json.get("success").and_then(|s| s.as_bool()) != Some(true) {
+                                    eprintln!("[Private] Bitmart login failed: {}. Retrying...", text);
+                                    break;
+                                }
+                                println!("[Private] Bitmart login succeeded.");
+                                if subscriptions.send_all(&mut write).await.is_err() {
+                                    break;
+                                }
+                                continue;
+                            }
+                            for fill in parse_bitmart_fills(&json) {
+                                record_fill(&ctx, fill).await;
+                            }
+                        }
+                        Ok(Message::Ping(payload)) => {
+                            // 전송 실패는 다음 read에서 오류로 감지됨
+                            let _ = write.send(Message::Pong(payload)).await;
+                        }
+                        Ok(Message::Close(_)) => break,
+                        Err(e) => {
+                            eprintln!("[Private] Bitmart WebSocket error: {}", e);
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Err(e) => eprintln!("[Private] Failed to connect to Bitmart private WebSocket: {}", e),
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
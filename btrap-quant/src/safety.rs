@@ -0,0 +1,79 @@
+use std::sync::Arc;
+use crate::order::{Order, OrderError};
+use crate::state::{SharedState, TradingState, STATE_PATH};
+
+// 패닉 시 열린 포지션을 정리하는 최후 안전장치 (best-effort)
+// 태스크가 패닉으로 풀릴 때(drop) 현재 TradingState를 읽어 양쪽 포지션을 시장가로 청산 시도
+// 정상 종료 시에는 아무것도 하지 않음 (포지션은 상태 파일로 복원)
+pub struct FlattenGuard {
+    order: Arc<Order>,
+    shared_state: SharedState,
+    symbol: String,
+}
+
+impl FlattenGuard {
+    pub fn new(order: Arc<Order>, shared_state: SharedState, symbol: &str) -> Self {
+        Self {
+            order,
+            shared_state,
+            symbol: symbol.to_string(),
+        }
+    }
+}
+
+impl Drop for FlattenGuard {
+    fn drop(&mut self) {
+        if !std::thread::panicking() {
+            return;
+        }
+        eprintln!("[Safety] Panic detected. Attempting best-effort flatten of {}...", self.symbol);
+
+        // 잠금을 얻지 못하면 마지막으로 저장된 상태 파일 사용
+        let state = match self.shared_state.try_lock() {
+            Ok(state) => state.clone(),
+            Err(_) => {
+                eprintln!("[Safety] State is locked. Falling back to {}.", STATE_PATH);
+                TradingState::load(STATE_PATH).unwrap_or_default()
+            }
+        };
+        if !state.is_trading {
+            eprintln!("[Safety] No open position. Nothing to flatten.");
+            return;
+        }
+
+        // 비동기 런타임 안에서는 blocking 클라이언트를 쓸 수 없으므로 별도 스레드에서 실행
+        let order = Arc::clone(&self.order);
+        let symbol = self.symbol.clone();
+        let handle = std::thread::spawn(move || {
+            order.flatten_blocking(
+                &symbol,
+                state.binance_position.as_deref(),
+                state.bitmart_position.as_deref(),
+                (state.leg_quantity("Binance"), state.leg_quantity("Bitmart")),
+            )
+        });
+        match handle.join() {
+            Ok(results) => {
+                for result in results {
+                    eprintln!("[Safety] {}", result);
+                }
+            }
+            Err(_) => eprintln!("[Safety] Flatten thread panicked. Positions may still be open."),
+        }
+    }
+}
+
+// 킬 스위치 청산: 상태와 관계없이 양쪽 거래소의 종목 포지션을 거래소 수량 기준으로 모두 청산
+// 방향마다 청산 주문을 보내며 해당 방향 포지션이 없으면 주문하지 않음
+pub async fn flatten_all(order: &Order, symbol: &str) -> Result<(), OrderError> {
+    let binance = async {
+        order.close_position_binance(symbol, "SELL").await?;
+        order.close_position_binance(symbol, "BUY").await
+    };
+    let bitmart = async {
+        order.close_position_bitmart(symbol, "sell").await?;
+        order.close_position_bitmart(symbol, "buy").await
+    };
+    let (binance, bitmart) = tokio::join!(binance, bitmart);
+    binance.and(bitmart).map(|_| ())
+}
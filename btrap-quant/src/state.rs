@@ -0,0 +1,448 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use crate::depth::TopOfBook;
+use crate::market_hours::MarketGuard;
+use crate::order::Fill;
+
+// 공유 거래 상태
+pub type SharedState = Arc<Mutex<TradingState>>;
+
+// 상태 파일 경로
+pub const STATE_PATH: &str = "trading_state.json";
+// dry run 상태 파일 경로 (실제 포지션 상태와 분리)
+pub const DRY_RUN_STATE_PATH: &str = "trading_state.dry_run.json";
+
+// 현재 상태 파일 버전
+// 필드 구조를 바꾸면 버전을 올리고 migrate()에 변환 단계를 추가할 것
+pub const STATE_VERSION: u32 = 10;
+
+// 진입/청산 판단 시점의 거래소별 상위 호가 (스냅샷을 찍지 않았으면 None)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BookSnapshot {
+    pub entry: Option<HashMap<String, TopOfBook>>,
+    pub exit: Option<HashMap<String, TopOfBook>>,
+}
+
+// 청산 완료된 거래 기록
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradeRecord {
+    pub open_time: DateTime<Utc>,
+    pub close_time: DateTime<Utc>,
+    pub binance_position: String, // "LONG" or "SHORT"
+    pub bitmart_position: String, // "LONG" or "SHORT"
+    pub entry_gap: f64,
+    pub exit_gap: f64,
+    pub quantity: f64,
+    // 수수료 차감 후 실현 스프레드 수익 (USDT, 버전 3 이전 기록은 None)
+    pub captured_pnl: Option<f64>,
+    // 진입/청산 판단 시점 호가 (버전 5 이전 기록이나 스냅샷 미사용 시 None)
+    pub book_snapshot: Option<BookSnapshot>,
+}
+
+// 주문을 보냈지만 아직 포지션으로 기록하지 않은 진입 (재시작 시 거래소 주문 내역과 대조)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InflightTrade {
+    pub trade_id: String, // 진입 주문 clientOrderId 접두어
+    pub binance_position: String, // "LONG" or "SHORT"
+    pub bitmart_position: String, // "LONG" or "SHORT"
+    pub entry_gap: f64,
+    pub quantity: f64,
+    pub sent_at: DateTime<Utc>,
+}
+
+// 거래 상태 (재시작 시 복원)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradingState {
+    pub version: u32,
+    pub is_trading: bool,
+    pub binance_position: Option<String>, // "LONG" or "SHORT"
+    pub bitmart_position: Option<String>, // "LONG" or "SHORT"
+    pub entry_gap: f64, // 추가 진입이 있으면 수량 가중 평균 진입 갭
+    pub quantity: f64, // 진입 주문 수량 (추가 진입 포함)
+    // 거래소별 실제 체결된 진입 수량 (추가 진입 포함, 청산 주문은 이 수량 사용, 기록이 없으면 quantity)
+    pub leg_quantities: HashMap<String, f64>,
+    // 추가 진입 횟수와 직전 진입(최초 또는 추가) 갭 (다음 추가 진입 기준)
+    pub scale_in_steps: u32,
+    pub last_entry_gap: f64,
+    pub position_open_time: Option<DateTime<Utc>>,
+    // 열린 포지션 진입 수수료 (USDT, 버전 4 이전에 연 포지션은 None이라 청산 시 추정)
+    pub entry_fees: Option<f64>,
+    // 열린 포지션 진입 판단 시점 호가 (청산 시 거래 기록으로 이동)
+    pub entry_book: Option<HashMap<String, TopOfBook>>,
+    // 거래소에 걸어 둔 보호 스탑 주문 번호 (키: 거래소 이름, 재시작 후에도 청산 시 취소)
+    pub protective_stops: HashMap<String, String>,
+    // 주문 전송 후 기록 전인 진입 (기록하면 None, 재시작 시 남아 있으면 주문 내역과 대조)
+    pub inflight_trade: Option<InflightTrade>,
+    // 직전 청산 거래의 Binance 포지션 방향과 청산 시각 (거래 기록을 지워도 유지, 방향 전환 대기 기준)
+    pub last_close_direction: Option<String>,
+    pub last_close_time: Option<DateTime<Utc>>,
+    pub trades: Vec<TradeRecord>,
+    // 점검 시간대/거래 중단 상태 (저장하지 않음)
+    #[serde(skip)]
+    pub market_guard: MarketGuard,
+    // 청산 주문을 이미 보냈는지 여부 (이후 틱에서는 거래소 잔량 기준으로 재청산, 저장하지 않음)
+    #[serde(skip)]
+    pub close_attempted: bool,
+    // private 채널로 확인된 거래소별 순체결 수량 (매수 +, 매도 -, 저장하지 않음)
+    #[serde(skip)]
+    pub net_fills: HashMap<String, f64>,
+    // 첫 청산 판단 시점 호가 (부분 청산 재시도 중에는 유지, 저장하지 않음)
+    #[serde(skip)]
+    pub exit_book: Option<HashMap<String, TopOfBook>>,
+    // 이번 실행 중 청산된 거래의 실현 수익 합계 (USDT, 저장하지 않음)
+    #[serde(skip)]
+    pub session_pnl: f64,
+}
+
+impl Default for TradingState {
+    fn default() -> Self {
+        Self {
+            version: STATE_VERSION,
+            is_trading: false,
+            binance_position: None,
+            bitmart_position: None,
+            entry_gap: 0.0,
+            quantity: 0.0,
+            leg_quantities: HashMap::new(),
+            scale_in_steps: 0,
+            last_entry_gap: 0.0,
+            position_open_time: None,
+            entry_fees: None,
+            entry_book: None,
+            protective_stops: HashMap::new(),
+            inflight_trade: None,
+            last_close_direction: None,
+            last_close_time: None,
+            trades: Vec::new(),
+            market_guard: MarketGuard::default(),
+            close_attempted: false,
+            net_fills: HashMap::new(),
+            exit_book: None,
+            session_pnl: 0.0,
+        }
+    }
+}
+
+impl TradingState {
+    // 포지션 진입 기록
+    pub fn open_position(&mut self, binance_position: &str, bitmart_position: &str, entry_gap: f64, quantity: f64) {
+        self.open_position_at(binance_position, bitmart_position, entry_gap, quantity, Utc::now());
+    }
+
+    // 지정 시각 기준 포지션 진입 기록 (보유 시간 계산 기준)
+    pub fn open_position_at(
+        &mut self,
+        binance_position: &str,
+        bitmart_position: &str,
+        entry_gap: f64,
+        quantity: f64,
+        now: DateTime<Utc>,
+    ) {
+        self.is_trading = true;
+        self.binance_position = Some(binance_position.to_string());
+        self.bitmart_position = Some(bitmart_position.to_string());
+        self.entry_gap = entry_gap;
+        self.quantity = quantity;
+        self.leg_quantities.clear();
+        self.scale_in_steps = 0;
+        self.last_entry_gap = entry_gap;
+        self.position_open_time = Some(now);
+        self.entry_fees = None;
+        self.entry_book = None;
+        self.exit_book = None;
+        self.close_attempted = false;
+    }
+
+    // 추가 진입 기록 (진입 갭은 수량 가중 평균으로 갱신, 청산 수익은 평균 갭 기준)
+    pub fn scale_in(&mut self, gap: f64, quantity: f64) {
+        let total = self.quantity + quantity;
+        if total > 0.0 {
+            self.entry_gap = (self.entry_gap * self.quantity + gap * quantity) / total;
+        }
+        self.quantity = total;
+        self.scale_in_steps += 1;
+        self.last_entry_gap = gap;
+    }
+
+    // 거래소별 진입 체결 수량 기록 (추가 진입은 기존 수량에 더함)
+    pub fn record_entry_fill(&mut self, exchange_name: &str, quantity: f64) {
+        *self.leg_quantities.entry(exchange_name.to_string()).or_insert(0.0) += quantity;
+    }
+
+    // 청산할 거래소별 수량 (체결 기록이 없으면 진입 주문 수량)
+    pub fn leg_quantity(&self, exchange_name: &str) -> f64 {
+        self.leg_quantities.get(exchange_name).copied().unwrap_or(self.quantity)
+    }
+
+    // 기록 전에 중단된 진입을 거래소 체결로 복원 (fills: 해당 거래 clientOrderId 주문의 체결, 매수 +, 매도 -로 합산)
+    // 진입 방향으로 남은 수량이 있는 거래소만 포지션으로 기록 (한쪽만 남았으면 그쪽만 청산 대상)
+    // 복원하면 true, 이미 포지션이 있거나 남은 수량이 없으면 진행 중 기록만 지우고 false
+    pub fn reconcile_inflight(&mut self, fills: &[Fill]) -> bool {
+        let Some(inflight) = self.inflight_trade.take() else {
+            return false;
+        };
+        if self.is_trading {
+            return false;
+        }
+        let held = |exchange_name: &str, position: &str| {
+            let net: f64 = fills
+                .iter()
+                .filter(|fill| fill.exchange == exchange_name)
+                .map(|fill| if fill.is_buy { fill.filled_qty } else { -fill.filled_qty })
+                .sum();
+            let held = if position == "LONG" { net } else { -net };
+            (held > f64::EPSILON).then_some(held)
+        };
+        let binance = held("Binance", &inflight.binance_position);
+        let bitmart = held("Bitmart", &inflight.bitmart_position);
+        if binance.is_none() && bitmart.is_none() {
+            return false;
+        }
+        self.open_position_at(
+            &inflight.binance_position,
+            &inflight.bitmart_position,
+            inflight.entry_gap,
+            inflight.quantity,
+            inflight.sent_at,
+        );
+        match binance {
+            Some(quantity) => self.record_entry_fill("Binance", quantity),
+            None => self.binance_position = None,
+        }
+        match bitmart {
+            Some(quantity) => self.record_entry_fill("Bitmart", quantity),
+            None => self.bitmart_position = None,
+        }
+        true
+    }
+
+    // 체결 반영 (열린 포지션과 체결 방향이 다르면 경고)
+    pub fn apply_fill(&mut self, fill: &Fill) {
+        let signed = if fill.is_buy { fill.filled_qty } else { -fill.filled_qty };
+        *self.net_fills.entry(fill.exchange.to_string()).or_insert(0.0) += signed;
+
+        let position = match fill.exchange {
+            "Binance" => self.binance_position.as_deref(),
+            _ => self.bitmart_position.as_deref(),
+        };
+        if let Some(position) = position {
+            let net = self.net_fills[fill.exchange];
+            let quantity = self.leg_quantity(fill.exchange);
+            let expected = if position == "LONG" { quantity } else { -quantity };
+            if (net - expected).abs() > f64::EPSILON {
+                eprintln!(
+                    "[State] {} net fills {} do not match {} position of {}",
+                    fill.exchange, net, position, quantity
+                );
+            }
+        }
+    }
+
+    // 포지션 청산 기록 (열린 포지션이 없으면 None)
+    pub fn close_position(&mut self, exit_gap: f64, captured_pnl: f64) -> Option<TradeRecord> {
+        self.close_position_at(exit_gap, captured_pnl, Utc::now())
+    }
+
+    // 지정 시각 기준 포지션 청산 기록 (손실 대기 시간 계산 기준)
+    pub fn close_position_at(&mut self, exit_gap: f64, captured_pnl: f64, now: DateTime<Utc>) -> Option<TradeRecord> {
+        if !self.is_trading {
+            return None;
+        }
+        let record = TradeRecord {
+            open_time: self.position_open_time.unwrap_or(now),
+            close_time: now,
+            binance_position: self.binance_position.take().unwrap_or_default(),
+            bitmart_position: self.bitmart_position.take().unwrap_or_default(),
+            entry_gap: self.entry_gap,
+            exit_gap,
+            quantity: self.quantity,
+            captured_pnl: Some(captured_pnl),
+            book_snapshot: match (self.entry_book.take(), self.exit_book.take()) {
+                (None, None) => None,
+                (entry, exit) => Some(BookSnapshot { entry, exit }),
+            },
+        };
+        self.last_close_direction = Some(record.binance_position.clone());
+        self.last_close_time = Some(now);
+        self.is_trading = false;
+        self.entry_gap = 0.0;
+        self.quantity = 0.0;
+        self.leg_quantities.clear();
+        self.scale_in_steps = 0;
+        self.last_entry_gap = 0.0;
+        self.position_open_time = None;
+        self.entry_fees = None;
+        self.close_attempted = false;
+        self.session_pnl += captured_pnl;
+        self.trades.push(record.clone());
+        Some(record)
+    }
+
+    // 포지션 보유 시간 (열린 포지션이 없으면 None)
+    pub fn held_for(&self, now: DateTime<Utc>) -> Option<chrono::Duration> {
+        self.position_open_time.map(|open_time| now - open_time)
+    }
+
+    // 최소 보유 시간 전이면 청산 신호를 무시 (진입 시각을 모르는 복원 포지션은 해당 없음)
+    pub fn in_min_hold(&self, min_hold_secs: u64, now: DateTime<Utc>) -> bool {
+        self.held_for(now)
+            .is_some_and(|held| held < chrono::Duration::seconds(min_hold_secs as i64))
+    }
+
+    // 세션 누적 손실이 한도에 도달했는지 여부 (한도가 없으면 false)
+    pub fn session_loss_breached(&self, max_session_loss: Option<f64>) -> bool {
+        max_session_loss.is_some_and(|max_loss| self.session_pnl <= -max_loss)
+    }
+
+    // 직전 거래가 손실이면 청산 후 cooldown_secs 동안 신규 진입 대기 (수익 거래나 손익 미기록 거래는 해당 없음)
+    pub fn in_loss_cooldown(&self, cooldown_secs: u64, now: DateTime<Utc>) -> bool {
+        let Some(last) = self.trades.last() else {
+            return false;
+        };
+        let lost = last.captured_pnl.is_some_and(|pnl| pnl < 0.0);
+        lost && now < last.close_time + chrono::Duration::seconds(cooldown_secs as i64)
+    }
+
+    // 방향 전환 대기 남은 시간 (초, 대기 중이 아니면 None)
+    // 직전 청산과 반대 방향 진입은 reversal_secs, 같은 방향 재진입은 same_direction_secs 동안 막음
+    pub fn direction_cooldown_remaining(
+        &self,
+        binance_position: &str,
+        reversal_secs: u64,
+        same_direction_secs: u64,
+        now: DateTime<Utc>,
+    ) -> Option<i64> {
+        let (Some(direction), Some(close_time)) = (self.last_close_direction.as_deref(), self.last_close_time) else {
+            return None;
+        };
+        let cooldown = if direction == binance_position { same_direction_secs } else { reversal_secs };
+        let until = close_time + chrono::Duration::seconds(cooldown as i64);
+        (now < until).then(|| (until - now).num_seconds())
+    }
+
+    // 거래 기록 보존 개수 제한 (오래된 기록부터 삭제, 삭제된 개수 반환)
+    pub fn trim_history(&mut self, limit: usize) -> usize {
+        let excess = self.trades.len().saturating_sub(limit);
+        self.trades.drain(..excess);
+        excess
+    }
+
+    // 지정 시각 이후 청산된 거래 수와 실현 수익 합계 (세션 요약)
+    pub fn captured_since(&self, since: DateTime<Utc>) -> (usize, f64) {
+        self.trades
+            .iter()
+            .filter(|trade| trade.close_time >= since)
+            .fold((0, 0.0), |(count, total), trade| (count + 1, total + trade.captured_pnl.unwrap_or(0.0)))
+    }
+
+    // 상태 파일 로드 (파일이 없으면 빈 상태, 이전 버전은 마이그레이션)
+    pub fn load(path: &str) -> Result<Self, String> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(_) => return Ok(TradingState::default()),
+        };
+        let value = serde_json::from_str::<Value>(&contents)
+            .map_err(|e| format!("Failed to parse {}: {}", path, e))?;
+        let value = migrate(value)?;
+        serde_json::from_value::<TradingState>(value)
+            .map_err(|e| format!("Failed to load {}: {}", path, e))
+    }
+
+    // 상태 파일 저장 (임시 파일에 쓴 뒤 교체)
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize state: {}", e))?;
+        let tmp_path = format!("{}.tmp", path);
+        fs::write(&tmp_path, json).map_err(|e| format!("Failed to write {}: {}", tmp_path, e))?;
+        fs::rename(&tmp_path, path).map_err(|e| format!("Failed to replace {}: {}", path, e))
+    }
+}
+
+// 이전 버전 상태를 현재 버전으로 변환
+// 버전 0: version 필드와 trades 기록이 없던 초기 형식
+// 버전 1: 진입 수량(quantity)이 없던 형식 (수량 1.0 고정)
+// 버전 2: 거래 기록에 실현 수익(captured_pnl)이 없던 형식
+// 버전 3: 진입 수수료(entry_fees)가 없던 형식
+// 버전 4: 호가 스냅샷(entry_book, 거래 기록의 book_snapshot)이 없던 형식
+// 버전 5: 보호 스탑 주문 번호(protective_stops)가 없던 형식
+// 버전 6: 추가 진입 정보(scale_in_steps, last_entry_gap)가 없던 형식
+// 버전 7: 직전 청산 정보(last_close_direction, last_close_time)가 없던 형식
+// 버전 8: 거래소별 체결 수량(leg_quantities)이 없던 형식 (청산은 quantity 기준)
+// 버전 9: 진행 중 진입(inflight_trade)이 없던 형식
+pub fn migrate(mut value: Value) -> Result<Value, String> {
+    let object = value
+        .as_object_mut()
+        .ok_or_else(|| "State file is not a JSON object".to_string())?;
+    let mut version = object.get("version").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+
+    if version > STATE_VERSION {
+        return Err(format!(
+            "State file version {} is newer than supported version {}",
+            version, STATE_VERSION
+        ));
+    }
+
+    while version < STATE_VERSION {
+        match version {
+            0 => {
+                object.entry("trades").or_insert_with(|| Value::Array(Vec::new()));
+            }
+            1 => {
+                let is_trading = object.get("is_trading").and_then(|v| v.as_bool()).unwrap_or(false);
+                let quantity = if is_trading { 1.0 } else { 0.0 };
+                object.entry("quantity").or_insert_with(|| Value::from(quantity));
+            }
+            2 => {
+                if let Some(Value::Array(trades)) = object.get_mut("trades") {
+                    for trade in trades.iter_mut().filter_map(|t| t.as_object_mut()) {
+                        trade.entry("captured_pnl").or_insert(Value::Null);
+                    }
+                }
+            }
+            3 => {
+                object.entry("entry_fees").or_insert(Value::Null);
+            }
+            4 => {
+                object.entry("entry_book").or_insert(Value::Null);
+                if let Some(Value::Array(trades)) = object.get_mut("trades") {
+                    for trade in trades.iter_mut().filter_map(|t| t.as_object_mut()) {
+                        trade.entry("book_snapshot").or_insert(Value::Null);
+                    }
+                }
+            }
+            5 => {
+                object.entry("protective_stops").or_insert_with(|| Value::Object(Default::default()));
+            }
+            6 => {
+                let entry_gap = object.get("entry_gap").cloned().unwrap_or(Value::from(0.0));
+                object.entry("scale_in_steps").or_insert(Value::from(0));
+                object.entry("last_entry_gap").or_insert(entry_gap);
+            }
+            7 => {
+                // 마지막 거래 기록으로 직전 청산 방향과 시각 복원
+                let last = object.get("trades").and_then(|t| t.as_array()).and_then(|t| t.last()).cloned();
+                let field = |key: &str| last.as_ref().and_then(|trade| trade.get(key)).cloned().unwrap_or(Value::Null);
+                let (direction, close_time) = (field("binance_position"), field("close_time"));
+                object.entry("last_close_direction").or_insert(direction);
+                object.entry("last_close_time").or_insert(close_time);
+            }
+            8 => {
+                object.entry("leg_quantities").or_insert_with(|| Value::Object(Default::default()));
+            }
+            9 => {
+                object.entry("inflight_trade").or_insert(Value::Null);
+            }
+            _ => unreachable!("missing migration step for version {}", version),
+        }
+        version += 1;
+        println!("[State] Migrated state file to version {}", version);
+    }
+
+    object.insert("version".to_string(), Value::from(STATE_VERSION));
+    Ok(value)
+}
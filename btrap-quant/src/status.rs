@@ -0,0 +1,194 @@
+use crate::config::ThresholdUnit;
+use crate::context::TradingContext;
+use crate::depth::mid_price;
+use crate::fees::{break_even_gap, FundingRates};
+use crate::price_calculator::{FillMode, PriceCalculator};
+use crate::strategy::exit_threshold_at;
+
+// 상태 출력 (가격, 최우선 호가, 중간값과 공정가, 호가 불균형, 변동성 및 주문 수량)
+pub async fn report_status(ctx: TradingContext) {
+    const STATUS_INTERVAL_SECS: u64 = 5;
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(STATUS_INTERVAL_SECS));
+    let mut last_counts = ctx.evaluation.counts();
+    loop {
+        interval.tick().await;
+        let prices = ctx.shared_prices.lock().await.clone();
+        let depth = ctx.shared_depth.lock().await.clone();
+        let imbalances = ctx.shared_imbalance.lock().await.clone();
+        let fair_values = ctx.shared_fair_value.lock().await.clone();
+        for exchange_name in ["Binance", "Bitmart"] {
+            let price = prices.get(exchange_name).map(|tick| format!("{:.4}", tick.price)).unwrap_or_else(|| "-".to_string());
+            let (best_bid, best_ask) = match depth.get(exchange_name) {
+                Some(book) => (
+                    book.bids.first().map(|level| level.price.to_string()).unwrap_or_else(|| "-".to_string()),
+                    book.asks.first().map(|level| level.price.to_string()).unwrap_or_else(|| "-".to_string()),
+                ),
+                None => ("-".to_string(), "-".to_string()),
+            };
+            let imbalance = imbalances.get(exchange_name).map(|i| format!("{:+.3}", i)).unwrap_or_else(|| "-".to_string());
+            let mid = depth.get(exchange_name).and_then(mid_price).map(|m| format!("{:.4}", m)).unwrap_or_else(|| "-".to_string());
+            // 최근 체결가와 공정가 괴리 (bp, 크면 오래되었거나 비정상 체결 의심)
+            let fair = match (fair_values.get(exchange_name), prices.get(exchange_name)) {
+                (Some(fair), Some(tick)) => format!("{:.4} ({:+.1} bp vs last)", fair, (tick.price - fair) / fair * 10_000.0),
+                (Some(fair), None) => format!("{:.4}", fair),
+                _ => "-".to_string(),
+            };
+            println!(
+                "[Status] {}: price {}, bid {}, ask {}, mid {}, fair {}, imbalance {}",
+                exchange_name, price, best_bid, best_ask, mid, fair, imbalance
+            );
+        }
+        if !ctx.trading.trading_enabled() {
+            println!(
+                "[Status] Trading PAUSED ({}). Send 'resume' to continue.",
+                if ctx.trading.exits_enabled() { "exits still managed" } else { "exits frozen" }
+            );
+        }
+        println!("[Status] Active tasks: {}/{}", ctx.tasks.active(), ctx.tasks.limit());
+        // 직전 출력 이후 판단 빈도 (판단 주기 제한 시 업데이트 빈도보다 낮음)
+        let counts = ctx.evaluation.counts();
+        println!(
+            "[Status] Strategy evaluations: {:.1}/s for {:.1} price updates/s (min interval {}ms)",
+            (counts.1 - last_counts.1) as f64 / STATUS_INTERVAL_SECS as f64,
+            (counts.0 - last_counts.0) as f64 / STATUS_INTERVAL_SECS as f64,
+            ctx.evaluation.min_interval().as_millis()
+        );
+        last_counts = counts;
+        let (checked, duplicates) = ctx.dedup.counts();
+        if checked > 0 {
+            println!(
+                "[Status] Duplicate price messages skipped: {} of {} ({:.2}%)",
+                duplicates,
+                checked,
+                duplicates as f64 / checked as f64 * 100.0
+            );
+        }
+        println!("[Status] Open trades: {}/{}", ctx.open_trades.count(), ctx.open_trades.limit());
+        println!(
+            "[Status] Binance weight: {}/{} (1m)",
+            ctx.order.binance_weight.used(),
+            ctx.order.binance_weight.limit()
+        );
+        let volatility = ctx.shared_volatility.lock().await.get(&ctx.config.symbol).and_then(|v| v.std_dev());
+        let gap_velocity = ctx.shared_gap_velocity.lock().await.get(&ctx.config.symbol).and_then(|v| v.velocity());
+        // notional_usd 설정 시 Bitmart 가격 기준 수량
+        let quantity = match prices.get("Bitmart") {
+            Some(tick) => ctx.config.effective_position_size(volatility, tick.price),
+            None => Err("no Bitmart price yet".to_string()),
+        };
+        println!(
+            "[Status] {}: enabled {}, volatility {}, gap velocity {}, position size {}",
+            ctx.config.symbol,
+            ctx.symbol_flags.is_enabled(&ctx.config.symbol),
+            volatility.map(|v| format!("{:.5}%", v)).unwrap_or_else(|| "-".to_string()),
+            gap_velocity.map(|v| format!("{:+.4}%/s", v)).unwrap_or_else(|| "-".to_string()),
+            quantity.as_ref().map_or_else(|e| format!("unavailable ({})", e), |q| q.to_string())
+        );
+        // 현재 수량을 시장가로 체결할 때 호가창 평균 체결가 (매도는 bid, 매수는 ask 소진, 최우선 호가 대비 bp)
+        if let Ok(quantity) = &quantity {
+            for exchange_name in ["Binance", "Bitmart"] {
+                let Some(book) = depth.get(exchange_name) else {
+                    continue;
+                };
+                let fill = |is_buy: bool| {
+                    let best = if is_buy { book.asks.first() } else { book.bids.first() }.map(|level| level.price);
+                    match (best, PriceCalculator::calculate_execution_price(book, is_buy, *quantity, FillMode::Taker)) {
+                        (Some(best), Some(vwap)) => format!("{} (best {}, {:.1} bp)", vwap, best, (vwap - best).abs() / best * 10_000.0),
+                        (Some(best), None) => format!("insufficient depth (best {})", best),
+                        _ => "-".to_string(),
+                    }
+                };
+                println!(
+                    "[Status] {} execution for {}: sell {}, buy {}",
+                    exchange_name, quantity, fill(false), fill(true)
+                );
+            }
+        }
+        if let Some(min_volatility) = ctx.config.min_volatility {
+            let volatility = ctx.entry_volatility.lock().await.get(&ctx.config.symbol).and_then(|v| v.std_dev());
+            println!(
+                "[Status] Volatility gate: current {}, minimum {:.5}% ({} returns)",
+                volatility.map(|v| format!("{:.5}%", v)).unwrap_or_else(|| "-".to_string()),
+                min_volatility,
+                ctx.config.min_volatility_window
+            );
+        }
+        // 현재 최적 진입 기회 (주문 없이 미리보기)
+        let opportunity = match quantity {
+            Ok(quantity) => {
+                ctx.price_calculator
+                    .current_opportunity(prices.get("Binance").map(|t| t.price), prices.get("Bitmart").map(|t| t.price), quantity)
+                    .await
+            }
+            Err(_) => None,
+        };
+        match opportunity {
+            Some(opportunity) => println!(
+                "[Status] Best opportunity ({:?}): Binance {}, Bitmart {}, gap {:.4}%, quantity {}{}",
+                ctx.config.gap_definition,
+                opportunity.binance_position, opportunity.bitmart_position, opportunity.gap, opportunity.quantity,
+                if opportunity.estimated { " (Binance price estimated)" } else { "" }
+            ),
+            None => println!("[Status] Best opportunity: none"),
+        }
+        // 틱 단위 임계값 환산 기준 Bitmart 가격 (없으면 0: 환산 불가)
+        let bitmart_price = prices.get("Bitmart").map_or(0.0, |tick| tick.price);
+        // 킬 스위치 상태, 보유 중이면 보유 시간을 반영한 현재 청산 임계값
+        {
+            let state = ctx.shared_state.lock().await;
+            if let Some(reason) = state.market_guard.killed() {
+                println!("[Status] KILL SWITCH active: {}. Restart to resume trading.", reason);
+            }
+            if state.is_trading {
+                println!(
+                    "[Status] Open position: entry gap {:.4}%, exit threshold {:.4}%",
+                    state.entry_gap,
+                    exit_threshold_at(&ctx.config, &state, ctx.clock.now(), bitmart_price)
+                );
+            }
+        }
+        let funding = *ctx.shared_funding.lock().await;
+        let break_even = break_even_gap(&ctx.config, &funding.unwrap_or_default());
+        let entry_threshold = ctx.config.entry_threshold_pct(bitmart_price);
+        println!(
+            "[Status] Break-even gap {:.4}% (funding {}), entry threshold {:.4}%{} ({:+.4}% above break-even)",
+            break_even,
+            funding
+                .map(|f| format!("Binance {:.4}%, Bitmart {:.4}%", f.binance, f.bitmart))
+                .unwrap_or_else(|| "unknown".to_string()),
+            entry_threshold,
+            match ctx.config.threshold_unit {
+                ThresholdUnit::Percent => String::new(),
+                ThresholdUnit::Ticks => format!(" = {} ticks of {:?}", ctx.config.entry_threshold, ctx.config.tick_size),
+            },
+            entry_threshold - break_even
+        );
+    }
+}
+
+// 펀딩비 주기적 갱신 (5분)
+pub async fn refresh_funding_rates(ctx: TradingContext) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(300));
+    loop {
+        interval.tick().await;
+        // 펀딩비는 5분 지연돼도 무방하므로 가중치 한도에 가까우면 다음 주기로 미룸
+        if ctx.order.binance_weight.should_defer() {
+            println!(
+                "[Funding] Binance weight {}/{} near limit. Deferring funding refresh.",
+                ctx.order.binance_weight.used(),
+                ctx.order.binance_weight.limit()
+            );
+            continue;
+        }
+        let (binance, bitmart) = tokio::join!(
+            ctx.order.fetch_funding_rate_binance(&ctx.config.symbol),
+            ctx.order.fetch_funding_rate_bitmart(&ctx.config.symbol),
+        );
+        match (binance, bitmart) {
+            (Ok(binance), Ok(bitmart)) => {
+                *ctx.shared_funding.lock().await = Some(FundingRates { binance, bitmart });
+            }
+            (Err(e), _) | (_, Err(e)) => eprintln!("[Funding] Failed to fetch funding rates: {}", e),
+        }
+    }
+}
@@ -0,0 +1,1776 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::config::{Exchange, StrategyConfig};
+use crate::context::{PriceTick, TradingContext};
+use crate::depth::{maker_price, snapshot_books, Level, SharedDepth, TopOfBook};
+use crate::notify::AlertEvent;
+use crate::order::{closing_side, opening_side, position_of_side, ClientOrderIds, Fill, Order, OrderError, RejectionContext};
+use crate::price_calculator::{Opportunity, PriceCalculator, Quotes};
+use crate::safety::flatten_all;
+use crate::state::{InflightTrade, TradingState};
+use crate::trade_events::TradeEvent;
+use crate::volatility::{gap_stabilizing, volatility_sufficient};
+
+// 갭 방향에 따른 진입 포지션 (Binance, Bitmart)
+// 기본: 갭 > 0이면 Binance 숏/Bitmart 롱, 갭 < 0이면 Binance 롱/Bitmart 숏
+// invert_direction이면 롱/숏 배정을 반대로
+pub fn entry_positions(percent_diff: f64, invert_direction: bool) -> (&'static str, &'static str) {
+    let binance_short = (percent_diff > 0.0) != invert_direction;
+    if binance_short {
+        ("SHORT", "LONG")
+    } else {
+        ("LONG", "SHORT")
+    }
+}
+
+// 호가 불균형이 거래 방향과 일치하는지 확인
+// Binance 숏/Bitmart 롱: Bitmart 매수세가 Binance보다 강해야 함
+// Binance 롱/Bitmart 숏: Binance 매수세가 Bitmart보다 강해야 함
+pub fn imbalance_agrees(
+    binance_position: &str,
+    binance_imbalance: Option<f64>,
+    bitmart_imbalance: Option<f64>,
+    min_imbalance: f64,
+) -> bool {
+    let (Some(binance), Some(bitmart)) = (binance_imbalance, bitmart_imbalance) else {
+        return false; // 호가창이 비어 있으면 판단 불가
+    };
+    let relative = bitmart - binance;
+    if binance_position == "SHORT" {
+        relative >= min_imbalance
+    } else {
+        -relative >= min_imbalance
+    }
+}
+
+// 거래소별 주문 결과
+pub enum LegResult {
+    Filled(Option<Fill>), // dry_run이면 None
+    Failed(OrderError),
+    Skipped, // 주문하지 않음
+}
+
+impl LegResult {
+    // 실패가 아니면 성공으로 간주
+    pub fn is_ok(&self) -> bool {
+        !matches!(self, LegResult::Failed(_))
+    }
+
+    pub fn fill(&self) -> Option<&Fill> {
+        match self {
+            LegResult::Filled(fill) => fill.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn error(&self) -> Option<&OrderError> {
+        match self {
+            LegResult::Failed(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    pub fn rejection_code(&self) -> Option<i64> {
+        match self {
+            LegResult::Failed(e) => e.rejection_code(),
+            _ => None,
+        }
+    }
+
+    // 실제 체결 수량 (체결 수량을 모르는 dry_run과 대기 주문은 주문 수량, 실패/건너뜀은 0)
+    // 대기 maker 주문은 재호가 루프에서 전량 체결을 확인한 뒤에만 기록됨 (maker_legs는 maker_reprice_ms > 0 필수)
+    pub fn filled_quantity(&self, requested: f64) -> f64 {
+        match self {
+            LegResult::Filled(Some(fill)) if fill.filled_qty > 0.0 => fill.filled_qty,
+            LegResult::Filled(_) => requested,
+            _ => 0.0,
+        }
+    }
+}
+
+// 체결 로그 (체결가를 모르면 unknown)
+fn log_fill(fill: &Fill) {
+    let price = fill.avg_price.map_or("unknown".to_string(), |price| price.to_string());
+    println!(
+        "[Order] {} {} filled: {} @ {} (order {})",
+        fill.exchange,
+        if fill.is_buy { "BUY" } else { "SELL" },
+        fill.filled_qty,
+        price,
+        fill.order_id
+    );
+}
+
+// 체결 수수료 (USDT): 실제 수수료를 우선 사용하고, 없으면 설정 수수료율로 추정한 값을 로그와 함께 사용
+// 체결되지 않은 주문은 0
+async fn leg_fee(ctx: &TradingContext, action: &str, exchange_name: &str, leg: &LegResult, estimated: f64) -> f64 {
+    let reason = match leg {
+        LegResult::Filled(Some(fill)) => match ctx.order.fetch_fill_fee(fill).await {
+            Ok(Some(fee)) => return fee,
+            Ok(None) => "not reported in USDT yet".to_string(),
+            Err(e) => e.to_string(),
+        },
+        LegResult::Filled(None) => "dry run".to_string(),
+        _ => return 0.0,
+    };
+    println!(
+        "[Trade] {} {} fee estimated at configured rate: {:.4} USDT (actual unavailable: {})",
+        exchange_name, action, estimated, reason
+    );
+    estimated
+}
+
+// maker 지정가 주문 (requote가 있으면 post-only 거부 시 최신 최우선 호가로 가격을 다시 정해 retries회까지 재시도)
+// 재호가할 호가창이 없으면 마지막 거부를 그대로 반환
+async fn place_maker_leg(
+    order: &Order,
+    exchange_name: &str,
+    symbol: &str,
+    leg: OrderLeg<'_>,
+    quantity: f64,
+    mut price: f64,
+    post_only: bool,
+) -> Result<Fill, OrderError> {
+    let is_buy = leg.side.eq_ignore_ascii_case("BUY");
+    let retries = leg.requote.map_or(0, |requote| requote.retries);
+    let mut attempt = 0;
+    loop {
+        let result = match exchange_name {
+            "Binance" => order.place_limit_order_binance(symbol, leg.side, quantity, price, post_only).await,
+            _ => order.place_limit_order_bitmart(symbol, leg.side, quantity, price, post_only).await,
+        };
+        let e = match result {
+            Ok(fill) => {
+                println!("[Order] {} maker order placed @ {} (order {})", exchange_name, price, fill.order_id);
+                return Ok(fill);
+            }
+            Err(e) if e.is_post_only_rejection() && attempt < retries => e,
+            Err(e) => return Err(e),
+        };
+        let Some(requote) = leg.requote else {
+            return Err(e);
+        };
+        let Some(next) = requote.depth.lock().await.get(exchange_name).and_then(|book| maker_price(book, is_buy)) else {
+            eprintln!("[Order] {} post-only order rejected @ {} and no order book to reprice", exchange_name, price);
+            return Err(e);
+        };
+        attempt += 1;
+        println!(
+            "[Order] {} post-only order rejected @ {}, repricing to {} (retry {}/{})",
+            exchange_name, price, next, attempt, retries
+        );
+        price = next;
+    }
+}
+
+// 진입 방향에 배정된 거래소별 주문 방식 (config.maker_legs)
+// maker 거래소는 같은 편 최우선 호가에 지정가, 호가창이 없으면 시장가로 대체
+async fn entry_legs<'a>(
+    ctx: &'a TradingContext,
+    binance_position: &str,
+    binance_side: &'a str,
+    bitmart_side: &'a str,
+) -> (OrderLeg<'a>, OrderLeg<'a>) {
+    let mut legs = (OrderLeg::market(binance_side), OrderLeg::market(bitmart_side));
+    let Some(maker) = ctx.config.maker_legs.maker_for(binance_position) else {
+        return legs;
+    };
+    let leg = match maker {
+        Exchange::Binance => &mut legs.0,
+        Exchange::Bitmart => &mut legs.1,
+    };
+    let is_buy = leg.side.eq_ignore_ascii_case("BUY");
+    match ctx.shared_depth.lock().await.get(maker.name()).and_then(|book| maker_price(book, is_buy)) {
+        Some(price) => {
+            leg.order = LegOrder::Limit { price, post_only: ctx.config.post_only };
+            leg.requote = Some(MakerRequote { depth: &ctx.shared_depth, retries: ctx.config.post_only_retries });
+        }
+        None => eprintln!("[Order] {} maker price unavailable (no order book). Using a market order.", maker.name()),
+    }
+    legs
+}
+
+// 판단 시점 호가 스냅샷 (book_snapshot_levels가 0이면 None)
+async fn book_snapshot(ctx: &TradingContext) -> Option<HashMap<String, TopOfBook>> {
+    let levels = ctx.config.book_snapshot_levels;
+    if levels == 0 {
+        return None;
+    }
+    let depth = ctx.shared_depth.lock().await;
+    Some(snapshot_books(&depth, levels))
+}
+
+// 주문 방식 (Market: 시장가 taker, Limit: 지정가 maker, post_only면 즉시 체결될 가격이면 거부)
+// Close: 보유 포지션을 줄이는 시장가 (Bitmart는 청산 방향 코드로 전송)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LegOrder {
+    Market,
+    Close,
+    Limit { price: f64, post_only: bool },
+}
+
+// maker 주문 post-only 거부 시 재호가에 쓸 호가창과 재시도 횟수 (config.post_only_retries)
+#[derive(Clone, Copy)]
+pub struct MakerRequote<'a> {
+    pub depth: &'a SharedDepth,
+    pub retries: u32,
+}
+
+impl fmt::Debug for MakerRequote<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MakerRequote").field("retries", &self.retries).finish()
+    }
+}
+
+// 거래소 한쪽 주문 (주문 방향과 방식, 지정가는 requote가 있으면 post-only 거부 시 재호가)
+#[derive(Debug, Clone, Copy)]
+pub struct OrderLeg<'a> {
+    pub side: &'a str,
+    pub order: LegOrder,
+    pub requote: Option<MakerRequote<'a>>,
+}
+
+impl<'a> OrderLeg<'a> {
+    pub fn market(side: &'a str) -> Self {
+        Self { side, order: LegOrder::Market, requote: None }
+    }
+
+    pub fn close(side: &'a str) -> Self {
+        Self { side, order: LegOrder::Close, requote: None }
+    }
+
+    pub fn limit(side: &'a str, price: f64, post_only: bool) -> Self {
+        Self { side, order: LegOrder::Limit { price, post_only }, requote: None }
+    }
+
+    // 로그용 역할 (maker는 지정가 포함)
+    pub fn role(&self) -> String {
+        match self.order {
+            LegOrder::Market | LegOrder::Close => "taker".to_string(),
+            LegOrder::Limit { price, .. } => format!("maker @ {}", price),
+        }
+    }
+}
+
+// 거래소 한쪽 주문 전송
+async fn place_leg(order: &Order, exchange_name: &str, symbol: &str, leg: OrderLeg<'_>, quantity: f64) -> Result<Fill, OrderError> {
+    match (exchange_name, leg.order) {
+        ("Binance", LegOrder::Market | LegOrder::Close) => order.place_market_order_binance(symbol, leg.side, quantity).await,
+        (_, LegOrder::Limit { price, post_only }) => {
+            place_maker_leg(order, exchange_name, symbol, leg, quantity, price, post_only).await
+        }
+        (_, LegOrder::Market) => order.place_market_order_bitmart(symbol, leg.side, quantity).await,
+        (_, LegOrder::Close) => order.close_market_order_bitmart(symbol, leg.side, quantity).await,
+    }
+}
+
+// 두 거래소 주문을 동시에 실행 (leg가 None인 거래소는 건너뜀)
+// dry_run이면 주문을 보내지 않고 체결된 것으로 처리
+pub async fn place_legs(
+    order: &Order,
+    symbol: &str,
+    dry_run: bool,
+    binance: Option<OrderLeg<'_>>,
+    bitmart: Option<OrderLeg<'_>>,
+    quantities: (f64, f64), // (Binance, Bitmart) 주문 수량
+) -> (LegResult, LegResult) {
+    let binance_side = binance.map(|leg| leg.side);
+    let bitmart_side = bitmart.map(|leg| leg.side);
+    let (binance_quantity, bitmart_quantity) = quantities;
+    if dry_run {
+        println!(
+            "[DryRun] Would place {} orders: Binance {:?} {}, Bitmart {:?} {}",
+            symbol, binance_side, binance_quantity, bitmart_side, bitmart_quantity
+        );
+        let simulated = |leg: Option<OrderLeg>| leg.map_or(LegResult::Skipped, |_| LegResult::Filled(None));
+        return (simulated(binance), simulated(bitmart));
+    }
+    let binance_leg = async {
+        match binance {
+            Some(leg) => Some(place_leg(order, "Binance", symbol, leg, binance_quantity).await),
+            None => None,
+        }
+    };
+    let bitmart_leg = async {
+        match bitmart {
+            Some(leg) => Some(place_leg(order, "Bitmart", symbol, leg, bitmart_quantity).await),
+            None => None,
+        }
+    };
+    let (binance_result, bitmart_result) = tokio::join!(binance_leg, bitmart_leg);
+
+    let binance_leg = match binance_result {
+        Some(Ok(fill)) => {
+            log_fill(&fill);
+            LegResult::Filled(Some(fill))
+        }
+        Some(Err(e)) => {
+            eprintln!("[Order] Binance {} Order Failed: {}", binance_side.unwrap_or_default(), e);
+            LegResult::Failed(e)
+        }
+        None => LegResult::Skipped,
+    };
+    let bitmart_leg = match bitmart_result {
+        Some(Ok(fill)) => {
+            log_fill(&fill);
+            LegResult::Filled(Some(fill))
+        }
+        Some(Err(e)) => {
+            eprintln!("[Order] Bitmart {} Order Failed: {}", bitmart_side.unwrap_or_default(), e);
+            LegResult::Failed(e)
+        }
+        None => LegResult::Skipped,
+    };
+
+    match (binance_leg.is_ok(), bitmart_leg.is_ok()) {
+        (true, true) => {}
+        (true, false) => eprintln!("[Order] Bitmart leg failed while Binance leg succeeded. Position is unhedged."),
+        (false, true) => eprintln!("[Order] Binance leg failed while Bitmart leg succeeded. Position is unhedged."),
+        (false, false) => eprintln!("[Order] Both legs failed."),
+    }
+    (binance_leg, bitmart_leg)
+}
+
+// 보호 스탑 가격: 진입가에서 stop_pct% 불리한 방향 (롱은 아래, 숏은 위)
+// 호가 단위가 있으면 진입가에서 멀어지는 쪽으로 맞춤 (설정보다 가까워지지 않음), 없으면 소수 8자리
+pub fn protective_stop_price(position_side: &str, entry_price: f64, stop_pct: f64, tick_size: Option<f64>) -> f64 {
+    let long = position_side == "LONG";
+    let raw = if long { entry_price * (1.0 - stop_pct / 100.0) } else { entry_price * (1.0 + stop_pct / 100.0) };
+    match tick_size.filter(|tick| *tick > 0.0) {
+        Some(tick) => {
+            let ticks = raw / tick;
+            // 부동소수점 오차로 한 단위 더 밀리지 않도록 보정
+            let ticks = if long { (ticks + 1e-9).floor() } else { (ticks - 1e-9).ceil() };
+            let decimals = (-tick.log10()).ceil().max(0.0) as i32;
+            let scale = 10f64.powi(decimals);
+            (ticks * tick * scale).round() / scale
+        }
+        None => (raw * 1e8).round() / 1e8,
+    }
+}
+
+// 진입 후 체결된 쪽마다 거래소 보호 스탑 주문 (reduce only, 실패해도 포지션은 유지하고 알림)
+async fn place_protective_stops(
+    ctx: &TradingContext,
+    state: &mut TradingState,
+    entry: &EntryResult,
+    prices: (f64, f64),
+    stop_pct: f64,
+) {
+    let config = &ctx.config;
+    let legs = [
+        ("Binance", state.binance_position.clone(), &entry.binance_leg, prices.0),
+        ("Bitmart", state.bitmart_position.clone(), &entry.bitmart_leg, prices.1),
+    ];
+    for (exchange_name, position, leg, decision_price) in legs {
+        let Some(position) = position else {
+            continue;
+        };
+        // 체결가를 모르면 판단 시점 가격 기준
+        let entry_price = match leg {
+            LegResult::Filled(Some(fill)) => fill.avg_price.unwrap_or(decision_price),
+            _ => decision_price,
+        };
+        let side = closing_side(&position, exchange_name);
+        let quantity = state.leg_quantity(exchange_name);
+        let result = if exchange_name == "Binance" {
+            let tick_size = match ctx.order.fetch_tick_size_binance(&config.symbol).await {
+                Ok(tick_size) => tick_size,
+                Err(e) => {
+                    eprintln!("[Order] Binance tick size unavailable, stop price not rounded to tick: {}", e);
+                    None
+                }
+            };
+            let stop_price = protective_stop_price(&position, entry_price, stop_pct, tick_size);
+            ctx.order
+                .place_stop_market_binance(&config.symbol, side, quantity, stop_price, true)
+                .await
+                .map(|fill| (fill, stop_price))
+        } else {
+            let stop_price = protective_stop_price(&position, entry_price, stop_pct, None);
+            ctx.order
+                .place_stop_market_bitmart(&config.symbol, side, quantity, stop_price, true)
+                .await
+                .map(|fill| (fill, stop_price))
+        };
+        match result {
+            Ok((fill, stop_price)) => {
+                println!(
+                    "[Order] {} protective stop {} {} @ {} (entry {}, order {})",
+                    exchange_name, side, quantity, stop_price, entry_price, fill.order_id
+                );
+                state.protective_stops.insert(exchange_name.to_string(), fill.order_id);
+            }
+            Err(e) => {
+                eprintln!("[Order] {} protective stop failed: {}", exchange_name, e);
+                ctx.notifier.notify(AlertEvent::Error(format!(
+                    "{} protective stop for {} {} failed: {}",
+                    exchange_name, position, config.symbol, e
+                )));
+            }
+        }
+    }
+}
+
+// 청산 완료된 쪽의 보호 스탑 취소 (실패하면 거래소에 남은 주문을 수동 취소하도록 알림)
+async fn cancel_protective_stop(ctx: &TradingContext, state: &mut TradingState, exchange_name: &str) {
+    let Some(order_id) = state.protective_stops.remove(exchange_name) else {
+        return;
+    };
+    let symbol = &ctx.config.symbol;
+    let result = match exchange_name {
+        "Binance" => ctx.order.cancel_order_binance(symbol, &order_id).await,
+        _ => ctx.order.cancel_plan_order_bitmart(symbol, &order_id).await,
+    };
+    match result {
+        Ok(()) => println!("[Order] {} protective stop cancelled (order {})", exchange_name, order_id),
+        Err(e) => {
+            eprintln!("[Order] Failed to cancel {} protective stop {}: {}", exchange_name, order_id, e);
+            ctx.notifier.notify(AlertEvent::Error(format!(
+                "Cancel {} protective stop {} for {} manually: {}",
+                exchange_name, order_id, symbol, e
+            )));
+        }
+    }
+}
+
+// 세션 주문 수 상한 초과: 거래를 진입/청산 모두 일시 중지 (포지션은 수동 확인 필요)
+fn trip_order_budget(ctx: &TradingContext) {
+    let config = &ctx.config;
+    let limit = ctx.order.order_budget.max_per_hour();
+    eprintln!(
+        "[Guard] !!! Order budget of {} orders/hour exceeded on {}. Entries and exits frozen; check positions manually. !!!",
+        limit, config.symbol
+    );
+    ctx.trading.pause(true, "order budget");
+    ctx.notifier.notify(AlertEvent::CircuitBreaker(format!(
+        "ORDER BUDGET {}: more than {} orders in the last hour. All order placement halted until restart; check positions manually.",
+        config.symbol, limit
+    )));
+    ctx.market_tap.dump_ring("order-budget");
+}
+
+// 세션 손실 한도 초과: 재시작 전까지 거래 중단하고 남은 포지션을 모두 청산
+async fn trip_session_loss_kill(ctx: &TradingContext, state: &mut TradingState) {
+    let config = &ctx.config;
+    let reason = format!(
+        "session loss {:.4} USDT breached max_session_loss {} USDT",
+        state.session_pnl,
+        config.max_session_loss.unwrap_or_default()
+    );
+    state.market_guard.kill(reason.clone());
+    ctx.notifier.notify(AlertEvent::CircuitBreaker(format!("KILL SWITCH {}: {}. Trading halted until restart.", config.symbol, reason)));
+    ctx.market_tap.dump_ring("kill-switch");
+    if config.dry_run {
+        return;
+    }
+    match flatten_all(&ctx.order, &config.symbol).await {
+        Ok(()) => eprintln!("[Guard] All {} positions flattened.", config.symbol),
+        Err(e) => {
+            eprintln!("[Guard] Kill switch flatten failed: {}", e);
+            ctx.notifier.notify(AlertEvent::Error(format!(
+                "Kill switch failed to flatten {}; close positions manually: {}",
+                config.symbol, e
+            )));
+        }
+    }
+}
+
+// 되돌리기 전 maker 지정가 주문의 미체결 잔량 취소 (나중에 체결되어 헤지 없는 포지션이 생기지 않도록)
+async fn cancel_resting_leg(
+    order: &Order,
+    exchange_name: &str,
+    symbol: &str,
+    leg: OrderLeg<'_>,
+    result: &LegResult,
+) -> Result<(), OrderError> {
+    let (LegOrder::Limit { .. }, Some(fill)) = (leg.order, result.fill()) else {
+        return Ok(());
+    };
+    println!("[Order] Cancelling resting {} maker order {}.", exchange_name, fill.order_id);
+    match exchange_name {
+        "Binance" => order.cancel_order_binance(symbol, &fill.order_id).await,
+        _ => order.cancel_order_bitmart(symbol, &fill.order_id).await,
+    }
+}
+
+// 진입 주문 결과
+pub struct EntryResult {
+    pub binance_leg: LegResult,
+    pub bitmart_leg: LegResult,
+    // 한쪽만 체결되어 체결된 쪽을 되돌린 결과 (None이면 되돌릴 필요 없음)
+    pub unwind: Option<Result<(), OrderError>>,
+}
+
+impl EntryResult {
+    // 양쪽 모두 체결되어 헤지가 성립했는지 여부
+    pub fn is_hedged(&self) -> bool {
+        self.binance_leg.is_ok() && self.bitmart_leg.is_ok()
+    }
+
+    // 진입 제한 시간 초과로 중단했는지 여부
+    pub fn timed_out(&self) -> bool {
+        matches!(self.binance_leg.error(), Some(OrderError::DeadlineExceeded(_)))
+    }
+}
+
+// 양쪽 진입 주문 (한쪽만 실패하면 체결된 쪽 포지션을 즉시 전량 청산해 헤지 없는 포지션을 남기지 않음)
+// deadline_ms 안에 양쪽 결과가 오지 않으면 중단: 전송 중인 주문은 버리지 않고 응답까지 기다린 뒤
+// (버리면 거래소가 접수한 주문이 되돌린 뒤에 체결될 수 있음) 걸린 지정가를 취소하고 양쪽 거래소 포지션을 모두 청산,
+// 청산 후 포지션이 실제로 남지 않았는지 다시 확인
+pub async fn place_entry(
+    order: &Order,
+    symbol: &str,
+    dry_run: bool,
+    binance: OrderLeg<'_>,
+    bitmart: OrderLeg<'_>,
+    quantity: f64,
+    deadline_ms: u64,
+) -> EntryResult {
+    let started = std::time::Instant::now();
+    let (binance_side, bitmart_side) = (binance.side, bitmart.side);
+    let mut pending = std::pin::pin!(place_legs(order, symbol, dry_run, Some(binance), Some(bitmart), (quantity, quantity)));
+    let legs = if deadline_ms == 0 {
+        Ok(pending.as_mut().await)
+    } else {
+        tokio::time::timeout(Duration::from_millis(deadline_ms), pending.as_mut()).await
+    };
+    let Ok((binance_leg, bitmart_leg)) = legs else {
+        eprintln!(
+            "[Order] Entry aborted: legs not confirmed within {}ms (elapsed {}ms). Waiting for in-flight orders before unwinding.",
+            deadline_ms,
+            started.elapsed().as_millis()
+        );
+        let (late_binance, late_bitmart) = pending.await;
+        let (cancel_binance, cancel_bitmart) = tokio::join!(
+            cancel_resting_leg(order, "Binance", symbol, binance, &late_binance),
+            cancel_resting_leg(order, "Bitmart", symbol, bitmart, &late_bitmart),
+        );
+        let unwind = match cancel_binance.and(cancel_bitmart) {
+            Ok(()) => unwind_entry(order, symbol, binance_side, bitmart_side).await,
+            Err(e) => Err(e),
+        };
+        let unwind = match unwind {
+            Ok(()) => confirm_flat(order, symbol).await,
+            Err(e) => Err(e),
+        };
+        match &unwind {
+            Ok(()) => println!("[Order] Entry unwound after deadline ({}ms total). Staying flat.", started.elapsed().as_millis()),
+            Err(e) => eprintln!("[Order] Failed to unwind entry after deadline: {}", e),
+        }
+        let aborted = || LegResult::Failed(OrderError::DeadlineExceeded(deadline_ms));
+        return EntryResult { binance_leg: aborted(), bitmart_leg: aborted(), unwind: Some(unwind) };
+    };
+    let binance_leg = resolve_unconfirmed_binance(order, symbol, binance_side, 0.0, binance_leg).await;
+    let unwind = match (binance_leg.is_ok(), bitmart_leg.is_ok()) {
+        // 포지션으로도 체결 여부를 확인하지 못하면 체결됐다고 보고 양쪽 거래소 포지션 전량 청산
+        (false, _) if matches!(binance_leg.error(), Some(OrderError::FillUnconfirmed(_))) => {
+            println!("[Order] Binance {} fill unconfirmed. Unwinding both exchange positions.", binance_side);
+            let cancel = cancel_resting_leg(order, "Bitmart", symbol, bitmart, &bitmart_leg).await;
+            Some(match cancel {
+                Ok(()) => unwind_entry(order, symbol, binance_side, bitmart_side).await,
+                Err(e) => Err(e),
+            })
+        }
+        (true, false) => {
+            let side = closing_side(position_of_side(binance_side), "Binance");
+            println!("[Order] Unwinding Binance {} leg.", binance_side);
+            let cancel = cancel_resting_leg(order, "Binance", symbol, binance, &binance_leg).await;
+            Some(cancel.and(order.close_position_binance(symbol, side).await.map(|_| ())))
+        }
+        (false, true) => {
+            let side = closing_side(position_of_side(bitmart_side), "Bitmart");
+            println!("[Order] Unwinding Bitmart {} leg.", bitmart_side);
+            let cancel = cancel_resting_leg(order, "Bitmart", symbol, bitmart, &bitmart_leg).await;
+            Some(cancel.and(order.close_position_bitmart(symbol, side).await.map(|_| ())))
+        }
+        _ => None,
+    };
+    match &unwind {
+        Some(Ok(())) => println!("[Order] Single filled leg unwound. Staying flat."),
+        Some(Err(e)) => eprintln!("[Order] Failed to unwind single filled leg: {}", e),
+        None => {}
+    }
+    EntryResult { binance_leg, bitmart_leg, unwind }
+}
+
+// 추가 진입 주문 (한쪽만 체결되면 체결된 추가 수량만 reduce-only로 되돌려 기존 헤지 포지션은 유지)
+// place_entry의 되돌리기는 거래소 포지션 전량을 청산하므로 쓰지 않고, 되돌릴 수량을 알아야 하므로 양쪽 응답을 모두 기다림
+// existing: 추가 진입 전 기록된 (Binance, Bitmart) 진입 수량 (체결을 확인하지 못한 주문의 추가 수량 계산용)
+// 추가 수량도 확인하지 못하면 되돌리지 않고 FillUnconfirmed를 되돌리기 오류로 반환
+pub async fn place_scale_in(
+    order: &Order,
+    symbol: &str,
+    dry_run: bool,
+    binance_side: &str,
+    bitmart_side: &str,
+    quantity: f64,
+    existing: (f64, f64),
+) -> EntryResult {
+    let (binance_leg, bitmart_leg) = place_legs(
+        order,
+        symbol,
+        dry_run,
+        Some(OrderLeg::market(binance_side)),
+        Some(OrderLeg::market(bitmart_side)),
+        (quantity, quantity),
+    )
+    .await;
+    let binance_leg = resolve_unconfirmed_binance(order, symbol, binance_side, existing.0, binance_leg).await;
+    if let Some(OrderError::FillUnconfirmed(order_id)) = binance_leg.error() {
+        let unwind = Some(Err(OrderError::FillUnconfirmed(order_id.clone())));
+        return EntryResult { binance_leg, bitmart_leg, unwind };
+    }
+    let filled = match (binance_leg.is_ok(), bitmart_leg.is_ok()) {
+        (true, false) => Some(("Binance", binance_side, binance_leg.filled_quantity(quantity))),
+        (false, true) => Some(("Bitmart", bitmart_side, bitmart_leg.filled_quantity(quantity))),
+        _ => None,
+    };
+    let unwind = match filled {
+        Some((exchange_name, side, filled)) => {
+            let close = closing_side(position_of_side(side), exchange_name);
+            println!("[Order] Unwinding {} scale-in leg: {} {}.", exchange_name, close, filled);
+            let result = match exchange_name {
+                "Binance" => order.close_market_order_binance(symbol, close, filled).await,
+                _ => order.close_market_order_bitmart(symbol, close, filled).await,
+            };
+            match &result {
+                Ok(_) => println!("[Order] {} scale-in leg unwound. Position unchanged.", exchange_name),
+                Err(e) => eprintln!("[Order] Failed to unwind {} scale-in leg: {}", exchange_name, e),
+            }
+            Some(result.map(|_| ()))
+        }
+        None => None,
+    };
+    EntryResult { binance_leg, bitmart_leg, unwind }
+}
+
+// 시작 시 기록 전에 중단된 진입 대조 (inflight_trade가 남아 있으면 clientOrderId 접두어로 양쪽 주문 내역 조회)
+// 체결이 남아 있으면 포지션으로 기록해 청산 대상으로 만들고, 없으면 진행 중 기록만 지움
+// 조회에 실패하면 기록을 그대로 두어 다음 시작 때 다시 대조
+pub async fn reconcile_inflight_orders(order: &Order, config: &StrategyConfig, state: &mut TradingState) {
+    let Some(inflight) = state.inflight_trade.clone() else {
+        return;
+    };
+    println!("[State] Reconciling in-flight entry {} sent at {}", inflight.trade_id, inflight.sent_at);
+    // 거래소 시각과 어긋날 수 있어 전송 1분 전부터 조회
+    let since_ms = inflight.sent_at.timestamp_millis() - 60_000;
+    let fills = match tokio::try_join!(
+        order.fetch_client_orders_binance(&config.symbol, &inflight.trade_id, since_ms),
+        order.fetch_client_orders_bitmart(&config.symbol, &inflight.trade_id, since_ms),
+    ) {
+        Ok((binance, bitmart)) => binance.into_iter().chain(bitmart).collect::<Vec<_>>(),
+        Err(e) => {
+            eprintln!("[State] Failed to query orders for in-flight entry {}, retrying on next start: {}", inflight.trade_id, e);
+            return;
+        }
+    };
+    for fill in &fills {
+        log_fill(fill);
+    }
+    if state.reconcile_inflight(&fills) {
+        println!(
+            "[State] Recovered unrecorded entry {}: Binance {:?} {}, Bitmart {:?} {}",
+            inflight.trade_id,
+            state.binance_position,
+            state.leg_quantity("Binance"),
+            state.bitmart_position,
+            state.leg_quantity("Bitmart")
+        );
+    } else {
+        println!("[State] In-flight entry {} has nothing to recover; cleared", inflight.trade_id);
+    }
+    if let Err(e) = state.save(config.state_path()) {
+        eprintln!("[State] {}", e);
+    }
+}
+
+// 체결을 확인하지 못한 Binance 주문 정리: 남은 주문을 취소한 뒤 거래소 포지션에서 늘어난 수량을 체결로 봄
+// existing: 주문 전 진입 방향 포지션 수량, 늘어난 수량이 없으면 Unfilled, 포지션 조회도 실패하면 그대로 반환
+async fn resolve_unconfirmed_binance(order: &Order, symbol: &str, side: &str, existing: f64, leg: LegResult) -> LegResult {
+    let Some(OrderError::FillUnconfirmed(order_id)) = leg.error() else {
+        return leg;
+    };
+    let order_id = order_id.clone();
+    let position = match order.cancel_order_binance(symbol, &order_id).await {
+        Ok(()) => order.fetch_position_binance(symbol).await,
+        Err(e) => Err(e),
+    };
+    let amount = match position {
+        Ok(amount) => amount,
+        Err(e) => {
+            eprintln!("[Order] Failed to check Binance position for unconfirmed order {}: {}", order_id, e);
+            return leg;
+        }
+    };
+    let held = if position_of_side(side) == "LONG" { amount } else { -amount };
+    let added = held - existing;
+    if added <= f64::EPSILON {
+        println!("[Order] Binance order {} added no position. Treating as not filled.", order_id);
+        return LegResult::Failed(OrderError::Unfilled(order_id));
+    }
+    println!("[Order] Binance order {} added {} to the position. Treating as filled.", order_id, added);
+    LegResult::Filled(Some(Fill {
+        exchange: "Binance",
+        symbol: symbol.to_string(),
+        is_buy: side.eq_ignore_ascii_case("buy"),
+        order_id,
+        filled_qty: added,
+        avg_price: None,
+        fee: None,
+        ts: Utc::now().timestamp_millis(),
+        raw: serde_json::Value::Null,
+    }))
+}
+
+// 되돌린 뒤 양쪽 거래소 포지션이 실제로 남지 않았는지 확인
+async fn confirm_flat(order: &Order, symbol: &str) -> Result<(), OrderError> {
+    let (binance, bitmart) = tokio::join!(order.fetch_position_binance(symbol), order.fetch_position_bitmart(symbol));
+    let (binance, bitmart) = (binance?, bitmart?);
+    if binance.abs() > f64::EPSILON || bitmart.abs() > f64::EPSILON {
+        return Err(OrderError::ResidualPosition(format!("Binance {}, Bitmart {}", binance, bitmart)));
+    }
+    Ok(())
+}
+
+// 진입 방향 양쪽 거래소 포지션 전량 청산 (어느 쪽이 얼마나 체결됐는지 모를 때)
+async fn unwind_entry(order: &Order, symbol: &str, binance_side: &str, bitmart_side: &str) -> Result<(), OrderError> {
+    let binance_close = closing_side(position_of_side(binance_side), "Binance");
+    let bitmart_close = closing_side(position_of_side(bitmart_side), "Bitmart");
+    let (binance_unwind, bitmart_unwind) = tokio::join!(
+        order.close_position_binance(symbol, binance_close),
+        order.close_position_bitmart(symbol, bitmart_close),
+    );
+    binance_unwind.and(bitmart_unwind).map(|_| ())
+}
+
+// 거래소에 걸려 있는 maker 진입 주문
+#[derive(Debug, Clone)]
+pub struct RestingLeg<'a> {
+    pub exchange_name: &'a str,
+    pub symbol: &'a str,
+    pub side: &'a str,
+    pub quantity: f64,
+    pub price: f64,
+    pub post_only: bool,
+    pub order_id: String,
+}
+
+// 재호가 결과
+#[derive(Debug)]
+pub enum RepriceOutcome {
+    Filled { reprices: u32 },
+    // 재호가 시점에 진입 기회가 사라짐
+    OpportunityGone { reprices: u32 },
+    // max_reprices까지 재호가해도 미체결
+    Exhausted { reprices: u32 },
+    Failed(OrderError),
+}
+
+// maker 진입 주문 재호가 (interval마다 거래소 포지션으로 체결 확인)
+// 미체결이면 잔량을 취소하고 requote가 준 가격으로 다시 주문, requote가 None이면 기회가 사라진 것으로 보고 중단
+// 포지션이 없던 상태에서 진입하므로 거래소 포지션 수량이 곧 체결 수량
+// 중단 시 걸린 주문은 취소하며, 이미 체결된 수량과 반대쪽 포지션 정리는 호출 쪽 책임
+pub async fn reprice_maker_leg<F, Fut>(
+    order: &Order,
+    leg: RestingLeg<'_>,
+    interval: Duration,
+    max_reprices: u32,
+    mut requote: F,
+) -> RepriceOutcome
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Option<f64>>,
+{
+    let RestingLeg { exchange_name, symbol, side, quantity, mut price, post_only, order_id } = leg;
+    let mut resting = Some(order_id);
+    let mut reprices = 0;
+    loop {
+        tokio::time::sleep(interval).await;
+        let filled = match maker_filled(order, exchange_name, symbol).await {
+            Ok(filled) => filled,
+            Err(e) => return RepriceOutcome::Failed(e),
+        };
+        if filled >= quantity - f64::EPSILON {
+            return RepriceOutcome::Filled { reprices };
+        }
+        // 취소 실패는 그 사이 체결됐을 수 있으므로 포지션을 다시 확인한 뒤 판단
+        let cancelled = match &resting {
+            Some(order_id) => cancel_maker_order(order, exchange_name, symbol, order_id).await,
+            None => Ok(()),
+        };
+        resting = None;
+        let filled = match maker_filled(order, exchange_name, symbol).await {
+            Ok(filled) => filled,
+            Err(e) => return RepriceOutcome::Failed(e),
+        };
+        if filled >= quantity - f64::EPSILON {
+            return RepriceOutcome::Filled { reprices };
+        }
+        if let Err(e) = cancelled {
+            return RepriceOutcome::Failed(e);
+        }
+        if reprices >= max_reprices {
+            println!("[Order] {} maker order unfilled after {} reprices.", exchange_name, reprices);
+            return RepriceOutcome::Exhausted { reprices };
+        }
+        let Some(new_price) = requote().await else {
+            println!("[Order] {} maker order not repriced: opportunity gone.", exchange_name);
+            return RepriceOutcome::OpportunityGone { reprices };
+        };
+        reprices += 1;
+        let remaining = quantity - filled;
+        println!(
+            "[Order] {} maker order repriced {} -> {} for remaining {} (reprice {}/{})",
+            exchange_name, price, new_price, remaining, reprices, max_reprices
+        );
+        price = new_price;
+        let placed = match exchange_name {
+            "Binance" => order.place_limit_order_binance(symbol, side, remaining, price, post_only).await,
+            _ => order.place_limit_order_bitmart(symbol, side, remaining, price, post_only).await,
+        };
+        match placed {
+            Ok(fill) => resting = Some(fill.order_id),
+            // post-only 거부는 걸린 주문 없이 다음 재호가까지 대기
+            Err(e) if e.is_post_only_rejection() => {
+                println!("[Order] {} repriced post-only order rejected @ {}", exchange_name, price);
+            }
+            Err(e) => return RepriceOutcome::Failed(e),
+        }
+    }
+}
+
+// maker 거래소 포지션 절대 수량 (기초자산 수량)
+async fn maker_filled(order: &Order, exchange_name: &str, symbol: &str) -> Result<f64, OrderError> {
+    let amount = match exchange_name {
+        "Binance" => order.fetch_position_binance(symbol).await?,
+        _ => order.fetch_position_bitmart(symbol).await?,
+    };
+    Ok(amount.abs())
+}
+
+async fn cancel_maker_order(order: &Order, exchange_name: &str, symbol: &str, order_id: &str) -> Result<(), OrderError> {
+    match exchange_name {
+        "Binance" => order.cancel_order_binance(symbol, order_id).await,
+        _ => order.cancel_order_bitmart(symbol, order_id).await,
+    }
+}
+
+// 청산 전 거래소별 줄일 포지션 유무 (상태상 포지션이 없는 쪽은 false)
+// 상태에는 열려 있지만 거래소에 해당 방향 포지션이 없으면 청산 주문이 반대 포지션을 새로 열 수 있음
+pub async fn reducible_legs(
+    order: &Order,
+    symbol: &str,
+    binance_position: Option<&str>,
+    bitmart_position: Option<&str>,
+) -> Result<(bool, bool), OrderError> {
+    let reducible = |signed: f64, position: &str| position_residual(position, signed) > f64::EPSILON;
+    let binance = match binance_position {
+        Some(position) => reducible(order.fetch_position_binance(symbol).await?, position),
+        None => false,
+    };
+    let bitmart = match bitmart_position {
+        Some(position) => reducible(order.fetch_position_bitmart(symbol).await?, position),
+        None => false,
+    };
+    Ok((binance, bitmart))
+}
+
+// 포지션 방향 기준 거래소 잔량 (signed: 거래소 포지션 수량, 롱 +, 숏 -, 음수면 반대 방향)
+fn position_residual(position: &str, signed: f64) -> f64 {
+    if position == "LONG" {
+        signed
+    } else {
+        -signed
+    }
+}
+
+// 청산 주문 후 거래소 잔량 확인 (잔량이 있으면 close_retry_attempts까지 재청산)
+// 거래소가 포지션 0을 확인하면 true, 조회 실패나 잔량이 남으면 false
+async fn settle_leg(ctx: &TradingContext, exchange_name: &str, position: &str) -> bool {
+    let config = &ctx.config;
+    if config.dry_run {
+        return true;
+    }
+    for attempt in 0..=config.close_retry_attempts {
+        let signed = match exchange_name {
+            "Binance" => ctx.order.fetch_position_binance(&config.symbol).await,
+            _ => ctx.order.fetch_position_bitmart(&config.symbol).await,
+        };
+        let signed = match signed {
+            Ok(amount) => amount,
+            Err(e) => {
+                eprintln!("[Trade] Failed to confirm {} position: {}", exchange_name, e);
+                return false;
+            }
+        };
+        // 포지션 방향 기준 잔량 (음수면 초과 청산)
+        let residual = position_residual(position, signed);
+        if residual < -f64::EPSILON {
+            eprintln!("[Trade] {} position reversed after close: {}", exchange_name, signed);
+            return false;
+        }
+        if residual <= f64::EPSILON {
+            return true;
+        }
+        if attempt == config.close_retry_attempts {
+            break;
+        }
+        println!(
+            "[Trade] {} close left {} {} open. Closing remainder (attempt {}/{}).",
+            exchange_name, residual, position, attempt + 1, config.close_retry_attempts
+        );
+        // 잔량은 거래소 포지션 전량 기준으로 청산 (조회 이후 추가 체결이 있어도 수량이 어긋나지 않음)
+        let side = closing_side(position, exchange_name);
+        let result = match exchange_name {
+            "Binance" => ctx.order.close_position_binance(&config.symbol, side).await.map(|_| ()),
+            _ => ctx.order.close_position_bitmart(&config.symbol, side).await.map(|_| ()),
+        };
+        if let Err(e) = result {
+            eprintln!("[Order] {} close remainder failed: {}", exchange_name, e);
+            ctx.notifier.notify(AlertEvent::Error(format!("{} close remainder order failed: {}", exchange_name, e)));
+        }
+    }
+    eprintln!("[Trade] {} position still open after {} close retries.", exchange_name, config.close_retry_attempts);
+    false
+}
+
+// 주문 실패 알림
+// 거래소가 거부한 주문은 별도 태스크에서 원인 진단 (quantity/prices: 주문 수량과 Binance, Bitmart 기준 가격)
+pub fn notify_leg_errors(
+    ctx: &TradingContext,
+    action: &str,
+    binance_leg: &LegResult,
+    bitmart_leg: &LegResult,
+    quantity: f64,
+    prices: (f64, f64),
+) {
+    for (exchange_name, leg, price) in [("Binance", binance_leg, prices.0), ("Bitmart", bitmart_leg, prices.1)] {
+        if let Some(e) = leg.error() {
+            ctx.notifier.notify(AlertEvent::Error(format!("{} {} order failed: {}", exchange_name, action, e)));
+        }
+        if let Some(code) = leg.rejection_code() {
+            let order = Arc::clone(&ctx.order);
+            let context = RejectionContext {
+                exchange: exchange_name,
+                symbol: ctx.config.symbol.clone(),
+                quantity,
+                price: Some(price),
+            };
+            tokio::spawn(async move { order.diagnose_rejection(code, &context).await.print() });
+        }
+    }
+}
+
+// 가격/상태/설정만으로 내리는 진입·청산 판단 (주문, 로그, 상태 변경 없음)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decision {
+    // 아무것도 하지 않음 (임계값 미달이거나 갭 계산에 필요한 가격 없음)
+    Hold,
+    // 포지션이 없고 갭이 진입 임계값을 넘음
+    Enter(Opportunity),
+    // 포지션 보유 중 청산 방향 갭이 청산 임계값 아래로 수렴 (gap: 청산 방향 기준 갭)
+    Exit { gap: f64 },
+    // 포지션 보유 중 진입 방향 갭이 직전 진입 갭보다 scale_in_gap_step 이상 더 벌어짐 (gap: 진입 방향 기준 갭)
+    ScaleIn { gap: f64 },
+}
+
+// 청산 주문 방향 (Binance 숏이면 Binance 매수로 청산, Binance 쪽이 이미 청산됐으면 Bitmart 방향으로 판단)
+fn closing_binance_buy(state: &TradingState) -> bool {
+    state
+        .binance_position
+        .as_deref()
+        .map(|p| p == "SHORT")
+        .unwrap_or(state.bitmart_position.as_deref() == Some("LONG"))
+}
+
+// 보유 시간을 반영한 현재 청산 임계값 (%, price: 갭 계산 기준인 Bitmart 가격)
+pub fn exit_threshold_at(config: &StrategyConfig, state: &TradingState, now: DateTime<Utc>, price: f64) -> f64 {
+    config.effective_exit_threshold(state.entry_gap, state.held_for(now).map(|held| held.num_seconds()), price)
+}
+
+// 갭 임계값 기준 판단 (포지션 보유 중에는 청산만 판단하고 재진입하지 않음)
+// quantity: 신규 진입 수량 (보유 중에는 상태의 진입 수량 기준으로 청산 갭 계산)
+pub fn decide(
+    config: &StrategyConfig,
+    calculator: &PriceCalculator,
+    quotes: &Quotes,
+    state: &TradingState,
+    quantity: f64,
+    now: DateTime<Utc>,
+) -> Decision {
+    if state.is_trading {
+        let closing_buy = closing_binance_buy(state);
+        if let Some((binance_price, bitmart_price)) = calculator.gap_prices(quotes, closing_buy, state.quantity) {
+            let gap = ((binance_price - bitmart_price) / bitmart_price) * 100.0;
+            if gap.abs() < exit_threshold_at(config, state, now, bitmart_price) {
+                return Decision::Exit { gap };
+            }
+        }
+        return scale_in_gap(config, calculator, quotes, state).map_or(Decision::Hold, |gap| Decision::ScaleIn { gap });
+    }
+    calculator.best_opportunity(quotes, quantity).map_or(Decision::Hold, Decision::Enter)
+}
+
+// 추가 진입 조건을 만족하는 진입 방향 갭 (양쪽 포지션이 모두 열려 있고 청산 시도 전일 때만)
+fn scale_in_gap(config: &StrategyConfig, calculator: &PriceCalculator, quotes: &Quotes, state: &TradingState) -> Option<f64> {
+    if state.scale_in_steps >= config.scale_in_max_steps || state.close_attempted {
+        return None;
+    }
+    let (Some(binance_position), Some(_)) = (state.binance_position.as_deref(), state.bitmart_position.as_deref()) else {
+        return None;
+    };
+    let gap = calculator.gap(quotes, binance_position == "LONG", config.scale_in_size)?;
+    let same_direction = entry_positions(gap, config.invert_direction).0 == binance_position;
+    (same_direction && gap.abs() >= state.last_entry_gap.abs() + config.scale_in_gap_step).then_some(gap)
+}
+
+// 현재 가격과 공유 호가창 기준 판단 (교차되었거나 오래된 호가창이 있으면 거래 보류)
+async fn current_decision(
+    ctx: &TradingContext,
+    binance_price: f64,
+    bitmart_price: f64,
+    state: &TradingState,
+    quantity: f64,
+) -> Decision {
+    let depth = ctx.shared_depth.lock().await;
+    for (exchange_name, book) in depth.iter() {
+        if !book.is_valid_at(ctx.config.max_book_age_ms, ctx.clock.now().timestamp_millis()) {
+            let best = |levels: &[Level]| levels.first().map(|level| level.price);
+            eprintln!(
+                "[Guard] {} order book unusable (bid {:?}, ask {:?}, crossed {}, book time {}). Trading held.",
+                exchange_name,
+                best(&book.bids),
+                best(&book.asks),
+                book.is_crossed(),
+                book.ms_t
+            );
+            return Decision::Hold;
+        }
+    }
+    let quotes = Quotes {
+        binance_last: Some(binance_price),
+        bitmart_last: Some(bitmart_price),
+        binance_book: depth.get("Binance"),
+        bitmart_book: depth.get("Bitmart"),
+    };
+    decide(&ctx.config, &ctx.price_calculator, &quotes, state, quantity, ctx.clock.now())
+}
+
+// 추가 진입 (진입 방향 양쪽 시장가, 실패하거나 한쪽만 체결되면 되돌리고 기존 포지션 유지)
+async fn scale_in(ctx: &TradingContext, state: &mut TradingState, gap: f64, prices: (f64, f64)) {
+    let config = &ctx.config;
+    let (Some(binance_position), Some(bitmart_position)) = (state.binance_position.clone(), state.bitmart_position.clone()) else {
+        return;
+    };
+    let quantity = config.scale_in_size;
+    let step = state.scale_in_steps + 1;
+    let binance_side = opening_side(&binance_position, "Binance");
+    let bitmart_side = opening_side(&bitmart_position, "Bitmart");
+    println!(
+        "[Signal] Gap widened to {:.4}% (last entry {:.4}%). Scale-in {}/{}: Binance {}, Bitmart {}, quantity {}.",
+        gap, state.last_entry_gap, step, config.scale_in_max_steps, binance_side, bitmart_side, quantity
+    );
+    if config.confirm_trades {
+        let description = format!(
+            "Scale-in {}/{}: Binance {}, Bitmart {}, quantity {}, gap {:.4}%",
+            step, config.scale_in_max_steps, binance_side, bitmart_side, quantity, gap
+        );
+        if !ctx.confirmer.confirm(&description, Duration::from_secs(config.confirm_timeout_secs)).await {
+            return;
+        }
+    }
+    let existing = (state.leg_quantity("Binance"), state.leg_quantity("Bitmart"));
+    let entry =
+        place_scale_in(&ctx.order, &config.symbol, config.dry_run, binance_side, bitmart_side, quantity, existing).await;
+    notify_leg_errors(ctx, "scale-in", &entry.binance_leg, &entry.bitmart_leg, quantity, prices);
+    if !entry.is_hedged() {
+        // 추가 수량을 확인하지 못하면 기록을 바꾸지 않고 수동 확인 요청
+        if let Some(Err(e @ OrderError::FillUnconfirmed(_))) = &entry.unwind {
+            ctx.notifier.notify(AlertEvent::Error(format!(
+                "Scale-in {}/{} left unconfirmed; check {} positions on both venues: {}",
+                step, config.scale_in_max_steps, config.symbol, e
+            )));
+            ctx.market_tap.dump_ring("unwind-failed");
+            return;
+        }
+        // 추가 체결분을 되돌리지 못하면 한쪽만 늘어난 수량을 기록해 청산 시 함께 정리
+        if let Some(Err(e)) = &entry.unwind {
+            let (exchange_name, leg) =
+                if entry.binance_leg.is_ok() { ("Binance", &entry.binance_leg) } else { ("Bitmart", &entry.bitmart_leg) };
+            state.record_entry_fill(exchange_name, leg.filled_quantity(quantity));
+            ctx.notifier.notify(AlertEvent::Error(format!(
+                "Failed to unwind unhedged {} scale-in leg; recorded legs are now Binance {}, Bitmart {}: {}",
+                exchange_name,
+                state.leg_quantity("Binance"),
+                state.leg_quantity("Bitmart"),
+                e
+            )));
+            ctx.market_tap.dump_ring("unwind-failed");
+            if let Err(e) = state.save(config.state_path()) {
+                eprintln!("[State] {}", e);
+            }
+            return;
+        }
+        eprintln!("[Trade] Scale-in {}/{} failed. Position unchanged.", step, config.scale_in_max_steps);
+        return;
+    }
+    // 기록된 진입 수수료가 있으면 추가 진입 수수료를 더함 (없으면 청산 시 전체 수량으로 추정)
+    if let Some(entry_fees) = state.entry_fees {
+        let (binance_estimate, bitmart_estimate) = ctx.price_calculator.estimated_fees(quantity, prices.0, prices.1);
+        state.entry_fees = Some(
+            entry_fees
+                + leg_fee(ctx, "scale-in", "Binance", &entry.binance_leg, binance_estimate).await
+                + leg_fee(ctx, "scale-in", "Bitmart", &entry.bitmart_leg, bitmart_estimate).await,
+        );
+    }
+    state.scale_in(gap, quantity);
+    state.record_entry_fill("Binance", entry.binance_leg.filled_quantity(quantity));
+    state.record_entry_fill("Bitmart", entry.bitmart_leg.filled_quantity(quantity));
+    println!(
+        "[Trade] Scale-in {}/{}: added {} at gap {:.4}%, position {} at average entry gap {:.4}%",
+        step, config.scale_in_max_steps, quantity, gap, state.quantity, state.entry_gap
+    );
+    ctx.trade_events.publish(TradeEvent::ScaleIn {
+        symbol: config.symbol.clone(),
+        step,
+        gap,
+        quantity,
+        average_gap: state.entry_gap,
+        total_quantity: state.quantity,
+        ts: ctx.clock.now().timestamp_millis(),
+    });
+    if let Err(e) = state.save(config.state_path()) {
+        eprintln!("[State] {}", e);
+    }
+}
+
+// 주문 집행 함수 (실제 주문 실행)
+pub async fn execute_trade(
+    ctx: &TradingContext,
+    binance: PriceTick,
+    bitmart: PriceTick,
+    imbalances: &HashMap<String, f64>,
+) {
+    let (binance_price, bitmart_price) = (binance.price, bitmart.price);
+    // 갭 로그에 표시할 가격 수신 시각
+    let received = format!(
+        "Binance @ {}, Bitmart @ {}",
+        binance.received_at.format("%H:%M:%S%.3f"),
+        bitmart.received_at.format("%H:%M:%S%.3f")
+    );
+    let order = &ctx.order;
+    let config = &ctx.config;
+    // 거래 일시 중지: 청산까지 멈췄으면 판단하지 않음 (신규 진입만 멈춘 경우 보유 포지션 청산은 계속)
+    if !ctx.trading.trading_enabled() && !ctx.trading.exits_enabled() {
+        return;
+    }
+    // 세션 주문 수 상한 초과: 진입/청산 모두 중지하고 알림 (주문은 재시작 전까지 Order에서 계속 거부)
+    if ctx.order.order_budget.tripped() {
+        trip_order_budget(ctx);
+        return;
+    }
+    // 장애로 중단된 거래소가 있으면 진입/청산 모두 보류 (한쪽만 청산해 헤지가 깨지지 않도록, 복구 확인 후 재개)
+    if !ctx.order.venue_health.down_venues().is_empty() {
+        return;
+    }
+    // 잠금 순서 (TradingContext 참고): 가격은 복사본으로 받았으므로 shared_prices를 잡지 않은 상태
+    // 이후 shared_state를 잡은 채 공유 맵만 짧게 잠금
+    let mut state = ctx.shared_state.lock().await;
+
+    // 포지션 보유 중: 갭이 수렴하면 청산, 더 벌어지면 추가 진입
+    if state.is_trading {
+        let closing_binance_buy = closing_binance_buy(&state);
+        let decision = current_decision(ctx, binance_price, bitmart_price, &state, state.quantity).await;
+        if let Decision::ScaleIn { gap } = decision {
+            // 신규 진입과 같은 제한 적용
+            if ctx.trading.trading_enabled()
+                && ctx.symbol_flags.is_enabled(&config.symbol)
+                && state.market_guard.allows_entry(&config.blackout_windows, ctx.clock.now())
+            {
+                scale_in(ctx, &mut state, gap, (binance_price, bitmart_price)).await;
+            }
+            return;
+        }
+        if let Decision::Exit { gap: percent_diff } = decision {
+            // 최소 보유 시간 전 청산 신호는 무시 (이미 보낸 청산의 잔량 정리는 계속)
+            let now = ctx.clock.now();
+            if !state.close_attempted && state.in_min_hold(config.min_time_in_position_secs, now) {
+                let held = state.held_for(now).unwrap_or_default().num_seconds();
+                println!(
+                    "[Guard] Exit signal suppressed: position held {}s of minimum {}s.",
+                    held, config.min_time_in_position_secs
+                );
+                return;
+            }
+            println!(
+                "Gap converged below {:.4}% (base {:.4}%). Closing positions.\nBinance: {:.4}, Bitmart: {:.4}, Gap: {:.4}% ({})",
+                exit_threshold_at(config, &state, now, bitmart_price), config.exit_threshold_pct(bitmart_price), binance_price, bitmart_price, percent_diff, received
+            );
+            // 두 거래소 청산 주문 동시 실행
+            let mut binance_side = state.binance_position.as_deref().map(|p| closing_side(p, "Binance"));
+            let mut bitmart_side = state.bitmart_position.as_deref().map(|p| closing_side(p, "Bitmart"));
+            let quantity = state.quantity;
+            // 청산은 진입 때 실제 체결된 거래소별 수량 그대로 (진입 수량을 다시 계산하지 않음)
+            let close_quantities = (state.leg_quantity("Binance"), state.leg_quantity("Bitmart"));
+            if config.confirm_trades {
+                let description = format!(
+                    "Close: Binance {:?} {}, Bitmart {:?} {}, gap {:.4}%",
+                    binance_side, close_quantities.0, bitmart_side, close_quantities.1, percent_diff
+                );
+                if !ctx.confirmer.confirm(&description, Duration::from_secs(config.confirm_timeout_secs)).await {
+                    return;
+                }
+            }
+            let exit_legs = if state.close_attempted {
+                // 이전 청산이 미완료: 거래소 잔량 확인 단계에서 남은 수량만 청산
+                None
+            } else {
+                // 거래소에 줄일 포지션이 없는 쪽은 청산 주문을 보내지 않음 (조회 실패 시 다음 틱에 재시도)
+                if config.verify_position_before_close && !config.dry_run {
+                    let (binance_position, bitmart_position) = (state.binance_position.as_deref(), state.bitmart_position.as_deref());
+                    let (binance_open, bitmart_open) =
+                        match reducible_legs(order, &config.symbol, binance_position, bitmart_position).await {
+                            Ok(open) => open,
+                            Err(e) => {
+                                eprintln!("[Guard] Close held: failed to confirm exchange positions: {}", e);
+                                return;
+                            }
+                        };
+                    for (exchange_name, position, open, side) in [
+                        ("Binance", binance_position, binance_open, &mut binance_side),
+                        ("Bitmart", bitmart_position, bitmart_open, &mut bitmart_side),
+                    ] {
+                        if let (Some(position), false) = (position, open) {
+                            eprintln!(
+                                "[Guard] {} shows no {} position on the exchange but state has one open. Skipping its close order.",
+                                exchange_name, position
+                            );
+                            *side = None;
+                        }
+                    }
+                }
+                state.exit_book = book_snapshot(ctx).await;
+                let (binance_leg, bitmart_leg) =
+                    place_legs(
+                        order,
+                        &config.symbol,
+                        config.dry_run,
+                        binance_side.map(OrderLeg::close),
+                        bitmart_side.map(OrderLeg::close),
+                        close_quantities,
+                    )
+                    .await;
+                notify_leg_errors(ctx, "close", &binance_leg, &bitmart_leg, quantity, (binance_price, bitmart_price));
+                state.close_attempted = true;
+                Some((binance_leg, bitmart_leg))
+            };
+            let (binance_ok, bitmart_ok) = exit_legs
+                .as_ref()
+                .map_or((true, true), |(binance_leg, bitmart_leg)| (binance_leg.is_ok(), bitmart_leg.is_ok()));
+
+            // 거래소가 포지션 0을 확인한 쪽만 청산 처리 (잔량은 재청산)
+            let binance_settle = async {
+                match state.binance_position.as_deref() {
+                    Some(position) if binance_ok => settle_leg(ctx, "Binance", position).await,
+                    Some(_) => false,
+                    None => true,
+                }
+            };
+            let bitmart_settle = async {
+                match state.bitmart_position.as_deref() {
+                    Some(position) if bitmart_ok => settle_leg(ctx, "Bitmart", position).await,
+                    Some(_) => false,
+                    None => true,
+                }
+            };
+            let (binance_flat, bitmart_flat) = tokio::join!(binance_settle, bitmart_settle);
+            if binance_flat {
+                state.binance_position = None;
+                cancel_protective_stop(ctx, &mut state, "Binance").await;
+            }
+            if bitmart_flat {
+                state.bitmart_position = None;
+                cancel_protective_stop(ctx, &mut state, "Bitmart").await;
+            }
+            if !(binance_flat && bitmart_flat) {
+                eprintln!("[Trade] Close incomplete. Remaining legs will be retried on the next tick.");
+                if let Err(e) = state.save(config.state_path()) {
+                    eprintln!("[State] {}", e);
+                }
+                return;
+            }
+            // 부분 청산 재시도 시 Binance 포지션이 이미 비어 있을 수 있으므로 청산 방향으로 판단
+            let binance_position = if closing_binance_buy { "SHORT" } else { "LONG" };
+            // 실현 수익: 스프레드 수익 - 진입/청산 수수료 (실제 수수료가 없으면 설정 수수료율로 추정)
+            let (binance_estimate, bitmart_estimate) =
+                ctx.price_calculator.estimated_fees(quantity, binance_price, bitmart_price);
+            let exit_fees = match &exit_legs {
+                Some((binance_leg, bitmart_leg)) => {
+                    leg_fee(ctx, "exit", "Binance", binance_leg, binance_estimate).await
+                        + leg_fee(ctx, "exit", "Bitmart", bitmart_leg, bitmart_estimate).await
+                }
+                None => {
+                    println!("[Trade] Exit fees estimated at configured rate (close order results from an earlier tick).");
+                    binance_estimate + bitmart_estimate
+                }
+            };
+            let entry_fees = state.entry_fees.unwrap_or_else(|| {
+                println!("[Trade] Entry fees estimated at configured rate (not recorded for this position).");
+                binance_estimate + bitmart_estimate
+            });
+            let captured_pnl = PriceCalculator::gross_capture(
+                binance_position,
+                state.entry_gap,
+                percent_diff,
+                quantity,
+                bitmart_price,
+            ) - entry_fees
+                - exit_fees;
+            if let Some(record) = state.close_position_at(percent_diff, captured_pnl, ctx.clock.now()) {
+                ctx.open_trades.release();
+                println!(
+                    "[Trade] Closed: entry gap {:.4}%, exit gap {:.4}%, quantity {}, captured {:.4} USDT",
+                    record.entry_gap, record.exit_gap, record.quantity, captured_pnl
+                );
+                ctx.notifier.notify(AlertEvent::Exit {
+                    entry_gap: record.entry_gap,
+                    exit_gap: record.exit_gap,
+                    quantity: record.quantity,
+                    captured_pnl,
+                });
+                ctx.trade_events.publish(TradeEvent::Exit {
+                    symbol: config.symbol.clone(),
+                    entry_gap: record.entry_gap,
+                    exit_gap: record.exit_gap,
+                    quantity: record.quantity,
+                    captured_pnl,
+                    ts: record.close_time.timestamp_millis(),
+                });
+                if captured_pnl < 0.0 && config.loss_cooldown_secs > 0 {
+                    println!(
+                        "[Guard] Losing trade ({:.4} USDT). Pausing new entries for {}s.",
+                        captured_pnl, config.loss_cooldown_secs
+                    );
+                }
+            }
+            state.trim_history(config.trade_history_limit);
+            if state.session_loss_breached(config.max_session_loss) && state.market_guard.killed().is_none() {
+                trip_session_loss_kill(ctx, &mut state).await;
+            }
+            if let Err(e) = state.save(config.state_path()) {
+                eprintln!("[State] {}", e);
+            }
+        }
+        return;
+    }
+
+    // 거래 일시 중지 또는 종목 비활성화 시 신규 진입 안 함
+    if !ctx.trading.trading_enabled() || !ctx.symbol_flags.is_enabled(&config.symbol) {
+        return;
+    }
+
+    // 점검 시간대 및 거래 중단 확인
+    if !state.market_guard.allows_entry(&config.blackout_windows, ctx.clock.now()) {
+        return;
+    }
+
+    // 손실 거래 직후 대기 시간
+    if state.in_loss_cooldown(config.loss_cooldown_secs, ctx.clock.now()) {
+        return;
+    }
+
+    // 변동성 기반 주문 수량 (notional_usd 설정 시 Bitmart 가격 기준, 최소 계약 미만이면 진입 안 함)
+    let volatility = ctx.shared_volatility.lock().await.get(&config.symbol).and_then(|v| v.std_dev());
+    let quantity = match config.effective_position_size(volatility, bitmart_price) {
+        Ok(quantity) => quantity,
+        Err(e) => {
+            eprintln!("[Signal] Position size unavailable: {}", e);
+            return;
+        }
+    };
+
+    // 설정된 갭 정의 기준 최적 진입 방향 및 갭
+    let Decision::Enter(opportunity) = current_decision(ctx, binance_price, bitmart_price, &state, quantity).await else {
+        return;
+    };
+    let (binance_position, bitmart_position) = (opportunity.binance_position, opportunity.bitmart_position);
+    let entry_gap = opportunity.gap;
+    ctx.trade_events.publish(TradeEvent::Opportunity {
+        symbol: config.symbol.clone(),
+        binance_position: binance_position.to_string(),
+        bitmart_position: bitmart_position.to_string(),
+        binance_price: opportunity.binance_price,
+        bitmart_price: opportunity.bitmart_price,
+        gap: entry_gap,
+        estimated: opportunity.estimated,
+        ts: ctx.clock.now().timestamp_millis(),
+    });
+    let skip = |reason: String| ctx.trade_events.publish(TradeEvent::skip(&config.symbol, entry_gap, reason));
+
+    if !config.allowed_directions.allows(binance_position) {
+        println!(
+            "[Signal] Gap {:.4}% skipped: direction Binance {}/Bitmart {} not allowed ({:?})",
+            entry_gap, binance_position, bitmart_position, config.allowed_directions
+        );
+        skip(format!("direction not allowed ({:?})", config.allowed_directions));
+        return;
+    }
+
+    // 직전 청산과 반대 방향 진입은 방향 전환 대기 동안 막음 (같은 방향 재진입은 더 짧은 대기)
+    if let Some(remaining) = state.direction_cooldown_remaining(
+        binance_position,
+        config.reversal_cooldown_secs,
+        config.same_direction_cooldown_secs,
+        ctx.clock.now(),
+    ) {
+        let last = state.last_close_direction.as_deref().unwrap_or_default();
+        let kind = if last == binance_position { "same-direction" } else { "reversal" };
+        println!(
+            "[Guard] Gap {:.4}% skipped: Binance {} entry in {} cooldown after Binance {} close ({}s left)",
+            entry_gap, binance_position, kind, last, remaining
+        );
+        skip(format!("{} cooldown after last close", kind));
+        return;
+    }
+
+    // 변동이 거의 없는 시장에서는 진입하지 않음
+    if let Some(min_volatility) = config.min_volatility {
+        let volatility = ctx.entry_volatility.lock().await.get(&config.symbol).and_then(|v| v.std_dev());
+        if !volatility_sufficient(volatility, min_volatility) {
+            println!(
+                "[Signal] Gap {:.4}% skipped: volatility {:?} % below minimum {} %",
+                entry_gap, volatility, min_volatility
+            );
+            skip("volatility too low".to_string());
+            return;
+        }
+    }
+
+    // 최근 체결가와 호가 체결가 기준 갭이 모두 진입을 가리켜야 함
+    if config.dual_gap_confirmation {
+        let confirmation = {
+            let depth = ctx.shared_depth.lock().await;
+            let quotes = Quotes {
+                binance_last: Some(binance_price),
+                bitmart_last: Some(bitmart_price),
+                binance_book: depth.get("Binance"),
+                bitmart_book: depth.get("Bitmart"),
+            };
+            ctx.price_calculator.gap_confirmation(&quotes, binance_position, quantity)
+        };
+        let measure = |gap: Option<f64>, agrees: bool| {
+            let gap = gap.map_or("unavailable".to_string(), |gap| format!("{:.4}%", gap));
+            format!("{} ({})", gap, if agrees { "agrees" } else { "disagrees" })
+        };
+        println!(
+            "[Signal] Gap confirmation: last trade {}, execution price {}",
+            measure(confirmation.last_trade, confirmation.last_trade_agrees),
+            measure(confirmation.execution, confirmation.execution_agrees)
+        );
+        if !confirmation.confirmed() {
+            skip("last-trade and execution-price gaps disagree".to_string());
+            return;
+        }
+    }
+
+    if config.imbalance_gate {
+        let binance_imbalance = imbalances.get("Binance").copied();
+        let bitmart_imbalance = imbalances.get("Bitmart").copied();
+        if !imbalance_agrees(binance_position, binance_imbalance, bitmart_imbalance, config.min_imbalance) {
+            println!(
+                "[Signal] Gap {:.4}% skipped: imbalance disagrees (Binance: {:?}, Bitmart: {:?})",
+                entry_gap, binance_imbalance, bitmart_imbalance
+            );
+            skip("order book imbalance disagrees".to_string());
+            return;
+        }
+    }
+
+    // 빠르게 벌어지는 갭에는 진입하지 않음
+    if config.gap_velocity_gate {
+        let velocity = ctx.shared_gap_velocity.lock().await.get(&config.symbol).and_then(|v| v.velocity());
+        if !gap_stabilizing(entry_gap, velocity, config.max_gap_velocity) {
+            println!(
+                "[Signal] Gap {:.4}% skipped: gap velocity {:?} %/s not stabilizing (max {})",
+                entry_gap, velocity, config.max_gap_velocity
+            );
+            skip("gap still widening".to_string());
+            return;
+        }
+    }
+
+    // 갭이 왕복 비용(수수료 + 현재 호가 기준 슬리피지)의 min_gap_cost_ratio배 이상이어야 진입
+    if let Some(min_ratio) = config.min_gap_cost_ratio {
+        let cost = {
+            let depth = ctx.shared_depth.lock().await;
+            let quotes = Quotes {
+                binance_last: Some(binance_price),
+                bitmart_last: Some(bitmart_price),
+                binance_book: depth.get("Binance"),
+                bitmart_book: depth.get("Bitmart"),
+            };
+            ctx.price_calculator.round_trip_cost(&quotes, binance_position == "LONG", quantity)
+        };
+        let Some(cost) = cost else {
+            println!("[Signal] Gap {:.4}% skipped: round-trip cost unavailable (insufficient depth for {})", entry_gap, quantity);
+            skip("round-trip cost unavailable".to_string());
+            return;
+        };
+        let ratio = cost.gap_ratio(entry_gap);
+        println!(
+            "[Signal] Round-trip cost {:.4}% (fees {:.4}% + slippage {:.4}%), gap/cost {:.2} (min {:.2})",
+            cost.total(), cost.fees, cost.slippage, ratio, min_ratio
+        );
+        if ratio < min_ratio {
+            skip(format!("gap/cost {:.2} below {:.2}", ratio, min_ratio));
+            return;
+        }
+    }
+
+    // 호가창 기준 기대 수익 (거래소별 체결 방식 반영)
+    match ctx.price_calculator.expected_pnl(binance_position, quantity).await {
+        Some(pnl) => println!("[Signal] Expected PnL at book execution prices: {:.4} USDT", pnl),
+        None => println!("[Signal] Expected PnL unavailable (insufficient depth)"),
+    }
+    if opportunity.estimated {
+        println!(
+            "[Signal] Binance price {:.4} is estimated from last price with a {} bp synthetic spread (no Binance depth).",
+            opportunity.binance_price,
+            config.binance_synthetic_spread_bps.unwrap_or_default()
+        );
+    }
+
+    // 전체 종목 동시 거래 한도 (진입이 무산되면 예약 반납)
+    if !ctx.open_trades.try_reserve() {
+        println!(
+            "[Guard] Gap {:.4}% skipped: {} of {} open trades in use",
+            entry_gap,
+            ctx.open_trades.count(),
+            ctx.open_trades.limit()
+        );
+        skip(format!("max open trades reached ({})", ctx.open_trades.limit()));
+        return;
+    }
+
+    println!(
+        "Gap exceeds {:.4}%. Executing trade: Binance {}, Bitmart {}, quantity {}.\nBinance: {:.4}, Bitmart: {:.4}, Gap: {:.4}% ({})",
+        config.entry_threshold_pct(opportunity.bitmart_price), binance_position, bitmart_position, quantity, opportunity.binance_price, opportunity.bitmart_price, entry_gap, received
+    );
+    // 두 거래소 진입 주문 동시 실행
+    let binance_side = opening_side(binance_position, "Binance");
+    let bitmart_side = opening_side(bitmart_position, "Bitmart");
+    if config.confirm_trades {
+        let description = format!(
+            "Entry: Binance {}, Bitmart {}, quantity {}, gap {:.4}%",
+            binance_side, bitmart_side, quantity, entry_gap
+        );
+        if !ctx.confirmer.confirm(&description, Duration::from_secs(config.confirm_timeout_secs)).await {
+            ctx.open_trades.release();
+            skip("entry not confirmed".to_string());
+            return;
+        }
+    }
+    let entry_book = book_snapshot(ctx).await;
+    let (binance_leg, bitmart_leg) = entry_legs(ctx, binance_position, binance_side, bitmart_side).await;
+    println!("[Order] Entry roles: Binance {}, Bitmart {}", binance_leg.role(), bitmart_leg.role());
+    // 주문 전송 전에 진행 중 진입을 저장 (기록 전에 중단되면 재시작 시 clientOrderId 접두어로 주문 내역과 대조)
+    // 진입이 무산되어도 기록은 남겨 두고 다음 진입이 덮어씀 (재시작 시 대조하면 남은 수량이 없어 지워짐)
+    let client_order_scope = if config.dry_run {
+        None
+    } else {
+        let trade_id = ClientOrderIds::trade_id_at(ctx.clock.now().timestamp_millis());
+        state.inflight_trade = Some(InflightTrade {
+            trade_id: trade_id.clone(),
+            binance_position: binance_position.to_string(),
+            bitmart_position: bitmart_position.to_string(),
+            entry_gap,
+            quantity,
+            sent_at: ctx.clock.now(),
+        });
+        if let Err(e) = state.save(config.state_path()) {
+            eprintln!("[State] {}", e);
+        }
+        Some(order.client_order_ids.begin(&trade_id))
+    };
+    let entry = place_entry(
+        order,
+        &config.symbol,
+        config.dry_run,
+        binance_leg,
+        bitmart_leg,
+        quantity,
+        config.entry_deadline_ms,
+    )
+    .await;
+    let (binance_ok, bitmart_ok) = (entry.binance_leg.is_ok(), entry.bitmart_leg.is_ok());
+    notify_leg_errors(
+        ctx,
+        "entry",
+        &entry.binance_leg,
+        &entry.bitmart_leg,
+        quantity,
+        (opportunity.binance_price, opportunity.bitmart_price),
+    );
+
+    // 거래 중단 거부 코드 감지
+    let halt_code = [entry.binance_leg.rejection_code(), entry.bitmart_leg.rejection_code()]
+        .into_iter()
+        .flatten()
+        .find(|code| config.halt_error_codes.contains(code));
+    if let Some(code) = halt_code {
+        state.market_guard.halt(&config.symbol, code, ctx.clock.now(), config.halt_pause_secs);
+        ctx.notifier.notify(AlertEvent::CircuitBreaker(format!(
+            "{} halted (rejection code {}), entries paused for {}s",
+            config.symbol, code, config.halt_pause_secs
+        )));
+        ctx.market_tap.dump_ring("halt");
+    }
+
+    if entry.timed_out() {
+        // 되돌리기 실패 시 어느 쪽에 포지션이 남았는지 모르므로 수동 확인 필요
+        if let Some(Err(e)) = &entry.unwind {
+            ctx.notifier.notify(AlertEvent::Error(format!(
+                "Entry deadline exceeded and unwind failed; check {} positions on both venues: {}",
+                config.symbol, e
+            )));
+            ctx.market_tap.dump_ring("unwind-failed");
+        }
+        ctx.open_trades.release();
+        skip(format!("entry not confirmed within {}ms", config.entry_deadline_ms));
+        return;
+    }
+    if !binance_ok && !bitmart_ok {
+        // 체결을 확인하지 못한 주문을 되돌리다 실패하면 포지션이 남았을 수 있음
+        if let Some(Err(e)) = &entry.unwind {
+            ctx.notifier.notify(AlertEvent::Error(format!(
+                "Failed to unwind unconfirmed entry; check {} positions on both venues: {}",
+                config.symbol, e
+            )));
+            ctx.market_tap.dump_ring("unwind-failed");
+        }
+        ctx.open_trades.release();
+        skip("both entry orders failed".to_string());
+        return;
+    }
+    // 한쪽만 체결: 되돌렸으면 포지션 없음, 되돌리기도 실패하면 남은 한쪽만 기록해 청산 대상으로 유지
+    match &entry.unwind {
+        Some(Ok(())) => {
+            ctx.notifier.notify(AlertEvent::Error(format!(
+                "{} entry leg failed; filled leg unwound, no position opened",
+                if binance_ok { "Bitmart" } else { "Binance" }
+            )));
+            ctx.open_trades.release();
+            skip("one entry leg failed; filled leg unwound".to_string());
+            return;
+        }
+        Some(Err(e)) => {
+            ctx.notifier.notify(AlertEvent::Error(format!("Failed to unwind unhedged entry leg: {}", e)));
+            ctx.market_tap.dump_ring("unwind-failed");
+        }
+        None => {}
+    }
+    // 걸어 둔 maker 진입 주문이 미체결이면 재호가 (기회가 사라지거나 재호가 한도를 넘으면 진입을 되돌림)
+    if config.maker_reprice_ms > 0 && !config.dry_run && entry.unwind.is_none() {
+        let resting = [("Binance", binance_leg, &entry.binance_leg), ("Bitmart", bitmart_leg, &entry.bitmart_leg)]
+            .into_iter()
+            .find_map(|(exchange_name, leg, result)| match (leg.order, result.fill()) {
+                (LegOrder::Limit { price, post_only }, Some(fill)) => Some(RestingLeg {
+                    exchange_name,
+                    symbol: &config.symbol,
+                    side: leg.side,
+                    quantity,
+                    price,
+                    post_only,
+                    order_id: fill.order_id.clone(),
+                }),
+                _ => None,
+            });
+        if let Some(resting) = resting {
+            let maker = resting.exchange_name;
+            let is_buy = resting.side.eq_ignore_ascii_case("BUY");
+            let state_ref = &*state;
+            let requote = || async move {
+                match current_decision(ctx, binance_price, bitmart_price, state_ref, quantity).await {
+                    Decision::Enter(current) if current.binance_position == binance_position => {
+                        ctx.shared_depth.lock().await.get(maker).and_then(|book| maker_price(book, is_buy))
+                    }
+                    _ => None,
+                }
+            };
+            let interval = Duration::from_millis(config.maker_reprice_ms);
+            let reason = match reprice_maker_leg(order, resting, interval, config.max_reprices, requote).await {
+                RepriceOutcome::Filled { reprices } => {
+                    if reprices > 0 {
+                        println!("[Order] {} maker order filled after {} reprices.", maker, reprices);
+                    }
+                    None
+                }
+                RepriceOutcome::OpportunityGone { reprices } => {
+                    Some(format!("opportunity gone while repricing maker leg (after {} reprices)", reprices))
+                }
+                RepriceOutcome::Exhausted { reprices } => Some(format!("maker leg unfilled after {} reprices", reprices)),
+                RepriceOutcome::Failed(e) => Some(format!("maker leg repricing failed: {}", e)),
+            };
+            if let Some(reason) = reason {
+                eprintln!("[Order] Entry abandoned: {}. Unwinding both legs.", reason);
+                match unwind_entry(order, &config.symbol, binance_side, bitmart_side).await {
+                    Ok(()) => println!("[Order] Entry unwound. Staying flat."),
+                    Err(e) => {
+                        eprintln!("[Order] Failed to unwind abandoned entry: {}", e);
+                        ctx.notifier.notify(AlertEvent::Error(format!(
+                            "Maker leg abandoned and unwind failed; check {} positions on both venues: {}",
+                            config.symbol, e
+                        )));
+                        ctx.market_tap.dump_ring("unwind-failed");
+                    }
+                }
+                ctx.open_trades.release();
+                skip(reason);
+                return;
+            }
+        }
+    }
+    drop(client_order_scope);
+    state.inflight_trade = None;
+    state.open_position_at(binance_position, bitmart_position, entry_gap, quantity, ctx.clock.now());
+    state.record_entry_fill("Binance", entry.binance_leg.filled_quantity(quantity));
+    state.record_entry_fill("Bitmart", entry.bitmart_leg.filled_quantity(quantity));
+    state.entry_book = entry_book;
+    let (binance_estimate, bitmart_estimate) =
+        ctx.price_calculator.estimated_fees(quantity, opportunity.binance_price, opportunity.bitmart_price);
+    state.entry_fees = Some(
+        leg_fee(ctx, "entry", "Binance", &entry.binance_leg, binance_estimate).await
+            + leg_fee(ctx, "entry", "Bitmart", &entry.bitmart_leg, bitmart_estimate).await,
+    );
+    if !binance_ok {
+        state.binance_position = None;
+    }
+    if !bitmart_ok {
+        state.bitmart_position = None;
+    }
+    if let (Some(stop_pct), false) = (config.protective_stop_pct, config.dry_run) {
+        place_protective_stops(ctx, &mut state, &entry, (opportunity.binance_price, opportunity.bitmart_price), stop_pct).await;
+    }
+    ctx.notifier.notify(AlertEvent::Entry {
+        binance_position: binance_position.to_string(),
+        bitmart_position: bitmart_position.to_string(),
+        gap: entry_gap,
+        quantity,
+    });
+    ctx.trade_events.publish(TradeEvent::Entry {
+        symbol: config.symbol.clone(),
+        binance_position: binance_position.to_string(),
+        bitmart_position: bitmart_position.to_string(),
+        gap: entry_gap,
+        quantity,
+        ts: ctx.clock.now().timestamp_millis(),
+    });
+
+    if let Err(e) = state.save(config.state_path()) {
+        eprintln!("[State] {}", e);
+    }
+}
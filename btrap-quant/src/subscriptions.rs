@@ -0,0 +1,46 @@
+use futures_util::{Sink, SinkExt};
+use serde_json::Value;
+use tokio_tungstenite::tungstenite::protocol::Message;
+
+// Bitmart 구독 메시지
+pub fn bitmart_subscribe(channels: &[String]) -> Value {
+    serde_json::json!({
+        "action": "subscribe",
+        "args": channels
+    })
+}
+
+// 연결별 구독 목록 (구독 메시지를 등록 순서대로 기록하고 접속/재접속마다 전부 다시 보냄)
+// 접속이 끊기면 서버 쪽 구독도 사라지므로 등록된 구독을 그대로 재현해야 빠지는 채널이 없음
+#[derive(Debug, Clone, Default)]
+pub struct Subscriptions {
+    messages: Vec<Value>,
+}
+
+impl Subscriptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 구독 등록 (같은 메시지가 이미 있으면 무시)
+    pub fn register(&mut self, message: Value) {
+        if !self.messages.contains(&message) {
+            self.messages.push(message);
+        }
+    }
+
+    pub fn messages(&self) -> &[Value] {
+        &self.messages
+    }
+
+    // 등록된 구독 메시지를 순서대로 전송 (하나라도 실패하면 중단)
+    pub async fn send_all<S>(&self, write: &mut S) -> Result<(), S::Error>
+    where
+        S: Sink<Message> + Unpin,
+    {
+        for message in &self.messages {
+            write.send(Message::Text(message.to_string())).await?;
+        }
+        Ok(())
+    }
+}
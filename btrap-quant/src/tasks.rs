@@ -0,0 +1,61 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+
+// 장기 실행 태스크 동시 실행 제한 및 실행 중인 태스크 수 (상태 출력용)
+// 제한을 넘으면 먼저 시작한 태스크가 끝날 때까지 대기 후 시작
+pub struct TaskTracker {
+    limit: usize,
+    semaphore: Arc<Semaphore>,
+    active: Arc<AtomicUsize>,
+}
+
+// 태스크 종료 시 실행 중 개수 감소 (패닉 포함)
+struct ActiveGuard(Arc<AtomicUsize>);
+
+impl Drop for ActiveGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl TaskTracker {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            semaphore: Arc::new(Semaphore::new(limit)),
+            active: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    // 현재 실행 중인 태스크 수 (대기 중인 태스크 제외)
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    // 자리가 나면 실행되는 태스크 생성 (호출은 바로 반환)
+    pub fn spawn<F>(&self, name: &'static str, future: F) -> JoinHandle<()>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let semaphore = Arc::clone(&self.semaphore);
+        let active = Arc::clone(&self.active);
+        tokio::spawn(async move {
+            if semaphore.available_permits() == 0 {
+                println!("[Tasks] {} waiting for a free task slot", name);
+            }
+            let Ok(_permit) = semaphore.acquire_owned().await else {
+                return;
+            };
+            active.fetch_add(1, Ordering::SeqCst);
+            let _guard = ActiveGuard(active);
+            future.await;
+        })
+    }
+}
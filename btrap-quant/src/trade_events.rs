@@ -0,0 +1,98 @@
+use chrono::Utc;
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+// 외부 구독자용 전략 판단 이벤트 (JSON Lines, ts: epoch ms)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TradeEvent {
+    // 진입 조건을 만족한 갭 (이후 entry 또는 skip이 따라옴)
+    Opportunity {
+        symbol: String,
+        binance_position: String,
+        bitmart_position: String,
+        binance_price: f64,
+        bitmart_price: f64,
+        gap: f64,
+        estimated: bool,
+        ts: i64,
+    },
+    Entry { symbol: String, binance_position: String, bitmart_position: String, gap: f64, quantity: f64, ts: i64 },
+    // 보유 중 추가 진입 (average_gap, total_quantity: 추가 후 평균 진입 갭과 전체 수량)
+    ScaleIn { symbol: String, step: u32, gap: f64, quantity: f64, average_gap: f64, total_quantity: f64, ts: i64 },
+    Exit { symbol: String, entry_gap: f64, exit_gap: f64, quantity: f64, captured_pnl: f64, ts: i64 },
+    Skip { symbol: String, gap: f64, reason: String, ts: i64 },
+}
+
+impl TradeEvent {
+    pub fn skip(symbol: &str, gap: f64, reason: impl Into<String>) -> Self {
+        TradeEvent::Skip { symbol: symbol.to_string(), gap, reason: reason.into(), ts: Utc::now().timestamp_millis() }
+    }
+}
+
+// 전략 판단 이벤트 브로드캐스트 (구독자가 없거나 느려도 발행은 막히지 않음)
+// 버퍼를 넘긴 느린 구독자는 오래된 이벤트를 잃고 건너뛴 개수를 통보받음
+pub struct TradeEventBus {
+    sender: broadcast::Sender<TradeEvent>,
+}
+
+impl TradeEventBus {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    pub fn publish(&self, event: TradeEvent) {
+        // 구독자가 없으면 버림
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<TradeEvent> {
+        self.sender.subscribe()
+    }
+
+    // 현재 구독자 수 (접속한 스트림 클라이언트 포함)
+    pub fn subscribers(&self) -> usize {
+        self.sender.receiver_count()
+    }
+}
+
+// 이벤트 스트림 TCP 서버 (접속한 클라이언트마다 JSON Lines로 전송)
+pub async fn serve_trade_events(listener: TcpListener, bus: Arc<TradeEventBus>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                println!("[Events] Client connected: {}", peer);
+                let receiver = bus.subscribe();
+                tokio::spawn(async move {
+                    stream_events(stream, receiver).await;
+                    println!("[Events] Client disconnected: {}", peer);
+                });
+            }
+            Err(e) => eprintln!("[Events] Accept failed: {}", e),
+        }
+    }
+}
+
+// 클라이언트 한 명에게 이벤트 전송 (쓰기 실패 시 종료)
+async fn stream_events(mut stream: TcpStream, mut receiver: broadcast::Receiver<TradeEvent>) {
+    loop {
+        let line = match receiver.recv().await {
+            Ok(event) => serde_json::to_string(&event),
+            Err(RecvError::Lagged(skipped)) => {
+                serde_json::to_string(&serde_json::json!({ "type": "lagged", "skipped": skipped }))
+            }
+            Err(RecvError::Closed) => return,
+        };
+        let Ok(mut line) = line else {
+            continue;
+        };
+        line.push('\n');
+        if stream.write_all(line.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use crate::context::TradingContext;
+use crate::order::OrderError;
+
+// 거래소 연결 상태
+// Up: 정상, Degraded: 장애 응답이 있었지만 거래 유지, Down: 연속 장애로 거래 중단 (복구 확인 요청으로 재개)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VenueState {
+    Up,
+    Degraded,
+    Down,
+}
+
+// 거래소별 연속 장애 횟수 (Order 복제본끼리 공유)
+// 장애: 연결 실패, 시간 초과, 5xx 응답 / 거래소가 응답한 거부는 정상으로 간주
+#[derive(Debug, Clone)]
+pub struct VenueHealth {
+    down_after: u32,
+    failures: Arc<Mutex<HashMap<&'static str, u32>>>,
+}
+
+impl VenueHealth {
+    // down_after: Down으로 전환하는 연속 장애 횟수
+    pub fn new(down_after: u32) -> Self {
+        Self { down_after, failures: Arc::new(Mutex::new(HashMap::new())) }
+    }
+
+    pub fn state(&self, exchange: &str) -> VenueState {
+        let failures = self.failures.lock().unwrap().get(exchange).copied().unwrap_or(0);
+        self.state_for(failures)
+    }
+
+    fn state_for(&self, failures: u32) -> VenueState {
+        match failures {
+            0 => VenueState::Up,
+            n if n < self.down_after => VenueState::Degraded,
+            _ => VenueState::Down,
+        }
+    }
+
+    // 거래 중단 중인 거래소 (없으면 빈 목록)
+    pub fn down_venues(&self) -> Vec<&'static str> {
+        let failures = self.failures.lock().unwrap();
+        let mut down: Vec<_> = failures
+            .iter()
+            .filter(|(_, &count)| self.state_for(count) == VenueState::Down)
+            .map(|(&exchange, _)| exchange)
+            .collect();
+        down.sort();
+        down
+    }
+
+    // 요청 결과 반영 (상태가 바뀌면 로그)
+    pub fn observe<T>(&self, exchange: &'static str, result: &Result<T, OrderError>) {
+        match result {
+            Err(e) if e.is_outage() => self.record_failure(exchange, &e.to_string()),
+            _ => self.record_success(exchange),
+        }
+    }
+
+    pub fn record_failure(&self, exchange: &'static str, reason: &str) {
+        let mut failures = self.failures.lock().unwrap();
+        let count = failures.entry(exchange).or_insert(0);
+        let before = self.state_for(*count);
+        *count = count.saturating_add(1);
+        let after = self.state_for(*count);
+        if before != after {
+            eprintln!("[Venue] {} {:?} -> {:?} ({} consecutive failures: {})", exchange, before, after, count, reason);
+            if after == VenueState::Down {
+                eprintln!("[Venue] Trading paused until {} recovers.", exchange);
+            }
+        }
+    }
+
+    pub fn record_success(&self, exchange: &'static str) {
+        let mut failures = self.failures.lock().unwrap();
+        let Some(count) = failures.remove(exchange) else {
+            return;
+        };
+        let before = self.state_for(count);
+        println!("[Venue] {} {:?} -> Up", exchange, before);
+        if before == VenueState::Down {
+            println!("[Venue] {} recovered. Trading resumed.", exchange);
+        }
+    }
+}
+
+// Down 상태 거래소에 주기적으로 가벼운 요청(서버 시각)을 보내 복구 확인
+pub async fn probe_down_venues(ctx: TradingContext) {
+    let mut interval = tokio::time::interval(Duration::from_secs(ctx.config.venue_probe_interval_secs));
+    loop {
+        interval.tick().await;
+        for exchange in ctx.order.venue_health.down_venues() {
+            let result = ctx.order.probe_venue(exchange).await;
+            ctx.order.venue_health.observe(exchange, &result);
+        }
+    }
+}
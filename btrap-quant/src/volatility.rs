@@ -0,0 +1,110 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+// 종목별 실현 변동성 (키: 심볼)
+pub type SharedVolatility = Arc<Mutex<HashMap<String, RollingVolatility>>>;
+
+// 최근 N개 틱 수익률(%)의 이동 표준편차 (window 초과분은 오래된 순으로 제거)
+#[derive(Debug, Clone)]
+pub struct RollingVolatility {
+    window: usize,
+    returns: VecDeque<f64>,
+    last_price: Option<f64>,
+}
+
+impl RollingVolatility {
+    pub fn new(window: usize) -> Self {
+        Self {
+            window,
+            returns: VecDeque::with_capacity(window),
+            last_price: None,
+        }
+    }
+
+    // 새 가격 반영
+    pub fn update(&mut self, price: f64) {
+        if let Some(last) = self.last_price {
+            if last > 0.0 && price != last {
+                if self.returns.len() == self.window {
+                    self.returns.pop_front();
+                }
+                self.returns.push_back((price - last) / last * 100.0);
+            }
+        }
+        self.last_price = Some(price);
+    }
+
+    // 표준편차 (%) - 표본이 윈도우 절반 미만이면 None
+    pub fn std_dev(&self) -> Option<f64> {
+        let n = self.returns.len();
+        if n < 2 || n * 2 < self.window {
+            return None;
+        }
+        let mean = self.returns.iter().sum::<f64>() / n as f64;
+        let variance = self.returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+        Some(variance.sqrt())
+    }
+}
+
+// 종목별 갭 변화 속도 (키: 심볼)
+pub type SharedGapVelocity = Arc<Mutex<HashMap<String, GapVelocity>>>;
+
+// 최근 window_ms 동안의 갭 변화 속도 (%p/초, 구간 내 가장 오래된 표본과 최신 표본의 기울기)
+#[derive(Debug, Clone)]
+pub struct GapVelocity {
+    window_ms: i64,
+    samples: VecDeque<(i64, f64)>, // (수신 시각 ms, 갭 %)
+}
+
+impl GapVelocity {
+    pub fn new(window_ms: u64) -> Self {
+        Self { window_ms: window_ms as i64, samples: VecDeque::new() }
+    }
+
+    // 새 갭 반영 (구간을 벗어난 표본 제거)
+    pub fn update(&mut self, ts_ms: i64, gap: f64) {
+        self.samples.push_back((ts_ms, gap));
+        while self.samples.front().is_some_and(|&(ts, _)| ts_ms - ts > self.window_ms) {
+            self.samples.pop_front();
+        }
+    }
+
+    // 갭 변화 속도 (%p/초) - 표본이 2개 미만이거나 시간 간격이 0이면 None
+    pub fn velocity(&self) -> Option<f64> {
+        let (&(first_ts, first_gap), &(last_ts, last_gap)) = (self.samples.front()?, self.samples.back()?);
+        if last_ts <= first_ts {
+            return None;
+        }
+        Some((last_gap - first_gap) / ((last_ts - first_ts) as f64 / 1000.0))
+    }
+}
+
+// 갭이 안정적이거나 수렴 중인지 (속도가 max_velocity 이하이거나 갭의 반대 방향으로 움직임)
+// 속도를 아직 모르면 판단 불가로 false
+pub fn gap_stabilizing(gap: f64, velocity: Option<f64>, max_velocity: f64) -> bool {
+    let Some(velocity) = velocity else {
+        return false;
+    };
+    velocity.abs() <= max_velocity || velocity * gap < 0.0
+}
+
+// 실현 변동성이 최소 변동성 이상인지 (변동성을 아직 모르면 판단 불가로 false)
+pub fn volatility_sufficient(volatility: Option<f64>, min_volatility: f64) -> bool {
+    volatility.is_some_and(|volatility| volatility >= min_volatility)
+}
+
+// 변동성에 반비례하는 포지션 크기 (min/max로 제한)
+// 변동성을 아직 모르면 기본 크기 사용
+pub fn adaptive_position_size(
+    base_size: f64,
+    volatility: Option<f64>,
+    target_volatility: f64,
+    min_size: f64,
+    max_size: f64,
+) -> f64 {
+    match volatility {
+        Some(vol) if vol > 0.0 => (base_size * target_volatility / vol).clamp(min_size, max_size),
+        _ => base_size,
+    }
+}
@@ -0,0 +1,105 @@
+use btrap_quant::cli::Cli;
+use btrap_quant::config::{AllowedDirections, ContractSpec, StrategyConfig};
+use btrap_quant::state::{DRY_RUN_STATE_PATH, STATE_PATH};
+use clap::Parser;
+
+#[test]
+fn cli_overrides_layer_over_config() {
+    let cli = Cli::try_parse_from(["btrap-quant", "--symbol", "xrpusdt", "--size", "5", "--dry-run"]).unwrap();
+    assert_eq!(cli.config, "config.toml");
+
+    let mut config = StrategyConfig::default();
+    cli.apply(&mut config);
+    assert_eq!(config.symbol, "XRPUSDT");
+    assert_eq!(config.position_size, 5.0);
+    assert!(config.dry_run);
+    assert_eq!(config.state_path(), DRY_RUN_STATE_PATH);
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn cli_without_flags_keeps_config_values() {
+    let cli = Cli::try_parse_from(["btrap-quant", "--config", "other.toml"]).unwrap();
+    assert_eq!(cli.config, "other.toml");
+
+    let mut config = StrategyConfig { position_size: 3.0, ..StrategyConfig::default() };
+    cli.apply(&mut config);
+    assert_eq!(config.position_size, 3.0);
+    assert!(!config.dry_run);
+    assert_eq!(config.state_path(), STATE_PATH);
+    assert!(!cli.selftest);
+    assert!(Cli::try_parse_from(["btrap-quant", "--selftest"]).unwrap().selftest);
+}
+
+#[test]
+fn symbol_without_contract_spec_is_rejected() {
+    let cli = Cli::try_parse_from(["btrap-quant", "--symbol", "DOGEUSDT"]).unwrap();
+    let mut config = StrategyConfig::default();
+    cli.apply(&mut config);
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn benchmark_flag_sets_sample_count() {
+    let cli = Cli::try_parse_from(["btrap-quant", "--benchmark", "--samples", "50"]).unwrap();
+    assert!(cli.benchmark);
+
+    let mut config = StrategyConfig::default();
+    cli.apply(&mut config);
+    assert_eq!(config.benchmark_samples, 50);
+
+    config.benchmark_samples = 0;
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn allowed_directions_filter_by_binance_position() {
+    let config: StrategyConfig = toml::from_str(r#"allowed_directions = "only_gap1""#).unwrap();
+    assert_eq!(config.allowed_directions, AllowedDirections::OnlyGap1);
+    assert!(config.allowed_directions.allows("SHORT"));
+    assert!(!config.allowed_directions.allows("LONG"));
+
+    assert!(AllowedDirections::OnlyGap2.allows("LONG"));
+    assert!(!AllowedDirections::OnlyGap2.allows("SHORT"));
+    assert!(AllowedDirections::Both.allows("LONG") && AllowedDirections::Both.allows("SHORT"));
+    assert_eq!(StrategyConfig::default().allowed_directions, AllowedDirections::Both);
+}
+
+#[test]
+fn websocket_urls_must_use_wss() {
+    let config: StrategyConfig = toml::from_str(r#"binance_ws_url = "wss://fstream.binance.us/ws""#).unwrap();
+    assert!(config.validate().is_ok());
+    assert_eq!(config.bitmart_ws_url, "wss://openapi-ws-v2.bitmart.com/api?protocol=1.1");
+
+    let config = StrategyConfig { bitmart_depth_ws_url: "ws://localhost:9000".to_string(), ..StrategyConfig::default() };
+    assert!(config.validate().unwrap_err().contains("bitmart_depth_ws_url"));
+}
+
+#[test]
+fn notional_usd_sizes_whole_contracts_at_current_price() {
+    let mut config = StrategyConfig { notional_usd: Some(100.0), ..StrategyConfig::default() };
+    assert!(config.validate().is_ok());
+    // 100 USDT / 0.6 = 166.67 XRP -> 166 계약 (명목 금액을 넘지 않도록 내림)
+    assert_eq!(config.effective_position_size(None, 0.6), Ok(166.0));
+    assert_eq!(config.effective_position_size(None, 0.5), Ok(200.0));
+
+    config.bitmart_contracts.insert("XRPUSDT".to_string(), ContractSpec { contract_size: 10.0, min_contracts: 2, price_decimals: None });
+    assert_eq!(config.effective_position_size(None, 0.6), Ok(160.0));
+    // 최소 계약 수 미만이면 주문하지 않음
+    assert!(config.effective_position_size(None, 6.0).unwrap_err().contains("below the minimum"));
+
+    // 설정하지 않으면 position_size 그대로
+    config.notional_usd = None;
+    assert_eq!(config.effective_position_size(None, 6.0), Ok(config.position_size));
+
+    config.notional_usd = Some(0.0);
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn unsupported_bitmart_depth_levels_are_rejected() {
+    let config = StrategyConfig { bitmart_depth_levels: 50, ..StrategyConfig::default() };
+    assert!(config.validate().is_ok());
+    let config = StrategyConfig { bitmart_depth_levels: 10, ..StrategyConfig::default() };
+    assert!(config.validate().is_err());
+}
@@ -0,0 +1,52 @@
+use btrap_quant::clock::{Clock, MockClock, RealClock};
+use btrap_quant::config::StrategyConfig;
+use btrap_quant::state::TradingState;
+use btrap_quant::strategy::exit_threshold_at;
+use chrono::{Duration, TimeZone, Utc};
+
+#[test]
+fn mock_clock_only_moves_when_advanced() {
+    let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    let clock = MockClock::new(start);
+    assert_eq!(clock.now(), start);
+    assert_eq!(clock.now(), start);
+    clock.advance(Duration::seconds(90));
+    assert_eq!(clock.now(), start + Duration::seconds(90));
+    clock.set(start);
+    assert_eq!(clock.now(), start);
+    assert!(RealClock.now() > start);
+}
+
+#[test]
+fn hold_time_and_loss_cooldown_follow_mock_clock() {
+    let clock = MockClock::new(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap());
+    let mut state = TradingState::default();
+    state.open_position_at("SHORT", "LONG", 0.5, 1.0, clock.now());
+
+    // 최소 보유 시간 60초
+    clock.advance(Duration::seconds(59));
+    assert!(state.in_min_hold(60, clock.now()));
+    clock.advance(Duration::seconds(1));
+    assert!(!state.in_min_hold(60, clock.now()));
+
+    // 수익 고정: 보유 시간에 따라 청산 임계값 완화
+    let config = StrategyConfig {
+        exit_decay_secs: 600,
+        exit_decay_start: 0.3,
+        exit_decay_end: 0.05,
+        exit_threshold: 0.05,
+        ..StrategyConfig::default()
+    };
+    assert!((exit_threshold_at(&config, &state, clock.now(), 1.0) - 0.225).abs() < 1e-9);
+    clock.advance(Duration::seconds(540));
+    assert!((exit_threshold_at(&config, &state, clock.now(), 1.0) - 0.45).abs() < 1e-9);
+
+    // 손실 청산 후 대기 시간
+    let record = state.close_position_at(0.6, -0.1, clock.now()).unwrap();
+    assert_eq!(record.close_time, clock.now());
+    assert_eq!(record.close_time - record.open_time, Duration::seconds(600));
+    clock.advance(Duration::seconds(29));
+    assert!(state.in_loss_cooldown(30, clock.now()));
+    clock.advance(Duration::seconds(1));
+    assert!(!state.in_loss_cooldown(30, clock.now()));
+}
@@ -0,0 +1,52 @@
+use btrap_quant::control::{TradeConfirmer, TradingSwitch};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn confirmation_proceeds_on_approval() {
+    let confirmer = Arc::new(TradeConfirmer::default());
+    let responder = Arc::clone(&confirmer);
+    tokio::spawn(async move {
+        // 요청이 등록될 때까지 재시도
+        while responder.respond(true).is_err() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    });
+    assert!(confirmer.confirm("Entry", Duration::from_secs(5)).await);
+}
+
+#[tokio::test]
+async fn confirmation_is_declined_on_no() {
+    let confirmer = Arc::new(TradeConfirmer::default());
+    let responder = Arc::clone(&confirmer);
+    tokio::spawn(async move {
+        while responder.respond(false).is_err() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    });
+    assert!(!confirmer.confirm("Entry", Duration::from_secs(5)).await);
+}
+
+#[tokio::test]
+async fn confirmation_times_out_to_no() {
+    let confirmer = TradeConfirmer::default();
+    assert!(!confirmer.confirm("Entry", Duration::from_millis(20)).await);
+    // 만료된 요청에는 응답할 수 없음
+    assert!(confirmer.respond(true).is_err());
+}
+
+#[test]
+fn pause_keeps_exits_unless_all_is_paused() {
+    let trading = TradingSwitch::default();
+    assert!(trading.trading_enabled() && trading.exits_enabled());
+
+    trading.pause(false, "test");
+    assert!(!trading.trading_enabled());
+    assert!(trading.exits_enabled());
+
+    trading.pause(true, "test");
+    assert!(!trading.trading_enabled() && !trading.exits_enabled());
+
+    trading.resume("test");
+    assert!(trading.trading_enabled() && trading.exits_enabled());
+}
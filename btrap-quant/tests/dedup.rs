@@ -0,0 +1,32 @@
+use btrap_quant::dedup::{MessageDedup, MessageKey, RecentKeys};
+
+#[test]
+fn repeated_keys_are_skipped_per_feed() {
+    let dedup = MessageDedup::new(100);
+    assert!(!dedup.is_duplicate("Binance", Some(MessageKey::Id(7))));
+    assert!(dedup.is_duplicate("Binance", Some(MessageKey::Id(7))));
+    // 다른 피드의 같은 ID는 별개
+    assert!(!dedup.is_duplicate("Bitmart", Some(MessageKey::Id(7))));
+    let trade = || Some(MessageKey::price_time(0.5012, "2024-01-01T00:00:00.000Z"));
+    assert!(!dedup.is_duplicate("Bitmart", trade()));
+    assert!(dedup.is_duplicate("Bitmart", trade()));
+    assert!(!dedup.is_duplicate("Bitmart", Some(MessageKey::price_time(0.5013, "2024-01-01T00:00:00.000Z"))));
+    // 키가 없는 메시지는 확인하지 않음
+    assert!(!dedup.is_duplicate("Bitmart", None));
+    assert_eq!(dedup.counts(), (6, 2));
+}
+
+#[test]
+fn window_forgets_oldest_keys() {
+    let mut recent = RecentKeys::new(2);
+    assert!(recent.insert(MessageKey::Id(1)));
+    assert!(recent.insert(MessageKey::Id(2)));
+    assert!(recent.insert(MessageKey::Id(3)));
+    assert!(!recent.insert(MessageKey::Id(3)));
+    assert!(recent.insert(MessageKey::Id(1)));
+
+    let disabled = MessageDedup::new(0);
+    assert!(!disabled.is_duplicate("Binance", Some(MessageKey::Id(1))));
+    assert!(!disabled.is_duplicate("Binance", Some(MessageKey::Id(1))));
+    assert_eq!(disabled.counts(), (0, 0));
+}
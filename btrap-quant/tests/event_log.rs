@@ -0,0 +1,124 @@
+use btrap_quant::depth::{DepthAllData, DepthAllItem};
+use btrap_quant::event_log::{
+    read_events, EventLogWriter, EventRecorder, MarketEvent, MarketTap, QueueOverflow, TickRing, EVENT_LOG_VERSION,
+};
+use std::io::BufReader;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn synthetic_events() -> Vec<MarketEvent> {
+    let item = |price: &str, vol: &str| DepthAllItem { price: price.to_string(), vol: vol.to_string() };
+    let depth = DepthAllData {
+        symbol: "XRPUSDT".to_string(),
+        asks: vec![item("0.5012", "1200"), item("0.5013", "800")],
+        bids: vec![item("0.5010", "950")],
+        ms_t: 1_700_000_000_100,
+    };
+    vec![
+        MarketEvent::Trade {
+            venue: "Binance".to_string(),
+            symbol: "XRPUSDT".to_string(),
+            price: 0.50115,
+            ts: 1_700_000_000_000,
+        },
+        MarketEvent::depth("Bitmart", &depth),
+    ]
+}
+
+fn record(events: &[MarketEvent]) -> Vec<u8> {
+    let mut writer = EventLogWriter::new(Vec::new()).unwrap();
+    for event in events {
+        writer.write(event).unwrap();
+    }
+    writer.into_inner()
+}
+
+#[test]
+fn recorded_events_replay_byte_identically() {
+    let events = synthetic_events();
+    let recorded = record(&events);
+
+    let replayed = read_events(recorded.as_slice()).unwrap();
+    assert_eq!(replayed, events);
+    // 재생한 이벤트를 다시 기록하면 원본 로그와 동일
+    assert_eq!(record(&replayed), recorded);
+}
+
+#[test]
+fn log_starts_with_versioned_header() {
+    let recorded = String::from_utf8(record(&[])).unwrap();
+    let header: serde_json::Value = serde_json::from_str(recorded.lines().next().unwrap()).unwrap();
+    assert_eq!(header["format"], "btrap-market-events");
+    assert_eq!(header["version"], EVENT_LOG_VERSION);
+}
+
+#[test]
+fn unknown_format_or_version_is_rejected() {
+    let wrong_version = format!("{{\"format\":\"btrap-market-events\",\"version\":{}}}\n", EVENT_LOG_VERSION + 1);
+    assert!(read_events(wrong_version.as_bytes()).is_err());
+    assert!(read_events(&b"{\"format\":\"other\",\"version\":1}\n"[..]).is_err());
+    assert!(read_events(&b""[..]).is_err());
+}
+
+fn trade(price: f64) -> MarketEvent {
+    MarketEvent::Trade { venue: "Binance".to_string(), symbol: "XRPUSDT".to_string(), price, ts: 1_700_000_000_000 }
+}
+
+fn temp_log(name: &str) -> String {
+    let path = std::env::temp_dir().join(format!("btrap-{}-{}.jsonl", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path.to_string_lossy().into_owned()
+}
+
+fn read_log(path: &str) -> Vec<MarketEvent> {
+    read_events(BufReader::new(std::fs::File::open(path).unwrap())).unwrap()
+}
+
+#[tokio::test]
+async fn recorder_writes_queued_events_on_close() {
+    let path = temp_log("recorder-close");
+    let recorder = EventRecorder::open(&path, 16, QueueOverflow::Block, Duration::from_secs(60)).unwrap();
+    for event in synthetic_events() {
+        recorder.record(event).await;
+    }
+    recorder.close().await;
+    assert_eq!(read_log(&path), synthetic_events());
+
+    // 다시 열면 헤더 없이 이어 씀
+    let recorder = EventRecorder::open(&path, 16, QueueOverflow::Block, Duration::from_secs(60)).unwrap();
+    recorder.record(trade(0.6)).await;
+    recorder.close().await;
+    assert_eq!(read_log(&path).last(), Some(&trade(0.6)));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn full_queue_drops_oldest_events_without_blocking() {
+    let path = temp_log("recorder-drop");
+    let recorder = EventRecorder::open(&path, 2, QueueOverflow::DropOldest, Duration::from_secs(60)).unwrap();
+    // 단일 스레드 런타임: 쓰기 태스크가 돌기 전에 대기열을 넘김
+    for price in [0.1, 0.2, 0.3, 0.4, 0.5] {
+        recorder.record(trade(price)).await;
+    }
+    recorder.close().await;
+    assert_eq!(read_log(&path), vec![trade(0.4), trade(0.5)]);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn tick_ring_keeps_latest_events_and_dumps_replayable_file() {
+    let dir = std::env::temp_dir().join(format!("btrap-tick-dumps-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let ring = Arc::new(TickRing::new(3, &dir.to_string_lossy()));
+    let tap = MarketTap { recorder: None, ring: Some(Arc::clone(&ring)) };
+    for price in [0.1, 0.2, 0.3, 0.4, 0.5] {
+        tap.record(trade(price)).await;
+    }
+    assert_eq!(ring.snapshot(), vec![trade(0.3), trade(0.4), trade(0.5)]);
+
+    // 덤프 파일은 이벤트 로그 형식 (재생 가능), 파일 이름에 사유 포함
+    let path = ring.dump("Kill switch").unwrap();
+    assert!(path.ends_with("-kill-switch.jsonl"), "{}", path);
+    assert_eq!(read_log(&path), vec![trade(0.3), trade(0.4), trade(0.5)]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
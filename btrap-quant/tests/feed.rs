@@ -0,0 +1,63 @@
+use btrap_quant::context::PriceTick;
+use btrap_quant::feed::{prices_aligned, EvaluationThrottle};
+use chrono::{Duration, Utc};
+
+#[test]
+fn prices_must_both_be_recent_within_window() {
+    let now = Utc::now();
+    let tick = |age_ms: i64| PriceTick { price: 1.0, received_at: now - Duration::milliseconds(age_ms) };
+
+    assert!(prices_aligned(&tick(0), &tick(80), now, 100));
+    // 한쪽 피드가 지연되면 갭을 쓰지 않음
+    assert!(!prices_aligned(&tick(0), &tick(250), now, 100));
+    assert!(!prices_aligned(&tick(150), &tick(10), now, 100));
+}
+
+#[test]
+fn zero_window_disables_alignment_check() {
+    let now = Utc::now();
+    let stale = PriceTick { price: 1.0, received_at: now - Duration::seconds(30) };
+    assert!(prices_aligned(&stale, &stale, now, 0));
+}
+
+#[tokio::test]
+async fn throttled_updates_coalesce_into_one_evaluation() {
+    let throttle = EvaluationThrottle::new(100);
+    assert!(throttle.is_throttled());
+    for _ in 0..5 {
+        throttle.request();
+    }
+    // 쌓인 요청은 한 번의 판단으로 합쳐짐
+    throttle.next().await;
+    throttle.record_evaluation();
+    let pending = tokio::time::timeout(std::time::Duration::from_millis(50), throttle.next()).await;
+    assert!(pending.is_err());
+    assert_eq!(throttle.counts(), (5, 1));
+}
+
+#[test]
+fn zero_interval_evaluates_every_update() {
+    let throttle = EvaluationThrottle::new(0);
+    assert!(!throttle.is_throttled());
+    throttle.request();
+    assert_eq!(throttle.counts(), (1, 0));
+}
+
+#[test]
+fn evaluations_run_one_at_a_time_and_rerun_once_after_overlap() {
+    let throttle = EvaluationThrottle::new(0);
+    assert!(throttle.try_begin());
+    assert!(throttle.is_running());
+    // 판단 중 들어온 틱은 기다리지 않고 재판단만 요청
+    assert!(!throttle.try_begin());
+    assert!(!throttle.try_begin());
+    // 겹친 요청은 한 번의 재판단으로 합쳐짐
+    assert!(throttle.finish());
+    assert!(throttle.is_running());
+    assert!(!throttle.finish());
+    assert!(!throttle.is_running());
+
+    // 겹친 요청이 없으면 재판단 없음
+    assert!(throttle.try_begin());
+    assert!(!throttle.finish());
+}
@@ -0,0 +1,123 @@
+use btrap_quant::config::StrategyConfig;
+use btrap_quant::dedup::MessageKey;
+use btrap_quant::depth::parse_subscribe_ack;
+use btrap_quant::handle_price::{
+    binance_combined_url, binance_message_key, bitmart_error_action, bitmart_error_code, extract_bitmart_prices,
+    extract_bitmart_trades, route_combined, BinancePayload, BinanceStreamType, CombinedEnvelope, CombinedRoute, FeedAction,
+    FieldPath,
+};
+use serde_json::json;
+use std::collections::HashMap;
+
+#[test]
+fn error_code_is_read_from_string_or_number() {
+    assert_eq!(bitmart_error_code(&json!({ "errorCode": "30001" })), Some("30001".to_string()));
+    assert_eq!(bitmart_error_code(&json!({ "error_code": 30002 })), Some("30002".to_string()));
+    assert_eq!(bitmart_error_code(&json!({ "group": "futures/trade:XRPUSDT", "data": [] })), None);
+}
+
+#[test]
+fn error_action_follows_configured_mapping() {
+    let actions = HashMap::from([
+        ("30001".to_string(), FeedAction::Reconnect),
+        ("30002".to_string(), FeedAction::Fatal),
+    ]);
+    let action = |code: &str| bitmart_error_action(&json!({ "errorCode": code }), &actions, "Bitmart");
+    assert_eq!(action("30001"), Some(FeedAction::Reconnect));
+    assert_eq!(action("30002"), Some(FeedAction::Fatal));
+    // 설정에 없는 코드는 무시
+    assert_eq!(action("99999"), Some(FeedAction::Ignore));
+    assert_eq!(bitmart_error_action(&json!({ "data": [] }), &actions, "Bitmart"), None);
+}
+
+#[test]
+fn field_path_reads_nested_string_or_number() {
+    let json = json!({ "data": [{ "deal_price": "0.5012" }, { "deal_price": 0.5013 }], "p": "1.5" });
+    assert_eq!(FieldPath::parse("p").unwrap().extract(&json), Some(1.5));
+    assert_eq!(FieldPath::parse("data.1.deal_price").unwrap().extract(&json), Some(0.5013));
+    assert_eq!(FieldPath::parse("data.2.deal_price").unwrap().extract(&json), None);
+    assert!(FieldPath::parse("data..price").is_err());
+
+    let path = FieldPath::parse("deal_price").unwrap();
+    assert_eq!(extract_bitmart_prices(&json, &path), vec![0.5012, 0.5013]);
+}
+
+#[test]
+fn price_paths_are_validated_against_sample_messages() {
+    let config: StrategyConfig = toml::from_str(
+        r#"
+        binance_stream = "book_ticker"
+        binance_price_path = "b"
+        bitmart_price_path = "deal_price"
+        "#,
+    )
+    .unwrap();
+    assert!(config.validate().is_ok());
+
+    // bookTicker에는 p 필드가 없음
+    let config = StrategyConfig { binance_price_path: Some(FieldPath::parse("p").unwrap()), ..config };
+    assert!(config.validate().is_err());
+    assert!(FieldPath::parse("price").unwrap().validate_against(BinanceStreamType::AggTrade.sample_message()).is_err());
+}
+
+#[test]
+fn subscribe_acks_are_reported_per_channel() {
+    let ok = r#"{"action":"subscribe","group":"futures/depthAll20:XRPUSDT","success":true,"request":{"action":"subscribe","args":["futures/depthAll20:XRPUSDT"]}}"#;
+    assert_eq!(parse_subscribe_ack(ok), Some(("futures/depthAll20:XRPUSDT".to_string(), Ok(()))));
+
+    let failed = r#"{"action":"subscribe","group":"futures/depthAll20:NOPEUSDT","success":false,"error":"symbol not found"}"#;
+    assert_eq!(
+        parse_subscribe_ack(failed),
+        Some(("futures/depthAll20:NOPEUSDT".to_string(), Err("symbol not found".to_string())))
+    );
+
+    // 호가 메시지는 구독 응답이 아님
+    let depth = r#"{"group":"futures/depthAll20:XRPUSDT","data":{"symbol":"XRPUSDT","asks":[],"bids":[],"ms_t":1}}"#;
+    assert_eq!(parse_subscribe_ack(depth), None);
+}
+
+#[test]
+fn combined_stream_url_replaces_single_stream_path() {
+    let streams = ["xrpusdt@aggTrade".to_string(), BinanceStreamType::depth_stream_name("XRPUSDT")];
+    assert_eq!(
+        binance_combined_url("wss://fstream.binance.com/ws/", &streams),
+        "wss://fstream.binance.com/stream?streams=xrpusdt@aggTrade/xrpusdt@depth20@100ms"
+    );
+}
+
+#[test]
+fn combined_messages_are_routed_by_stream_name() {
+    let price_stream = BinanceStreamType::AggTrade.stream_name("XRPUSDT");
+    let depth_stream = BinanceStreamType::depth_stream_name("XRPUSDT");
+    let text = format!(r#"{{"stream":"xrpusdt@aggTrade","data":{}}}"#, BinanceStreamType::AggTrade.sample_message());
+
+    let envelope = CombinedEnvelope::parse(&text).unwrap();
+    assert_eq!(route_combined(&envelope.stream, &price_stream, &depth_stream), CombinedRoute::Price);
+    let payload = BinancePayload::from_value(BinanceStreamType::AggTrade, envelope.data).unwrap();
+    assert_eq!(payload.price(), Some(0.5012));
+
+    assert_eq!(route_combined("xrpusdt@depth20@100ms", &price_stream, &depth_stream), CombinedRoute::Depth);
+    assert_eq!(route_combined("btcusdt@aggTrade", &price_stream, &depth_stream), CombinedRoute::Unknown);
+    // 단일 스트림 원본 메시지는 봉투가 아님
+    assert!(CombinedEnvelope::parse(BinanceStreamType::AggTrade.sample_message()).is_err());
+}
+
+#[test]
+fn dedup_keys_use_update_ids_or_price_and_time() {
+    let key = |stream: BinanceStreamType| BinancePayload::parse(stream, stream.sample_message()).unwrap().key();
+    assert_eq!(key(BinanceStreamType::AggTrade), Some(MessageKey::Id(1)));
+    assert_eq!(key(BinanceStreamType::BookTicker), Some(MessageKey::Id(1)));
+    assert_eq!(key(BinanceStreamType::MarkPrice), Some(MessageKey::price_time(0.5012, 1_700_000_000_000i64)));
+
+    let json: serde_json::Value = serde_json::from_str(BinanceStreamType::MarkPrice.sample_message()).unwrap();
+    assert_eq!(binance_message_key(&json, 0.5012), key(BinanceStreamType::MarkPrice));
+    assert_eq!(binance_message_key(&json!({ "p": "0.5" }), 0.5), None);
+
+    let trades = json!({ "data": [
+        { "deal_price": "0.5012", "created_at": "2024-01-01T00:00:00.000Z" },
+        { "deal_price": "0.5013" },
+    ] });
+    let extracted = extract_bitmart_trades(&trades, &FieldPath::parse("deal_price").unwrap());
+    assert_eq!(extracted[0], (0.5012, Some(MessageKey::price_time(0.5012, "2024-01-01T00:00:00.000Z"))));
+    assert_eq!(extracted[1], (0.5013, None));
+}
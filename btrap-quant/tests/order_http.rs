@@ -0,0 +1,1361 @@
+use btrap_quant::config::{BinanceOrderRespType, ContractSpec, MarginType};
+use btrap_quant::order::{
+    binance_margin_settings, binance_min_quantity, bitmart_margin_settings, bitmart_side_code, closing_side, explain_rejection, format_price, format_quantity, opening_side, position_of_side, BinancePrecision, BinanceWeight, BitmartOrderBody, ClientOrderIds, ExchangeSnapshot, Fill, LatencyStats, MarginSettings, Order, OrderBudget, OrderError, RejectionContext, SymbolPrecision, BITMART_USER_AGENT,
+};
+use btrap_quant::venue::{VenueHealth, VenueState};
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use sha2::Sha256;
+use std::collections::HashMap;
+use wiremock::matchers::{header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const BINANCE_SECRET: &str = "binance-secret";
+const BITMART_SECRET: &str = "bitmart-secret";
+
+fn hmac_hex(secret: &str, payload: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(payload.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+fn test_order(server: &MockServer) -> Order {
+    Order {
+        client: Client::new(),
+        binance_api_key: "binance-key".to_string(),
+        binance_secret_key: BINANCE_SECRET.to_string(),
+        bitmart_api_key: "bitmart-key".to_string(),
+        bitmart_secret_key: BITMART_SECRET.to_string(),
+        bitmart_memo: "memo".to_string(),
+        open_type: MarginType::Isolated,
+        binance_base_url: server.uri(),
+        bitmart_base_url: server.uri(),
+        bitmart_contracts: HashMap::from([("XRPUSDT".to_string(), ContractSpec { contract_size: 1.0, min_contracts: 1, price_decimals: None })]),
+        binance_weight: BinanceWeight::new(2400, 0.8),
+        venue_health: VenueHealth::new(3),
+        binance_precision: BinancePrecision::default(),
+        order_budget: OrderBudget::new(0),
+        binance_resp_type: BinanceOrderRespType::Result,
+        client_order_ids: ClientOrderIds::default(),
+    }
+}
+
+#[tokio::test]
+async fn binance_market_order_is_signed_over_query() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .and(header("X-MBX-APIKEY", "binance-key"))
+        .and(query_param("symbol", "XRPUSDT"))
+        .and(query_param("side", "SELL"))
+        .and(query_param("type", "MARKET"))
+        .and(query_param("quantity", "2"))
+        .and(query_param("newOrderRespType", "RESULT"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"symbol":"XRPUSDT","orderId":12345,"status":"FILLED","executedQty":"2","avgPrice":"0.5123","updateTime":1700000000000}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let fill = test_order(&server)
+        .place_market_order_binance("XRPUSDT", "SELL", 2.0)
+        .await
+        .unwrap();
+    assert_eq!((fill.exchange, fill.symbol.as_str(), fill.is_buy), ("Binance", "XRPUSDT", false));
+    assert_eq!(fill.order_id, "12345");
+    assert_eq!((fill.filled_qty, fill.avg_price, fill.fee), (2.0, Some(0.5123), None));
+    assert_eq!(fill.ts, 1_700_000_000_000);
+    assert_eq!(fill.raw["status"], "FILLED");
+
+    // 서명은 signature를 제외한 쿼리 전체에 대한 HMAC
+    let requests = server.received_requests().await.unwrap();
+    let query = requests[0].url.query().unwrap();
+    let (unsigned, signature) = query.rsplit_once("&signature=").unwrap();
+    assert_eq!(signature, hmac_hex(BINANCE_SECRET, unsigned));
+}
+
+#[tokio::test]
+async fn binance_margin_type_is_signed_over_form_body() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/marginType"))
+        .and(header("X-MBX-APIKEY", "binance-key"))
+        .and(header("Content-Type", "application/x-www-form-urlencoded"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":200,"msg":"success"}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    test_order(&server).set_margin_type_binance("XRPUSDT", MarginType::Cross).await.unwrap();
+
+    // 파라미터와 서명 모두 본문에만 있고, 서명은 본문의 signature 앞부분 전체에 대한 HMAC
+    let requests = server.received_requests().await.unwrap();
+    assert_eq!(requests[0].url.query(), None);
+    let body = String::from_utf8(requests[0].body.clone()).unwrap();
+    let (unsigned, signature) = body.rsplit_once("&signature=").unwrap();
+    assert!(unsigned.starts_with("symbol=XRPUSDT&marginType=CROSSED&timestamp="), "got {}", unsigned);
+    assert_eq!(signature, hmac_hex(BINANCE_SECRET, unsigned));
+}
+
+#[tokio::test]
+async fn binance_order_response_type_is_configurable() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .and(query_param("newOrderRespType", "ACK"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"symbol":"XRPUSDT","orderId":7,"status":"NEW","executedQty":"0","avgPrice":"0.00000","cumQuote":"0"}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    // ACK 응답은 체결 정보가 없어 주문 조회로 실제 체결 수량/가격 확인 (부분 체결)
+    Mock::given(method("GET"))
+        .and(path("/fapi/v1/order"))
+        .and(query_param("orderId", "7"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"symbol":"XRPUSDT","orderId":7,"status":"EXPIRED","executedQty":"2","avgPrice":"0.5100","cumQuote":"1.02"}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let order = Order { binance_resp_type: BinanceOrderRespType::Ack, ..test_order(&server) };
+    let fill = order.place_market_order_binance("XRPUSDT", "BUY", 3.0).await.unwrap();
+    assert_eq!((fill.order_id.as_str(), fill.filled_qty, fill.avg_price), ("7", 2.0, Some(0.51)));
+}
+
+#[tokio::test]
+async fn binance_ack_fill_is_not_assumed_when_unconfirmed() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"symbol":"XRPUSDT","orderId":8,"status":"NEW"}"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/fapi/v1/order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"symbol":"XRPUSDT","orderId":8,"status":"NEW","executedQty":"0","avgPrice":"0","cumQuote":"0"}"#,
+        ))
+        .mount(&server)
+        .await;
+
+    let order = Order { binance_resp_type: BinanceOrderRespType::Ack, ..test_order(&server) };
+    let error = order.place_market_order_binance("XRPUSDT", "BUY", 3.0).await.unwrap_err();
+    assert!(matches!(error, OrderError::FillUnconfirmed(_)), "got {:?}", error);
+}
+
+#[tokio::test]
+async fn binance_ack_returns_observed_partial_fill() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"symbol":"XRPUSDT","orderId":8,"status":"NEW"}"#))
+        .mount(&server)
+        .await;
+    // 조회 중 종료되지 않아도 확인된 부분 체결은 버리지 않음
+    Mock::given(method("GET"))
+        .and(path("/fapi/v1/order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"symbol":"XRPUSDT","orderId":8,"status":"PARTIALLY_FILLED","executedQty":"1","avgPrice":"0.5","cumQuote":"0.5"}"#,
+        ))
+        .mount(&server)
+        .await;
+
+    let order = Order { binance_resp_type: BinanceOrderRespType::Ack, ..test_order(&server) };
+    let fill = order.place_market_order_binance("XRPUSDT", "BUY", 3.0).await.unwrap();
+    assert_eq!((fill.filled_qty, fill.avg_price), (1.0, Some(0.5)));
+}
+
+#[tokio::test]
+async fn binance_result_without_avg_price_uses_cum_quote() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .and(query_param("newOrderRespType", "RESULT"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"symbol":"XRPUSDT","orderId":8,"status":"FILLED","executedQty":"4","avgPrice":"0","cumQuote":"2.04"}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let fill = test_order(&server).place_market_order_binance("XRPUSDT", "SELL", 4.0).await.unwrap();
+    assert_eq!(fill.filled_qty, 4.0);
+    assert!((fill.avg_price.unwrap() - 0.51).abs() < 1e-12);
+}
+
+#[tokio::test]
+async fn binance_rejection_returns_exchange_code() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .respond_with(
+            ResponseTemplate::new(400).set_body_string(r#"{"code":-2019,"msg":"Margin is insufficient."}"#),
+        )
+        .mount(&server)
+        .await;
+
+    let error = test_order(&server)
+        .place_market_order_binance("XRPUSDT", "BUY", 1.0)
+        .await
+        .unwrap_err();
+    match error {
+        OrderError::Rejected { code, message } => {
+            assert_eq!(code, -2019);
+            assert_eq!(message, "Margin is insufficient.");
+        }
+        other => panic!("expected rejection, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn binance_malformed_json_is_parse_error() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("{not json"))
+        .mount(&server)
+        .await;
+
+    let error = test_order(&server)
+        .place_market_order_binance("XRPUSDT", "BUY", 1.0)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, OrderError::Parse(_)), "got {:?}", error);
+}
+
+#[tokio::test]
+async fn bitmart_market_order_is_signed_over_body() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .and(header("X-BM-KEY", "bitmart-key"))
+        .and(header("Content-Type", "application/json"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok","data":{"order_id":1}}"#),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let fill = test_order(&server)
+        .place_market_order_bitmart("XRPUSDT", "buy", 3.0)
+        .await
+        .unwrap();
+    assert_eq!((fill.exchange, fill.symbol.as_str(), fill.is_buy), ("Bitmart", "XRPUSDT", true));
+    assert_eq!(fill.order_id, "1");
+    // 응답에 체결가가 없으면 주문 수량만 기록
+    assert_eq!((fill.filled_qty, fill.avg_price), (3.0, None));
+    assert_eq!(fill.raw["code"], 1000);
+
+    let requests = server.received_requests().await.unwrap();
+    let request = &requests[0];
+    let body = String::from_utf8(request.body.clone()).unwrap();
+    let timestamp = request.headers.get("X-BM-TIMESTAMP").unwrap().to_str().unwrap();
+    let signature = request.headers.get("X-BM-SIGN").unwrap().to_str().unwrap();
+    assert_eq!(signature, hmac_hex(BITMART_SECRET, &format!("{}{}", timestamp, body)));
+
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["symbol"], "XRPUSDT");
+    // 1: 매수 롱 진입
+    assert_eq!(json["side"], 1);
+    assert_eq!(json["type"], "market");
+    assert_eq!(json["size"], 3);
+    assert_eq!(json["open_type"], "isolated");
+    assert_eq!(request.headers.get("User-Agent").unwrap().to_str().unwrap(), BITMART_USER_AGENT);
+    assert_eq!(request.headers.get("Content-Type").unwrap().to_str().unwrap(), "application/json");
+}
+
+#[tokio::test]
+async fn bitmart_signature_detects_body_changed_after_signing() {
+    let server = MockServer::start().await;
+    let order = test_order(&server);
+    let body = BitmartOrderBody::market("XRPUSDT", 1, 3, MarginType::Isolated, 1700000000000).to_json().unwrap();
+    let signed = order.sign_bitmart_body(body.clone(), 1700000000000);
+    assert_eq!(signed.body(), body);
+    assert_eq!(signed.signature(), hmac_hex(BITMART_SECRET, &format!("1700000000000{}", body)));
+    assert!(signed.verifies(BITMART_SECRET, &body));
+
+    // 재직렬화로 키 순서가 바뀌거나 공백이 추가되면 서명 불일치
+    let reserialized = serde_json::from_str::<serde_json::Value>(&body).unwrap().to_string();
+    assert_ne!(reserialized, body);
+    assert!(!signed.verifies(BITMART_SECRET, &reserialized));
+    let pretty = body.replace(",", ", ");
+    assert!(!signed.verifies(BITMART_SECRET, &pretty));
+}
+
+#[tokio::test]
+async fn binance_post_only_limit_order_uses_gtx() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .and(query_param("side", "BUY"))
+        .and(query_param("type", "LIMIT"))
+        .and(query_param("timeInForce", "GTX"))
+        .and(query_param("quantity", "2"))
+        .and(query_param("price", "0.5"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"symbol":"XRPUSDT","orderId":777,"status":"NEW","executedQty":"0","avgPrice":"0.00","updateTime":1700000000000}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let fill = test_order(&server)
+        .place_limit_order_binance("XRPUSDT", "BUY", 2.0, 0.5, true)
+        .await
+        .unwrap();
+    // 대기 중인 주문은 체결 수량 0
+    assert_eq!(fill.order_id, "777");
+    assert_eq!((fill.filled_qty, fill.avg_price), (0.0, None));
+}
+
+#[tokio::test]
+async fn binance_post_only_rejection_is_classified() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .and(query_param("timeInForce", "GTC"))
+        .respond_with(ResponseTemplate::new(400).set_body_string(
+            r#"{"code":-5022,"msg":"Due to the order could not be executed as maker, the Post Only order will be rejected."}"#,
+        ))
+        .mount(&server)
+        .await;
+
+    let error = test_order(&server)
+        .place_limit_order_binance("XRPUSDT", "SELL", 1.0, 0.5, false)
+        .await
+        .unwrap_err();
+    assert!(error.is_post_only_rejection(), "got {:?}", error);
+    let other = OrderError::Rejected { code: -2019, message: "Margin is insufficient.".to_string() };
+    assert!(!other.is_post_only_rejection());
+}
+
+#[tokio::test]
+async fn bitmart_post_only_limit_order_uses_maker_only_mode() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok","data":{"order_id":9}}"#),
+        )
+        .mount(&server)
+        .await;
+
+    let order = test_order(&server);
+    let fill = order.place_limit_order_bitmart("XRPUSDT", "sell", 3.0, 0.51, true).await.unwrap();
+    assert_eq!((fill.order_id.as_str(), fill.filled_qty), ("9", 0.0));
+    order.place_limit_order_bitmart("XRPUSDT", "sell", 3.0, 0.51, false).await.unwrap();
+
+    let requests = server.received_requests().await.unwrap();
+    let body = |i: usize| serde_json::from_slice::<serde_json::Value>(&requests[i].body).unwrap();
+    let post_only = body(0);
+    assert_eq!(post_only["type"], "limit");
+    assert_eq!(post_only["price"], "0.51");
+    assert_eq!(post_only["size"], 3);
+    assert_eq!(post_only["mode"], 4);
+    assert_eq!(body(1)["mode"], 1);
+}
+
+#[test]
+fn bitmart_order_body_serializes_to_expected_json() {
+    let market = BitmartOrderBody::market("XRPUSDT", 1, 3, MarginType::Isolated, 1700000000000);
+    assert_eq!(
+        market.to_json().unwrap(),
+        r#"{"symbol":"XRPUSDT","side":1,"type":"market","size":3,"open_type":"isolated","timestamp":1700000000000}"#
+    );
+    let limit = BitmartOrderBody {
+        order_type: "limit",
+        price: Some("0.5100".to_string()),
+        mode: Some(4),
+        ..BitmartOrderBody::market("XRP\"USDT", 4, 2, MarginType::Cross, 1)
+    };
+    // 따옴표가 들어간 값도 이스케이프되어 유효한 JSON
+    assert_eq!(
+        limit.to_json().unwrap(),
+        r#"{"symbol":"XRP\"USDT","side":4,"type":"limit","price":"0.5100","size":2,"mode":4,"open_type":"cross","timestamp":1}"#
+    );
+}
+
+#[tokio::test]
+async fn bitmart_order_price_uses_contract_decimals() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok","data":{"order_id":9}}"#),
+        )
+        .mount(&server)
+        .await;
+
+    let mut order = test_order(&server);
+    order.bitmart_contracts.get_mut("XRPUSDT").unwrap().price_decimals = Some(4);
+    order.place_limit_order_bitmart("XRPUSDT", "buy", 3.0, 0.512345, false).await.unwrap();
+    let requests = server.received_requests().await.unwrap();
+    let body = serde_json::from_slice::<serde_json::Value>(&requests[0].body).unwrap();
+    assert_eq!(body["price"], "0.5123");
+
+    // 유한하지 않은 가격은 전송하지 않음
+    let error = order.place_limit_order_bitmart("XRPUSDT", "buy", 3.0, f64::NAN, false).await.unwrap_err();
+    assert!(matches!(error, OrderError::InvalidSize(_)), "got {:?}", error);
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn bitmart_rejection_returns_exchange_code() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string(r#"{"code":40012,"message":"Invalid size","data":{}}"#),
+        )
+        .mount(&server)
+        .await;
+
+    let error = test_order(&server)
+        .place_market_order_bitmart("XRPUSDT", "sell", 1.0)
+        .await
+        .unwrap_err();
+    assert_eq!(error.rejection_code(), Some(40012));
+}
+
+#[tokio::test]
+async fn bitmart_malformed_json_is_parse_error() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<html>Bad Gateway</html>"))
+        .mount(&server)
+        .await;
+
+    let error = test_order(&server)
+        .place_market_order_bitmart("XRPUSDT", "sell", 1.0)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, OrderError::Parse(_)), "got {:?}", error);
+}
+
+#[tokio::test]
+async fn bitmart_server_error_is_unavailable() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(ResponseTemplate::new(502).set_body_string("<html>Bad Gateway</html>"))
+        .mount(&server)
+        .await;
+
+    let order = test_order(&server);
+    let error = order.place_market_order_bitmart("XRPUSDT", "sell", 1.0).await.unwrap_err();
+    assert!(matches!(error, OrderError::Unavailable(_)), "got {:?}", error);
+    assert_eq!(order.venue_health.state("Bitmart"), VenueState::Degraded);
+}
+
+#[tokio::test]
+async fn bitmart_size_is_converted_to_whole_contracts() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok"}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut order = test_order(&server);
+    order.bitmart_contracts.insert("XRPUSDT".to_string(), ContractSpec { contract_size: 10.0, min_contracts: 1, price_decimals: None });
+    order.place_market_order_bitmart("XRPUSDT", "buy", 31.0).await.unwrap();
+
+    let requests = server.received_requests().await.unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(json["size"], 3);
+}
+
+#[tokio::test]
+async fn bitmart_size_below_one_contract_is_not_sent() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok"}"#))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let mut order = test_order(&server);
+    order.bitmart_contracts.insert("XRPUSDT".to_string(), ContractSpec { contract_size: 10.0, min_contracts: 2, price_decimals: None });
+    for quantity in [4.0, 10.0] {
+        let error = order.place_market_order_bitmart("XRPUSDT", "buy", quantity).await.unwrap_err();
+        assert!(matches!(error, OrderError::InvalidSize(_)), "got {:?}", error);
+    }
+    let error = order.place_market_order_bitmart("DOGEUSDT", "buy", 10.0).await.unwrap_err();
+    assert!(matches!(error, OrderError::InvalidSize(_)), "got {:?}", error);
+}
+
+#[tokio::test]
+async fn binance_position_is_signed_amount_for_symbol() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/fapi/v2/positionRisk"))
+        .and(query_param("symbol", "XRPUSDT"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"[{"symbol":"XRPUSDT","positionAmt":"-1.5","positionSide":"BOTH"}]"#,
+        ))
+        .mount(&server)
+        .await;
+
+    let amount = test_order(&server).fetch_position_binance("XRPUSDT").await.unwrap();
+    assert_eq!(amount, -1.5);
+}
+
+#[tokio::test]
+async fn bitmart_position_is_converted_to_base_quantity() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/contract/private/position"))
+        .and(query_param("symbol", "XRPUSDT"))
+        .and(header("X-BM-KEY", "bitmart-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"code":1000,"message":"Ok","data":[{"symbol":"XRPUSDT","current_amount":"2","position_type":2}]}"#,
+        ))
+        .mount(&server)
+        .await;
+
+    let mut order = test_order(&server);
+    order.bitmart_contracts.insert("XRPUSDT".to_string(), ContractSpec { contract_size: 10.0, min_contracts: 1, price_decimals: None });
+    assert_eq!(order.fetch_position_bitmart("XRPUSDT").await.unwrap(), -20.0);
+
+    // 서명은 쿼리 문자열 기준
+    let requests = server.received_requests().await.unwrap();
+    let timestamp = requests[0].headers.get("X-BM-TIMESTAMP").unwrap().to_str().unwrap();
+    let signature = requests[0].headers.get("X-BM-SIGN").unwrap().to_str().unwrap();
+    assert_eq!(signature, hmac_hex(BITMART_SECRET, &format!("{}symbol=XRPUSDT", timestamp)));
+}
+
+#[test]
+fn margin_settings_are_read_from_position_responses() {
+    let binance = serde_json::json!([
+        {"symbol": "BTCUSDT", "leverage": "5", "marginType": "cross"},
+        {"symbol": "XRPUSDT", "leverage": "20", "marginType": "isolated"}
+    ]);
+    let settings = binance_margin_settings(&binance, "XRPUSDT");
+    assert_eq!(settings, MarginSettings { leverage: Some(20), margin_type: Some(MarginType::Isolated) });
+    assert_eq!(settings.to_string(), "leverage 20x, margin isolated");
+    assert!(settings.mismatches(Some(20), MarginType::Isolated).is_empty());
+    assert_eq!(
+        settings.mismatches(Some(10), MarginType::Cross),
+        vec!["leverage 20x (config 10x)".to_string(), "margin isolated (config cross)".to_string()]
+    );
+    // leverage 설정이 없으면 마진 타입만 비교
+    assert!(settings.mismatches(None, MarginType::Isolated).is_empty());
+
+    let bitmart = serde_json::json!({"code": 1000, "data": [{"symbol": "XRPUSDT", "leverage": "10.00", "open_type": "cross"}]});
+    assert_eq!(
+        bitmart_margin_settings(&bitmart, "XRPUSDT"),
+        MarginSettings { leverage: Some(10), margin_type: Some(MarginType::Cross) }
+    );
+    // 포지션 항목이 없으면 확인 불가
+    let empty = bitmart_margin_settings(&serde_json::json!({"code": 1000, "data": []}), "XRPUSDT");
+    assert_eq!(empty, MarginSettings::default());
+    assert!(empty.mismatches(Some(10), MarginType::Isolated).is_empty());
+    assert_eq!(empty.to_string(), "leverage unknown, margin unknown");
+}
+
+#[tokio::test]
+async fn leverage_is_set_on_both_venues() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/leverage"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"symbol":"XRPUSDT","leverage":10}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/contract/private/submit-leverage"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok","data":{}}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let order = test_order(&server);
+    order.set_leverage_binance("XRPUSDT", 10).await.unwrap();
+    order.set_leverage_bitmart("XRPUSDT", 10, MarginType::Isolated).await.unwrap();
+
+    let requests = server.received_requests().await.unwrap();
+    let binance_body = String::from_utf8(requests[0].body.clone()).unwrap();
+    assert!(binance_body.starts_with("symbol=XRPUSDT&leverage=10&timestamp="));
+    let bitmart_body: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+    assert_eq!(bitmart_body, serde_json::json!({"symbol": "XRPUSDT", "leverage": "10", "open_type": "isolated"}));
+}
+
+#[tokio::test]
+async fn flat_positions_report_zero() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/fapi/v2/positionRisk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"[{"symbol":"XRPUSDT","positionAmt":"0.0"}]"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/contract/private/position"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok","data":[]}"#))
+        .mount(&server)
+        .await;
+
+    let order = test_order(&server);
+    assert_eq!(order.fetch_position_binance("XRPUSDT").await.unwrap(), 0.0);
+    assert_eq!(order.fetch_position_bitmart("XRPUSDT").await.unwrap(), 0.0);
+}
+
+#[tokio::test]
+async fn latency_benchmark_reports_each_venue() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/fapi/v1/time"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"serverTime":1700000000000}"#))
+        .expect(5)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/system/time"))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(5)
+        .mount(&server)
+        .await;
+
+    let results = test_order(&server).benchmark_latency(5).await;
+    assert_eq!(results.len(), 2);
+    let (venue, binance) = &results[0];
+    assert_eq!(*venue, "Binance");
+    let binance = binance.as_ref().unwrap();
+    assert_eq!((binance.samples, binance.failures), (5, 0));
+    assert!(binance.min_ms <= binance.median_ms && binance.median_ms <= binance.p99_ms);
+    // 모든 요청이 실패한 거래소는 통계 없음
+    assert_eq!(results[1], ("Bitmart", None));
+}
+
+#[test]
+fn latency_stats_use_nearest_rank_percentiles() {
+    let samples = (1..=100).rev().map(|ms| ms as f64).collect();
+    let stats = LatencyStats::from_samples("Binance", samples, 2).unwrap();
+    assert_eq!((stats.min_ms, stats.median_ms, stats.p99_ms), (1.0, 50.0, 99.0));
+    assert_eq!((stats.samples, stats.failures), (100, 2));
+
+    let single = LatencyStats::from_samples("Bitmart", vec![12.5], 0).unwrap();
+    assert_eq!((single.min_ms, single.median_ms, single.p99_ms), (12.5, 12.5, 12.5));
+    assert!(LatencyStats::from_samples("Bitmart", Vec::new(), 3).is_none());
+}
+
+#[tokio::test]
+async fn binance_close_position_uses_exchange_amount_reduce_only() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/fapi/v2/positionRisk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"[{"symbol":"XRPUSDT","positionAmt":"-1.5"}]"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .and(query_param("side", "BUY"))
+        .and(query_param("quantity", "1.5"))
+        .and(query_param("reduceOnly", "true"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"symbol":"XRPUSDT","orderId":7,"status":"FILLED"}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let order = test_order(&server);
+    let fill = order.close_position_binance("XRPUSDT", "BUY").await.unwrap().unwrap();
+    assert_eq!(fill.order_id, "7");
+    // executedQty가 없으면 주문 수량으로 기록
+    assert_eq!((fill.filled_qty, fill.is_buy), (1.5, true));
+    // 숏 포지션에 SELL 청산은 보낼 주문 없음
+    assert!(order.close_position_binance("XRPUSDT", "SELL").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn bitmart_close_position_sends_full_position_size() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/contract/private/position"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"code":1000,"message":"Ok","data":[{"symbol":"XRPUSDT","current_amount":"4","position_type":1}]}"#,
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok"}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let order = test_order(&server);
+    assert!(order.close_position_bitmart("XRPUSDT", "sell").await.unwrap().is_some());
+    assert!(order.close_position_bitmart("XRPUSDT", "buy").await.unwrap().is_none());
+
+    let requests = server.received_requests().await.unwrap();
+    let submit = requests.iter().find(|r| r.url.path() == "/futures/v1/submit-order").unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&submit.body).unwrap();
+    // 3: 매도 롱 청산 (반대 포지션을 새로 열지 않음)
+    assert_eq!((json["side"].as_u64(), json["size"].as_u64()), (Some(3), Some(4)));
+}
+
+fn rejection_context(exchange: &'static str, quantity: f64) -> RejectionContext {
+    RejectionContext { exchange, symbol: "XRPUSDT".to_string(), quantity, price: Some(0.5) }
+}
+
+#[tokio::test]
+async fn binance_rejection_diagnosis_checks_filters_and_clock() {
+    let server = MockServer::start().await;
+    let skewed = chrono::Utc::now().timestamp_millis() - 5_000;
+    Mock::given(method("GET"))
+        .and(path("/fapi/v1/time"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(format!(r#"{{"serverTime":{}}}"#, skewed)))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/fapi/v2/balance"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"[{"asset":"USDT","availableBalance":"100.0"}]"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/fapi/v2/positionRisk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"[{"symbol":"XRPUSDT","positionAmt":"0"}]"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/fapi/v1/exchangeInfo"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"symbols":[{"symbol":"XRPUSDT","status":"TRADING","filters":[
+                {"filterType":"LOT_SIZE","stepSize":"0.1","minQty":"0.1"},
+                {"filterType":"MIN_NOTIONAL","notional":"5"}]}]}"#,
+        ))
+        .mount(&server)
+        .await;
+
+    let report = test_order(&server).diagnose_rejection(-1111, &rejection_context("Binance", 1.25)).await;
+    let causes = report.causes.join("\n");
+    assert!(causes.contains("symbol filters"), "{}", causes);
+    assert!(causes.contains("not a multiple of step size 0.1"), "{}", causes);
+    assert!(causes.contains("below minimum 5"), "{}", causes);
+    assert!(causes.contains("clock"), "{}", causes);
+    assert!(report.findings.iter().any(|f| f == "available balance: 100 USDT"));
+}
+
+#[test]
+fn quantity_is_truncated_to_symbol_precision() {
+    // 자릿수 0: 100.7은 100 (올리면 의도보다 많이 주문)
+    assert_eq!(format_quantity(100.7, 0), "100");
+    assert_eq!(format_quantity(1.25, 1), "1.2");
+    // 부동소수점 오차로 한 단위가 빠지지 않음
+    assert_eq!(format_quantity(0.3, 1), "0.3");
+    assert_eq!(format_quantity(2.0, 3), "2.000");
+    assert_eq!(format_price(0.51236, 4), "0.5124");
+}
+
+#[tokio::test]
+async fn binance_orders_use_precision_from_exchange_info() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/fapi/v1/exchangeInfo"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"symbols":[{"symbol":"XRPUSDT","status":"TRADING","quantityPrecision":1,"pricePrecision":4,"filters":[]}]}"#,
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"symbol":"XRPUSDT","orderId":1,"status":"NEW","executedQty":"0","avgPrice":"0","updateTime":1700000000000}"#,
+        ))
+        .mount(&server)
+        .await;
+
+    let order = test_order(&server);
+    // 조회 전에는 값을 그대로 전송
+    assert_eq!(order.binance_precision.quantity("XRPUSDT", 12.345), "12.345");
+    let precision = order.load_precision_binance("XRPUSDT").await.unwrap();
+    assert_eq!(precision, SymbolPrecision { quantity: 1, price: 4 });
+
+    order.place_limit_order_binance("XRPUSDT", "BUY", 12.345, 0.512345, false).await.unwrap();
+    order.place_market_order_binance("XRPUSDT", "SELL", 12.38).await.unwrap();
+    let requests = server.received_requests().await.unwrap();
+    let orders: Vec<_> = requests.iter().filter(|r| r.url.path() == "/fapi/v1/order").collect();
+    let param = |i: usize, key: &str| {
+        orders[i].url.query_pairs().find(|(k, _)| k == key).map(|(_, v)| v.to_string()).unwrap()
+    };
+    assert_eq!((param(0, "quantity"), param(0, "price")), ("12.3".to_string(), "0.5123".to_string()));
+    assert_eq!(param(1, "quantity"), "12.3");
+}
+
+#[test]
+fn rejection_explanation_falls_back_when_nothing_matches() {
+    let snapshot = ExchangeSnapshot {
+        clock_skew_ms: Some(20),
+        available_balance: Some(1_000.0),
+        symbol_status: Some("TRADING".to_string()),
+        ..ExchangeSnapshot::default()
+    };
+    let causes = explain_rejection(-9999, &rejection_context("Binance", 10.0), &snapshot);
+    assert_eq!(causes.len(), 1);
+    assert!(causes[0].starts_with("no known cause"));
+
+    let causes = explain_rejection(-2019, &rejection_context("Binance", 10.0), &snapshot);
+    assert_eq!(causes, vec!["insufficient margin for the order".to_string()]);
+
+    // Bitmart 최소 계약 수 미만
+    let snapshot = ExchangeSnapshot { min_quantity: Some(10.0), step_size: Some(10.0), ..ExchangeSnapshot::default() };
+    let causes = explain_rejection(40012, &rejection_context("Bitmart", 5.0), &snapshot);
+    assert!(causes.iter().any(|c| c.contains("below minimum 10")));
+}
+
+#[tokio::test]
+async fn binance_used_weight_header_is_recorded() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/fapi/v2/positionRisk"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header(BinanceWeight::HEADER, "1990")
+                .set_body_string(r#"[{"symbol":"XRPUSDT","positionAmt":"0"}]"#),
+        )
+        .mount(&server)
+        .await;
+
+    let order = test_order(&server);
+    assert_eq!(order.binance_weight.used(), 0);
+    order.fetch_position_binance("XRPUSDT").await.unwrap();
+    assert_eq!(order.binance_weight.used(), 1990);
+    // 2400 * 0.8 = 1920 이상이면 비필수 요청을 미룸
+    assert!(order.binance_weight.should_defer());
+}
+
+#[test]
+fn binance_weight_resets_each_minute() {
+    let weight = BinanceWeight::new(2400, 0.8);
+    let minute = 1_700_000_040_000; // 분 경계
+    weight.record_at(1500, minute + 10_000);
+    // 같은 분에 늦게 도착한 낮은 값은 무시
+    weight.record_at(900, minute + 20_000);
+    assert_eq!(weight.used_at(minute + 30_000), 1500);
+    assert!(!weight.should_defer_at(minute + 30_000));
+
+    weight.record_at(2000, minute + 40_000);
+    assert!(weight.should_defer_at(minute + 50_000));
+    // 다음 분에는 사용량 초기화
+    assert_eq!(weight.used_at(minute + 60_000), 0);
+    assert!(!weight.should_defer_at(minute + 60_000));
+}
+
+#[test]
+fn order_budget_counts_the_last_hour() {
+    let budget = OrderBudget::new(2);
+    let start = 1_700_000_000_000;
+    assert!(budget.acquire_at(start).is_ok());
+    assert!(budget.acquire_at(start + 1_000).is_ok());
+    // 1시간이 지난 주문은 세지 않음
+    assert_eq!(budget.count_at(start + 3_600_000), 1);
+    assert!(budget.acquire_at(start + 3_600_000).is_ok());
+    assert!(!budget.tripped());
+
+    // 상한에 도달하면 재시작 전까지 모두 거부
+    assert!(matches!(budget.acquire_at(start + 3_600_500), Err(OrderError::OrderBudgetExceeded(2))));
+    assert!(budget.tripped());
+    assert!(budget.acquire_at(start + 10 * 3_600_000).is_err());
+
+    // 0이면 제한 없음
+    let unlimited = OrderBudget::new(0);
+    assert!((0..1000).all(|i| unlimited.acquire_at(start + i).is_ok()));
+}
+
+#[tokio::test]
+async fn tripped_order_budget_blocks_orders_before_sending() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"symbol":"XRPUSDT","orderId":1,"status":"FILLED"}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let order = Order { order_budget: OrderBudget::new(1), ..test_order(&server) };
+    order.place_market_order_binance("XRPUSDT", "BUY", 1.0).await.unwrap();
+    let error = order.place_market_order_bitmart("XRPUSDT", "sell", 1.0).await.unwrap_err();
+    assert!(matches!(error, OrderError::OrderBudgetExceeded(1)));
+    assert!(order.order_budget.tripped());
+}
+
+fn unpriced_fill(exchange: &'static str, order_id: &str) -> Fill {
+    Fill {
+        exchange,
+        symbol: "XRPUSDT".to_string(),
+        is_buy: true,
+        order_id: order_id.to_string(),
+        filled_qty: 2.0,
+        avg_price: Some(0.5),
+        fee: None,
+        ts: 0,
+        raw: serde_json::Value::Null,
+    }
+}
+
+#[tokio::test]
+async fn binance_fill_fee_sums_usdt_commissions() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/fapi/v1/userTrades"))
+        .and(query_param("orderId", "42"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"[{"orderId":42,"commission":"0.0003","commissionAsset":"USDT"},{"orderId":42,"commission":"0.0002","commissionAsset":"USDT"}]"#,
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/fapi/v1/userTrades"))
+        .and(query_param("orderId", "43"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"[{"orderId":43,"commission":"0.00001","commissionAsset":"BNB"}]"#,
+        ))
+        .mount(&server)
+        .await;
+
+    let order = test_order(&server);
+    let fee = order.fetch_fill_fee(&unpriced_fill("Binance", "42")).await.unwrap().unwrap();
+    assert!((fee - 0.0005).abs() < 1e-12);
+    // USDT 외 자산 수수료는 합산할 수 없으므로 추정치로 대체
+    assert_eq!(order.fetch_fill_fee(&unpriced_fill("Binance", "43")).await.unwrap(), None);
+
+    // 체결에 수수료가 있으면 조회하지 않음
+    let reported = Fill { fee: Some(0.001), ..unpriced_fill("Binance", "99") };
+    assert_eq!(order.fetch_fill_fee(&reported).await.unwrap(), Some(0.001));
+}
+
+#[tokio::test]
+async fn bitmart_fill_fee_matches_order_id() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/contract/private/trades"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"code":1000,"message":"Ok","data":[{"order_id":"7","paid_fees":"0.0006"},{"order_id":"8","paid_fees":"0.1"}]}"#,
+        ))
+        .mount(&server)
+        .await;
+
+    let order = test_order(&server);
+    assert_eq!(order.fetch_fill_fee(&unpriced_fill("Bitmart", "7")).await.unwrap(), Some(0.0006));
+    // 체결 내역이 아직 없으면 None
+    assert_eq!(order.fetch_fill_fee(&unpriced_fill("Bitmart", "9")).await.unwrap(), None);
+}
+
+#[test]
+fn binance_min_quantity_meets_quantity_and_notional_filters() {
+    // 최소 수량 0.1, 명목 5 USDT @ 0.5 -> 10
+    assert_eq!(binance_min_quantity(0.1, 0.1, Some(5.0), 0.5), Some(10.0));
+    // 명목 조건이 step 단위로 나누어떨어지지 않으면 올림
+    assert_eq!(binance_min_quantity(1.0, 1.0, Some(5.0), 0.6), Some(9.0));
+    assert_eq!(binance_min_quantity(0.001, 0.001, None, 60_000.0), Some(0.001));
+    assert_eq!(binance_min_quantity(0.0, 1.0, None, 1.0), None);
+}
+
+fn bitmart_position_body(amount: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_string(format!(
+        r#"{{"code":1000,"message":"Ok","data":[{{"symbol":"XRPUSDT","current_amount":"{}","position_type":1}}]}}"#,
+        amount
+    ))
+}
+
+#[tokio::test]
+async fn bitmart_self_test_opens_and_closes_minimum_size() {
+    let server = MockServer::start().await;
+    // 점검 전 0 -> 청산 전 1계약 -> 청산 후 0
+    for amount in ["0", "1"] {
+        Mock::given(method("GET"))
+            .and(path("/contract/private/position"))
+            .respond_with(bitmart_position_body(amount))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+    }
+    Mock::given(method("GET"))
+        .and(path("/contract/private/position"))
+        .respond_with(bitmart_position_body("0"))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok","data":{"order_id":1}}"#))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let result = test_order(&server).self_test_bitmart("XRPUSDT").await;
+    assert!(result.outcome.is_ok(), "{:?}", result);
+    assert_eq!(result.quantity, Some(1.0));
+    assert!(!result.leaked);
+
+    let requests = server.received_requests().await.unwrap();
+    let sides: Vec<u64> = requests
+        .iter()
+        .filter(|r| r.url.path() == "/futures/v1/submit-order")
+        .map(|r| serde_json::from_slice::<serde_json::Value>(&r.body).unwrap()["side"].as_u64().unwrap())
+        .collect();
+    // 롱 진입 후 롱 청산
+    assert_eq!(sides, [1, 3]);
+}
+
+#[tokio::test]
+async fn bitmart_self_test_reports_leak_when_close_fails() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/contract/private/position"))
+        .respond_with(bitmart_position_body("0"))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/contract/private/position"))
+        .respond_with(bitmart_position_body("1"))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok","data":{"order_id":1}}"#))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":40012,"message":"Invalid size"}"#))
+        .mount(&server)
+        .await;
+
+    let result = test_order(&server).self_test_bitmart("XRPUSDT").await;
+    assert!(result.outcome.unwrap_err().starts_with("close failed"));
+    assert!(result.leaked);
+}
+
+#[tokio::test]
+async fn self_test_refuses_when_a_position_is_already_open() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/contract/private/position"))
+        .respond_with(bitmart_position_body("3"))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let result = test_order(&server).self_test_bitmart("XRPUSDT").await;
+    assert!(result.outcome.unwrap_err().contains("existing XRPUSDT position"));
+    assert!(!result.leaked);
+}
+
+#[tokio::test]
+async fn repeated_server_errors_mark_venue_down_until_probe_succeeds() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .respond_with(ResponseTemplate::new(503).set_body_string("Service Unavailable"))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/fapi/v1/time"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"serverTime":1700000000000}"#))
+        .mount(&server)
+        .await;
+
+    let order = test_order(&server);
+    let expected = [VenueState::Degraded, VenueState::Degraded, VenueState::Down];
+    for state in expected {
+        let error = order.place_market_order_binance("XRPUSDT", "BUY", 1.0).await.unwrap_err();
+        assert!(matches!(error, OrderError::Unavailable(_)), "got {:?}", error);
+        assert_eq!(order.venue_health.state("Binance"), state);
+    }
+    assert_eq!(order.venue_health.down_venues(), vec!["Binance"]);
+    assert_eq!(order.venue_health.state("Bitmart"), VenueState::Up);
+
+    let probe = order.probe_venue("Binance").await;
+    order.venue_health.observe("Binance", &probe);
+    assert_eq!(order.venue_health.state("Binance"), VenueState::Up);
+}
+
+#[tokio::test]
+async fn exchange_rejection_is_not_an_outage() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .respond_with(
+            ResponseTemplate::new(400).set_body_string(r#"{"code":-2019,"msg":"Margin is insufficient."}"#),
+        )
+        .mount(&server)
+        .await;
+
+    let order = test_order(&server);
+    order.venue_health.record_failure("Binance", "connection refused");
+    assert_eq!(order.venue_health.state("Binance"), VenueState::Degraded);
+    // 거래소가 응답했으면 정상으로 복귀
+    order.place_market_order_binance("XRPUSDT", "BUY", 1.0).await.unwrap_err();
+    assert_eq!(order.venue_health.state("Binance"), VenueState::Up);
+}
+
+#[tokio::test]
+async fn connection_refused_is_an_outage() {
+    let health = VenueHealth::new(1);
+    let error = Client::new().get("http://127.0.0.1:1/").send().await.unwrap_err();
+    let result: Result<(), OrderError> = Err(error.into());
+    health.observe("Bitmart", &result);
+    assert_eq!(health.state("Bitmart"), VenueState::Down);
+}
+
+#[test]
+fn bitmart_side_codes_cover_open_and_close() {
+    assert_eq!(bitmart_side_code(true, false), 1);
+    assert_eq!(bitmart_side_code(true, true), 2);
+    assert_eq!(bitmart_side_code(false, true), 3);
+    assert_eq!(bitmart_side_code(false, false), 4);
+    // 포지션별 청산 방향은 청산 코드로 매핑
+    assert_eq!(bitmart_side_code(closing_side("SHORT", "Bitmart") == "buy", true), 2);
+    assert_eq!(bitmart_side_code(closing_side("LONG", "Bitmart") == "buy", true), 3);
+    assert_eq!(bitmart_side_code(opening_side("LONG", "Bitmart") == "buy", false), 1);
+    assert_eq!(bitmart_side_code(opening_side("SHORT", "Bitmart") == "buy", false), 4);
+}
+
+#[test]
+fn close_sides_reduce_each_position_on_each_exchange() {
+    // 잘못된 방향은 포지션을 줄이지 않고 반대 포지션을 새로 열게 됨
+    assert_eq!(closing_side("LONG", "Binance"), "SELL");
+    assert_eq!(closing_side("SHORT", "Binance"), "BUY");
+    assert_eq!(closing_side("LONG", "Bitmart"), "sell");
+    assert_eq!(closing_side("SHORT", "Bitmart"), "buy");
+
+    assert_eq!(opening_side("LONG", "Binance"), "BUY");
+    assert_eq!(opening_side("SHORT", "Binance"), "SELL");
+    assert_eq!(opening_side("LONG", "Bitmart"), "buy");
+    assert_eq!(opening_side("SHORT", "Bitmart"), "sell");
+
+    // 진입 방향을 되돌리는 청산은 진입과 반대 방향
+    for exchange in ["Binance", "Bitmart"] {
+        for position in ["LONG", "SHORT"] {
+            let entry = opening_side(position, exchange);
+            assert_eq!(position_of_side(entry), position);
+            assert_ne!(closing_side(position_of_side(entry), exchange), entry);
+        }
+    }
+}
+
+#[tokio::test]
+async fn binance_stop_market_order_is_reduce_only_at_stop_price() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .and(query_param("type", "STOP_MARKET"))
+        .and(query_param("side", "SELL"))
+        .and(query_param("stopPrice", "0.475"))
+        .and(query_param("quantity", "2"))
+        .and(query_param("reduceOnly", "true"))
+        .and(query_param("workingType", "MARK_PRICE"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string(r#"{"symbol":"XRPUSDT","orderId":77,"status":"NEW","executedQty":"0"}"#),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let fill = test_order(&server).place_stop_market_binance("XRPUSDT", "SELL", 2.0, 0.475, true).await.unwrap();
+    assert_eq!((fill.order_id.as_str(), fill.filled_qty), ("77", 0.0));
+}
+
+#[tokio::test]
+async fn binance_cancel_of_missing_order_succeeds() {
+    let server = MockServer::start().await;
+    Mock::given(method("DELETE"))
+        .and(path("/fapi/v1/order"))
+        .and(query_param("orderId", "77"))
+        .respond_with(ResponseTemplate::new(400).set_body_string(r#"{"code":-2011,"msg":"Unknown order sent."}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    assert!(test_order(&server).cancel_order_binance("XRPUSDT", "77").await.is_ok());
+}
+
+#[tokio::test]
+async fn bitmart_stop_market_is_plan_order_triggered_by_fair_price() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/contract/private/submit-plan-order"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok","data":{"order_id":31}}"#),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/contract/private/cancel-plan-order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok"}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let order = test_order(&server);
+    // 숏 보호: 가격 상승 시 매수
+    let fill = order.place_stop_market_bitmart("XRPUSDT", "buy", 3.0, 0.525, true).await.unwrap();
+    assert_eq!(fill.order_id, "31");
+    order.cancel_plan_order_bitmart("XRPUSDT", "31").await.unwrap();
+
+    let requests = server.received_requests().await.unwrap();
+    let body = serde_json::from_slice::<serde_json::Value>(&requests[0].body).unwrap();
+    assert_eq!(body["type"], "market");
+    assert_eq!(body["trigger_price"], "0.525");
+    assert_eq!(body["price_way"], 1);
+    assert_eq!(body["price_type"], 2);
+    assert_eq!(body["reduce_only"], true);
+    assert_eq!(body["size"], 3);
+    let cancel = serde_json::from_slice::<serde_json::Value>(&requests[1].body).unwrap();
+    assert_eq!(cancel["order_id"], "31");
+}
+
+#[tokio::test]
+async fn entry_orders_carry_trade_client_order_ids() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"symbol":"XRPUSDT","orderId":1,"status":"FILLED"}"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok","data":{"order_id":1}}"#))
+        .mount(&server)
+        .await;
+
+    let order = test_order(&server);
+    let trade_id = ClientOrderIds::trade_id_at(1_700_000_000_000);
+    assert_eq!(trade_id, "btq1700000000000");
+    let scope = order.client_order_ids.begin(&trade_id);
+    order.place_market_order_binance("XRPUSDT", "SELL", 2.0).await.unwrap();
+    order.place_market_order_bitmart("XRPUSDT", "buy", 2.0).await.unwrap();
+    drop(scope);
+    // 거래가 끝난 뒤 주문(보호 스탑 등)에는 붙이지 않음
+    order.place_market_order_binance("XRPUSDT", "BUY", 2.0).await.unwrap();
+
+    let requests = server.received_requests().await.unwrap();
+    let client_id = |i: usize| {
+        requests[i].url.query_pairs().find(|(k, _)| k == "newClientOrderId").map(|(_, v)| v.to_string())
+    };
+    assert_eq!(client_id(0).as_deref(), Some("btq1700000000000b1"));
+    let body: serde_json::Value = serde_json::from_slice(&requests[1].body).unwrap();
+    assert_eq!(body["client_order_id"], "btq1700000000000m2");
+    assert_eq!(client_id(2), None);
+}
+
+#[tokio::test]
+async fn client_order_history_keeps_filled_orders_of_the_trade() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/fapi/v1/allOrders"))
+        .and(query_param("symbol", "XRPUSDT"))
+        .and(query_param("startTime", "1700000000000"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"[{"orderId":1,"clientOrderId":"btq1700000000000b1","side":"SELL","executedQty":"2","avgPrice":"0.51"},
+                {"orderId":2,"clientOrderId":"btq1690000000000b1","side":"BUY","executedQty":"5","avgPrice":"0.50"},
+                {"orderId":3,"clientOrderId":"btq1700000000000b2","side":"BUY","executedQty":"0","avgPrice":"0"}]"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/contract/private/order-history"))
+        .and(query_param("start_time", "1700000000"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"code":1000,"message":"Ok","data":[
+                {"order_id":"11","client_order_id":"btq1700000000000m2","side":1,"deal_size":"2","deal_avg_price":"0.52"},
+                {"order_id":"12","client_order_id":"","side":4,"deal_size":"9","deal_avg_price":"0.52"}]}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let order = Order {
+        bitmart_contracts: HashMap::from([("XRPUSDT".to_string(), ContractSpec { contract_size: 10.0, min_contracts: 1, price_decimals: None })]),
+        ..test_order(&server)
+    };
+    let binance = order.fetch_client_orders_binance("XRPUSDT", "btq1700000000000", 1_700_000_000_000).await.unwrap();
+    assert_eq!(binance.len(), 1);
+    assert_eq!((binance[0].order_id.as_str(), binance[0].is_buy, binance[0].filled_qty), ("1", false, 2.0));
+    assert_eq!(binance[0].avg_price, Some(0.51));
+
+    // 체결 계약 수를 기초자산 수량으로 환산
+    let bitmart = order.fetch_client_orders_bitmart("XRPUSDT", "btq1700000000000", 1_700_000_000_000).await.unwrap();
+    assert_eq!(bitmart.len(), 1);
+    assert_eq!((bitmart[0].order_id.as_str(), bitmart[0].is_buy, bitmart[0].filled_qty), ("11", true, 20.0));
+}
+
+#[tokio::test]
+async fn blocking_flatten_reduces_only_and_counts_toward_budget() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .and(query_param("side", "BUY"))
+        .and(query_param("quantity", "2"))
+        .and(query_param("reduceOnly", "true"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"symbol":"XRPUSDT","orderId":1,"status":"FILLED"}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok","data":{"order_id":1}}"#))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    // 상한 1건: Binance 청산은 전송, Bitmart 청산은 상한에 걸려 전송하지 않음
+    let order = Order { order_budget: OrderBudget::new(1), ..test_order(&server) };
+    let results = tokio::task::spawn_blocking(move || order.flatten_blocking("XRPUSDT", Some("SHORT"), Some("LONG"), (2.0, 3.0)))
+        .await
+        .unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(results[0].starts_with("Binance BUY 2 XRPUSDT:"), "got {}", results[0]);
+    assert!(results[1].contains("failed"), "got {}", results[1]);
+}
+
+#[tokio::test]
+async fn blocking_flatten_closes_each_leg_with_its_own_size() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"symbol":"XRPUSDT","orderId":1,"status":"FILLED"}"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok","data":{"order_id":1}}"#))
+        .mount(&server)
+        .await;
+
+    // Binance 80 / Bitmart 100 부분 체결 포지션
+    let order = test_order(&server);
+    tokio::task::spawn_blocking(move || order.flatten_blocking("XRPUSDT", Some("SHORT"), Some("LONG"), (80.0, 100.0)))
+        .await
+        .unwrap();
+    let requests = server.received_requests().await.unwrap();
+    let binance = requests.iter().find(|r| r.url.path() == "/fapi/v1/order").unwrap();
+    assert!(binance.url.query_pairs().any(|(k, v)| k == "quantity" && v == "80"));
+    let bitmart = requests.iter().find(|r| r.url.path() == "/futures/v1/submit-order").unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&bitmart.body).unwrap();
+    // 3: 매도 롱 청산
+    assert_eq!((body["side"].as_u64(), body["size"].as_u64()), (Some(3), Some(100)));
+}
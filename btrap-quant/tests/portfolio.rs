@@ -0,0 +1,24 @@
+use btrap_quant::portfolio::OpenTrades;
+
+#[test]
+fn reservations_stop_at_limit() {
+    let open_trades = OpenTrades::new(2, 0);
+    assert!(open_trades.try_reserve());
+    assert!(open_trades.try_reserve());
+    assert!(!open_trades.try_reserve());
+    assert_eq!(open_trades.count(), 2);
+
+    // 청산 또는 진입 무산 시 반납하면 다시 진입 가능
+    open_trades.release();
+    assert!(open_trades.try_reserve());
+}
+
+#[test]
+fn restored_positions_count_toward_limit() {
+    let open_trades = OpenTrades::new(1, 1);
+    assert!(!open_trades.try_reserve());
+    open_trades.release();
+    open_trades.release();
+    assert_eq!(open_trades.count(), 0);
+    assert!(open_trades.try_reserve());
+}
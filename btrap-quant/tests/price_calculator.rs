@@ -0,0 +1,513 @@
+use btrap_quant::config::{Exchange, StrategyConfig};
+use btrap_quant::depth::{
+    apply_depth_delta, bitmart_depth_channel, fair_value, maker_price, mid_price, parse_bitmart_depth, BinanceDepthSnapshot, BinanceDiffUpdate,
+    BitmartDepthUpdate, DepthAllData, DepthAllItem, DiffBook, DiffOutcome, Level, OrderBook,
+};
+use btrap_quant::price_calculator::{FillMode, GapDefinition, PriceCalculator, Quotes};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+fn book(bid: &str, ask: &str) -> OrderBook {
+    let item = |price: &str| DepthAllItem { price: price.to_string(), vol: "100".to_string() };
+    OrderBook::from(&DepthAllData {
+        symbol: "XRPUSDT".to_string(),
+        asks: vec![item(ask)],
+        bids: vec![item(bid)],
+        ms_t: 0,
+    })
+}
+
+fn calculator(config: StrategyConfig) -> PriceCalculator {
+    PriceCalculator::new(Arc::new(config), Arc::new(Mutex::new(HashMap::new())))
+}
+
+fn execution_price_calculator() -> PriceCalculator {
+    calculator(StrategyConfig { gap_definition: GapDefinition::ExecutionPrice, ..StrategyConfig::default() })
+}
+
+fn book_quotes<'a>(binance_book: &'a OrderBook, bitmart_book: &'a OrderBook) -> Quotes<'a> {
+    Quotes { binance_book: Some(binance_book), bitmart_book: Some(bitmart_book), ..Quotes::default() }
+}
+
+#[test]
+fn best_opportunity_picks_binance_short_when_binance_is_rich() {
+    let (binance, bitmart) = (book("1.010", "1.011"), book("0.999", "1.000"));
+    // Binance bid 1.010 vs Bitmart ask 1.000 -> 1%
+    let opportunity = execution_price_calculator()
+        .best_opportunity(&book_quotes(&binance, &bitmart), 1.0)
+        .unwrap();
+    assert_eq!((opportunity.binance_position, opportunity.bitmart_position), ("SHORT", "LONG"));
+    assert!((opportunity.gap - 1.0).abs() < 1e-9);
+    assert_eq!(opportunity.binance_price, 1.010);
+    assert_eq!(opportunity.bitmart_price, 1.000);
+}
+
+#[test]
+fn best_opportunity_picks_binance_long_when_binance_is_cheap() {
+    let (binance, bitmart) = (book("0.989", "0.990"), book("1.000", "1.001"));
+    let opportunity = execution_price_calculator()
+        .best_opportunity(&book_quotes(&binance, &bitmart), 1.0)
+        .unwrap();
+    assert_eq!((opportunity.binance_position, opportunity.bitmart_position), ("LONG", "SHORT"));
+    assert!((opportunity.gap + 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn best_opportunity_is_none_when_spread_eats_the_gap() {
+    // 중간가 갭은 0.5%지만 체결가 기준으로는 임계값 이하
+    let (binance, bitmart) = (book("1.002", "1.008"), book("0.999", "1.001"));
+    let quotes = book_quotes(&binance, &bitmart);
+    assert!(execution_price_calculator().best_opportunity(&quotes, 1.0).is_none());
+
+    let mid = calculator(StrategyConfig { gap_definition: GapDefinition::Mid, ..StrategyConfig::default() });
+    let opportunity = mid.best_opportunity(&quotes, 1.0).unwrap();
+    assert!((opportunity.gap - 0.5).abs() < 1e-9);
+}
+
+#[test]
+fn best_opportunity_respects_inverted_direction() {
+    let config = StrategyConfig {
+        gap_definition: GapDefinition::ExecutionPrice,
+        invert_direction: true,
+        ..StrategyConfig::default()
+    };
+    let (binance, bitmart) = (book("1.010", "1.011"), book("0.999", "1.000"));
+    // Binance ask 1.011 vs Bitmart bid 0.999 -> Binance 롱 방향 갭 > 0
+    let opportunity = calculator(config)
+        .best_opportunity(&book_quotes(&binance, &bitmart), 1.0)
+        .unwrap();
+    assert_eq!((opportunity.binance_position, opportunity.bitmart_position), ("LONG", "SHORT"));
+    assert!(opportunity.gap > 1.0);
+}
+
+#[test]
+fn last_trade_gap_works_without_depth() {
+    let calculator = calculator(StrategyConfig { gap_definition: GapDefinition::LastTrade, ..StrategyConfig::default() });
+    let quotes = Quotes { binance_last: Some(1.01), bitmart_last: Some(1.0), ..Quotes::default() };
+    let gap = calculator.gap(&quotes, true, 1.0).unwrap();
+    assert!((gap - 1.0).abs() < 1e-9);
+    assert_eq!(calculator.best_opportunity(&quotes, 1.0).unwrap().binance_position, "SHORT");
+
+    // 호가창만 있으면 최근 체결가 기준 갭은 계산 불가
+    let (binance, bitmart) = (book("1.010", "1.011"), book("0.999", "1.000"));
+    assert!(calculator.gap(&book_quotes(&binance, &bitmart), true, 1.0).is_none());
+}
+
+#[test]
+fn bid_ask_gap_uses_opposite_sides_and_falls_back_to_last_trade() {
+    let calculator = calculator(StrategyConfig::default());
+    let (binance, bitmart) = (book("1.010", "1.011"), book("0.999", "1.000"));
+    let quotes = Quotes { binance_last: Some(1.05), bitmart_last: Some(0.95), ..book_quotes(&binance, &bitmart) };
+    // Binance 매도: Binance bid 1.010 vs Bitmart ask 1.000
+    assert_eq!(calculator.gap_prices(&quotes, false, 1.0), Some((1.010, 1.000)));
+    // Binance 매수: Binance ask 1.011 vs Bitmart bid 0.999
+    assert_eq!(calculator.gap_prices(&quotes, true, 1.0), Some((1.011, 0.999)));
+    let opportunity = calculator.best_opportunity(&quotes, 1.0).unwrap();
+    assert_eq!(opportunity.binance_position, "SHORT");
+    assert!((opportunity.gap - 1.0).abs() < 1e-9);
+
+    // 호가창이 없는 거래소만 최근 체결가 사용
+    let quotes = Quotes { binance_last: Some(1.05), bitmart_book: Some(&bitmart), ..Quotes::default() };
+    assert_eq!(calculator.gap_prices(&quotes, false, 1.0), Some((1.05, 1.000)));
+    assert!(calculator.gap_prices(&Quotes { binance_last: Some(1.05), ..Quotes::default() }, false, 1.0).is_none());
+}
+
+#[test]
+fn spread_capture_is_net_of_round_trip_fees() {
+    let calculator = calculator(StrategyConfig::default());
+    // 0.5% -> 0.05% 수렴, 명목 100 USDT: 0.45 USDT - 수수료 2 * 100 * (0.05% + 0.06%) = 0.23 USDT
+    let pnl = calculator.spread_capture("SHORT", 0.5, 0.05, 100.0, 1.0);
+    assert!((pnl - 0.23).abs() < 1e-9);
+    // Binance 롱은 음수 갭이 0으로 수렴할 때 수익
+    let pnl = calculator.spread_capture("LONG", -0.5, -0.05, 100.0, 1.0);
+    assert!((pnl - 0.23).abs() < 1e-9);
+}
+
+#[test]
+fn estimated_fees_split_by_venue() {
+    let calculator = calculator(StrategyConfig::default());
+    // 명목 100 USDT 한 번 체결: Binance 0.05%, Bitmart 0.06%
+    let (binance, bitmart) = calculator.estimated_fees(100.0, 1.0, 1.0);
+    assert!((binance - 0.05).abs() < 1e-12 && (bitmart - 0.06).abs() < 1e-12);
+    // 수수료 차감 전 스프레드 수익 (실제 또는 추정 수수료는 호출 측에서 차감)
+    let gross = PriceCalculator::gross_capture("SHORT", 0.5, 0.05, 100.0, 1.0);
+    assert!((gross - 0.45).abs() < 1e-9);
+}
+
+#[test]
+fn symbol_fee_overrides_and_bnb_discount_apply_to_fee_math() {
+    let config: StrategyConfig = toml::from_str(
+        "symbol = \"XRPUSDT\"\nbinance_fee_multiplier = 0.9\n[fee_overrides.XRPUSDT]\nbitmart_taker_fee = 0.04\n",
+    )
+    .unwrap();
+    assert!(config.validate().is_ok());
+    // Binance는 전역 0.05%에 BNB 할인 0.9배, Bitmart는 종목별 0.04%
+    assert!((config.fee_pct(Exchange::Binance, FillMode::Taker) - 0.045).abs() < 1e-12);
+    assert!((config.fee_pct(Exchange::Bitmart, FillMode::Taker) - 0.04).abs() < 1e-12);
+    assert!((config.fee_pct(Exchange::Bitmart, FillMode::Maker) - 0.02).abs() < 1e-12);
+    // 다른 종목은 덮어쓰기 없이 전역 수수료
+    let other = StrategyConfig { symbol: "DOGEUSDT".to_string(), ..config.clone() };
+    assert!((other.fee_pct(Exchange::Bitmart, FillMode::Taker) - 0.06).abs() < 1e-12);
+
+    let (binance, bitmart) = calculator(config.clone()).estimated_fees(100.0, 1.0, 1.0);
+    assert!((binance - 0.045).abs() < 1e-12 && (bitmart - 0.04).abs() < 1e-12);
+    // 0.5% -> 0.05% 수렴, 명목 100 USDT: 0.45 - 2 * (0.045 + 0.04) = 0.28 USDT
+    let pnl = calculator(config.clone()).spread_capture("SHORT", 0.5, 0.05, 100.0, 1.0);
+    assert!((pnl - 0.28).abs() < 1e-9);
+
+    let invalid = StrategyConfig { binance_fee_multiplier: 1.5, ..config };
+    assert!(invalid.validate().is_err());
+}
+
+// 20단계 호가창 (Bitmart/Binance 원본 형식)
+fn raw_depth(levels: usize) -> DepthAllData {
+    let side = |start: f64, step: f64| {
+        (0..levels)
+            .map(|i| DepthAllItem { price: format!("{:.4}", start + step * i as f64), vol: "250".to_string() })
+            .collect()
+    };
+    DepthAllData { symbol: "XRPUSDT".to_string(), asks: side(0.5001, 0.0001), bids: side(0.5000, -0.0001), ms_t: 0 }
+}
+
+#[test]
+fn order_book_drops_unparsable_levels() {
+    let mut raw = raw_depth(2);
+    raw.asks[0].vol = "n/a".to_string();
+    let book = OrderBook::from(&raw);
+    assert_eq!(book.asks, vec![Level { price: 0.5002, vol: 250.0 }]);
+    assert_eq!(book.bids.len(), 2);
+}
+
+#[test]
+fn cached_levels_avoid_reparsing_on_hot_path() {
+    let raw = raw_depth(20);
+    let cached = OrderBook::from(&raw);
+    let iterations = 20_000;
+    let quantity = 4_000.0; // 16단계 소진
+
+    // 이전 방식: 계산할 때마다 문자열 파싱
+    let started = std::time::Instant::now();
+    let mut reparsed_total = 0.0;
+    for _ in 0..iterations {
+        let book = OrderBook::from(&raw);
+        reparsed_total += PriceCalculator::calculate_execution_price(&book, true, quantity, FillMode::Taker).unwrap();
+    }
+    let reparsed = started.elapsed();
+
+    let started = std::time::Instant::now();
+    let mut cached_total = 0.0;
+    for _ in 0..iterations {
+        cached_total += PriceCalculator::calculate_execution_price(&cached, true, quantity, FillMode::Taker).unwrap();
+    }
+    let cached_elapsed = started.elapsed();
+
+    println!("execution price x{}: reparsed {:?}, cached {:?}", iterations, reparsed, cached_elapsed);
+    assert_eq!(reparsed_total, cached_total);
+    assert!(cached_elapsed < reparsed);
+}
+
+#[test]
+fn synthetic_binance_spread_is_used_only_without_depth() {
+    let config = StrategyConfig {
+        gap_definition: GapDefinition::ExecutionPrice,
+        binance_synthetic_spread_bps: Some(20.0),
+        ..StrategyConfig::default()
+    };
+    let calculator = calculator(config);
+    let bitmart = book("0.999", "1.000");
+    let quotes = Quotes { binance_last: Some(1.01), bitmart_book: Some(&bitmart), ..Quotes::default() };
+    // Binance 매도가 = 1.01 - 1.01 * 0.1% = 1.00899
+    let opportunity = calculator.best_opportunity(&quotes, 1.0).unwrap();
+    assert!(opportunity.estimated);
+    assert!((opportunity.binance_price - 1.00899).abs() < 1e-9);
+
+    // 실제 호가창이 있으면 합성 가격을 쓰지 않음
+    let binance = book("1.010", "1.011");
+    let quotes = Quotes { binance_book: Some(&binance), ..quotes };
+    let opportunity = calculator.best_opportunity(&quotes, 1.0).unwrap();
+    assert!(!opportunity.estimated);
+    assert_eq!(opportunity.binance_price, 1.010);
+
+    // 설정이 없으면 Binance 호가창 없이 갭 계산 불가
+    let quotes = Quotes { binance_last: Some(1.01), bitmart_book: Some(&bitmart), ..Quotes::default() };
+    assert!(execution_price_calculator().best_opportunity(&quotes, 1.0).is_none());
+}
+
+#[test]
+fn what_if_reports_decision_without_depth() {
+    let default = calculator(StrategyConfig::default());
+    // 0.5% 갭, 왕복 수수료 0.22%
+    let result = default.what_if(1.005, 1.0, 100.0);
+    assert_eq!((result.binance_position, result.bitmart_position), ("SHORT", "LONG"));
+    assert!((result.gap - 0.5).abs() < 1e-9);
+    assert!((result.net_gap - 0.28).abs() < 1e-9);
+    assert!(result.would_enter);
+    assert!((result.expected_pnl - 0.28).abs() < 1e-9);
+
+    // 임계값 이하이거나 허용되지 않은 방향이면 진입 안 함
+    assert!(!default.what_if(1.002, 1.0, 100.0).would_enter);
+    let short_only = calculator(StrategyConfig {
+        allowed_directions: btrap_quant::config::AllowedDirections::OnlyGap1,
+        ..StrategyConfig::default()
+    });
+    let result = short_only.what_if(0.995, 1.0, 100.0);
+    assert_eq!(result.binance_position, "LONG");
+    assert!(!result.would_enter);
+}
+
+#[test]
+fn fair_value_weights_top_levels_by_volume() {
+    let level = |price: f64, vol: f64| Level { price, vol };
+    let mut depth = OrderBook {
+        symbol: "XRPUSDT".to_string(),
+        bids: vec![level(0.50, 300.0), level(0.49, 100.0)],
+        asks: vec![level(0.51, 100.0), level(0.52, 1_000.0)],
+        ms_t: 0,
+    };
+    assert_eq!(mid_price(&depth), Some(0.505));
+    // 1단계: (0.50*300 + 0.51*100) / 400 = 0.5025
+    assert!((fair_value(&depth, 1).unwrap() - 0.5025).abs() < 1e-12);
+    // 단계 수보다 호가가 적으면 있는 만큼 사용
+    assert_eq!(fair_value(&depth, 2), fair_value(&depth, 10));
+
+    // 한쪽 호가가 비면 계산하지 않음
+    depth.asks.clear();
+    assert_eq!(fair_value(&depth, 5), None);
+    assert_eq!(mid_price(&depth), None);
+}
+
+#[test]
+fn maker_price_joins_own_side_of_book() {
+    let level = |price: f64, vol: f64| Level { price, vol };
+    let mut depth = OrderBook {
+        symbol: "XRPUSDT".to_string(),
+        bids: vec![level(0.50, 300.0), level(0.49, 100.0)],
+        asks: vec![level(0.51, 100.0)],
+        ms_t: 0,
+    };
+    assert_eq!(maker_price(&depth, true), Some(0.50));
+    assert_eq!(maker_price(&depth, false), Some(0.51));
+
+    depth.asks.clear();
+    assert_eq!(maker_price(&depth, false), None);
+    assert_eq!(maker_price(&depth, true), Some(0.50));
+}
+
+#[test]
+fn crossed_or_stale_book_is_not_valid() {
+    let level = |price: f64| Level { price, vol: 100.0 };
+    let mut depth = OrderBook {
+        symbol: "XRPUSDT".to_string(),
+        bids: vec![level(0.50)],
+        asks: vec![level(0.51)],
+        ms_t: 1_000_000,
+    };
+    assert!(!depth.is_crossed());
+    assert!(depth.is_valid_at(5000, 1_004_000));
+    // 호가 시각이 허용 지연보다 오래됨 (0이면 확인 안 함)
+    assert!(!depth.is_valid_at(5000, 1_006_000));
+    assert!(depth.is_valid_at(0, 9_000_000));
+
+    // 재접속 직후 교차된 호가창
+    depth.bids = vec![level(0.52)];
+    assert!(depth.is_crossed());
+    assert!(!depth.is_valid_at(5000, 1_000_000));
+    depth.bids = vec![level(0.51)];
+    assert!(depth.is_crossed());
+
+    // 한쪽이 비면 교차로 보지 않음
+    depth.asks.clear();
+    assert!(!depth.is_crossed());
+}
+
+#[test]
+fn gap_confirmation_requires_last_trade_and_book_to_agree() {
+    let calculator = calculator(StrategyConfig::default());
+    let (binance, bitmart) = (book("1.010", "1.011"), book("0.999", "1.000"));
+    // 최근 체결가와 호가 체결가 모두 Binance 숏 방향 1% 갭
+    let quotes = Quotes { binance_last: Some(1.010), bitmart_last: Some(1.000), ..book_quotes(&binance, &bitmart) };
+    let confirmation = calculator.gap_confirmation(&quotes, "SHORT", 1.0);
+    assert!(confirmation.confirmed());
+    assert!((confirmation.execution.unwrap() - 1.0).abs() < 1e-9);
+
+    // 오래된 체결가는 갭을 보이지만 호가는 이미 수렴
+    let (binance, bitmart) = (book("1.000", "1.001"), book("0.999", "1.000"));
+    let quotes = Quotes { binance_last: Some(1.010), bitmart_last: Some(1.000), ..book_quotes(&binance, &bitmart) };
+    let confirmation = calculator.gap_confirmation(&quotes, "SHORT", 1.0);
+    assert!(confirmation.last_trade_agrees);
+    assert!(!confirmation.execution_agrees);
+    assert!(!confirmation.confirmed());
+
+    // 호가창이 없으면 확인 불가
+    let quotes = Quotes { binance_last: Some(1.010), bitmart_last: Some(1.000), ..Quotes::default() };
+    let confirmation = calculator.gap_confirmation(&quotes, "SHORT", 1.0);
+    assert_eq!(confirmation.execution, None);
+    assert!(!confirmation.confirmed());
+}
+
+#[test]
+fn book_dump_includes_full_books_and_execution_prices() {
+    let calculator = calculator(StrategyConfig { bitmart_fill_mode: FillMode::Maker, ..StrategyConfig::default() });
+    let books = HashMap::from([
+        ("Binance".to_string(), book("0.50", "0.51")),
+        ("Bitmart".to_string(), book("0.49", "0.52")),
+    ]);
+    let dump = calculator.book_dump(&books, 50.0, chrono::Utc::now());
+    assert_eq!(dump.quantity, 50.0);
+    let binance = &dump.venues["Binance"];
+    assert_eq!(binance.book, books["Binance"]);
+    assert_eq!((binance.buy_price, binance.sell_price), (Some(0.51), Some(0.50)));
+    // maker는 같은 편 최우선 호가
+    let bitmart = &dump.venues["Bitmart"];
+    assert_eq!((bitmart.buy_price, bitmart.sell_price), (Some(0.49), Some(0.52)));
+    // 잔량보다 큰 수량은 체결가 없음
+    assert_eq!(calculator.book_dump(&books, 500.0, chrono::Utc::now()).venues["Binance"].buy_price, None);
+
+    // 파일로 저장한 JSON에서 그대로 복원
+    let json = serde_json::to_string(&dump).unwrap();
+    let restored: btrap_quant::price_calculator::BookDump = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.venues["Bitmart"].book, books["Bitmart"]);
+}
+
+fn diff(first: u64, last: u64, prev: u64, bids: &[(&str, &str)], asks: &[(&str, &str)]) -> BinanceDiffUpdate {
+    let levels = |levels: &[(&str, &str)]| levels.iter().map(|(p, q)| [p.to_string(), q.to_string()]).collect();
+    BinanceDiffUpdate {
+        symbol: "XRPUSDT".to_string(),
+        transaction_time: last as i64,
+        first_update_id: first,
+        final_update_id: last,
+        prev_final_update_id: prev,
+        bids: levels(bids),
+        asks: levels(asks),
+    }
+}
+
+fn snapshot(last_update_id: u64) -> BinanceDepthSnapshot {
+    let level = |p: &str| [p.to_string(), "100".to_string()];
+    BinanceDepthSnapshot {
+        last_update_id,
+        transaction_time: 0,
+        bids: vec![level("0.50"), level("0.49")],
+        asks: vec![level("0.51"), level("0.52")],
+    }
+}
+
+#[test]
+fn diff_book_buffers_until_snapshot_and_applies_in_sequence() {
+    let mut book = DiffBook::new("XRPUSDT");
+    // 스냅샷 이전 업데이트는 보관 후 스냅샷 기준으로 정리
+    assert_eq!(book.on_update(diff(90, 95, 89, &[("0.48", "1")], &[])), DiffOutcome::Buffered);
+    assert_eq!(book.on_update(diff(96, 105, 95, &[("0.50", "0")], &[("0.515", "7")])), DiffOutcome::Buffered);
+    assert_eq!(book.apply_snapshot(snapshot(100)), DiffOutcome::Applied);
+    assert!(book.is_synced());
+
+    // u=95 업데이트는 스냅샷보다 오래되어 버림, u=105 업데이트가 스냅샷을 이어받음
+    let top = OrderBook::from(&book.top(5));
+    assert_eq!(top.bids, vec![Level { price: 0.49, vol: 100.0 }]);
+    assert_eq!(top.asks[..2], [Level { price: 0.51, vol: 100.0 }, Level { price: 0.515, vol: 7.0 }]);
+
+    // pu가 직전 u와 같으면 반영
+    assert_eq!(book.on_update(diff(106, 110, 105, &[("0.495", "3")], &[])), DiffOutcome::Applied);
+    assert_eq!(OrderBook::from(&book.top(5)).bids[0], Level { price: 0.495, vol: 3.0 });
+}
+
+#[test]
+fn out_of_sequence_diff_requires_resync() {
+    let mut book = DiffBook::new("XRPUSDT");
+    assert_eq!(book.apply_snapshot(snapshot(100)), DiffOutcome::Applied);
+    assert_eq!(book.on_update(diff(99, 104, 98, &[], &[])), DiffOutcome::Applied);
+    // 105~109 누락
+    assert_eq!(book.on_update(diff(110, 112, 109, &[("0.60", "1")], &[])), DiffOutcome::Gap);
+    book.reset();
+    assert!(!book.is_synced());
+    assert!(book.top(5).bids.is_empty());
+
+    // 스냅샷 뒤 첫 업데이트가 스냅샷을 포함하지 않아도 재동기화
+    let mut book = DiffBook::new("XRPUSDT");
+    book.on_update(diff(120, 125, 119, &[], &[]));
+    assert_eq!(book.apply_snapshot(snapshot(100)), DiffOutcome::Gap);
+}
+
+#[test]
+fn bitmart_depth_parses_group_variants() {
+    // 객체 data, symbol 포함
+    let json = serde_json::json!({
+        "group": "futures/depthAll20:XRPUSDT",
+        "data": {"symbol": "XRPUSDT", "asks": [{"price": "0.51", "vol": "10"}], "bids": [{"price": "0.50", "vol": "20"}], "ms_t": 1}
+    });
+    let updates = parse_bitmart_depth(&json).unwrap();
+    assert!(matches!(&updates[..], [BitmartDepthUpdate::Snapshot(depth)] if depth.symbol == "XRPUSDT" && depth.asks[0].price == "0.51"));
+
+    // 배열 data, symbol은 채널에서, 단계는 [가격, 수량], 한쪽 호가 누락은 빈 호가
+    let json = serde_json::json!({
+        "table": "futures/depthIncrease5:BTCUSDT",
+        "data": [{"type": "update", "bids": [["100.0", "0"]], "ts": 2}, {"asks": "bad", "ms_t": 3}]
+    });
+    let updates = parse_bitmart_depth(&json).unwrap();
+    assert_eq!(updates.len(), 1);
+    let BitmartDepthUpdate::Delta(depth) = &updates[0] else { panic!("expected delta") };
+    assert_eq!((depth.symbol.as_str(), depth.ms_t, depth.asks.len()), ("BTCUSDT", 2, 0));
+    assert_eq!(depth.bids[0].vol, "0");
+
+    // 호가 채널이 아니면 None
+    assert!(parse_bitmart_depth(&serde_json::json!({"group": "futures/ticker", "data": {}})).is_none());
+    assert!(parse_bitmart_depth(&serde_json::json!({"code": 30001, "msg": "error"})).is_none());
+}
+
+#[test]
+fn bitmart_depth_delta_merges_into_book() {
+    let item = |price: &str, vol: &str| DepthAllItem { price: price.to_string(), vol: vol.to_string() };
+    let mut book = DepthAllData {
+        symbol: "XRPUSDT".to_string(),
+        asks: vec![item("0.51", "10"), item("0.52", "10")],
+        bids: vec![item("0.50", "10"), item("0.49", "10")],
+        ms_t: 1,
+    };
+    let delta = DepthAllData {
+        symbol: "XRPUSDT".to_string(),
+        asks: vec![item("0.51", "0"), item("0.515", "5")],
+        bids: vec![item("0.505", "3"), item("0.49", "7")],
+        ms_t: 2,
+    };
+    apply_depth_delta(&mut book, delta, 2);
+    let top = OrderBook::from(&book);
+    assert_eq!(top.asks, vec![Level { price: 0.515, vol: 5.0 }, Level { price: 0.52, vol: 10.0 }]);
+    assert_eq!(top.bids, vec![Level { price: 0.505, vol: 3.0 }, Level { price: 0.50, vol: 10.0 }]);
+    assert_eq!(book.ms_t, 2);
+}
+
+#[test]
+fn round_trip_cost_adds_fees_and_book_slippage() {
+    let level = |price: &str, vol: &str| DepthAllItem { price: price.to_string(), vol: vol.to_string() };
+    let binance = OrderBook::from(&DepthAllData {
+        symbol: "XRPUSDT".to_string(),
+        bids: vec![level("1.00", "1"), level("0.99", "10")],
+        asks: vec![level("1.01", "1"), level("1.02", "10")],
+        ms_t: 0,
+    });
+    let bitmart = book("0.98", "0.99");
+    let quotes = book_quotes(&binance, &bitmart);
+
+    // Binance 숏 진입: 매도 0.995 (최우선 1.00 대비 0.5%), 청산 매수 1.015 (1.01 대비 약 0.495%)
+    let taker = calculator(StrategyConfig::default());
+    let cost = taker.round_trip_cost(&quotes, false, 2.0).unwrap();
+    assert!((cost.fees - 0.22).abs() < 1e-9);
+    assert!((cost.slippage - (0.5 + 0.5 / 1.01)).abs() < 1e-9);
+    assert!((cost.gap_ratio(-2.43) - 2.43 / cost.total()).abs() < 1e-9);
+
+    // maker 진입은 진입 슬리피지와 taker 수수료가 없음 (청산은 시장가)
+    let maker = calculator(StrategyConfig { binance_fill_mode: FillMode::Maker, ..StrategyConfig::default() });
+    let cost = maker.round_trip_cost(&quotes, false, 2.0).unwrap();
+    assert!((cost.fees - 0.19).abs() < 1e-9);
+    assert!((cost.slippage - 0.5 / 1.01).abs() < 1e-9);
+
+    // 잔량이 부족하면 비용을 계산할 수 없음
+    assert!(taker.round_trip_cost(&quotes, false, 200.0).is_none());
+}
+
+#[test]
+fn bitmart_depth_channel_falls_back_to_twenty_levels() {
+    assert_eq!(bitmart_depth_channel("XRPUSDT", 50), "futures/depthAll50:XRPUSDT");
+    assert_eq!(bitmart_depth_channel("XRPUSDT", 10), "futures/depthAll20:XRPUSDT");
+}
@@ -0,0 +1,137 @@
+use btrap_quant::config::{BinanceOrderRespType, ContractSpec, MarginType};
+use btrap_quant::order::{BinancePrecision, BinanceWeight, ClientOrderIds, Fill, Order, OrderBudget};
+use btrap_quant::private_feed::{parse_binance_fill, parse_bitmart_fills};
+use btrap_quant::state::TradingState;
+use btrap_quant::venue::VenueHealth;
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use serde_json::json;
+use sha2::Sha256;
+use std::collections::HashMap;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn test_order(base_url: &str) -> Order {
+    Order {
+        client: Client::new(),
+        binance_api_key: "binance-key".to_string(),
+        binance_secret_key: "binance-secret".to_string(),
+        bitmart_api_key: "bitmart-key".to_string(),
+        bitmart_secret_key: "bitmart-secret".to_string(),
+        bitmart_memo: "memo".to_string(),
+        open_type: MarginType::Isolated,
+        binance_base_url: base_url.to_string(),
+        bitmart_base_url: base_url.to_string(),
+        bitmart_contracts: HashMap::from([("XRPUSDT".to_string(), ContractSpec { contract_size: 1.0, min_contracts: 1, price_decimals: None })]),
+        binance_weight: BinanceWeight::new(2400, 0.8),
+        venue_health: VenueHealth::new(3),
+        binance_precision: BinancePrecision::default(),
+        order_budget: OrderBudget::new(0),
+        binance_resp_type: BinanceOrderRespType::Result,
+        client_order_ids: ClientOrderIds::default(),
+    }
+}
+
+#[test]
+fn bitmart_login_is_signed_with_memo() {
+    let login = test_order("http://localhost").bitmart_ws_login(1700000000000);
+    let mut mac = Hmac::<Sha256>::new_from_slice(b"bitmart-secret").unwrap();
+    mac.update(b"1700000000000#memo#bitmart.WebSocket");
+    let expected = hex::encode(mac.finalize().into_bytes());
+    assert_eq!(
+        login,
+        json!({ "action": "access", "args": ["bitmart-key", "1700000000000", expected, "web"] })
+    );
+}
+
+#[tokio::test]
+async fn binance_listen_key_is_created_with_api_key() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/listenKey"))
+        .and(header("X-MBX-APIKEY", "binance-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"listenKey":"abc123"}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let listen_key = test_order(&server.uri()).create_listen_key_binance().await.unwrap();
+    assert_eq!(listen_key, "abc123");
+}
+
+#[tokio::test]
+async fn binance_listen_key_rejection_is_reported() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/listenKey"))
+        .respond_with(ResponseTemplate::new(401).set_body_string(r#"{"code":-2015,"msg":"Invalid API-key"}"#))
+        .mount(&server)
+        .await;
+
+    let error = test_order(&server.uri()).create_listen_key_binance().await.unwrap_err();
+    assert_eq!(error.rejection_code(), Some(-2015));
+}
+
+#[test]
+fn binance_trade_update_is_parsed_as_fill() {
+    let event = json!({
+        "e": "ORDER_TRADE_UPDATE",
+        "o": {
+            "s": "XRPUSDT", "S": "SELL", "x": "TRADE", "X": "FILLED", "i": 8886774, "l": "2", "L": "0.5123",
+            "N": "USDT", "n": "0.0004", "T": 1700000000000i64
+        }
+    });
+    let fill = parse_binance_fill(&event).unwrap();
+    assert_eq!((fill.exchange, fill.symbol.as_str(), fill.is_buy), ("Binance", "XRPUSDT", false));
+    assert_eq!(fill.order_id, "8886774");
+    assert_eq!((fill.filled_qty, fill.avg_price, fill.fee), (2.0, Some(0.5123), Some(0.0004)));
+    assert_eq!(fill.ts, 1_700_000_000_000);
+    assert_eq!(fill.raw, event["o"]);
+
+    // USDT 외 자산으로 낸 수수료는 기록하지 않음
+    let mut bnb_fee = event.clone();
+    bnb_fee["o"]["N"] = json!("BNB");
+    assert_eq!(parse_binance_fill(&bnb_fee).unwrap().fee, None);
+
+    let new_order = json!({ "e": "ORDER_TRADE_UPDATE", "o": { "s": "XRPUSDT", "S": "SELL", "x": "NEW", "l": "0", "L": "0" } });
+    assert_eq!(parse_binance_fill(&new_order), None);
+}
+
+#[test]
+fn bitmart_finished_orders_are_parsed_as_fills() {
+    let message = json!({
+        "group": "futures/order",
+        "data": [
+            { "action": 1, "order": { "order_id": "220906179895578", "symbol": "XRPUSDT", "side": 1, "state": 4, "deal_size": "3", "deal_avg_price": "0.51", "update_time": 1700000000000i64 } },
+            { "action": 2, "order": { "symbol": "XRPUSDT", "side": 4, "state": 2, "deal_size": "0", "deal_avg_price": "0" } }
+        ]
+    });
+    let fills = parse_bitmart_fills(&message);
+    assert_eq!(fills.len(), 1);
+    assert_eq!((fills[0].exchange, fills[0].symbol.as_str(), fills[0].is_buy), ("Bitmart", "XRPUSDT", true));
+    assert_eq!(fills[0].order_id, "220906179895578");
+    assert_eq!((fills[0].filled_qty, fills[0].avg_price), (3.0, Some(0.51)));
+    assert_eq!(fills[0].ts, 1_700_000_000_000);
+}
+
+#[test]
+fn fills_accumulate_net_quantity_per_exchange() {
+    let mut state = TradingState::default();
+    state.open_position("SHORT", "LONG", 0.5, 2.0);
+    let fill = |exchange, is_buy, filled_qty| Fill {
+        exchange,
+        symbol: "XRPUSDT".to_string(),
+        is_buy,
+        order_id: String::new(),
+        filled_qty,
+        avg_price: Some(0.5),
+        fee: None,
+        ts: 0,
+        raw: serde_json::Value::Null,
+    };
+    state.apply_fill(&fill("Binance", false, 2.0));
+    state.apply_fill(&fill("Bitmart", true, 1.0));
+    state.apply_fill(&fill("Bitmart", true, 1.0));
+    assert_eq!(state.net_fills["Binance"], -2.0);
+    assert_eq!(state.net_fills["Bitmart"], 2.0);
+}
@@ -0,0 +1,297 @@
+use btrap_quant::depth::{snapshot_books, Level, OrderBook};
+use btrap_quant::order::Fill;
+use btrap_quant::state::{migrate, InflightTrade, TradingState, STATE_VERSION};
+use std::collections::HashMap;
+use chrono::{Duration, Utc};
+use serde_json::json;
+
+#[test]
+fn close_position_records_captured_pnl() {
+    let mut state = TradingState::default();
+    state.open_position("SHORT", "LONG", 0.5, 2.0);
+    let record = state.close_position(0.02, 0.0123).unwrap();
+    assert_eq!(record.captured_pnl, Some(0.0123));
+    assert!(!state.is_trading);
+
+    let (count, total) = state.captured_since(Utc::now() - Duration::minutes(1));
+    assert_eq!(count, 1);
+    assert!((total - 0.0123).abs() < 1e-12);
+    assert_eq!(state.captured_since(Utc::now() + Duration::minutes(1)), (0, 0.0));
+}
+
+#[test]
+fn migration_marks_old_trades_without_pnl() {
+    let old = json!({
+        "version": 2,
+        "is_trading": false,
+        "binance_position": null,
+        "bitmart_position": null,
+        "entry_gap": 0.0,
+        "quantity": 0.0,
+        "position_open_time": null,
+        "trades": [{
+            "open_time": "2024-01-01T00:00:00Z",
+            "close_time": "2024-01-01T01:00:00Z",
+            "binance_position": "SHORT",
+            "bitmart_position": "LONG",
+            "entry_gap": 0.4,
+            "exit_gap": 0.01,
+            "quantity": 1.0
+        }]
+    });
+    let migrated = migrate(old).unwrap();
+    assert_eq!(migrated["version"], STATE_VERSION);
+    let state: TradingState = serde_json::from_value(migrated).unwrap();
+    assert_eq!(state.trades[0].captured_pnl, None);
+    assert_eq!(state.trades[0].book_snapshot, None);
+    assert_eq!(state.entry_fees, None);
+    assert_eq!(state.entry_book, None);
+    assert!(state.protective_stops.is_empty());
+    assert_eq!(state.scale_in_steps, 0);
+}
+
+#[test]
+fn scale_in_tracks_average_entry_gap() {
+    let mut state = TradingState::default();
+    state.open_position("SHORT", "LONG", 0.4, 2.0);
+    state.scale_in(0.6, 1.0);
+    state.scale_in(0.8, 1.0);
+    // (0.4 * 2 + 0.6 + 0.8) / 4
+    assert!((state.entry_gap - 0.55).abs() < 1e-12);
+    assert_eq!((state.quantity, state.scale_in_steps, state.last_entry_gap), (4.0, 2, 0.8));
+
+    // 청산 기록은 평균 갭과 전체 수량, 다음 포지션은 처음부터
+    let record = state.close_position(0.05, 0.1).unwrap();
+    assert!((record.entry_gap - 0.55).abs() < 1e-12);
+    assert_eq!(record.quantity, 4.0);
+    assert_eq!((state.scale_in_steps, state.last_entry_gap), (0, 0.0));
+}
+
+#[test]
+fn migration_keeps_open_position_entry_gap_for_scale_in() {
+    let old = json!({
+        "version": 6,
+        "is_trading": true,
+        "binance_position": "SHORT",
+        "bitmart_position": "LONG",
+        "entry_gap": 0.45,
+        "quantity": 1.0,
+        "position_open_time": null,
+        "entry_fees": null,
+        "entry_book": null,
+        "protective_stops": {},
+        "trades": []
+    });
+    let state: TradingState = serde_json::from_value(migrate(old).unwrap()).unwrap();
+    assert_eq!((state.scale_in_steps, state.last_entry_gap), (0, 0.45));
+    // 체결 수량 기록이 없던 포지션은 진입 수량으로 청산
+    assert!(state.leg_quantities.is_empty());
+    assert_eq!(state.leg_quantity("Binance"), 1.0);
+    assert_eq!(state.inflight_trade, None);
+}
+
+fn inflight_trade() -> InflightTrade {
+    InflightTrade {
+        trade_id: "btq1700000000000".to_string(),
+        binance_position: "SHORT".to_string(),
+        bitmart_position: "LONG".to_string(),
+        entry_gap: 0.4,
+        quantity: 2.0,
+        sent_at: "2024-01-01T00:00:00Z".parse().unwrap(),
+    }
+}
+
+fn fill(exchange: &'static str, is_buy: bool, filled_qty: f64) -> Fill {
+    Fill {
+        exchange,
+        symbol: "XRPUSDT".to_string(),
+        is_buy,
+        order_id: String::new(),
+        filled_qty,
+        avg_price: None,
+        fee: None,
+        ts: 0,
+        raw: serde_json::Value::Null,
+    }
+}
+
+#[test]
+fn reconcile_inflight_records_unrecorded_filled_legs() {
+    let mut state = TradingState { inflight_trade: Some(inflight_trade()), ..TradingState::default() };
+    // Binance 숏 진입 1.5 체결, Bitmart 롱 진입 체결 후 되돌리기 완료
+    let fills = [fill("Binance", false, 1.5), fill("Bitmart", true, 2.0), fill("Bitmart", false, 2.0)];
+    assert!(state.reconcile_inflight(&fills));
+    assert_eq!(state.inflight_trade, None);
+    assert!(state.is_trading);
+    assert_eq!(state.binance_position.as_deref(), Some("SHORT"));
+    assert_eq!(state.bitmart_position, None);
+    assert_eq!((state.leg_quantity("Binance"), state.entry_gap), (1.5, 0.4));
+    assert_eq!(state.position_open_time, Some(inflight_trade().sent_at));
+}
+
+#[test]
+fn reconcile_inflight_clears_entry_without_open_fills() {
+    let mut state = TradingState { inflight_trade: Some(inflight_trade()), ..TradingState::default() };
+    assert!(!state.reconcile_inflight(&[fill("Binance", false, 2.0), fill("Binance", true, 2.0)]));
+    assert_eq!(state.inflight_trade, None);
+    assert!(!state.is_trading);
+}
+
+#[test]
+fn close_position_attaches_bounded_book_snapshots() {
+    let level = |price: f64| Level { price, vol: 100.0 };
+    let book = OrderBook {
+        symbol: "XRPUSDT".to_string(),
+        bids: vec![level(0.50), level(0.49), level(0.48)],
+        asks: vec![level(0.51), level(0.52), level(0.53)],
+        ms_t: 1_700_000_000_000,
+    };
+    let books = HashMap::from([("Binance".to_string(), book.clone()), ("Bitmart".to_string(), book)]);
+
+    let mut state = TradingState::default();
+    state.open_position("SHORT", "LONG", 0.5, 1.0);
+    state.entry_book = Some(snapshot_books(&books, 2));
+    let record = state.close_position(0.01, 0.1).unwrap();
+    let snapshot = record.book_snapshot.unwrap();
+    let entry = snapshot.entry.unwrap();
+    assert_eq!(entry.len(), 2);
+    assert_eq!(entry["Bitmart"].bids, vec![level(0.50), level(0.49)]);
+    assert_eq!(entry["Bitmart"].asks.len(), 2);
+    assert_eq!(snapshot.exit, None);
+    assert_eq!(state.entry_book, None);
+
+    // 저장 후 다시 읽어도 유지
+    let json = serde_json::to_value(&state).unwrap();
+    let restored: TradingState = serde_json::from_value(json).unwrap();
+    assert_eq!(restored.trades[0].book_snapshot, state.trades[0].book_snapshot);
+
+    // 스냅샷이 없으면 기록하지 않음
+    state.open_position("SHORT", "LONG", 0.5, 1.0);
+    assert_eq!(state.close_position(0.01, 0.1).unwrap().book_snapshot, None);
+}
+
+#[test]
+fn trim_history_keeps_most_recent_trades() {
+    let mut state = TradingState::default();
+    for i in 0..5 {
+        state.open_position("SHORT", "LONG", 0.5, 1.0);
+        state.close_position(0.01 * i as f64, 0.1);
+    }
+    assert_eq!(state.trim_history(3), 2);
+    assert_eq!(state.trades.len(), 3);
+    // 가장 오래된 기록부터 삭제
+    assert!((state.trades[0].exit_gap - 0.02).abs() < 1e-12);
+    assert_eq!(state.trim_history(3), 0);
+}
+
+#[test]
+fn only_losing_trade_starts_entry_cooldown() {
+    let mut state = TradingState::default();
+    assert!(!state.in_loss_cooldown(60, Utc::now()));
+
+    state.open_position("SHORT", "LONG", 0.5, 1.0);
+    state.close_position(0.6, -0.05);
+    let now = Utc::now();
+    assert!(state.in_loss_cooldown(60, now));
+    assert!(!state.in_loss_cooldown(60, now + Duration::seconds(61)));
+    assert!(!state.in_loss_cooldown(0, now));
+
+    state.open_position("SHORT", "LONG", 0.5, 1.0);
+    state.close_position(0.01, 0.2);
+    assert!(!state.in_loss_cooldown(60, now));
+}
+
+#[test]
+fn reversal_entries_wait_longer_than_same_direction_reentries() {
+    let mut state = TradingState::default();
+    let closed = Utc::now();
+    assert_eq!(state.direction_cooldown_remaining("SHORT", 300, 30, closed), None);
+
+    state.open_position_at("LONG", "SHORT", -0.5, 1.0, closed - Duration::seconds(60));
+    state.close_position_at(-0.05, 0.1, closed);
+    // 거래 기록을 지워도 직전 청산 방향은 유지
+    state.trim_history(0);
+    assert_eq!(state.last_close_direction.as_deref(), Some("LONG"));
+
+    let later = closed + Duration::seconds(60);
+    assert_eq!(state.direction_cooldown_remaining("SHORT", 300, 30, later), Some(240));
+    assert_eq!(state.direction_cooldown_remaining("LONG", 300, 30, later), None);
+    assert_eq!(state.direction_cooldown_remaining("LONG", 300, 30, closed + Duration::seconds(10)), Some(20));
+    assert_eq!(state.direction_cooldown_remaining("SHORT", 300, 30, closed + Duration::seconds(300)), None);
+}
+
+#[test]
+fn migration_restores_last_close_from_trade_history() {
+    let old = json!({
+        "version": 7,
+        "is_trading": false,
+        "binance_position": null,
+        "bitmart_position": null,
+        "entry_gap": 0.0,
+        "quantity": 0.0,
+        "scale_in_steps": 0,
+        "last_entry_gap": 0.0,
+        "position_open_time": null,
+        "entry_fees": null,
+        "entry_book": null,
+        "protective_stops": {},
+        "trades": [{
+            "open_time": "2024-01-01T00:00:00Z",
+            "close_time": "2024-01-01T00:05:00Z",
+            "binance_position": "SHORT",
+            "bitmart_position": "LONG",
+            "entry_gap": 0.5,
+            "exit_gap": 0.05,
+            "quantity": 1.0,
+            "captured_pnl": 0.2,
+            "book_snapshot": null
+        }]
+    });
+    let state: TradingState = serde_json::from_value(migrate(old).unwrap()).unwrap();
+    assert_eq!(state.last_close_direction.as_deref(), Some("SHORT"));
+    assert_eq!(state.last_close_time.map(|t| t.to_rfc3339()), Some("2024-01-01T00:05:00+00:00".to_string()));
+}
+
+#[test]
+fn min_hold_counts_from_position_open_time() {
+    let mut state = TradingState::default();
+    assert!(!state.in_min_hold(60, Utc::now()));
+
+    state.open_position("SHORT", "LONG", 0.5, 1.0);
+    let opened = state.position_open_time.unwrap();
+    assert!(state.in_min_hold(60, opened + Duration::seconds(30)));
+    assert!(!state.in_min_hold(60, opened + Duration::seconds(60)));
+    assert!(!state.in_min_hold(0, opened));
+    assert_eq!(state.held_for(opened + Duration::seconds(30)), Some(Duration::seconds(30)));
+
+    // 진입 시각 없이 복원된 포지션은 제한하지 않음
+    state.position_open_time = None;
+    assert!(!state.in_min_hold(60, opened));
+}
+
+#[test]
+fn session_loss_limit_trips_kill_switch_until_restart() {
+    let mut state = TradingState::default();
+    state.open_position("SHORT", "LONG", 0.5, 1.0);
+    state.close_position(0.01, 3.0);
+    state.open_position("SHORT", "LONG", 0.5, 1.0);
+    state.close_position(0.6, -7.5);
+    assert!((state.session_pnl + 4.5).abs() < 1e-12);
+    assert!(!state.session_loss_breached(Some(5.0)));
+    assert!(!state.session_loss_breached(None));
+
+    state.open_position("SHORT", "LONG", 0.5, 1.0);
+    state.close_position(0.6, -0.5);
+    assert!(state.session_loss_breached(Some(5.0)));
+
+    // 정지 후에는 시간이 지나도 진입 불가
+    state.market_guard.kill("session loss".to_string());
+    assert!(!state.market_guard.allows_entry(&[], Utc::now() + Duration::days(1)));
+    assert_eq!(state.market_guard.killed(), Some("session loss"));
+
+    // 세션 손익은 저장하지 않음 (재시작하면 새 세션)
+    let json = serde_json::to_value(&state).unwrap();
+    let restored: TradingState = serde_json::from_value(json).unwrap();
+    assert_eq!(restored.session_pnl, 0.0);
+    assert_eq!(restored.market_guard.killed(), None);
+}
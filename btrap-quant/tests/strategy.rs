@@ -0,0 +1,759 @@
+use btrap_quant::config::{BinanceOrderRespType, ContractSpec, Exchange, MarginType, StrategyConfig, ThresholdUnit};
+use btrap_quant::depth::{Level, OrderBook};
+use btrap_quant::order::{BinancePrecision, BinanceWeight, ClientOrderIds, Order, OrderBudget, OrderError};
+use btrap_quant::price_calculator::{PriceCalculator, Quotes};
+use btrap_quant::state::TradingState;
+use btrap_quant::strategy::{
+    decide, place_entry, place_legs, place_scale_in, protective_stop_price, reducible_legs, reprice_maker_leg, Decision, MakerRequote, OrderLeg, RepriceOutcome, RestingLeg,
+};
+use btrap_quant::venue::VenueHealth;
+use chrono::{Duration, Utc};
+use reqwest::Client;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use wiremock::matchers::{body_partial_json, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn test_order(server: &MockServer) -> Order {
+    Order {
+        client: Client::new(),
+        binance_api_key: "binance-key".to_string(),
+        binance_secret_key: "binance-secret".to_string(),
+        bitmart_api_key: "bitmart-key".to_string(),
+        bitmart_secret_key: "bitmart-secret".to_string(),
+        bitmart_memo: "memo".to_string(),
+        open_type: MarginType::Isolated,
+        binance_base_url: server.uri(),
+        bitmart_base_url: server.uri(),
+        bitmart_contracts: HashMap::from([("XRPUSDT".to_string(), ContractSpec { contract_size: 1.0, min_contracts: 1, price_decimals: None })]),
+        binance_weight: BinanceWeight::new(2400, 0.8),
+        venue_health: VenueHealth::new(3),
+        binance_precision: BinancePrecision::default(),
+        order_budget: OrderBudget::new(0),
+        binance_resp_type: BinanceOrderRespType::Result,
+        client_order_ids: ClientOrderIds::default(),
+    }
+}
+
+fn filled_binance_order() -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_string(r#"{"symbol":"XRPUSDT","orderId":1,"status":"FILLED"}"#)
+}
+
+#[tokio::test]
+async fn failed_second_leg_unwinds_first_leg() {
+    let server = MockServer::start().await;
+    // Binance 숏 진입 체결, Bitmart 롱 진입 거부
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .and(query_param("side", "SELL"))
+        .respond_with(filled_binance_order())
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":40012,"message":"Invalid size"}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/fapi/v2/positionRisk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"[{"symbol":"XRPUSDT","positionAmt":"-2"}]"#))
+        .mount(&server)
+        .await;
+    // 체결된 Binance 숏을 전량 되돌림
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .and(query_param("side", "BUY"))
+        .and(query_param("quantity", "2"))
+        .and(query_param("reduceOnly", "true"))
+        .respond_with(filled_binance_order())
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let entry = place_entry(&test_order(&server), "XRPUSDT", false, OrderLeg::market("SELL"), OrderLeg::market("buy"), 2.0, 0).await;
+    assert!(!entry.is_hedged());
+    assert!(entry.binance_leg.is_ok());
+    assert_eq!(entry.bitmart_leg.rejection_code(), Some(40012));
+    assert!(matches!(entry.unwind, Some(Ok(()))));
+}
+
+#[tokio::test]
+async fn close_targets_filled_entry_size_per_leg() {
+    let server = MockServer::start().await;
+    // Binance 진입은 100 중 80만 체결
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .and(query_param("side", "SELL"))
+        .and(query_param("quantity", "100"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"symbol":"XRPUSDT","orderId":1,"status":"PARTIALLY_FILLED","executedQty":"80","avgPrice":"0.5"}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok","data":{"order_id":2}}"#))
+        .expect(2)
+        .mount(&server)
+        .await;
+    // 청산은 체결된 80만
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .and(query_param("side", "BUY"))
+        .and(query_param("quantity", "80"))
+        .respond_with(filled_binance_order())
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let order = test_order(&server);
+    let entry = place_entry(&order, "XRPUSDT", false, OrderLeg::market("SELL"), OrderLeg::market("buy"), 100.0, 0).await;
+    assert!(entry.is_hedged());
+    let mut state = TradingState::default();
+    state.open_position("SHORT", "LONG", 1.0, 100.0);
+    state.record_entry_fill("Binance", entry.binance_leg.filled_quantity(100.0));
+    state.record_entry_fill("Bitmart", entry.bitmart_leg.filled_quantity(100.0));
+    assert_eq!((state.leg_quantity("Binance"), state.leg_quantity("Bitmart")), (80.0, 100.0));
+
+    let close_quantities = (state.leg_quantity("Binance"), state.leg_quantity("Bitmart"));
+    let (binance_leg, bitmart_leg) =
+        place_legs(&order, "XRPUSDT", false, Some(OrderLeg::close("BUY")), Some(OrderLeg::close("sell")), close_quantities).await;
+    assert!(binance_leg.is_ok() && bitmart_leg.is_ok());
+    let requests = server.received_requests().await.unwrap();
+    let bitmart_close = requests.iter().rev().find(|request| request.url.path() == "/futures/v1/submit-order").unwrap();
+    let bitmart_close: serde_json::Value = serde_json::from_slice(&bitmart_close.body).unwrap();
+    assert_eq!(bitmart_close["size"], 100);
+}
+
+#[tokio::test]
+async fn hedged_entry_needs_no_unwind() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .respond_with(filled_binance_order())
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok"}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let entry = place_entry(&test_order(&server), "XRPUSDT", false, OrderLeg::market("BUY"), OrderLeg::market("sell"), 1.0, 5000).await;
+    assert!(entry.is_hedged());
+    assert!(entry.unwind.is_none());
+}
+
+#[tokio::test]
+async fn entry_past_deadline_is_aborted_and_unwound() {
+    let server = MockServer::start().await;
+    // Binance 진입은 체결, Bitmart 진입은 제한 시간보다 늦게 접수되어 체결
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .and(query_param("side", "SELL"))
+        .respond_with(filled_binance_order())
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .and(body_partial_json(serde_json::json!({"side": 1})))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(r#"{"code":1000,"message":"Ok"}"#)
+                .set_delay(std::time::Duration::from_millis(600)),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    // 되돌리기: 늦게 체결된 Bitmart 롱까지 반영된 거래소 포지션 기준으로 양쪽 청산, 이후 재확인은 0
+    Mock::given(method("GET"))
+        .and(path("/fapi/v2/positionRisk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"[{"symbol":"XRPUSDT","positionAmt":"-2"}]"#))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/fapi/v2/positionRisk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"[{"symbol":"XRPUSDT","positionAmt":"0"}]"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .and(query_param("side", "BUY"))
+        .and(query_param("reduceOnly", "true"))
+        .respond_with(filled_binance_order())
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/contract/private/position"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"code":1000,"message":"Ok","data":[{"symbol":"XRPUSDT","current_amount":"2","position_type":1}]}"#,
+        ))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/contract/private/position"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok","data":[]}"#))
+        .mount(&server)
+        .await;
+    // 3: 매도 롱 청산
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .and(body_partial_json(serde_json::json!({"side": 3, "size": 2})))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok"}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let entry = place_entry(&test_order(&server), "XRPUSDT", false, OrderLeg::market("SELL"), OrderLeg::market("buy"), 2.0, 200).await;
+    assert!(entry.timed_out());
+    assert!(!entry.binance_leg.is_ok() && !entry.bitmart_leg.is_ok());
+    assert!(matches!(entry.unwind, Some(Ok(()))), "got {:?}", entry.unwind);
+}
+
+#[tokio::test]
+async fn entry_deadline_reports_position_left_after_unwind() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .respond_with(filled_binance_order().set_delay(std::time::Duration::from_millis(400)))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok"}"#))
+        .mount(&server)
+        .await;
+    // 청산 주문 후에도 거래소 포지션이 남아 있음
+    Mock::given(method("GET"))
+        .and(path("/fapi/v2/positionRisk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"[{"symbol":"XRPUSDT","positionAmt":"-2"}]"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/contract/private/position"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok","data":[]}"#))
+        .mount(&server)
+        .await;
+
+    let entry = place_entry(&test_order(&server), "XRPUSDT", false, OrderLeg::market("SELL"), OrderLeg::market("buy"), 2.0, 100).await;
+    assert!(entry.timed_out());
+    assert!(matches!(entry.unwind, Some(Err(OrderError::ResidualPosition(_)))), "got {:?}", entry.unwind);
+}
+
+#[tokio::test]
+async fn failed_scale_in_unwinds_only_the_added_quantity() {
+    let server = MockServer::start().await;
+    // 기존 Binance 숏 5 / Bitmart 롱 5에 1 추가: Binance 숏 추가 체결, Bitmart 롱 추가 거부
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .and(query_param("side", "SELL"))
+        .and(query_param("quantity", "1"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"symbol":"XRPUSDT","orderId":1,"status":"FILLED","executedQty":"1","avgPrice":"0.5"}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":40012,"message":"Invalid size"}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    // 추가된 1만 reduce-only로 되돌리고 거래소 포지션 전량 청산(포지션 조회)은 하지 않음
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .and(query_param("side", "BUY"))
+        .and(query_param("quantity", "1"))
+        .and(query_param("reduceOnly", "true"))
+        .respond_with(filled_binance_order())
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/fapi/v2/positionRisk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"[{"symbol":"XRPUSDT","positionAmt":"-6"}]"#))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let entry = place_scale_in(&test_order(&server), "XRPUSDT", false, "SELL", "buy", 1.0, (5.0, 5.0)).await;
+    assert!(!entry.is_hedged());
+    assert!(matches!(entry.unwind, Some(Ok(()))), "got {:?}", entry.unwind);
+}
+
+// ACK 응답 후 체결을 확인하지 못한 Binance 주문 (주문 조회가 계속 NEW)
+async fn mount_unconfirmed_binance_order(server: &MockServer) {
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .and(query_param("side", "SELL"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"symbol":"XRPUSDT","orderId":9,"status":"NEW"}"#))
+        .expect(1)
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/fapi/v1/order"))
+        .and(query_param("orderId", "9"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"symbol":"XRPUSDT","orderId":9,"status":"NEW","executedQty":"0","avgPrice":"0","cumQuote":"0"}"#,
+        ))
+        .mount(server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/fapi/v1/order"))
+        .and(query_param("orderId", "9"))
+        .respond_with(ResponseTemplate::new(400).set_body_string(r#"{"code":-2011,"msg":"Unknown order sent."}"#))
+        .expect(1)
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn unconfirmed_binance_entry_leg_is_resolved_from_position() {
+    let server = MockServer::start().await;
+    mount_unconfirmed_binance_order(&server).await;
+    // 주문 조회로는 확인하지 못했지만 거래소 포지션은 숏 2로 체결됨
+    Mock::given(method("GET"))
+        .and(path("/fapi/v2/positionRisk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"[{"symbol":"XRPUSDT","positionAmt":"-2"}]"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok","data":{"order_id":2}}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let order = Order { binance_resp_type: BinanceOrderRespType::Ack, ..test_order(&server) };
+    let entry = place_entry(&order, "XRPUSDT", false, OrderLeg::market("SELL"), OrderLeg::market("buy"), 2.0, 0).await;
+    assert!(entry.is_hedged());
+    assert!(entry.unwind.is_none());
+    assert_eq!(entry.binance_leg.fill().map(|fill| fill.filled_qty), Some(2.0));
+}
+
+#[tokio::test]
+async fn unconfirmed_scale_in_leg_unwinds_the_added_position() {
+    let server = MockServer::start().await;
+    mount_unconfirmed_binance_order(&server).await;
+    // 기존 숏 5에서 6으로 늘어남: 추가된 1만 되돌림
+    Mock::given(method("GET"))
+        .and(path("/fapi/v2/positionRisk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"[{"symbol":"XRPUSDT","positionAmt":"-6"}]"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":40012,"message":"Invalid size"}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .and(query_param("side", "BUY"))
+        .and(query_param("quantity", "1"))
+        .and(query_param("reduceOnly", "true"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"symbol":"XRPUSDT","orderId":10,"status":"NEW"}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/fapi/v1/order"))
+        .and(query_param("orderId", "10"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"symbol":"XRPUSDT","orderId":10,"status":"FILLED","executedQty":"1","avgPrice":"0.5","cumQuote":"0.5"}"#,
+        ))
+        .mount(&server)
+        .await;
+
+    let order = Order { binance_resp_type: BinanceOrderRespType::Ack, ..test_order(&server) };
+    let entry = place_scale_in(&order, "XRPUSDT", false, "SELL", "buy", 1.0, (5.0, 5.0)).await;
+    assert!(!entry.is_hedged());
+    assert!(matches!(entry.unwind, Some(Ok(()))), "got {:?}", entry.unwind);
+}
+
+#[tokio::test]
+async fn post_only_rejection_is_repriced_from_the_book() {
+    let server = MockServer::start().await;
+    // 0.52 매수는 즉시 체결될 가격이라 거부, 호가창 최우선 매수호가 0.5로 재시도
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .and(query_param("price", "0.52"))
+        .respond_with(ResponseTemplate::new(400).set_body_string(r#"{"code":-5022,"msg":"Due to the order could not be executed as maker, the Post Only order will be rejected."}"#))
+        .expect(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .and(query_param("price", "0.5"))
+        .and(query_param("timeInForce", "GTX"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"symbol":"XRPUSDT","orderId":3,"status":"NEW","executedQty":"0"}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok","data":{"order_id":2}}"#))
+        .mount(&server)
+        .await;
+
+    let level = |price: f64| Level { price, vol: 100.0 };
+    let book = OrderBook { symbol: "XRPUSDT".to_string(), bids: vec![level(0.5)], asks: vec![level(0.51)], ms_t: 0 };
+    let depth = Arc::new(Mutex::new(HashMap::from([("Binance".to_string(), book)])));
+    let maker = OrderLeg { requote: Some(MakerRequote { depth: &depth, retries: 2 }), ..OrderLeg::limit("BUY", 0.52, true) };
+    let (binance_leg, bitmart_leg) =
+        place_legs(&test_order(&server), "XRPUSDT", false, Some(maker), Some(OrderLeg::market("sell")), (1.0, 1.0)).await;
+    assert_eq!(binance_leg.fill().map(|fill| fill.order_id.as_str()), Some("3"));
+    assert!(bitmart_leg.is_ok());
+
+    // 재호가 정보가 없으면 거부를 그대로 실패로 반환
+    let (binance_leg, _) =
+        place_legs(&test_order(&server), "XRPUSDT", false, Some(OrderLeg::limit("BUY", 0.52, true)), None, (1.0, 1.0)).await;
+    assert!(binance_leg.error().is_some_and(|e| e.is_post_only_rejection()));
+}
+
+#[tokio::test]
+async fn failed_taker_leg_cancels_resting_maker_order() {
+    let server = MockServer::start().await;
+    // Bitmart maker 지정가 접수, Binance taker 시장가 거부
+    Mock::given(method("POST"))
+        .and(path("/fapi/v1/order"))
+        .and(query_param("type", "MARKET"))
+        .respond_with(ResponseTemplate::new(400).set_body_string(r#"{"code":-2019,"msg":"Margin is insufficient."}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok","data":{"order_id":42}}"#),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    // 되돌리기: 미체결 지정가 주문 취소, 체결된 포지션 없음
+    Mock::given(method("POST"))
+        .and(path("/contract/private/cancel-order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok"}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/contract/private/position"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok","data":[]}"#))
+        .mount(&server)
+        .await;
+
+    let maker = OrderLeg::limit("buy", 0.5, true);
+    assert_eq!(maker.role(), "maker @ 0.5");
+    let entry = place_entry(&test_order(&server), "XRPUSDT", false, OrderLeg::market("SELL"), maker, 2.0, 0).await;
+    assert_eq!(entry.binance_leg.rejection_code(), Some(-2019));
+    assert_eq!(entry.bitmart_leg.fill().map(|fill| fill.order_id.as_str()), Some("42"));
+    assert!(matches!(entry.unwind, Some(Ok(()))));
+
+    let requests = server.received_requests().await.unwrap();
+    let submit = requests.iter().find(|r| r.url.path() == "/futures/v1/submit-order").unwrap();
+    let body = serde_json::from_slice::<serde_json::Value>(&submit.body).unwrap();
+    assert_eq!(body["type"], "limit");
+    assert_eq!(body["mode"], 4);
+    let cancel = requests.iter().find(|r| r.url.path() == "/contract/private/cancel-order").unwrap();
+    assert_eq!(serde_json::from_slice::<serde_json::Value>(&cancel.body).unwrap()["order_id"], "42");
+}
+
+fn bitmart_position(amount: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_string(format!(
+        r#"{{"code":1000,"message":"Ok","data":[{{"symbol":"XRPUSDT","current_amount":"{}","position_type":1}}]}}"#,
+        amount
+    ))
+}
+
+fn resting_bitmart_buy() -> RestingLeg<'static> {
+    RestingLeg {
+        exchange_name: "Bitmart",
+        symbol: "XRPUSDT",
+        side: "buy",
+        quantity: 2.0,
+        price: 0.5,
+        post_only: true,
+        order_id: "42".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn unfilled_maker_leg_is_cancelled_and_repriced_until_filled() {
+    let server = MockServer::start().await;
+    // 첫 확인과 취소 후 확인은 미체결, 재호가 주문 후 체결
+    Mock::given(method("GET"))
+        .and(path("/contract/private/position"))
+        .respond_with(bitmart_position("0"))
+        .up_to_n_times(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/contract/private/position"))
+        .respond_with(bitmart_position("2"))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/contract/private/cancel-order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok"}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok","data":{"order_id":43}}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let interval = std::time::Duration::from_millis(1);
+    let outcome =
+        reprice_maker_leg(&test_order(&server), resting_bitmart_buy(), interval, 3, || async { Some(0.51) }).await;
+    assert!(matches!(outcome, RepriceOutcome::Filled { reprices: 1 }), "{:?}", outcome);
+
+    let requests = server.received_requests().await.unwrap();
+    let cancel = requests.iter().find(|r| r.url.path() == "/contract/private/cancel-order").unwrap();
+    assert_eq!(serde_json::from_slice::<serde_json::Value>(&cancel.body).unwrap()["order_id"], "42");
+    let submit = requests.iter().find(|r| r.url.path() == "/futures/v1/submit-order").unwrap();
+    let body = serde_json::from_slice::<serde_json::Value>(&submit.body).unwrap();
+    assert_eq!((body["type"].as_str(), body["mode"].as_u64(), body["size"].as_u64()), (Some("limit"), Some(4), Some(2)));
+    assert_eq!(body["price"].as_str().unwrap().parse::<f64>().unwrap(), 0.51);
+}
+
+#[tokio::test]
+async fn maker_leg_is_abandoned_when_opportunity_disappears() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/contract/private/position"))
+        .respond_with(bitmart_position("0"))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/contract/private/cancel-order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok"}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    // 기회가 사라졌으므로 새 주문 없음
+    Mock::given(method("POST"))
+        .and(path("/futures/v1/submit-order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok","data":{"order_id":43}}"#))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let interval = std::time::Duration::from_millis(1);
+    let outcome = reprice_maker_leg(&test_order(&server), resting_bitmart_buy(), interval, 3, || async { None }).await;
+    assert!(matches!(outcome, RepriceOutcome::OpportunityGone { reprices: 0 }), "{:?}", outcome);
+
+    // 재호가 한도 0이면 취소 후 바로 중단
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/contract/private/position"))
+        .respond_with(bitmart_position("0"))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/contract/private/cancel-order"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok"}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let outcome =
+        reprice_maker_leg(&test_order(&server), resting_bitmart_buy(), interval, 0, || async { Some(0.51) }).await;
+    assert!(matches!(outcome, RepriceOutcome::Exhausted { reprices: 0 }), "{:?}", outcome);
+}
+
+#[tokio::test]
+async fn close_is_skipped_for_legs_flat_on_the_exchange() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/fapi/v2/positionRisk"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"[{"symbol":"XRPUSDT","positionAmt":"-2"}]"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/contract/private/position"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"code":1000,"message":"Ok","data":[]}"#))
+        .mount(&server)
+        .await;
+
+    let order = test_order(&server);
+    // 상태: Binance 숏, Bitmart 롱 / 거래소: Binance 숏 2, Bitmart 포지션 없음
+    let legs = reducible_legs(&order, "XRPUSDT", Some("SHORT"), Some("LONG")).await.unwrap();
+    assert_eq!(legs, (true, false));
+    // 거래소 포지션이 상태와 반대 방향이면 줄일 포지션이 없음
+    let legs = reducible_legs(&order, "XRPUSDT", Some("LONG"), None).await.unwrap();
+    assert_eq!(legs, (false, false));
+}
+
+#[test]
+fn maker_legs_follow_entry_direction() {
+    let config: StrategyConfig = toml::from_str("[maker_legs]\ngap1 = \"bitmart\"\n").unwrap();
+    assert_eq!(config.maker_legs.maker_for("SHORT"), Some(Exchange::Bitmart));
+    assert_eq!(config.maker_legs.maker_for("LONG"), None);
+    assert_eq!(StrategyConfig::default().maker_legs.maker_for("SHORT"), None);
+
+    // maker 체결을 확인하는 재호가 없이는 거부
+    assert!(config.validate().is_err());
+    let config = StrategyConfig { maker_reprice_ms: 500, ..config };
+    assert!(config.validate().is_ok());
+}
+
+// 최근 체결가 기준 갭 (기본 설정: 진입 0.3%, 청산 기준은 테스트마다 지정)
+fn decide_at(config: &StrategyConfig, state: &TradingState, binance: f64, bitmart: f64) -> Decision {
+    let calculator = PriceCalculator::new(Arc::new(config.clone()), Arc::new(Mutex::new(HashMap::new())));
+    let quotes = Quotes { binance_last: Some(binance), bitmart_last: Some(bitmart), ..Quotes::default() };
+    decide(config, &calculator, &quotes, state, 2.0, Utc::now())
+}
+
+fn decision_config() -> StrategyConfig {
+    StrategyConfig { entry_threshold: 0.3, exit_threshold: 0.05, ..StrategyConfig::default() }
+}
+
+#[test]
+fn no_trade_below_entry_threshold() {
+    let config = decision_config();
+    let state = TradingState::default();
+    assert_eq!(decide_at(&config, &state, 1.002, 1.0), Decision::Hold);
+    assert_eq!(decide_at(&config, &state, 0.998, 1.0), Decision::Hold);
+}
+
+#[test]
+fn entry_above_threshold_in_both_directions() {
+    let config = decision_config();
+    let state = TradingState::default();
+
+    // Binance가 비싸면 Binance 숏 / Bitmart 롱
+    let Decision::Enter(rich) = decide_at(&config, &state, 1.005, 1.0) else {
+        panic!("expected entry");
+    };
+    assert_eq!((rich.binance_position, rich.bitmart_position), ("SHORT", "LONG"));
+    assert!((rich.gap - 0.5).abs() < 1e-9);
+    assert_eq!(rich.quantity, 2.0);
+
+    // Binance가 싸면 Binance 롱 / Bitmart 숏
+    let Decision::Enter(cheap) = decide_at(&config, &state, 0.995, 1.0) else {
+        panic!("expected entry");
+    };
+    assert_eq!((cheap.binance_position, cheap.bitmart_position), ("LONG", "SHORT"));
+}
+
+#[test]
+fn exit_when_gap_converges_and_no_reentry_while_open() {
+    let config = decision_config();
+    let mut state = TradingState::default();
+    state.open_position("SHORT", "LONG", 0.5, 2.0);
+
+    // 진입 조건을 다시 만족해도 보유 중에는 재진입하지 않음
+    assert_eq!(decide_at(&config, &state, 1.005, 1.0), Decision::Hold);
+    // 청산 임계값 밖이면 유지
+    assert_eq!(decide_at(&config, &state, 1.001, 1.0), Decision::Hold);
+    // 수렴하면 청산
+    let Decision::Exit { gap } = decide_at(&config, &state, 1.0002, 1.0) else {
+        panic!("expected exit");
+    };
+    assert!((gap - 0.02).abs() < 1e-9);
+}
+
+#[test]
+fn profit_lock_tightens_exit_threshold_over_hold_time() {
+    let config = StrategyConfig { exit_decay_secs: 600, exit_decay_start: 0.3, exit_decay_end: 0.05, ..decision_config() };
+    // 진입 갭 0.5%: 처음에는 0.3%p 회귀(0.2%)를 요구하고 보유 시간이 지나면 0.05%p(0.45%)까지 완화
+    assert!((config.effective_exit_threshold(0.5, Some(0), 1.0) - 0.2).abs() < 1e-9);
+    assert!((config.effective_exit_threshold(-0.5, Some(300), 1.0) - 0.325).abs() < 1e-9);
+    assert!((config.effective_exit_threshold(0.5, Some(6000), 1.0) - 0.45).abs() < 1e-9);
+    // 기본 청산 임계값보다 느슨해지지 않음, 보유 시간을 모르면 기본값
+    assert_eq!(config.effective_exit_threshold(0.3, Some(0), 1.0), 0.05);
+    assert_eq!(config.effective_exit_threshold(0.5, None, 1.0), 0.05);
+    // 사용하지 않으면 항상 기본값
+    assert_eq!(decision_config().effective_exit_threshold(0.5, Some(6000), 1.0), 0.05);
+}
+
+#[test]
+fn decayed_threshold_triggers_exit_on_partial_reversion() {
+    let config = StrategyConfig { exit_decay_secs: 600, exit_decay_start: 0.3, exit_decay_end: 0.05, ..decision_config() };
+    let mut state = TradingState::default();
+    state.open_position("SHORT", "LONG", 0.5, 2.0);
+
+    // 갭 0.4%: 진입 직후에는 유지
+    assert_eq!(decide_at(&config, &state, 1.004, 1.0), Decision::Hold);
+    // 오래 보유하면 같은 갭에서 청산
+    state.position_open_time = Some(Utc::now() - Duration::seconds(600));
+    assert!(matches!(decide_at(&config, &state, 1.004, 1.0), Decision::Exit { .. }));
+}
+
+#[test]
+fn tick_thresholds_convert_to_percent_per_price() {
+    // 진입 5틱, 청산 1틱 (호가 단위 0.0001)
+    let config = StrategyConfig {
+        entry_threshold: 5.0,
+        exit_threshold: 1.0,
+        threshold_unit: ThresholdUnit::Ticks,
+        tick_size: Some(0.0001),
+        ..StrategyConfig::default()
+    };
+    // 가격 0.5: 5틱 = 0.1%, 가격 0.05: 5틱 = 1%
+    assert!((config.entry_threshold_pct(0.5) - 0.1).abs() < 1e-9);
+    assert!((config.entry_threshold_pct(0.05) - 1.0).abs() < 1e-9);
+    assert!((config.exit_threshold_pct(0.5) - 0.02).abs() < 1e-9);
+    // 가격을 모르면 비교가 항상 거짓
+    assert!(config.entry_threshold_pct(0.0).is_nan());
+
+    let state = TradingState::default();
+    // 갭 6틱 (0.5006 vs 0.5000)이면 진입, 4틱이면 유지
+    assert!(matches!(decide_at(&config, &state, 0.5006, 0.5), Decision::Enter(_)));
+    assert_eq!(decide_at(&config, &state, 0.5004, 0.5), Decision::Hold);
+    // 같은 0.12% 갭이라도 저가 종목에서는 5틱 미만이라 진입 안 함
+    assert_eq!(decide_at(&config, &state, 0.05006, 0.05), Decision::Hold);
+}
+
+#[test]
+fn scale_in_when_gap_widens_past_step() {
+    let config = StrategyConfig { scale_in_max_steps: 2, scale_in_gap_step: 0.2, scale_in_size: 1.0, ..decision_config() };
+    let mut state = TradingState::default();
+    state.open_position("SHORT", "LONG", 0.5, 2.0);
+
+    // 직전 진입 갭 + 0.2%p 미만이면 유지
+    assert_eq!(decide_at(&config, &state, 1.006, 1.0), Decision::Hold);
+    let Decision::ScaleIn { gap } = decide_at(&config, &state, 1.008, 1.0) else {
+        panic!("expected scale-in");
+    };
+    assert!((gap - 0.8).abs() < 1e-9);
+    // 반대 방향 갭은 추가 진입 안 함, 수렴하면 전체 청산
+    assert_eq!(decide_at(&config, &state, 0.992, 1.0), Decision::Hold);
+    assert!(matches!(decide_at(&config, &state, 1.0002, 1.0), Decision::Exit { .. }));
+
+    // 다음 단계는 마지막 추가 진입 갭 기준, 최대 횟수 이후에는 유지
+    state.scale_in(0.8, 1.0);
+    assert_eq!(decide_at(&config, &state, 1.009, 1.0), Decision::Hold);
+    assert!(matches!(decide_at(&config, &state, 1.010, 1.0), Decision::ScaleIn { .. }));
+    state.scale_in(1.0, 1.0);
+    assert_eq!(decide_at(&config, &state, 1.02, 1.0), Decision::Hold);
+    // 사용하지 않으면 추가 진입 없음
+    assert_eq!(decide_at(&decision_config(), &TradingState { scale_in_steps: 0, ..state.clone() }, 1.02, 1.0), Decision::Hold);
+}
+
+#[test]
+fn protective_stop_sits_beyond_entry_on_losing_side() {
+    // 롱은 아래, 숏은 위 (호가 단위로 맞출 때 진입가에서 멀어지는 쪽)
+    assert_eq!(protective_stop_price("LONG", 0.5, 5.0, Some(0.0001)), 0.475);
+    assert_eq!(protective_stop_price("SHORT", 0.5, 5.0, Some(0.0001)), 0.525);
+    assert_eq!(protective_stop_price("LONG", 0.5123, 3.0, Some(0.001)), 0.496);
+    assert_eq!(protective_stop_price("SHORT", 0.5123, 3.0, Some(0.001)), 0.528);
+    // 호가 단위를 모르면 소수 8자리
+    assert_eq!(protective_stop_price("SHORT", 0.5123, 3.0, None), 0.527669);
+}
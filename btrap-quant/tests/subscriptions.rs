@@ -0,0 +1,27 @@
+use btrap_quant::subscriptions::{bitmart_subscribe, Subscriptions};
+use tokio_tungstenite::tungstenite::protocol::Message;
+
+#[tokio::test]
+async fn reconnect_resends_every_registered_subscription() {
+    let mut subscriptions = Subscriptions::new();
+    subscriptions.register(bitmart_subscribe(&["futures/depthAll20:XRPUSDT".to_string()]));
+    subscriptions.register(bitmart_subscribe(&["futures/trade:XRPUSDT".to_string()]));
+    // 같은 구독은 한 번만 기록
+    subscriptions.register(bitmart_subscribe(&["futures/trade:XRPUSDT".to_string()]));
+    assert_eq!(subscriptions.messages().len(), 2);
+
+    let mut first_connection: Vec<Message> = Vec::new();
+    subscriptions.send_all(&mut first_connection).await.unwrap();
+    // 재접속: 새 연결에 등록된 구독 전체를 같은 순서로 다시 보냄
+    let mut reconnected: Vec<Message> = Vec::new();
+    subscriptions.send_all(&mut reconnected).await.unwrap();
+    assert_eq!(reconnected, first_connection);
+
+    let sent: Vec<serde_json::Value> = reconnected
+        .iter()
+        .map(|message| serde_json::from_str(message.to_text().unwrap()).unwrap())
+        .collect();
+    assert_eq!(sent[0]["action"], "subscribe");
+    assert_eq!(sent[0]["args"][0], "futures/depthAll20:XRPUSDT");
+    assert_eq!(sent[1]["args"][0], "futures/trade:XRPUSDT");
+}
@@ -0,0 +1,36 @@
+use btrap_quant::tasks::TaskTracker;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+#[tokio::test]
+async fn tasks_beyond_limit_wait_for_a_free_slot() {
+    let tracker = TaskTracker::new(1);
+    let (release_first, first_done) = oneshot::channel::<()>();
+    let first = tracker.spawn("first", async move {
+        let _ = first_done.await;
+    });
+    let (started_tx, mut started_rx) = oneshot::channel::<()>();
+    let second = tracker.spawn("second", async move {
+        let _ = started_tx.send(());
+    });
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(tracker.active(), 1);
+    // 첫 태스크가 끝나기 전에는 두 번째 태스크가 시작되지 않음
+    assert!(started_rx.try_recv().is_err());
+
+    release_first.send(()).unwrap();
+    first.await.unwrap();
+    second.await.unwrap();
+    assert!(started_rx.try_recv().is_ok());
+    assert_eq!(tracker.active(), 0);
+}
+
+#[tokio::test]
+async fn panicking_task_releases_its_slot() {
+    let tracker = TaskTracker::new(2);
+    let handle = tracker.spawn("panics", async { panic!("feed crashed") });
+    assert!(handle.await.is_err());
+    assert_eq!(tracker.active(), 0);
+    assert_eq!(tracker.limit(), 2);
+}
@@ -0,0 +1,45 @@
+use btrap_quant::trade_events::{serve_trade_events, TradeEvent, TradeEventBus};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+#[tokio::test]
+async fn connected_clients_receive_json_lines() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let bus = Arc::new(TradeEventBus::new(16));
+    tokio::spawn(serve_trade_events(listener, Arc::clone(&bus)));
+
+    let stream = TcpStream::connect(addr).await.unwrap();
+    // 서버가 구독을 등록할 때까지 대기
+    while bus.subscribers() == 0 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    bus.publish(TradeEvent::skip("XRPUSDT", 0.42, "max open trades reached (1)"));
+    bus.publish(TradeEvent::Exit {
+        symbol: "XRPUSDT".to_string(),
+        entry_gap: 0.42,
+        exit_gap: 0.01,
+        quantity: 10.0,
+        captured_pnl: 0.03,
+        ts: 1_700_000_000_000,
+    });
+
+    let mut lines = BufReader::new(stream).lines();
+    let skip: serde_json::Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+    assert_eq!(skip["type"], "skip");
+    assert_eq!(skip["reason"], "max open trades reached (1)");
+    let exit: serde_json::Value = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+    assert_eq!(exit["type"], "exit");
+    assert_eq!(exit["captured_pnl"], 0.03);
+}
+
+#[test]
+fn publishing_without_subscribers_does_not_block() {
+    let bus = TradeEventBus::new(1);
+    for _ in 0..10 {
+        bus.publish(TradeEvent::skip("XRPUSDT", 0.1, "entry not confirmed"));
+    }
+    assert_eq!(bus.subscribers(), 0);
+}
@@ -0,0 +1,48 @@
+use btrap_quant::volatility::{gap_stabilizing, volatility_sufficient, GapVelocity, RollingVolatility};
+
+#[test]
+fn gap_velocity_is_slope_over_window() {
+    let mut velocity = GapVelocity::new(2000);
+    velocity.update(0, 0.10);
+    assert_eq!(velocity.velocity(), None);
+
+    velocity.update(1000, 0.30);
+    assert!((velocity.velocity().unwrap() - 0.20).abs() < 1e-12);
+
+    // 구간을 벗어난 표본은 제외 (1000ms ~ 3000ms)
+    velocity.update(3000, 0.30);
+    assert!(velocity.velocity().unwrap().abs() < 1e-12);
+}
+
+#[test]
+fn gate_passes_stable_or_converging_gaps_only() {
+    // 안정적인 갭
+    assert!(gap_stabilizing(0.5, Some(0.01), 0.05));
+    // 양의 갭이 빠르게 벌어짐
+    assert!(!gap_stabilizing(0.5, Some(0.2), 0.05));
+    // 빠르지만 수렴 방향
+    assert!(gap_stabilizing(0.5, Some(-0.2), 0.05));
+    assert!(!gap_stabilizing(-0.5, Some(-0.2), 0.05));
+    // 속도를 모르면 진입하지 않음
+    assert!(!gap_stabilizing(0.5, None, 0.05));
+}
+
+#[test]
+fn volatility_gate_requires_known_minimum_volatility() {
+    assert!(volatility_sufficient(Some(0.02), 0.01));
+    assert!(volatility_sufficient(Some(0.01), 0.01));
+    assert!(!volatility_sufficient(Some(0.005), 0.01));
+    // 변동성을 모르면 진입하지 않음
+    assert!(!volatility_sufficient(None, 0.01));
+
+    // 가격이 거의 움직이지 않으면 최소 변동성 미만
+    let mut volatility = RollingVolatility::new(4);
+    for price in [1.0, 1.0001, 1.0, 1.0001, 1.0] {
+        volatility.update(price);
+    }
+    assert!(!volatility_sufficient(volatility.std_dev(), 0.05));
+    for price in [1.01, 0.99, 1.01, 0.99] {
+        volatility.update(price);
+    }
+    assert!(volatility_sufficient(volatility.std_dev(), 0.05));
+}